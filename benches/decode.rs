@@ -0,0 +1,63 @@
+//! Throughput of name and timestamp decoding on the record parsing hot path.
+//!
+//! Run with `cargo bench --bench decode`. Compares the per-unit UTF-16 decode and
+//! eager DateTime construction the parsers used before against the ASCII fast path
+//! and raw FILETIME storage they use now.
+
+use chrono::{DateTime, Utc};
+use mfte_rs::ntfs::filetime::FileTime;
+use mfte_rs::ntfs::names::decode_utf16le;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 2_000_000;
+
+fn utf16le(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn decode_per_unit(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16(&units).unwrap_or_default()
+}
+
+fn filetime_to_datetime(filetime: u64) -> DateTime<Utc> {
+    const WINDOWS_TICK: u64 = 10_000_000;
+    const SEC_TO_UNIX_EPOCH: u64 = 11_644_473_600;
+    let seconds = (filetime / WINDOWS_TICK).saturating_sub(SEC_TO_UNIX_EPOCH);
+    let nanos = (filetime % WINDOWS_TICK) * 100;
+    DateTime::from_timestamp(seconds as i64, nanos as u32).unwrap_or_default()
+}
+
+fn bench(label: &str, mut run: impl FnMut(u32)) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        run(i);
+    }
+    let per_op = start.elapsed().as_nanos() as f64 / ITERATIONS as f64;
+    println!("{label:<40} {per_op:>8.1} ns/op");
+}
+
+fn main() {
+    let names = [
+        ("ascii", utf16le("Microsoft.Windows.ShellExperienceHost_cw5n1h2txyewy.manifest")),
+        ("mixed", utf16le("Résumé - Überprüfung 2024.docx")),
+    ];
+
+    for (kind, bytes) in &names {
+        bench(&format!("utf16 per-unit ({kind})"), |_| {
+            black_box(decode_per_unit(black_box(bytes)));
+        });
+        bench(&format!("utf16 fast path ({kind})"), |_| {
+            black_box(decode_utf16le(black_box(bytes)).unwrap_or_default());
+        });
+    }
+
+    let base = 133_500_000_000_000_000u64;
+    bench("filetime -> DateTime", |i| {
+        black_box(filetime_to_datetime(black_box(base + i as u64)));
+    });
+    bench("filetime raw store + timestamp", |i| {
+        black_box(FileTime(black_box(base + i as u64)).timestamp());
+    });
+}
@@ -0,0 +1,232 @@
+use crate::cli::Cli;
+use crate::evidence;
+use crate::ntfs::compression;
+use crate::output::json::AnalysisSummary;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Number of leading bytes handed to detectors
+pub const DETECT_HEADER_SIZE: usize = 512;
+
+/// Detection confidence: the input is not this artifact
+pub const NO_MATCH: u8 = 0;
+/// Detection confidence: structure is plausible but has no signature
+pub const WEAK_MATCH: u8 = 25;
+/// Detection confidence: several structural checks agree
+pub const LIKELY_MATCH: u8 = 60;
+/// Detection confidence: a magic signature matched
+pub const SIGNATURE_MATCH: u8 = 100;
+
+/// Smallest input any built-in artifact fits in: a 512-byte boot sector
+const MIN_ARTIFACT_SIZE: u64 = 512;
+
+/// How far into a zero-filled input to look for data before calling it empty
+const ZERO_SCAN_LIMIT: u64 = 256 * 1024 * 1024;
+
+/// Signatures of artifacts besides $MFT to look for inside LZNT1 chunks:
+/// $LogFile restart pages and $I30 index records
+const LZNT1_SIGNATURES: [&[u8]; 2] = [b"RSTR", b"INDX"];
+
+/// Archive, image and compression formats that have to be unpacked before an
+/// artifact inside them can be parsed
+const CONTAINER_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1F\x8B", "gzip file"),
+    (b"7z\xBC\xAF\x27\x1C", "7-Zip archive"),
+    (b"\xFD7zXZ\x00", "xz file"),
+    (b"\x28\xB5\x2F\xFD", "zstd file"),
+    (b"BZh", "bzip2 file"),
+    (b"Rar!\x1A\x07", "RAR archive"),
+    (b"EVF\x09\x0D\x0A\xFF\x00", "EnCase (E01) image"),
+    (b"vhdxfile", "VHDX disk image"),
+    (b"KDMV", "VMDK disk image"),
+    (b"conectix", "VHD disk image"),
+];
+
+/// What a detector gets to see of an input
+#[derive(Debug, Clone)]
+pub struct InputInfo {
+    /// Up to DETECT_HEADER_SIZE bytes from the start of the input
+    pub header: Vec<u8>,
+    /// Size in bytes, measured by seeking so block devices report their real size
+    pub size: u64,
+    /// False for block/character devices and other special files
+    pub is_regular_file: bool,
+}
+
+impl InputInfo {
+    /// Read the header and size of an input without memory-mapping it
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = evidence::open_read_only(path)?;
+
+        let mut header = vec![0u8; DETECT_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < header.len() {
+            let read = file.read(&mut header[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        header.truncate(filled);
+
+        let size = file.seek(SeekFrom::End(0))?;
+        let is_regular_file = file.metadata()?.is_file();
+
+        Ok(Self {
+            header,
+            size,
+            is_regular_file,
+        })
+    }
+}
+
+/// A self-contained artifact type: how to recognize it and how to process it.
+///
+/// Built-in NTFS artifacts and parsers from other crates implement this trait and
+/// are added to a [`ParserRegistry`]; the input is handed to the most confident one.
+pub trait ArtifactParser: Send + Sync {
+    /// Short lowercase name used in output file names (e.g. "mft")
+    fn name(&self) -> &'static str;
+
+    /// Human readable artifact name for logs (e.g. "MFT")
+    fn description(&self) -> &'static str;
+
+    /// Confidence from NO_MATCH to SIGNATURE_MATCH that the input is this artifact
+    fn detect(&self, input: &InputInfo) -> u8;
+
+    /// Parse the input named by the CLI and write every requested output
+    fn process(&self, cli: &Cli) -> anyhow::Result<AnalysisSummary>;
+}
+
+/// Parser assembled from plain functions, for artifacts that need no state
+pub struct FnParser {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub detect: fn(&InputInfo) -> u8,
+    pub process: fn(&Cli) -> anyhow::Result<AnalysisSummary>,
+}
+
+impl ArtifactParser for FnParser {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn detect(&self, input: &InputInfo) -> u8 {
+        (self.detect)(input)
+    }
+
+    fn process(&self, cli: &Cli) -> anyhow::Result<AnalysisSummary> {
+        (self.process)(cli)
+    }
+}
+
+#[derive(Default)]
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn ArtifactParser>>,
+}
+
+impl ParserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<P: ArtifactParser + 'static>(&mut self, parser: P) {
+        self.parsers.push(Box::new(parser));
+    }
+
+    pub fn parsers(&self) -> impl Iterator<Item = &dyn ArtifactParser> {
+        self.parsers.iter().map(|parser| parser.as_ref())
+    }
+
+    /// Pick the most confident parser; ties go to the one registered first
+    pub fn detect(&self, input: &InputInfo) -> Option<&dyn ArtifactParser> {
+        let mut best: Option<(&dyn ArtifactParser, u8)> = None;
+
+        for parser in self.parsers() {
+            let confidence = parser.detect(input);
+            log::debug!("{} detector confidence: {}", parser.description(), confidence);
+            if confidence > NO_MATCH && best.is_none_or(|(_, current)| confidence > current) {
+                best = Some((parser, confidence));
+            }
+        }
+
+        best.map(|(parser, _)| parser)
+    }
+}
+
+/// Why no parser recognized an input, phrased as what to do about it: empty
+/// and truncated extracts, archives and images, LZNT1-compressed data copied
+/// raw from a compressed folder, and sparse files extracted as zeros
+pub fn unrecognized_reason<P: AsRef<Path>>(path: P, input: &InputInfo) -> String {
+    let path = path.as_ref();
+    if input.size == 0 {
+        return format!(
+            "{} is empty (0 bytes). The extraction failed or copied a placeholder; re-extract the artifact with a raw NTFS copy tool (e.g. KAPE, FTK Imager) or point -f at the volume",
+            path.display()
+        );
+    }
+    if let Some((_, kind)) = CONTAINER_SIGNATURES.iter().find(|(signature, _)| input.header.starts_with(signature)) {
+        return format!("{} is a {}; unpack or mount it and point -f at the artifact inside", path.display(), kind);
+    }
+    // A compressed $MFT is decompressed by the MFT parser; other artifacts are not
+    if LZNT1_SIGNATURES.iter().any(|signature| compression::lznt1_wraps(&input.header, signature)) {
+        return format!(
+            "{} looks like LZNT1-compressed NTFS data: the file was copied raw from a compressed folder. Re-extract it with a tool that decompresses NTFS compression, or copy it through Windows, which decompresses on read",
+            path.display()
+        );
+    }
+    if input.header.iter().all(|&byte| byte == 0) {
+        return match first_non_zero(path, input) {
+            Some(offset) => format!(
+                "{} starts with {} bytes of zeros, as a sparse file (such as $UsnJrnl:$J, whose purged head is sparse) extracted with its unallocated ranges filled in. Re-extract only the allocated data, or cut the zeros off, e.g. tail -c +{} {} > trimmed",
+                path.display(),
+                offset,
+                offset + 1,
+                path.display()
+            ),
+            None if input.size <= ZERO_SCAN_LIMIT || !input.is_regular_file => format!(
+                "{} holds only zeros: a sparse file extracted without its data, or a failed copy. Re-extract the artifact from the volume",
+                path.display()
+            ),
+            None => format!(
+                "{} holds only zeros in its first {} MB: a sparse file extracted without its data, or a failed copy. Re-extract the artifact from the volume",
+                path.display(),
+                ZERO_SCAN_LIMIT / (1024 * 1024)
+            ),
+        };
+    }
+    if input.size < MIN_ARTIFACT_SIZE {
+        return format!(
+            "{} is only {} bytes, smaller than any NTFS artifact (a $Boot sector is 512 bytes, a FILE record 1024): the extract is truncated; re-extract it",
+            path.display(),
+            input.size
+        );
+    }
+    format!(
+        "Unknown file type for: {} (expected $MFT, $J, $LogFile, $Boot, $SDS, $I30 or an NTFS volume)",
+        path.display()
+    )
+}
+
+/// Offset of the first non-zero byte, looking no further than ZERO_SCAN_LIMIT
+fn first_non_zero(path: &Path, input: &InputInfo) -> Option<u64> {
+    let mut file = evidence::open_read_only(path).ok()?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut offset = 0u64;
+    while offset < ZERO_SCAN_LIMIT.min(input.size) {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            return None;
+        }
+        if let Some(position) = buffer[..read].iter().position(|&byte| byte != 0) {
+            return Some(offset + position as u64);
+        }
+        offset += read as u64;
+    }
+    None
+}
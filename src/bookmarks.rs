@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A tagged MFT record, written as `Entry` or `Entry-Seq`.
+///
+/// Without a sequence number the bookmark matches every reuse of the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bookmark {
+    pub entry_number: u32,
+    pub sequence_number: Option<u16>,
+}
+
+impl Bookmark {
+    pub fn matches(&self, entry_number: u32, sequence_number: u16) -> bool {
+        self.entry_number == entry_number && self.sequence_number.is_none_or(|seq| seq == sequence_number)
+    }
+}
+
+impl FromStr for Bookmark {
+    type Err = anyhow::Error;
+
+    /// Decimal or hex, e.g. `624`, `624-5` or `0x270-0x5`
+    fn from_str(spec: &str) -> Result<Self> {
+        let (entry, sequence) = match spec.split_once('-') {
+            Some((entry, sequence)) => (entry, Some(sequence)),
+            None => (spec, None),
+        };
+
+        let entry_number = parse_number(entry)
+            .and_then(|value| u32::try_from(value).ok())
+            .with_context(|| format!("Invalid entry number in bookmark: {}", spec))?;
+        let sequence_number = sequence
+            .map(|sequence| {
+                parse_number(sequence)
+                    .and_then(|value| u16::try_from(value).ok())
+                    .with_context(|| format!("Invalid sequence number in bookmark: {}", spec))
+            })
+            .transpose()?;
+
+        Ok(Self { entry_number, sequence_number })
+    }
+}
+
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sequence_number {
+            Some(sequence) => write!(f, "{}-{}", self.entry_number, sequence),
+            None => write!(f, "{}", self.entry_number),
+        }
+    }
+}
+
+impl Serialize for Bookmark {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bookmark {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        spec.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Named sets of bookmarked records, persisted as a JSON sidecar so review can
+/// continue across runs:
+///
+/// ```json
+/// { "sets": { "reviewed": ["624-5", "1022"] } }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookmarkStore {
+    pub sets: BTreeMap<String, BTreeSet<Bookmark>>,
+}
+
+impl BookmarkStore {
+    /// Load a sidecar; a missing file is an empty store
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bookmarks from {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse bookmarks in {}", path.display()))
+    }
+
+    /// Write the sidecar, replacing it only once the new contents are complete
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)? + "\n")?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to write bookmarks to {}", path.display()))
+    }
+
+    /// Add bookmarks to a set, creating it if needed; returns how many were new
+    pub fn add(&mut self, set: &str, bookmarks: impl IntoIterator<Item = Bookmark>) -> usize {
+        let entries = self.sets.entry(set.to_string()).or_default();
+        bookmarks.into_iter().filter(|&bookmark| entries.insert(bookmark)).count()
+    }
+
+    /// Remove bookmarks from a set, dropping the set once empty; returns how many were removed
+    pub fn remove(&mut self, set: &str, bookmarks: impl IntoIterator<Item = Bookmark>) -> usize {
+        let Some(entries) = self.sets.get_mut(set) else {
+            return 0;
+        };
+        let removed = bookmarks.into_iter().filter(|bookmark| entries.remove(bookmark)).count();
+        if entries.is_empty() {
+            self.sets.remove(set);
+        }
+        removed
+    }
+
+    pub fn get(&self, set: &str) -> Option<&BTreeSet<Bookmark>> {
+        self.sets.get(set)
+    }
+}
@@ -1,20 +1,77 @@
+use crate::output::csv::{CsvOptions, CsvPreamble};
+use crate::output::json::JsonOptions;
+use crate::output::template::Template;
+use chrono::NaiveDate;
 use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(name = "mfte-rs")]
 #[command(about = "Cross-platform NTFS file system artifact parser")]
 #[command(version = "0.1.0")]
 #[command(author = "Claude Code")]
+#[command(after_help = "Subcommands:\n  mfte-rs mactime --help   Render bodyfiles as a timeline\n  mfte-rs whohas --help    Find the files that own a cluster\n  mfte-rs tag --help       Bookmark records for later export\n  mfte-rs sdsdiff --help   Compare two $SDS files for permission drift\n  mfte-rs auto --help      Process every artifact of a drive in one run\n  mfte-rs monitor --help   Stream new $J changes as file telemetry\n  mfte-rs merge --help     Combine outputs from many hosts into one dataset")]
 pub struct Cli {
-    /// File to process ($MFT | $J | $LogFile | $Boot | $SDS | $I30). Required
+    /// File to process ($MFT | $J | $LogFile | $Boot | $SDS | $I30), an NTFS volume/block device such as /dev/sdb2 or \\.\C:, or a triage directory whose artifacts are all processed. Required
     #[arg(short = 'f', long = "file", required = true)]
     pub file: PathBuf,
 
-    /// $MFT file to use when -f points to a $J file (Use this to resolve parent path in $J CSV output)
+    /// $MFT file to use when -f points to a $J, $LogFile or $I30 file (Use this to resolve parent paths in $J, $LogFile and $I30 output)
     #[arg(short = 'm', long = "mft")]
     pub mft_file: Option<PathBuf>,
 
+    /// $SDS file to use when -f points to a $MFT file (Use this to resolve owner SIDs for --owner-sid)
+    #[arg(long = "sds")]
+    pub sds_file: Option<PathBuf>,
+
+    /// $Bitmap file to use when -f points to a $MFT file (Use this to check whether deleted files' clusters were reallocated for the recoverability column). Read from the volume when -f is a volume
+    #[arg(long = "bitmap")]
+    pub bitmap_file: Option<PathBuf>,
+
+    /// Size in bytes of the $MFT's FILE records, e.g. 4096 for a 4Kn volume. Default is the size the records declare, or the boot sector's when -f is a volume
+    #[arg(long = "record-size", alias = "recordsize", value_parser = parse_record_size)]
+    pub record_size: Option<usize>,
+
+    /// Only include files whose security descriptor owner matches this SID. Requires --sds. Example: S-1-5-21-1004336348-1177238915-682003330-1001
+    #[arg(long = "owner-sid")]
+    pub owner_sid: Option<String>,
+
+    /// JSON file of weights and thresholds for the timestomping heuristics behind anomaly_score. Fields left out keep their defaults. Example: {"si_zero_fraction": 10, "threshold": 60}
+    #[arg(long = "anomaly-model")]
+    pub anomaly_model_file: Option<PathBuf>,
+
+    /// Bookmark sidecar JSON written by `mfte-rs tag`, used with --export-tagged
+    #[arg(long = "tags")]
+    pub tags_file: Option<PathBuf>,
+
+    /// Only include MFT records bookmarked in this set of the --tags file. Requires --tags. Example: reviewed
+    #[arg(long = "export-tagged")]
+    pub export_tagged: Option<String>,
+
+    /// CSV lookup table to join against output records, appending its other columns to every row. The first header names the record field to match; fields with "path" in their name match by path prefix. Can be repeated. Example: owners.csv
+    #[arg(long = "lookup")]
+    pub lookup_tables: Vec<PathBuf>,
+
+    /// CSV of time windows and the processes running in them, e.g. exported from SRUM or Prefetch by another tool, used to add likely_process and likely_process_pid columns to $J output. Columns: start, end, process and optionally pid, with UTC times. Example: hints.csv
+    #[arg(long = "process-hints")]
+    pub process_hints_file: Option<PathBuf>,
+
+    /// Volume serial number (hex) to derive record_id values from, so loose $MFT, $J, ... files get the same IDs as the volume they came from. Read from the volume when -f is a volume, otherwise 0 when omitted. Example: 0x1234ABCD5678EF00
+    #[arg(long = "volume-serial", value_parser = parse_volume_serial)]
+    pub volume_serial: Option<u64>,
+
+    /// Constant field added to every output record, so datasets merged from several hosts stay attributable. Can be repeated. Example: hostname=WKS-042
+    #[arg(long = "context", value_parser = parse_context)]
+    pub context: Vec<(String, String)>,
+
+    /// Round every output timestamp down to this unit (second, minute, hour or day), for datasets shared publicly or for tool benchmarks. Order between events is kept. Default is full precision
+    #[arg(long = "round-times", value_enum)]
+    pub round_times: Option<TimeUnit>,
+
+    /// Move every output timestamp by one random offset of up to this many seconds either way, the same for the whole run, so absolute times are hidden while order and gaps are kept. Example: 86400
+    #[arg(long = "jitter-times")]
+    pub jitter_times: Option<u64>,
+
     /// Directory to save JSON formatted results to. This or --csv required unless --de or --body is specified
     #[arg(long = "json")]
     pub json_dir: Option<PathBuf>,
@@ -23,6 +80,18 @@ pub struct Cli {
     #[arg(long = "jsonf")]
     pub json_filename: Option<String>,
 
+    /// When true, indent JSON output for readability. Default is FALSE (compact JSON)
+    #[arg(long = "pretty")]
+    pub json_pretty: bool,
+
+    /// When true, write JSON records as arrays of values after a header array of field names. Default is FALSE
+    #[arg(long = "json-values")]
+    pub json_values: bool,
+
+    /// Directory to save JSON Lines results to (one JSON object per line)
+    #[arg(long = "jsonl")]
+    pub jsonl_dir: Option<PathBuf>,
+
     /// Directory to save CSV formatted results to. This or --json required unless --de or --body is specified
     #[arg(long = "csv")]
     pub csv_dir: Option<PathBuf>,
@@ -31,6 +100,20 @@ pub struct Cli {
     #[arg(long = "csvf")]
     pub csv_filename: Option<String>,
 
+    /// Directory to save results to as a SQLite database with one table per artifact. Requires the sqlite feature
+    #[cfg(feature = "sqlite")]
+    #[arg(long = "sqlite")]
+    pub sqlite_dir: Option<PathBuf>,
+
+    /// Directory to save results to as Parquet, one file per artifact. Requires the parquet feature
+    #[cfg(feature = "parquet")]
+    #[arg(long = "parquet")]
+    pub parquet_dir: Option<PathBuf>,
+
+    /// Line layout for --csv record output instead of the standard columns, e.g. "{entry_number},{full_path},{si_created:%Y%m%d}". Placeholders are column names (si_/fn_ created, modified, changed and accessed name the $MFT timestamps); timestamps take an optional strftime format. Requires --csv
+    #[arg(long = "template")]
+    pub template: Option<String>,
+
     /// Directory to save bodyfile formatted results to. --bdl is also required when using this option
     #[arg(long = "body")]
     pub body_dir: Option<PathBuf>,
@@ -39,7 +122,7 @@ pub struct Cli {
     #[arg(long = "bodyf")]
     pub body_filename: Option<String>,
 
-    /// Drive letter (C, D, etc.) to use with bodyfile. Only the drive letter itself should be provided
+    /// Drive letter (C, D, etc.) to use with bodyfile and as the full_path prefix. Only the drive letter itself should be provided
     #[arg(long = "bdl")]
     pub body_drive_letter: Option<String>,
 
@@ -47,6 +130,30 @@ pub struct Cli {
     #[arg(long = "blf")]
     pub body_lf: bool,
 
+    /// Directory to save a mactime-style timeline (grouped by day) to. Uses the same rows as --body
+    #[arg(long = "mactime")]
+    pub mactime_dir: Option<PathBuf>,
+
+    /// Directory to save a cluster allocation map to: a CSV of which file owns each cluster range and a PPM heatmap of the volume. Applies to $MFT and volume input
+    #[arg(long = "cluster-map")]
+    pub cluster_map_dir: Option<PathBuf>,
+
+    /// Directory to save an entry number vs creation time dataset to: a CSV of each in-use file's entry number and $STANDARD_INFORMATION created time, and a PNG heatmap of the two when built with the png feature. Applies to $MFT and volume input
+    #[arg(long = "entry-map")]
+    pub entry_map_dir: Option<PathBuf>,
+
+    /// When true, add a row for each other hard link of a file (a further $FILE_NAME attribute) after its MFT record, with that link's name, parent path and $FILE_NAME timestamps. Default is FALSE
+    #[arg(long = "hl")]
+    pub hard_links: bool,
+
+    /// When true, add a row with the $FILE_NAME timestamps after each MFT record in bodyfile output, its name suffixed with ($FILE_NAME). Default is FALSE
+    #[arg(long = "bfn")]
+    pub body_file_name: bool,
+
+    /// Directory to save the content of deleted files to, as <entry>-<seq>_<name>: resident files from any $MFT, and files whose clusters are all unallocated when -f is a volume. NTFS-compressed files are decompressed. Writes manifest.csv and hashdeep.txt with the MD5, SHA-1 and SHA-256 of each file saved
+    #[arg(long = "recover")]
+    pub recover_dir: Option<PathBuf>,
+
     /// Directory to save exported FILE record. --do is also required when using this option
     #[arg(long = "dd")]
     pub dump_dir: Option<PathBuf>,
@@ -59,7 +166,7 @@ pub struct Cli {
     #[arg(long = "de")]
     pub dump_entry: Option<String>,
 
-    /// When true, dump resident files to dir specified by --csv or --json, in 'Resident' subdirectory
+    /// When true, dump resident files and extended attribute ($EA) values to dir specified by --csv or --json, in 'Resident' subdirectory, with manifest.csv and hashdeep.txt listing their MD5, SHA-1 and SHA-256
     #[arg(long = "dr")]
     pub dump_resident: bool,
 
@@ -75,14 +182,42 @@ pub struct Cli {
     #[arg(long = "dt")]
     pub datetime_format: Option<String>,
 
-    /// Include DOS file name types. Default is FALSE
+    /// Include DOS file name types: fill short_name with each long name's 8.3 alias. Default is FALSE
     #[arg(long = "sn")]
     pub include_short_names: bool,
 
-    /// Generate condensed file listing. Requires --csv. Default is FALSE
+    /// Generate condensed file listing of files and directories in use (entry, path, extension, size, created, modified). Requires --csv. Default is FALSE
     #[arg(long = "fl")]
     pub file_listing: bool,
 
+    /// When true, leave NTFS metadata files ($MFT, $Bitmap, $Secure and the other reserved entries, and everything under $Extend) out of $MFT output, timelines and statistics. The root directory is kept. Default is FALSE
+    #[arg(long = "exclude-system")]
+    pub exclude_system: bool,
+
+    /// When true, leave extension records (rows with a base_record_reference, whose attributes are already merged into their base record's row) out of $MFT output. Default is FALSE
+    #[arg(long = "exclude-extensions")]
+    pub exclude_extensions: bool,
+
+    /// When true, write the data runs (VCN, LCN, cluster count) of every non-resident $DATA stream to *_runs.json. Requires --json. Default is FALSE
+    #[arg(long = "runs")]
+    pub data_runs: bool,
+
+    /// When true, write every $MFT record that could not be read (BAAD signature or a broken attribute chain) with its position, offset and reason to *_errors.json. Requires --json. Default is FALSE
+    #[arg(long = "errors")]
+    pub corrupt_records: bool,
+
+    /// When true, write recursive per-directory totals (file count, logical size, deleted files) to *_directories.csv, largest first. Requires --csv. Default is FALSE
+    #[arg(long = "dir-sizes")]
+    pub directory_sizes: bool,
+
+    /// When true, write $MFT entries whose sequence number stands far above their neighbours' to *_sequencechurn.csv and, for $J with -m or a volume, the files that occupied reused entries before their current record to *_entryreuse.csv. Requires --csv. Default is FALSE
+    #[arg(long = "entry-reuse")]
+    pub entry_reuse: bool,
+
+    /// When true, write USN Journal entries with raw integers (FILETIME timestamp, reason flags) and no extension or reason-name decoding, for pipelines that post-process anyway. Default is FALSE
+    #[arg(long = "raw")]
+    pub raw: bool,
+
     /// When true, include all timestamps from 0x30 attribute vs only when they differ from 0x10. Default is FALSE
     #[arg(long = "at")]
     pub all_timestamps: bool,
@@ -95,6 +230,38 @@ pub struct Cli {
     #[arg(long = "dedupe")]
     pub deduplicate: bool,
 
+    /// Field separator for CSV output: comma, tab, pipe or semicolon. Default is comma
+    #[arg(long = "delimiter", value_enum, default_value_t = CsvDelimiter::Comma, conflicts_with = "template")]
+    pub delimiter: CsvDelimiter,
+
+    /// When CSV fields are quoted: necessary (only fields containing the delimiter, quotes or line breaks), always, non-numeric or never. With never, a value containing the delimiter shifts the columns of its row. Default is necessary
+    #[arg(long = "quote", value_enum, default_value_t = CsvQuoting::Necessary, conflicts_with = "template")]
+    pub quote: CsvQuoting,
+
+    /// When true, write commented provenance lines (tool version, source, SHA-256, timezone, schema version) above the CSV header. Leave off for strict CSV consumers. Default is FALSE
+    #[arg(long = "csv-meta")]
+    pub csv_meta: bool,
+
+    /// When true, write diff-friendly CSV/JSON/JSONL: no volatile fields (source offsets) and no processing metadata (CSV preamble, processing time), so runs can be compared byte for byte. Default is FALSE
+    #[arg(long = "normalized", conflicts_with = "csv_meta")]
+    pub normalized: bool,
+
+    /// When true, refuse to write any output inside the directory tree that holds -f. Default is FALSE
+    #[arg(long = "evidence-lock")]
+    pub evidence_lock: bool,
+
+    /// When true, only check that -f and the -m, --sds and --bitmap inputs can be opened and read (live volumes need an elevated prompt or root), report the result and exit. Default is FALSE
+    #[arg(long = "check-access")]
+    pub check_access: bool,
+
+    /// When true, SHA-256 hash -f before and after processing and fail if it changed. Hashes are recorded in the JSON summary. Default is FALSE
+    #[arg(long = "verify-hash")]
+    pub verify_hash: bool,
+
+    /// When true, append a chain-of-custody record (tool version, arguments, input SHA-256 hashes, start/end times, host) to mfte-rs_audit.jsonl in each output directory. Default is FALSE
+    #[arg(long = "audit")]
+    pub audit: bool,
+
     /// Show debug information during processing
     #[arg(long = "debug")]
     pub debug: bool,
@@ -107,9 +274,477 @@ pub struct Cli {
     #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
     pub output_format: OutputFormat,
 
+    /// When true, skip the console preview of parsed records (useful for scripted runs). Default is FALSE
+    #[arg(long = "no-preview")]
+    pub no_preview: bool,
+
     /// Show progress bar for large files
     #[arg(long = "progress")]
     pub show_progress: bool,
+
+    /// Stop parsing an input after this many seconds and write the records parsed so far, noting the timeout in the summary. Applies to each input of a directory separately
+    #[arg(long = "timeout")]
+    pub timeout: Option<u64>,
+
+    /// Only parse the first N MB of $MFT, $J and $SDS data, noting the cut in the summary. Bounds memory use on unexpectedly large inputs
+    #[arg(long = "max-input-mb")]
+    pub max_input_mb: Option<u64>,
+
+    /// How inputs are read: mmap (default) maps them with sequential read-ahead hints; read copies them in with buffered reads, which is much faster on network filesystems
+    #[arg(long = "io-mode", value_enum, default_value_t = IoMode::Mmap)]
+    pub io_mode: IoMode,
+
+    /// When true, fault the whole mapping in before parsing instead of on first touch (Linux only). Requires --io-mode mmap. Default is FALSE
+    #[arg(long = "populate")]
+    pub populate: bool,
+
+    /// When true, read inputs with O_DIRECT (Linux) or FILE_FLAG_NO_BUFFERING (Windows) in large aligned chunks, bypassing the page cache. Falls back to buffered reads where unsupported. Replaces --io-mode. Default is FALSE
+    #[arg(long = "direct-io", conflicts_with_all = ["io_mode", "populate"])]
+    pub direct_io: bool,
+
+    /// Number of inputs to process at once when -f is a directory. Default is the number of CPUs
+    #[arg(long = "threads")]
+    pub threads: Option<usize>,
+
+    /// When --timeout expires for the input being processed
+    #[arg(skip)]
+    pub deadline: Option<std::time::Instant>,
+
+    /// Set on the per-input copies made for a directory, so concurrent inputs don't interleave console tables
+    #[arg(skip)]
+    pub batch_job: bool,
+
+    /// Set by `mfte-rs auto`: a volume's $Boot, $Secure:$SDS and $LogFile are processed along with $MFT and $J
+    #[arg(skip)]
+    pub all_artifacts: bool,
+}
+
+/// First argument that selects the mactime subcommand instead of artifact processing
+pub const MACTIME_SUBCOMMAND: &str = "mactime";
+
+/// Arguments of `mfte-rs mactime`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs mactime", bin_name = "mfte-rs mactime")]
+#[command(about = "Render bodyfiles as a chronological timeline grouped by day, like TSK's mactime")]
+pub struct MactimeCli {
+    /// Bodyfile(s) to read, as written by --body. Required
+    #[arg(short = 'b', long = "body", required = true, num_args = 1..)]
+    pub body_files: Vec<PathBuf>,
+
+    /// File to save the timeline to. Default is standard output
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+
+    /// Only include events on or after this date (UTC). Format is YYYY-MM-DD
+    #[arg(long = "from")]
+    pub from: Option<NaiveDate>,
+
+    /// Only include events on or before this date (UTC). Format is YYYY-MM-DD
+    #[arg(long = "to")]
+    pub to: Option<NaiveDate>,
+}
+
+impl MactimeCli {
+    /// Inclusive time range in Unix seconds
+    pub fn range(&self) -> (Option<i64>, Option<i64>) {
+        let start = self.from.and_then(|date| date.and_hms_opt(0, 0, 0)).map(|dt| dt.and_utc().timestamp());
+        let end = self.to.and_then(|date| date.and_hms_opt(23, 59, 59)).map(|dt| dt.and_utc().timestamp());
+        (start, end)
+    }
+}
+
+/// First argument that selects the whohas subcommand
+pub const WHOHAS_SUBCOMMAND: &str = "whohas";
+
+/// Arguments of `mfte-rs whohas`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs whohas", bin_name = "mfte-rs whohas")]
+#[command(about = "Report which MFT records' data runs contain the given clusters or volume offsets, including deleted records")]
+pub struct WhohasCli {
+    /// $MFT file, or an NTFS volume/block device such as /dev/sdb2 or \\.\C:. Required
+    #[arg(short = 'f', long = "file", required = true)]
+    pub file: PathBuf,
+
+    /// Cluster number(s) to look up as decimal or hex. Example: 123456 or 0x1E240. This or --offset required
+    #[arg(short = 'c', long = "cluster", num_args = 1.., required_unless_present = "offsets")]
+    pub clusters: Vec<String>,
+
+    /// Byte offset(s) from the start of the volume to look up as decimal or hex, e.g. a carving hit. Example: 505679872 or 0x1E240000
+    #[arg(short = 'o', long = "offset", num_args = 1..)]
+    pub offsets: Vec<String>,
+
+    /// Bytes per cluster, used to convert offsets. Read from the boot sector when -f is a volume; required for offsets into an extracted $MFT's volume
+    #[arg(long = "cluster-size")]
+    pub cluster_size: Option<u64>,
+}
+
+/// First argument that selects the tag subcommand
+pub const TAG_SUBCOMMAND: &str = "tag";
+
+/// Arguments of `mfte-rs tag`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs tag", bin_name = "mfte-rs tag")]
+#[command(about = "Add or remove MFT records in named bookmark sets, kept in a sidecar JSON for --export-tagged")]
+pub struct TagCli {
+    /// Bookmark sidecar JSON to update. Created if it does not exist. Required
+    #[arg(short = 't', long = "tags", required = true)]
+    pub tags_file: PathBuf,
+
+    /// Bookmark set to change. Required unless --list is used
+    #[arg(short = 's', long = "set", required_unless_present = "list")]
+    pub set: Option<String>,
+
+    /// Record(s) to bookmark as 'Entry' or 'Entry-Seq', decimal or hex. Example: 5, 624-5 or 0x270-0x5
+    #[arg(num_args = 1.., required_unless_present = "list")]
+    pub entries: Vec<String>,
+
+    /// When true, remove the records from the set instead of adding them. Default is FALSE
+    #[arg(short = 'r', long = "remove")]
+    pub remove: bool,
+
+    /// When true, list the bookmarks of --set, or every set when --set is omitted. Default is FALSE
+    #[arg(short = 'l', long = "list")]
+    pub list: bool,
+}
+
+/// First argument that selects the sdsdiff subcommand
+pub const SDSDIFF_SUBCOMMAND: &str = "sdsdiff";
+
+/// Arguments of `mfte-rs sdsdiff`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs sdsdiff", bin_name = "mfte-rs sdsdiff")]
+#[command(about = "Compare a baseline $SDS with a later one and report added, removed and changed descriptors and the files whose owner or ACLs changed")]
+pub struct SdsDiffCli {
+    /// Baseline $SDS file. Required
+    #[arg(long = "old", required = true)]
+    pub old_sds: PathBuf,
+
+    /// Later $SDS file to compare with the baseline. Required
+    #[arg(long = "new", required = true)]
+    pub new_sds: PathBuf,
+
+    /// $MFT (or NTFS volume) from the same point in time as --new, used to list the files whose permissions changed
+    #[arg(short = 'm', long = "mft")]
+    pub mft_file: Option<PathBuf>,
+
+    /// $MFT (or NTFS volume) from the same point in time as --old. With -m, files are compared through the security IDs they used at each point. Requires -m
+    #[arg(long = "old-mft", requires = "mft_file")]
+    pub old_mft_file: Option<PathBuf>,
+
+    /// Directory to save sdsdiff.csv and, with -m, permission_drift.csv to. Default is console output only
+    #[arg(long = "csv")]
+    pub csv_dir: Option<PathBuf>,
+}
+
+/// First argument that selects the auto subcommand
+pub const AUTO_SUBCOMMAND: &str = "auto";
+
+/// Arguments of `mfte-rs auto`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs auto", bin_name = "mfte-rs auto")]
+#[command(about = "Locate and process $MFT, $UsnJrnl:$J, $Boot, $Secure:$SDS and $LogFile from a mounted drive or NTFS volume in one run")]
+#[command(after_help = "Any other mfte-rs options apply to every artifact, e.g.\n  mfte-rs auto -d C: --csv C:\\out --json C:\\out --body C:\\out --bdl C")]
+pub struct AutoCli {
+    /// Drive letter of a mounted volume (C or C:, Windows only), or an NTFS volume/block device such as /dev/sdb2 or an image of one. Required
+    #[arg(short = 'd', long = "drive", required = true)]
+    pub drive: String,
+
+    /// Output and processing options, as for a single file (--csv, --json, --body, --bdl, ...)
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub options: Vec<std::ffi::OsString>,
+}
+
+impl AutoCli {
+    /// The volume to open for -d
+    pub fn volume_path(&self) -> Result<PathBuf, clap::Error> {
+        drive_volume_path(&self.drive)
+    }
+
+    /// Main arguments for the run: -f set to the volume, followed by the pass-through options
+    pub fn to_cli(&self) -> Result<Cli, clap::Error> {
+        let mut args: Vec<std::ffi::OsString> = vec!["mfte-rs".into(), "-f".into(), self.volume_path()?.into()];
+        args.extend(self.options.iter().cloned());
+
+        let mut cli = Cli::try_parse_from(args)?;
+        cli.all_artifacts = true;
+        Ok(cli)
+    }
+}
+
+/// First argument that selects the monitor subcommand
+pub const MONITOR_SUBCOMMAND: &str = "monitor";
+
+/// Arguments of `mfte-rs monitor`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs monitor", bin_name = "mfte-rs monitor")]
+#[command(about = "Poll a volume's $UsnJrnl:$J and write each new change as a JSON line, to rotating local files and/or a TCP log forwarder")]
+#[command(group(clap::ArgGroup::new("destination").required(true).multiple(true).args(["jsonl_dir", "forward"])))]
+pub struct MonitorCli {
+    /// Drive letter of a mounted volume (C or C:, Windows only), or an NTFS volume/block device such as /dev/sdb2. Required
+    #[arg(short = 'd', long = "drive", required = true)]
+    pub drive: String,
+
+    /// Directory to write <volume>_monitor.jsonl to, rotated by --rotate-mb
+    #[arg(long = "jsonl")]
+    pub jsonl_dir: Option<PathBuf>,
+
+    /// host:port of a log forwarder accepting JSON lines over TCP
+    #[arg(long = "forward")]
+    pub forward: Option<String>,
+
+    /// Constant field added to every change written or forwarded, so several hosts can share a forwarder. Can be repeated. Example: hostname=WKS-042
+    #[arg(long = "context", value_parser = parse_context)]
+    pub context: Vec<(String, String)>,
+
+    /// Lines written to --forward before each flush. Default is 500
+    #[arg(long = "batch-size", default_value_t = 500, value_parser = clap::value_parser!(u64).range(1..))]
+    pub batch_size: u64,
+
+    /// Seconds --forward may hold lines while waiting for a full batch; 0 sends every poll. Default is 0
+    #[arg(long = "flush-secs", default_value_t = 0)]
+    pub flush_secs: u64,
+
+    /// Most lines per second sent to --forward. Default is unlimited
+    #[arg(long = "max-rate", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_rate: Option<u32>,
+
+    /// Times a failed --forward batch is retried, waiting 1s, 2s, 4s, ... up to 30s between attempts. Default is 3
+    #[arg(long = "retries", default_value_t = 3)]
+    pub retries: u32,
+
+    /// Directory to spool lines to while --forward is unreachable; they are sent first once it is back. Default is to drop them
+    #[arg(long = "spool", requires = "forward")]
+    pub spool_dir: Option<PathBuf>,
+
+    /// Size in MB the spool may grow to before the newest lines are dropped. Default is 1024
+    #[arg(long = "spool-mb", default_value_t = 1024, value_parser = clap::value_parser!(u64).range(1..))]
+    pub spool_mb: u64,
+
+    /// Directory to keep <volume>_monitor_state.json in, recording the next USN to read so a restarted monitor resumes there. Default is the --jsonl directory
+    #[arg(long = "state")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Seconds between polls of $J. Default is 30
+    #[arg(long = "interval", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Size in MB at which the --jsonl file is rotated. Default is 64
+    #[arg(long = "rotate-mb", default_value_t = 64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rotate_mb: u64,
+
+    /// Number of rotated --jsonl files to keep. Default is 10
+    #[arg(long = "keep", default_value_t = 10)]
+    pub keep: usize,
+
+    /// Stop after this many polls, e.g. 1 for a scheduled task. Default is to run until stopped
+    #[arg(long = "iterations", value_parser = clap::value_parser!(u64).range(1..))]
+    pub iterations: Option<u64>,
+
+    /// When true, the first poll emits the changes already in $J instead of only those made after startup; ignored when resuming from a checkpoint. Default is FALSE
+    #[arg(long = "from-start")]
+    pub from_start: bool,
+
+    /// Only emit changes with at least one of these reasons, comma separated (e.g. FILE_CREATE,FILE_DELETE,RENAME_NEW_NAME). Default is every reason
+    #[arg(long = "reason", value_delimiter = ',', value_parser = parse_usn_reason)]
+    pub reasons: Vec<u32>,
+
+    /// Only emit changes to files with one of these extensions, comma separated (e.g. exe,dll,ps1)
+    #[arg(long = "ext", value_delimiter = ',')]
+    pub extensions: Vec<String>,
+
+    /// Only emit changes under this directory (e.g. C:\Users). Can be repeated
+    #[arg(long = "path")]
+    pub include_paths: Vec<String>,
+
+    /// Drop changes under this directory (e.g. C:\build). Can be repeated
+    #[arg(long = "exclude-path")]
+    pub exclude_paths: Vec<String>,
+
+    /// Show debug information during processing
+    #[arg(long = "debug")]
+    pub debug: bool,
+}
+
+impl MonitorCli {
+    /// The volume to open for -d
+    pub fn volume_path(&self) -> Result<PathBuf, clap::Error> {
+        drive_volume_path(&self.drive)
+    }
+}
+
+/// First argument that selects the merge subcommand
+pub const MERGE_SUBCOMMAND: &str = "merge";
+
+/// Arguments of `mfte-rs merge`
+#[derive(Parser, Clone)]
+#[command(name = "mfte-rs merge", bin_name = "mfte-rs merge")]
+#[command(about = "Combine CSV and JSON Lines outputs from many hosts into one dataset with a host column, optionally deduplicated and sorted by time")]
+pub struct MergeCli {
+    /// Output file(s) or directories to merge, each optionally prefixed with HOST= to name the host it came from. Otherwise the host is the directory's name, or the file's parent directory's name. Example: WKS-042=/cases/wks042 /cases/srv01. Required
+    #[arg(short = 'i', long = "input", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// File to save the merged dataset to, as CSV or JSON Lines by its .csv or .jsonl extension. Required
+    #[arg(short = 'o', long = "output", required = true)]
+    pub output: PathBuf,
+
+    /// Only merge files in the input directories whose name contains this text (case-insensitive). Example: _mft. for every host's $MFT output
+    #[arg(long = "match")]
+    pub name_filter: Option<String>,
+
+    /// Name of the column holding each row's host. Default is host
+    #[arg(long = "host-column", default_value = "host")]
+    pub host_column: String,
+
+    /// When true, drop rows already merged for the same host: the same record_id, or identical values for outputs without one. Default is FALSE
+    #[arg(long = "dedupe")]
+    pub dedupe: bool,
+
+    /// Column to sort the merged rows by, oldest first for timestamps; rows without a value go last. Example: created_0x10 or timestamp. Default is input order
+    #[arg(long = "sort")]
+    pub sort: Option<String>,
+}
+
+impl MergeCli {
+    /// Host name and path of each input
+    pub fn hosts(&self) -> Vec<(String, PathBuf)> {
+        self.inputs
+            .iter()
+            .map(|input| match input.split_once('=') {
+                Some((host, path)) if !host.is_empty() && !host.contains(['/', '\\']) => (host.to_string(), PathBuf::from(path)),
+                _ => {
+                    let path = PathBuf::from(input);
+                    let directory = if path.is_dir() { Some(path.as_path()) } else { path.parent() };
+                    let host = directory
+                        .and_then(|directory| std::fs::canonicalize(directory).ok())
+                        .and_then(|directory| directory.file_name().map(|name| name.to_string_lossy().into_owned()))
+                        .unwrap_or_default();
+                    (host, path)
+                }
+            })
+            .collect()
+    }
+}
+
+/// USN_REASON_* bit for a --reason name
+fn parse_usn_reason(name: &str) -> Result<u32, String> {
+    crate::ntfs::usn_journal::reason_flag(name).ok_or_else(|| {
+        let names: Vec<&str> = crate::ntfs::usn_journal::reason_names().collect();
+        format!("unknown reason '{}'; expected one of {}", name, names.join(", "))
+    })
+}
+
+/// A --record-size: a power of two from 512 to 65536
+fn parse_record_size(size: &str) -> Result<usize, String> {
+    match size.parse::<usize>() {
+        Ok(size) if crate::ntfs::mft::is_valid_record_size(size) => Ok(size),
+        _ => Err(format!("expected a power of two from 512 to 65536, e.g. 1024 or 4096, got '{}'", size)),
+    }
+}
+
+/// A --volume-serial, hex with or without 0x
+fn parse_volume_serial(serial: &str) -> Result<u64, String> {
+    let digits = serial.trim_start_matches("0x").trim_start_matches("0X");
+    u64::from_str_radix(digits, 16).map_err(|_| format!("expected a hex serial number, got '{}'", serial))
+}
+
+/// A --context `key=value` pair
+fn parse_context(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, e.g. case_id=2024-017, got '{}'", pair)),
+    }
+}
+
+/// `\\.\C:` for a drive letter, otherwise the path as given
+fn drive_volume_path(drive: &str) -> Result<PathBuf, clap::Error> {
+    let letter = drive.strip_suffix(':').unwrap_or(drive);
+    if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_alphabetic()) {
+        if !cfg!(windows) {
+            return Err(clap::Error::raw(
+                clap::error::ErrorKind::InvalidValue,
+                format!(
+                    "Drive letters are only available on Windows; pass the volume device (e.g. /dev/sdb2) instead of {}\n",
+                    drive
+                ),
+            ));
+        }
+        return Ok(PathBuf::from(format!(r"\\.\{}:", letter.to_ascii_uppercase())));
+    }
+    Ok(PathBuf::from(drive))
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IoMode {
+    /// Memory-map inputs and read them as pages are touched
+    #[default]
+    Mmap,
+    /// Read inputs into memory with buffered reads
+    Read,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsvDelimiter {
+    #[default]
+    Comma,
+    Tab,
+    Pipe,
+    Semicolon,
+}
+
+impl CsvDelimiter {
+    pub fn byte(self) -> u8 {
+        match self {
+            Self::Comma => b',',
+            Self::Tab => b'\t',
+            Self::Pipe => b'|',
+            Self::Semicolon => b';',
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsvQuoting {
+    /// Quote fields containing the delimiter, a quote or a line break
+    #[default]
+    Necessary,
+    /// Quote every field
+    Always,
+    /// Quote every field that isn't a number
+    NonNumeric,
+    /// Never quote
+    Never,
+}
+
+impl From<CsvQuoting> for csv::QuoteStyle {
+    fn from(quoting: CsvQuoting) -> Self {
+        match quoting {
+            CsvQuoting::Necessary => Self::Necessary,
+            CsvQuoting::Always => Self::Always,
+            CsvQuoting::NonNumeric => Self::NonNumeric,
+            CsvQuoting::Never => Self::Never,
+        }
+    }
+}
+
+/// Granularity for --round-times
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl TimeUnit {
+    pub fn seconds(self) -> u64 {
+        match self {
+            Self::Second => 1,
+            Self::Minute => 60,
+            Self::Hour => 60 * 60,
+            Self::Day => 24 * 60 * 60,
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -128,11 +763,30 @@ impl Cli {
     pub fn validate(&self) -> Result<(), String> {
         // Check that at least one output format is specified
         if self.json_dir.is_none()
+            && self.jsonl_dir.is_none()
             && self.csv_dir.is_none()
+            && self.sqlite_dir().is_none()
+            && self.parquet_dir().is_none()
             && self.body_dir.is_none()
+            && self.mactime_dir.is_none()
+            && self.cluster_map_dir.is_none()
+            && self.entry_map_dir.is_none()
+            && self.recover_dir.is_none()
             && self.dump_entry.is_none()
-            && self.dump_security.is_none() {
-            return Err("At least one output option must be specified (--json, --csv, --body, --de, or --ds)".to_string());
+            && self.dump_security.is_none()
+            && self.owner_sid.is_none()
+            && !self.check_access {
+            return Err("At least one output option must be specified (--json, --jsonl, --csv, --body, --mactime, --cluster-map, --entry-map, --recover, --de, --ds, or --owner-sid)".to_string());
+        }
+
+        // Check owner SID requirements
+        if self.owner_sid.is_some() && self.sds_file.is_none() {
+            return Err("--sds is required when using --owner-sid".to_string());
+        }
+
+        // Check bookmark requirements
+        if self.export_tagged.is_some() && self.tags_file.is_none() {
+            return Err("--tags is required when using --export-tagged".to_string());
         }
 
         // Check bodyfile requirements
@@ -149,22 +803,195 @@ impl Cli {
         if self.file_listing && self.csv_dir.is_none() {
             return Err("--fl requires --csv".to_string());
         }
+        if self.dump_resident && self.csv_dir.is_none() && self.json_dir.is_none() {
+            return Err("--dr requires --csv or --json".to_string());
+        }
+        if self.data_runs && self.json_dir.is_none() {
+            return Err("--runs requires --json".to_string());
+        }
+        if self.corrupt_records && self.json_dir.is_none() {
+            return Err("--errors requires --json".to_string());
+        }
+        if self.directory_sizes && self.csv_dir.is_none() {
+            return Err("--dir-sizes requires --csv".to_string());
+        }
+        if self.template.is_some() && self.csv_dir.is_none() {
+            return Err("--template requires --csv".to_string());
+        }
+        if let Some(ref template) = self.template
+            && let Err(e) = Template::parse(template) {
+            return Err(format!("Invalid --template: {}", e));
+        }
+        if self.entry_reuse && self.csv_dir.is_none() {
+            return Err("--entry-reuse requires --csv".to_string());
+        }
+        // A dumped entry or raw FILE record is for examining exact times, not for sharing
+        if (self.dump_entry.is_some() || self.dump_dir.is_some()) && (self.round_times.is_some() || self.jitter_times.is_some()) {
+            return Err("--de and --dd show exact times and cannot be used with --round-times or --jitter-times".to_string());
+        }
+        for (index, (key, _)) in self.context.iter().enumerate() {
+            if self.context[..index].iter().any(|(earlier, _)| earlier == key) {
+                return Err(format!("--context {} is given more than once", key));
+            }
+        }
 
         // Validate file exists
-        if !self.file.exists() {
-            return Err(format!("Input file does not exist: {}", self.file.display()));
+        check_input(&self.file, "Input file")?;
+
+        // Check directory (batch) requirements
+        if self.file.is_dir() {
+            if self.all_artifacts {
+                return Err("mfte-rs auto needs a drive letter or NTFS volume, not a directory".to_string());
+            }
+            if self.dump_entry.is_some() || self.dump_security.is_some() || self.dump_dir.is_some() {
+                return Err("--de, --ds and --dd need a single input file, not a directory".to_string());
+            }
+            if self.json_filename.is_some() || self.csv_filename.is_some() || self.body_filename.is_some() {
+                return Err("--jsonf, --csvf and --bodyf cannot be used when -f is a directory".to_string());
+            }
+        }
+        if self.threads == Some(0) {
+            return Err("--threads must be at least 1".to_string());
+        }
+        if self.timeout == Some(0) || self.max_input_mb == Some(0) {
+            return Err("--timeout and --max-input-mb must be greater than zero".to_string());
+        }
+        if self.populate && self.io_mode != IoMode::Mmap {
+            return Err("--populate requires --io-mode mmap".to_string());
         }
 
         // Validate MFT file if provided
         if let Some(ref mft_file) = self.mft_file {
-            if !mft_file.exists() {
-                return Err(format!("MFT file does not exist: {}", mft_file.display()));
+            check_input(mft_file, "MFT file")?;
+        }
+
+        // Validate $Bitmap file if provided
+        if let Some(ref bitmap_file) = self.bitmap_file {
+            check_input(bitmap_file, "Bitmap file")?;
+        }
+
+        // Validate anomaly model file if provided
+        if let Some(ref anomaly_model_file) = self.anomaly_model_file {
+            check_input(anomaly_model_file, "Anomaly model file")?;
+        }
+
+        // Validate process hints file if provided
+        if let Some(ref process_hints_file) = self.process_hints_file {
+            check_input(process_hints_file, "Process hints file")?;
+        }
+
+        // Validate bookmark file if provided
+        if let Some(ref tags_file) = self.tags_file {
+            check_input(tags_file, "Bookmark file")?;
+        }
+
+        // Validate SDS file if provided
+        if let Some(ref sds_file) = self.sds_file {
+            check_input(sds_file, "SDS file")?;
+        }
+
+        // Keep outputs out of the evidence tree
+        if self.evidence_lock {
+            for output_dir in self.output_dirs() {
+                crate::evidence::check_output_location(&self.file, output_dir)?;
             }
         }
 
         Ok(())
     }
 
+    /// Every directory this run may write to
+    pub fn output_dirs(&self) -> Vec<&PathBuf> {
+        [&self.json_dir, &self.jsonl_dir, &self.csv_dir, &self.body_dir, &self.mactime_dir, &self.cluster_map_dir, &self.entry_map_dir, &self.recover_dir, &self.dump_dir]
+            .into_iter()
+            .flatten()
+            .chain(self.sqlite_dir())
+            .chain(self.parquet_dir())
+            .collect()
+    }
+
+    /// Copy of these arguments for one input of a directory, with every output
+    /// directory moved into `output_subdir` so inputs with the same name don't collide
+    pub fn for_batch_input(&self, file: PathBuf, output_subdir: &std::path::Path) -> Cli {
+        let mut job = self.clone();
+        job.file = file;
+        job.no_preview = true;
+        job.batch_job = true;
+
+        for dir in [
+            &mut job.json_dir,
+            &mut job.jsonl_dir,
+            &mut job.csv_dir,
+            &mut job.body_dir,
+            &mut job.mactime_dir,
+            &mut job.cluster_map_dir,
+            &mut job.entry_map_dir,
+            &mut job.recover_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *dir = dir.join(output_subdir);
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(ref mut dir) = job.sqlite_dir {
+            *dir = dir.join(output_subdir);
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(ref mut dir) = job.parquet_dir {
+            *dir = dir.join(output_subdir);
+        }
+
+        job
+    }
+
+    /// Parser deadline for the input being processed
+    pub fn deadline(&self) -> crate::ntfs::deadline::Deadline {
+        crate::ntfs::deadline::Deadline::at(self.deadline)
+    }
+
+    /// --max-input-mb in bytes
+    pub fn max_input_bytes(&self) -> Option<u64> {
+        self.max_input_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    /// SQLite output directory; always None when built without the sqlite feature
+    pub fn sqlite_dir(&self) -> Option<&PathBuf> {
+        #[cfg(feature = "sqlite")]
+        return self.sqlite_dir.as_ref();
+        #[cfg(not(feature = "sqlite"))]
+        None
+    }
+
+    /// Parquet output directory; always None when built without the parquet feature
+    pub fn parquet_dir(&self) -> Option<&PathBuf> {
+        #[cfg(feature = "parquet")]
+        return self.parquet_dir.as_ref();
+        #[cfg(not(feature = "parquet"))]
+        None
+    }
+
+    /// Provenance preamble for CSV outputs, when --csv-meta is set
+    pub fn csv_preamble(&self) -> Option<CsvPreamble> {
+        self.csv_meta.then(|| CsvPreamble::new(&self.file))
+    }
+
+    pub fn csv_options(&self) -> CsvOptions {
+        CsvOptions {
+            delimiter: self.delimiter.byte(),
+            quote_style: self.quote.into(),
+            preamble: self.csv_preamble(),
+        }
+    }
+
+    pub fn json_options(&self) -> JsonOptions {
+        JsonOptions {
+            pretty: self.json_pretty,
+            values: self.json_values,
+            normalized: self.normalized,
+        }
+    }
+
     pub fn get_default_filename(&self, extension: &str, file_type: &str) -> String {
         let input_name = self.file
             .file_stem()
@@ -173,4 +1000,13 @@ impl Cli {
 
         format!("{}_{}.{}", input_name, file_type, extension)
     }
-}
\ No newline at end of file
+}
+
+/// An input must exist; one that can't even be checked is usually missing privileges
+fn check_input(path: &std::path::Path, what: &str) -> Result<(), String> {
+    match path.try_exists() {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("{} does not exist: {}", what, path.display())),
+        Err(e) => Err(crate::evidence::open_failure_message(path, &e)),
+    }
+}
@@ -0,0 +1,260 @@
+use crate::ntfs::filetime::{FileTime, MapTimes, TimeCoarsening};
+use crate::record::{ArtifactRecord, RecordEnricher};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// A user-supplied CSV joined against output records, e.g. entry numbers to
+/// case tags or path prefixes to asset owners:
+///
+/// ```text
+/// full_path,owner,department
+/// C:\Users\alice,Alice Smith,Finance
+/// C:\Shares\HR,HR team,HR
+/// ```
+///
+/// The first column names the record field to match; the rest are appended to
+/// every record, empty where nothing matches. Fields with `path` in their name
+/// match by path prefix, the longest prefix winning, so a row covers everything
+/// beneath it; other fields match exactly. Both compare case-insensitively.
+pub struct LookupTable {
+    key_field: String,
+    prefix: bool,
+    columns: Vec<Arc<str>>,
+    rows: HashMap<String, Vec<String>>,
+}
+
+impl LookupTable {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = ::csv::ReaderBuilder::new()
+            .trim(::csv::Trim::All)
+            .from_path(path)
+            .with_context(|| format!("Failed to open lookup table {}", path.display()))?;
+
+        let headers = reader.headers()?.clone();
+        let Some(key_field) = headers.get(0).filter(|name| !name.is_empty()) else {
+            bail!("Lookup table {} has no header row naming the field to match", path.display());
+        };
+        if headers.len() < 2 {
+            bail!("Lookup table {} has no columns to add besides {}", path.display(), key_field);
+        }
+        let prefix = key_field.to_lowercase().contains("path");
+
+        let mut rows = HashMap::new();
+        for (index, row) in reader.records().enumerate() {
+            let row = row.with_context(|| format!("Failed to read row {} of lookup table {}", index + 2, path.display()))?;
+            let key = normalize_key(&row[0], prefix);
+            let values = (1..headers.len()).map(|column| row.get(column).unwrap_or_default().to_string()).collect();
+            // The first row for a key wins, as a later duplicate is usually a mistake
+            rows.entry(key).or_insert(values);
+        }
+
+        let columns = headers.iter().skip(1).map(Arc::from).collect();
+
+        log::info!("Loaded {} rows keyed by {} from lookup table {}", rows.len(), key_field, path.display());
+        Ok(Self { key_field: key_field.to_string(), prefix, columns, rows })
+    }
+
+    /// Values to add for a record whose key field holds `key`; None when no row matches
+    fn lookup(&self, key: &str) -> Option<&[String]> {
+        let key = normalize_key(key, self.prefix);
+        if !self.prefix {
+            return self.rows.get(&key).map(Vec::as_slice);
+        }
+
+        // Try the path itself, then its file for a `file:stream` path, then each parent up to the root
+        let mut candidate = key.as_str();
+        loop {
+            if let Some(values) = self.rows.get(candidate) {
+                return Some(values);
+            }
+            match candidate.rfind(['/', ':']) {
+                Some(end) => candidate = &candidate[..end],
+                None if !candidate.is_empty() => candidate = "",
+                None => return None,
+            }
+        }
+    }
+
+    /// Append this table's columns to each record passing through the pipeline
+    pub fn into_enricher(self) -> RecordEnricher {
+        Box::new(move |record: ArtifactRecord| {
+            let key = record.field(&self.key_field);
+            let values = key.as_deref().and_then(|key| self.lookup(key));
+            let columns = self.columns.iter().enumerate().map(|(index, name)| {
+                let value = values.and_then(|values| values.get(index)).cloned().unwrap_or_default();
+                (name.clone(), value)
+            });
+            record.with_columns(columns)
+        })
+    }
+}
+
+/// Time windows in which a process is known to have run, from execution
+/// artifacts parsed by other tools (SRUM app usage, Prefetch run times):
+///
+/// ```text
+/// start,end,process,pid
+/// 2024-03-01T10:15:00Z,2024-03-01T10:17:30Z,7z.exe,4312
+/// 2024-03-01 10:16:05,2024-03-01 10:16:45,powershell.exe,
+/// ```
+///
+/// Times are UTC, as RFC 3339 or `YYYY-MM-DD HH:MM:SS[.fraction]`; `pid` is
+/// optional. Each $J change is attributed to the narrowest window containing
+/// its timestamp, which is a lead rather than proof of which process made it.
+pub struct ProcessHints {
+    /// Sorted by start
+    windows: Vec<ProcessWindow>,
+}
+
+struct ProcessWindow {
+    start: FileTime,
+    end: FileTime,
+    process: String,
+    pid: String,
+}
+
+impl ProcessHints {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = ::csv::ReaderBuilder::new()
+            .trim(::csv::Trim::All)
+            .from_path(path)
+            .with_context(|| format!("Failed to open process hints {}", path.display()))?;
+
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|header| header.eq_ignore_ascii_case(name));
+        let (Some(start), Some(end), Some(process)) = (column("start"), column("end"), column("process")) else {
+            bail!("Process hints {} need start, end and process columns", path.display());
+        };
+        let pid = column("pid");
+
+        let mut windows = Vec::new();
+        for (index, row) in reader.records().enumerate() {
+            let line = index + 2;
+            let row = row.with_context(|| format!("Failed to read row {} of process hints {}", line, path.display()))?;
+            let time = |column: usize| {
+                let text = row.get(column).unwrap_or_default();
+                parse_hint_time(text).with_context(|| format!("Invalid time '{}' in row {} of process hints {}", text, line, path.display()))
+            };
+            let (start, end) = (time(start)?, time(end)?);
+            if end < start {
+                bail!("Row {} of process hints {} ends before it starts", line, path.display());
+            }
+            windows.push(ProcessWindow {
+                start,
+                end,
+                process: row.get(process).unwrap_or_default().to_string(),
+                pid: pid.and_then(|pid| row.get(pid)).unwrap_or_default().to_string(),
+            });
+        }
+        windows.sort_by_key(|window| window.start);
+
+        log::info!("Loaded {} process windows from {}", windows.len(), path.display());
+        Ok(Self { windows })
+    }
+
+    /// The narrowest window containing `time`
+    fn lookup(&self, time: FileTime) -> Option<&ProcessWindow> {
+        let started = self.windows.partition_point(|window| window.start <= time);
+        self.windows[..started]
+            .iter()
+            .filter(|window| time <= window.end)
+            .min_by_key(|window| window.end.raw() - window.start.raw())
+    }
+
+    /// Add `likely_process` and `likely_process_pid` columns to $J changes
+    pub fn into_enricher(self) -> RecordEnricher {
+        let names: [Arc<str>; 2] = ["likely_process".into(), "likely_process_pid".into()];
+        Box::new(move |record: ArtifactRecord| {
+            let window = usn_timestamp(&record).and_then(|time| self.lookup(time));
+            let (process, pid) = window.map_or_else(Default::default, |window| (window.process.clone(), window.pid.clone()));
+            let [process_name, pid_name] = names.clone();
+            record.with_columns([(process_name, process), (pid_name, pid)])
+        })
+    }
+}
+
+/// Timestamp of a $J change, parsed or --raw
+fn usn_timestamp(record: &ArtifactRecord) -> Option<FileTime> {
+    match record {
+        ArtifactRecord::Usn(entry) => Some(entry.timestamp),
+        ArtifactRecord::UsnRaw(entry) => Some(FileTime(entry.timestamp)),
+        ArtifactRecord::Enriched(record, _) => usn_timestamp(record),
+        _ => None,
+    }
+}
+
+/// RFC 3339, or a UTC date and time separated by a space or 'T'
+fn parse_hint_time(text: &str) -> Option<FileTime> {
+    let datetime = DateTime::parse_from_rfc3339(text).map(|datetime| datetime.with_timezone(&Utc)).ok().or_else(|| {
+        ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .map(|datetime| datetime.and_utc())
+    })?;
+    Some(FileTime::from_datetime(datetime))
+}
+
+/// Add a `record_id` column, see [`ArtifactRecord::record_id`]
+pub fn record_id_enricher(volume_serial_number: u64) -> RecordEnricher {
+    let name: Arc<str> = Arc::from("record_id");
+    Box::new(move |record: ArtifactRecord| {
+        let id = record.record_id(volume_serial_number);
+        record.with_columns([(name.clone(), id)])
+    })
+}
+
+/// --context `key=value` pairs as columns
+pub fn context_columns(pairs: &[(String, String)]) -> Vec<(Arc<str>, String)> {
+    pairs.iter().map(|(key, value)| (Arc::from(key.as_str()), value.clone())).collect()
+}
+
+/// Add the same `key=value` columns to every record, e.g. the host and case a
+/// dataset came from
+pub fn context_enricher(pairs: &[(String, String)]) -> RecordEnricher {
+    let columns = context_columns(pairs);
+    Box::new(move |record: ArtifactRecord| record.with_columns(columns.iter().cloned()))
+}
+
+/// Round and shift every timestamp of each record, see [`TimeCoarsening`]
+pub fn time_coarsening_enricher(coarsening: TimeCoarsening) -> RecordEnricher {
+    Box::new(move |mut record: ArtifactRecord| {
+        record.map_times(|time| coarsening.apply(time));
+        record
+    })
+}
+
+/// The --jitter-times offset for this run: random within `max_seconds` either
+/// way, drawn once so every artifact of a volume moves by the same amount
+pub fn run_time_shift(max_seconds: u64) -> i64 {
+    static SHIFT: OnceLock<i64> = OnceLock::new();
+    *SHIFT.get_or_init(|| {
+        let seed = Sha256::new()
+            .chain_update(Utc::now().timestamp_nanos_opt().unwrap_or_default().to_le_bytes())
+            .chain_update(std::process::id().to_le_bytes())
+            .finalize();
+        let random = u64::from_le_bytes(seed[..8].try_into().unwrap());
+        let span = max_seconds.min(i64::MAX as u64 / 2) as i128;
+        (random as i128 % (2 * span + 1) - span) as i64
+    })
+}
+
+/// Lowercase keys; paths as `/`-separated components without a drive letter or
+/// surrounding slashes, so `C:\Users\` and `/Users` compare equal
+fn normalize_key(key: &str, path: bool) -> String {
+    let key = key.trim().to_lowercase();
+    if !path {
+        return key;
+    }
+    let key = key.replace('\\', "/");
+    let key = match key.as_bytes() {
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => &key[2..],
+        _ => &key[..],
+    };
+    key.trim_matches('/').to_string()
+}
@@ -0,0 +1,346 @@
+use crate::cli::IoMode;
+use memmap2::{Mmap, MmapOptions};
+use serde::Serialize;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const HASH_BUFFER_SIZE: usize = 1 << 20;
+
+/// Linux O_NOATIME: reading evidence must not update its access time
+#[cfg(target_os = "linux")]
+const O_NOATIME: i32 = 0o1000000;
+
+/// Linux O_DIRECT, whose value differs between architectures
+#[cfg(all(target_os = "linux", any(target_arch = "aarch64", target_arch = "arm")))]
+const O_DIRECT: i32 = 0o200000;
+#[cfg(all(target_os = "linux", not(any(target_arch = "aarch64", target_arch = "arm"))))]
+const O_DIRECT: i32 = 0o40000;
+
+/// Windows FILE_FLAG_NO_BUFFERING
+#[cfg(windows)]
+const FILE_FLAG_NO_BUFFERING: u32 = 0x20000000;
+
+/// Size of each --direct-io read; a multiple of every sector size
+const DIRECT_IO_CHUNK_SIZE: usize = 8 << 20;
+
+/// Buffer and read alignment unbuffered I/O needs, at least the sector size
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Windows ERROR_SHARING_VIOLATION, returned for metadata files the running system holds open
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+/// Before/after hashes proving an input was not altered while it was processed
+#[derive(Debug, Clone, Serialize)]
+pub struct EvidenceVerification {
+    pub algorithm: &'static str,
+    pub hash_before: String,
+    pub hash_after: String,
+    pub unaltered: bool,
+}
+
+/// Open an input strictly read-only
+pub fn open_read_only<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    let path = path.as_ref();
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        // O_NOATIME is refused for files we do not own, so fall back to a plain read-only open
+        if let Ok(file) = OpenOptions::new().read(true).custom_flags(O_NOATIME).open(path) {
+            return Ok(file);
+        }
+    }
+
+    OpenOptions::new().read(true).open(path)
+}
+
+/// Contents of an input, either mapped or read into memory according to --io-mode
+pub enum InputData {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl InputData {
+    /// The first `length` bytes as an owned buffer; a buffered input is handed
+    /// over without another copy
+    pub fn into_vec(self, length: usize) -> Vec<u8> {
+        match self {
+            InputData::Mapped(mmap) => mmap[..length.min(mmap.len())].to_vec(),
+            InputData::Buffered(mut data) => {
+                data.truncate(length);
+                data
+            }
+        }
+    }
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputData::Mapped(mmap) => mmap,
+            InputData::Buffered(data) => data,
+        }
+    }
+}
+
+/// Bring an opened input into memory.
+///
+/// Mappings are advised for a sequential scan so the kernel reads ahead instead
+/// of faulting page by page; `populate` additionally faults the whole mapping in
+/// up front. Buffered reads avoid page faults entirely, which is much faster on
+/// network filesystems.
+pub fn load_input(file: &File, mode: IoMode, populate: bool) -> io::Result<InputData> {
+    match mode {
+        IoMode::Mmap => {
+            let mut options = MmapOptions::new();
+            if populate {
+                options.populate();
+            }
+            let mmap = unsafe { options.map(file)? };
+
+            #[cfg(unix)]
+            {
+                use memmap2::Advice;
+
+                // Hints only; a kernel that rejects them still serves the mapping
+                for advice in [Advice::Sequential, Advice::WillNeed] {
+                    if let Err(e) = mmap.advise(advice) {
+                        log::debug!("madvise({:?}) failed: {}", advice, e);
+                    }
+                }
+            }
+
+            Ok(InputData::Mapped(mmap))
+        }
+        IoMode::Read => {
+            let mut data = Vec::with_capacity(file.metadata().map_or(0, |m| m.len() as usize));
+            let mut reader = file;
+            reader.read_to_end(&mut data)?;
+            Ok(InputData::Buffered(data))
+        }
+    }
+}
+
+/// Read a whole input around the page cache (O_DIRECT / FILE_FLAG_NO_BUFFERING)
+/// in large aligned chunks, so mass processing doesn't evict everything else a
+/// collection server has cached. Fails where the platform or filesystem has no
+/// unbuffered reads.
+pub fn read_direct<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut file = open_unbuffered(path.as_ref())?;
+    let mut data = Vec::with_capacity(file.metadata().map_or(0, |m| m.len() as usize));
+
+    let mut buffer = vec![0u8; DIRECT_IO_CHUNK_SIZE + DIRECT_IO_ALIGNMENT];
+    let start = buffer.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+    let chunk = &mut buffer[start..start + DIRECT_IO_CHUNK_SIZE];
+
+    loop {
+        let read = file.read(chunk)?;
+        data.extend_from_slice(&chunk[..read]);
+        // Only the read that reaches the end of the file is short of alignment
+        if read == 0 || !read.is_multiple_of(DIRECT_IO_ALIGNMENT) {
+            break;
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(target_os = "linux")]
+fn open_unbuffered(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if let Ok(file) = OpenOptions::new().read(true).custom_flags(O_DIRECT | O_NOATIME).open(path) {
+        return Ok(file);
+    }
+    OpenOptions::new().read(true).custom_flags(O_DIRECT).open(path)
+}
+
+#[cfg(windows)]
+fn open_unbuffered(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    OpenOptions::new().read(true).custom_flags(FILE_FLAG_NO_BUFFERING).open(path)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn open_unbuffered(_path: &Path) -> io::Result<File> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "direct I/O is only available on Linux and Windows"))
+}
+
+/// SHA-256 of a file or device, read through the same read-only path as parsing
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = open_read_only(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// MD5, SHA-1 and SHA-256 of content written out of the evidence, as lowercase hex
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentHashes {
+    pub md5: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+pub fn hash_content(content: &[u8]) -> ContentHashes {
+    ContentHashes {
+        md5: hex::encode(Md5::digest(content)),
+        sha1: hex::encode(Sha1::digest(content)),
+        sha256: hex::encode(Sha256::digest(content)),
+    }
+}
+
+/// SHA-256 of an input, computed once per run and shared by every provenance record
+pub fn input_sha256<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    static HASHES: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+
+    let path = path.as_ref();
+    let hashes = HASHES.get_or_init(Default::default);
+    if let Some(hash) = hashes.lock().unwrap().get(path) {
+        return Ok(hash.clone());
+    }
+
+    let hash = sha256_file(path)?;
+    hashes.lock().unwrap().insert(path.to_path_buf(), hash.clone());
+    Ok(hash)
+}
+
+/// Hash an input again and compare it against the hash taken before processing
+pub fn verify_unaltered<P: AsRef<Path>>(path: P, hash_before: String) -> io::Result<EvidenceVerification> {
+    let hash_after = sha256_file(path)?;
+    Ok(EvidenceVerification {
+        algorithm: "SHA-256",
+        unaltered: hash_before == hash_after,
+        hash_before,
+        hash_after,
+    })
+}
+
+/// Message for an input that can't be opened, with what to do about it when the
+/// cause is missing privileges or a file the running system has locked
+pub fn open_failure_message(path: &Path, error: &io::Error) -> String {
+    #[cfg(windows)]
+    {
+        if error.raw_os_error() == Some(ERROR_SHARING_VIOLATION) {
+            return format!(
+                "{} is locked by the running system; read it from the volume instead (-f \\\\.\\C: or mfte-rs auto -d C:)",
+                path.display()
+            );
+        }
+    }
+
+    if error.kind() != io::ErrorKind::PermissionDenied {
+        return format!("Failed to open {}: {}", path.display(), error);
+    }
+
+    if !is_device(path) {
+        return format!("Permission denied reading {}; check the file's permissions or run elevated", path.display());
+    }
+    if cfg!(windows) {
+        format!(
+            "Access denied to {}: live volumes and shadow copies can only be read from an elevated prompt (Run as administrator)",
+            path.display()
+        )
+    } else {
+        format!(
+            "Permission denied reading {}: block devices need root (sudo) or membership of the group that owns the device (usually disk)",
+            path.display()
+        )
+    }
+}
+
+/// Whether an input is a raw device or volume handle rather than a file
+fn is_device(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        std::fs::metadata(path)
+            .map(|metadata| metadata.file_type().is_block_device() || metadata.file_type().is_char_device())
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        let path = path.to_string_lossy();
+        path.starts_with(r"\\.\") || path.starts_with(r"\\?\GLOBALROOT")
+    }
+}
+
+/// Open an input and read its first sector, as parsing would; returns the input's size
+pub fn check_access(path: &Path) -> Result<u64, String> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = open_read_only(path).map_err(|e| open_failure_message(path, &e))?;
+    io::copy(&mut (&mut file).take(512), &mut io::sink()).map_err(|e| open_failure_message(path, &e))?;
+    file.seek(SeekFrom::End(0)).map_err(|e| open_failure_message(path, &e))
+}
+
+/// Refuse output directories that fall inside the directory tree holding the input
+pub fn check_output_location(input: &Path, output_dir: &Path) -> Result<(), String> {
+    let input = input
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve input path {}: {}", input.display(), e))?;
+    let Some(evidence_root) = input.parent() else {
+        return Ok(());
+    };
+
+    let output = resolve_existing(output_dir)
+        .map_err(|e| format!("Cannot resolve output path {}: {}", output_dir.display(), e))?;
+
+    if output.starts_with(evidence_root) {
+        return Err(format!(
+            "Output directory {} is inside the evidence directory {}; choose a location outside it",
+            output_dir.display(),
+            evidence_root.display()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Canonicalize the nearest existing ancestor, since output directories are created on demand
+fn resolve_existing(path: &Path) -> io::Result<PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    let mut missing = Vec::new();
+    let mut current = absolute.as_path();
+    loop {
+        match current.canonicalize() {
+            Ok(resolved) => {
+                return Ok(missing.iter().rev().fold(resolved, |acc, part| acc.join(part)));
+            }
+            Err(e) => {
+                let (Some(parent), Some(name)) = (current.parent(), current.file_name()) else {
+                    return Err(e);
+                };
+                missing.push(name.to_os_string());
+                current = parent;
+            }
+        }
+    }
+}
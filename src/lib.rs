@@ -1,3 +1,12 @@
+pub mod artifact;
+pub mod bookmarks;
+pub mod cli;
+pub mod enrichment;
+pub mod evidence;
+pub mod ntfs;
+pub mod output;
+pub mod record;
+
 // Helper function to get filename with proper borrowing
 pub fn get_filename_with_default(
     provided: Option<&str>,
@@ -7,4 +16,4 @@ pub fn get_filename_with_default(
         Some(name) => name.to_string(),
         None => default_fn(),
     }
-}
\ No newline at end of file
+}
@@ -1,26 +1,58 @@
-mod cli;
-mod ntfs;
-mod output;
-
 fn get_filename_with_default(provided: Option<&str>, default: String) -> String {
     provided.map(|s| s.to_string()).unwrap_or(default)
 }
 
-use cli::{Cli, OutputFormat};
-use ntfs::{FileType, *};
-use output::*;
+use mfte_rs::artifact::{self, ArtifactParser, FnParser, InputInfo, ParserRegistry};
+use mfte_rs::bookmarks;
+use mfte_rs::enrichment::{self, LookupTable};
+use mfte_rs::cli::{
+    AutoCli, Cli, IoMode, MactimeCli, MergeCli, MonitorCli, OutputFormat, SdsDiffCli, TagCli, WhohasCli,
+    AUTO_SUBCOMMAND, MACTIME_SUBCOMMAND, MERGE_SUBCOMMAND, MONITOR_SUBCOMMAND, SDSDIFF_SUBCOMMAND, TAG_SUBCOMMAND,
+    WHOHAS_SUBCOMMAND,
+};
+use mfte_rs::evidence;
+use mfte_rs::ntfs::filetime::MapTimes;
+use mfte_rs::ntfs::{self, FileType, *};
+use mfte_rs::output::*;
+use mfte_rs::record::{self, ArtifactRecord, RecordFilter, RecordPipeline};
 
 use anyhow::{Context, Result};
 use clap::Parser;
 use log::{error, info, warn};
 use memmap2::Mmap;
-use std::fs::File;
-use std::path::Path;
-use std::time::Instant;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Number of records shown in the console preview
+const PREVIEW_LIMIT: usize = 20;
+
+// Exit code when --timeout cut processing short; partial outputs were still written
+const TIMEOUT_EXIT_CODE: i32 = 2;
 
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let args: Vec<OsString> = std::env::args_os().collect();
+    match args.get(1).and_then(|arg| arg.to_str()) {
+        Some(MACTIME_SUBCOMMAND) => return run_mactime(&MactimeCli::parse_from(&args[1..])),
+        Some(WHOHAS_SUBCOMMAND) => return run_whohas(&WhohasCli::parse_from(&args[1..])),
+        Some(TAG_SUBCOMMAND) => return run_tag(&TagCli::parse_from(&args[1..])),
+        Some(SDSDIFF_SUBCOMMAND) => return run_sdsdiff(&SdsDiffCli::parse_from(&args[1..])),
+        Some(MONITOR_SUBCOMMAND) => return run_monitor(&MonitorCli::parse_from(&args[1..])),
+        Some(MERGE_SUBCOMMAND) => return run_merge(&MergeCli::parse_from(&args[1..])),
+        _ => {}
+    }
+
+    let cli = match args.get(1).and_then(|arg| arg.to_str()) {
+        // `auto` is an ordinary volume run that also processes $Boot, $SDS and $LogFile
+        Some(AUTO_SUBCOMMAND) => AutoCli::parse_from(&args[1..]).to_cli().unwrap_or_else(|e| e.exit()),
+        _ => Cli::parse_from(args),
+    };
 
     // Initialize logger
     let log_level = if cli.trace {
@@ -31,362 +63,2130 @@ fn main() -> Result<()> {
         "info"
     };
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
-        .init();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
+        .init();
+
+    // Validate command line arguments
+    if let Err(e) = cli.validate() {
+        error!("Validation error {}: {}", ErrorCode::InvalidOption, e);
+        std::process::exit(1);
+    }
+
+    if cli.check_access {
+        return run_access_check(&cli);
+    }
+
+    let registry = builtin_registry();
+
+    // A directory is a triage folder: process every artifact found in it
+    if cli.file.is_dir() {
+        return run_batch(&cli, &registry);
+    }
+
+    let start_time = Instant::now();
+
+    // Determine file type
+    // Missing privileges are reported with what to do about them rather than a bare OS error
+    let input = match InputInfo::read(&cli.file) {
+        Ok(input) => input,
+        Err(e) => {
+            error!("{}", evidence::open_failure_message(&cli.file, &e));
+            std::process::exit(1);
+        }
+    };
+    let Some(parser) = registry.detect(&input) else {
+        error!("{}", artifact::unrecognized_reason(&cli.file, &input));
+        std::process::exit(1);
+    };
+    info!("Detected file type: {}", parser.description());
+    if cli.all_artifacts && parser.name() != "volume" {
+        error!("Not an NTFS volume: {}", cli.file.display());
+        std::process::exit(1);
+    }
+
+    let result = process_input(&cli, parser);
+    let processing_time = start_time.elapsed();
+
+    match result {
+        Ok(ref summary) if is_altered(summary) => std::process::exit(1),
+        Ok(ref summary) if summary.timed_out => std::process::exit(TIMEOUT_EXIT_CODE),
+        Ok(_) => {
+            info!("Processing completed successfully in {} ms", processing_time.as_millis());
+        }
+        Err(e) => {
+            error!("Processing failed: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// --check-access: open and read every input without processing anything
+fn run_access_check(cli: &Cli) -> Result<()> {
+    let inputs = [
+        ("-f", Some(&cli.file)),
+        ("-m", cli.mft_file.as_ref()),
+        ("--sds", cli.sds_file.as_ref()),
+        ("--bitmap", cli.bitmap_file.as_ref()),
+    ];
+
+    let mut failed = false;
+    for (option, path) in inputs {
+        let Some(path) = path else {
+            continue;
+        };
+        if path.is_dir() {
+            println!("OK    {} {} (directory)", option, path.display());
+            continue;
+        }
+        match evidence::check_access(path) {
+            Ok(size) => println!("OK    {} {} ({} bytes readable)", option, path.display(), size),
+            Err(message) => {
+                println!("FAIL  {} {}", option, message);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// True when --verify-hash found the input changed while it was processed
+fn is_altered(summary: &json::AnalysisSummary) -> bool {
+    summary.evidence.as_ref().is_some_and(|v| !v.unaltered)
+}
+
+/// Hash, process and audit one input, writing its summary
+fn process_input(cli: &Cli, parser: &dyn ArtifactParser) -> Result<json::AnalysisSummary> {
+    let start_time = Instant::now();
+
+    // The --timeout clock starts per input, so each input of a directory gets the full budget
+    let timed_cli;
+    let cli = match cli.timeout {
+        Some(seconds) => {
+            let mut with_deadline = cli.clone();
+            with_deadline.deadline = Some(start_time + Duration::from_secs(seconds));
+            timed_cli = with_deadline;
+            &timed_cli
+        }
+        None => cli,
+    };
+
+    // Hash the input up front so any change during processing can be detected
+    let hash_before = if cli.verify_hash {
+        info!("Hashing input before processing: {}", cli.file.display());
+        Some(evidence::input_sha256(&cli.file)
+            .with_context(|| format!("Failed to hash input: {}", cli.file.display()))?)
+    } else {
+        None
+    };
+
+    // Open the chain-of-custody record before any output is written
+    let mut audit_record = cli.audit.then(|| {
+        let mut inputs = vec![("file", cli.file.clone())];
+        if let Some(ref mft_file) = cli.mft_file {
+            inputs.push(("mft", mft_file.clone()));
+        }
+        if let Some(ref sds_file) = cli.sds_file {
+            inputs.push(("sds", sds_file.clone()));
+        }
+        let record = audit::AuditRecord::begin(inputs, cli.output_dirs().into_iter().cloned().collect());
+        if record.outputs.is_empty() {
+            warn!("--audit has no output directory to write {} to", audit::AUDIT_LOG_NAME);
+        }
+        record
+    });
+
+    // Process file based on type
+    let result = parser.process(cli);
+
+    let processing_time = start_time.elapsed();
+
+    let result = result.and_then(|mut summary| {
+        // Timing differs on every run, so normalized output leaves it at zero
+        if !cli.normalized {
+            summary.processing_time_ms = processing_time.as_millis();
+        }
+
+        if summary.timed_out {
+            warn!("Stopped at the {} s --timeout; results for {} are partial", cli.timeout.unwrap_or_default(), cli.file.display());
+            summary.warnings.push(format!(
+                "Parsing stopped at the {} s --timeout; results are partial",
+                cli.timeout.unwrap_or_default()
+            ));
+        }
+
+        if let Some(hash_before) = hash_before {
+            let verification = evidence::verify_unaltered(&cli.file, hash_before)
+                .with_context(|| format!("Failed to hash input: {}", cli.file.display()))?;
+            if verification.unaltered {
+                info!("Input unaltered during processing (SHA-256 {})", verification.hash_after);
+            } else {
+                error!(
+                    "Input changed during processing! SHA-256 before {}, after {}",
+                    verification.hash_before, verification.hash_after
+                );
+            }
+            summary.evidence = Some(verification);
+        }
+
+        write_summary(cli, parser, &summary)?;
+        Ok(summary)
+    });
+
+    if let Some(ref mut record) = audit_record {
+        record.evidence = result.as_ref().ok().and_then(|summary| summary.evidence.clone());
+        let failure = match result {
+            Err(ref e) => Some(format!("{:#}", e)),
+            Ok(ref summary) if is_altered(summary) => Some("Input changed during processing".to_string()),
+            Ok(_) => None,
+        };
+        record.finish(failure);
+        match record.append_to_outputs() {
+            Ok(paths) => paths.iter().for_each(|path| info!("Audit record appended to: {}", path.display())),
+            Err(e) => error!("Failed to write audit log: {}", e),
+        }
+    }
+
+    result
+}
+
+/// Process every recognized artifact under a directory on up to --threads
+/// workers, then report a consolidated summary.
+///
+/// Outputs mirror the directory layout below the root, so `triage/C/$MFT`
+/// writes into `<output>/C/`.
+fn run_batch(cli: &Cli, registry: &ParserRegistry) -> Result<()> {
+    let start_time = Instant::now();
+
+    let inputs = collect_batch_inputs(cli, registry)?;
+    if inputs.is_empty() {
+        warn!("No recognized artifacts found under {}", cli.file.display());
+        return Ok(());
+    }
+
+    let threads = cli.threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .min(inputs.len());
+    info!("Processing {} inputs from {} on {} thread(s)", inputs.len(), cli.file.display(), threads);
+
+    let next_input = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<json::BatchInputSummary>>> = Mutex::new((0..inputs.len()).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next_input.fetch_add(1, Ordering::Relaxed);
+                let Some((path, parser)) = inputs.get(index) else {
+                    break;
+                };
+                let position = format!("[{}/{}]", index + 1, inputs.len());
+                let relative = path.strip_prefix(&cli.file).unwrap_or(path);
+                info!("{} Processing {} as {}", position, relative.display(), parser.description());
+
+                let output_subdir = relative.parent().unwrap_or(Path::new(""));
+                let job = cli.for_batch_input(path.clone(), output_subdir);
+                let result = process_input(&job, *parser);
+
+                let (status, error) = match result {
+                    Ok(ref summary) if is_altered(summary) => ("altered", Some("Input changed during processing".to_string())),
+                    Ok(ref summary) if summary.timed_out => ("timeout", Some("Stopped at --timeout; results are partial".to_string())),
+                    Ok(_) => ("ok", None),
+                    Err(ref e) => ("failed", Some(format!("{:#}", e))),
+                };
+                match error {
+                    Some(ref e) => error!("{} {} {}: {}", position, relative.display(), status, e),
+                    None => info!("{} Finished {}", position, relative.display()),
+                }
+
+                results.lock().unwrap()[index] = Some(json::BatchInputSummary {
+                    input: relative.display().to_string(),
+                    file_type: parser.name().to_string(),
+                    status,
+                    error,
+                    error_code: result.as_ref().err().and_then(error_code),
+                    summary: result.ok(),
+                });
+            });
+        }
+    });
+
+    let batch = json::BatchSummary {
+        root: cli.file.display().to_string(),
+        threads,
+        processing_time_ms: if cli.normalized { 0 } else { start_time.elapsed().as_millis() },
+        inputs: results.into_inner().unwrap().into_iter().flatten().collect(),
+    };
+    table::TableOutput::print_batch_summary(&batch);
+
+    if let Some(ref json_dir) = cli.json_dir {
+        let output_path = json_dir.join("batch_summary.json");
+        json::JsonOutput::write_batch_summary(&batch, &output_path)?;
+        info!("Batch summary written to: {}", output_path.display());
+    }
+
+    if batch.inputs.iter().any(|input| input.status != "ok") {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Every file under the -f directory that some parser recognizes, in path
+/// order; output directories inside it are skipped
+fn collect_batch_inputs<'a>(cli: &Cli, registry: &'a ParserRegistry) -> Result<Vec<(PathBuf, &'a dyn ArtifactParser)>> {
+    let output_dirs = cli.output_dirs();
+    let mut pending = vec![cli.file.clone()];
+    let mut files = Vec::new();
+
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if output_dirs.iter().any(|output| path.starts_with(output)) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    let mut inputs = Vec::new();
+    for path in files {
+        let parser = InputInfo::read(&path).ok().and_then(|input| registry.detect(&input));
+        match parser {
+            Some(parser) => inputs.push((path, parser)),
+            None => log::debug!("Skipping unrecognized file: {}", path.display()),
+        }
+    }
+    Ok(inputs)
+}
+
+/// Every artifact type this build understands, in tie-breaking order
+fn builtin_registry() -> ParserRegistry {
+    let mut registry = ParserRegistry::new();
+    registry.register(FnParser {
+        name: "mft",
+        description: "MFT",
+        detect: ntfs::mft::detect,
+        process: process_mft,
+    });
+    registry.register(FnParser {
+        name: "i30",
+        description: "I30",
+        detect: ntfs::i30::detect,
+        process: process_i30,
+    });
+    registry.register(FnParser {
+        name: "boot",
+        description: "Boot",
+        detect: ntfs::boot::detect,
+        process: process_boot,
+    });
+    registry.register(FnParser {
+        name: "volume",
+        description: "Volume",
+        detect: ntfs::volume::detect,
+        process: process_volume,
+    });
+    registry.register(FnParser {
+        name: "sds",
+        description: "SDS",
+        detect: ntfs::sds::detect,
+        process: process_sds,
+    });
+    registry.register(FnParser {
+        name: "usn",
+        description: "USN Journal",
+        detect: ntfs::usn_journal::detect,
+        process: process_usn_journal,
+    });
+    registry.register(FnParser {
+        name: "logfile",
+        description: "LogFile",
+        detect: ntfs::logfile::detect,
+        process: process_logfile,
+    });
+    registry
+}
+
+fn process_logfile(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing LogFile: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+
+    let file_size = input.len() as u64;
+    let (length, limit_notice) = input_limit(cli, input.len(), "$LogFile");
+    let lookup_mft = cli.mft_file.as_deref().map(parse_mft_for_lookup).transpose()?;
+    let mut summary = process_logfile_data(cli, input.into_vec(length), lookup_mft)?;
+    summary.file_size = file_size;
+    summary.warnings.extend(limit_notice);
+    Ok(summary)
+}
+
+/// `lookup_mft` resolves parent paths and whether each carved name is still in use
+fn process_logfile_data(cli: &Cli, data: Vec<u8>, lookup_mft: Option<mft::MftParser>) -> Result<json::AnalysisSummary> {
+    let file_size = data.len() as u64;
+    let mut parser = logfile::LogFileParser::new(data).with_deadline(cli.deadline());
+    parser.parse()?;
+
+    if let Some(ref mft) = lookup_mft {
+        parser.resolve_with_mft(mft);
+    }
+
+    let names = parser.get_names();
+    info!("Carved {} file names from $LogFile", names.len());
+
+    write_outputs(cli, names.iter().cloned(), "logfile", true, false)?;
+    print_preview(cli, &preview_records(cli, names), "$LogFile names", Some(PREVIEW_LIMIT), table::TableOutput::print_logfile_names)?;
+
+    let mut summary = json::AnalysisSummary::new(FileType::LogFile, file_size, names.len());
+    summary.errors_encountered = parser.get_error_count();
+    summary.count_errors(ErrorCode::Fixup, parser.get_error_count());
+    summary.timed_out = parser.timed_out();
+    if parser.get_error_count() > 0 {
+        summary.warnings.push(format!(
+            "{} of {} RCRD pages failed their update sequence check",
+            parser.get_error_count(),
+            parser.get_page_count()
+        ));
+    }
+    let gone = names.iter().filter(|name| name.in_mft == Some(false)).count();
+    if gone > 0 {
+        summary.warnings.push(format!(
+            "{} $LogFile names have no in-use $MFT record in their directory (recently deleted or renamed)",
+            gone
+        ));
+    }
+
+    Ok(summary)
+}
+
+fn process_mft(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing MFT file: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+
+    let bitmap = match cli.bitmap_file {
+        Some(ref bitmap_path) => {
+            let bits = std::fs::read(bitmap_path)
+                .with_context(|| format!("Failed to read bitmap file: {}", bitmap_path.display()))?;
+            Some(bitmap::ClusterBitmap::new(bits))
+        }
+        None => None,
+    };
+
+    // Only the part --max-input-mb allows is copied out of the input
+    let file_size = input.len() as u64;
+    let (length, limit_notice) = input_limit(cli, input.len(), "$MFT");
+    let mut data = input.into_vec(length);
+
+    // Extracts copied raw out of a compressed folder, or as a WOF-compressed
+    // stream, are decompressed before parsing
+    let mut unwrap_notice = None;
+    if let Some(wrapper) = ntfs::compression::Wrapper::detect(&data, b"FILE") {
+        let record_size = cli.record_size.unwrap_or(mft::DEFAULT_RECORD_SIZE);
+        let unwrapped = wrapper.unwrap(&data, record_size).with_context(|| {
+            format!("{} looks like a $MFT wrapped in {} but could not be decompressed", cli.file.display(), wrapper.description())
+        })?;
+        let notice = format!(
+            "{} was wrapped in {}; decompressed {} bytes to {}",
+            cli.file.display(),
+            wrapper.description(),
+            data.len(),
+            unwrapped.len()
+        );
+        warn!("{}", notice);
+        unwrap_notice = Some(notice);
+        data = unwrapped;
+    }
+
+    let mut summary = process_mft_data(cli, data, bitmap, None)?;
+    summary.file_size = file_size;
+    summary.warnings.extend(limit_notice);
+    summary.warnings.extend(unwrap_notice);
+    Ok(summary)
+}
+
+/// `volume` is the volume the $MFT was read from, if any, for --recover to read
+/// file content from
+fn process_mft_data(
+    cli: &Cli,
+    data: Vec<u8>,
+    bitmap: Option<bitmap::ClusterBitmap>,
+    mut volume: Option<&mut volume::VolumeReader>,
+) -> Result<json::AnalysisSummary> {
+    let file_size = data.len() as u64;
+
+    if let Some(ref bitmap) = bitmap {
+        info!("Checking deleted file clusters against a $Bitmap of {} clusters", bitmap.cluster_count());
+    }
+    let volume_clusters = bitmap.as_ref().map(|bitmap| bitmap.cluster_count());
+    let free_space = bitmap.as_ref().and_then(|bitmap| wiping::free_space_finding(bitmap, bitmap.cluster_count()));
+
+    let mut parser = mft::MftParser::new(data)
+        .with_record_size(cli.record_size)
+        .with_hard_link_rows(cli.hard_links)
+        .with_short_names(cli.include_short_names)
+        .with_bitmap(bitmap)
+        .with_anomaly_model(load_anomaly_model(cli)?)
+        .with_deadline(cli.deadline());
+    parser.parse()?;
+
+    if let Some(ref drive_letter) = cli.body_drive_letter {
+        parser.apply_drive_letter(drive_letter);
+    }
+
+    let records = parser.get_records();
+    info!("Parsed {} MFT records", records.len());
+
+    let mut summary = json::AnalysisSummary::new(FileType::Mft, file_size, records.len());
+    summary.errors_encountered = parser.get_error_count();
+    summary.timed_out = parser.timed_out();
+
+    // Header record numbers that disagree with their position point at a
+    // carved, mis-extracted or spliced $MFT
+    let shifts = parser.get_record_number_shifts();
+    if !shifts.is_empty() {
+        let shifted: usize = shifts.iter().map(|region| region.record_count).sum();
+        summary.warnings.push(format!(
+            "{} record(s) in {} region(s) have a header record number that does not match their position",
+            shifted,
+            shifts.len()
+        ));
+        summary.record_number_shifts = shifts.to_vec();
+    }
+
+    let corrupt = parser.get_corrupt_records();
+    summary.corrupt_records = corrupt.len();
+    for record in corrupt {
+        summary.count_errors(record.code, 1);
+    }
+    if !corrupt.is_empty() {
+        let baad = corrupt.iter().filter(|record| record.baad).count();
+        summary.warnings.push(format!(
+            "{} $MFT record(s) could not be read: {} marked BAAD, {} with a broken attribute chain",
+            corrupt.len(),
+            baad,
+            corrupt.len() - baad
+        ));
+    }
+    if cli.corrupt_records
+        && let Some(ref json_dir) = cli.json_dir {
+        let path = json_dir.join(cli.get_default_filename("json", "errors"));
+        json::JsonOutput::write_corrupt_records(corrupt, &path, cli.json_options())?;
+        info!("Wrote {} corrupt records to {}", corrupt.len(), path.display());
+    }
+
+    summary.anomalous_records = records
+        .iter()
+        .filter(|record| !record.is_ads && !record.is_hard_link && parser.anomaly_model().is_anomalous(record.anomaly_score))
+        .count();
+    if summary.anomalous_records > 0 {
+        summary.warnings.push(format!(
+            "{} record(s) have an anomaly_score of {} or more, suggesting timestomping",
+            summary.anomalous_records,
+            parser.anomaly_model().threshold
+        ));
+    }
+
+    // NTFS writes records in order and never shrinks the $MFT, so holes below
+    // the highest record and an input shorter than record 0 claims stand out
+    let layout = parser.layout();
+    if layout.truncated {
+        summary.warnings.push(format!(
+            "The $MFT is truncated: {} bytes of {}",
+            layout.actual_size,
+            layout.expected_size.map_or_else(|| "a whole number of records".to_string(), |size| size.to_string())
+        ));
+    }
+    if !layout.gaps.is_empty() {
+        let missing: usize = layout.gaps.iter().map(|gap| gap.record_count).sum();
+        summary.warnings.push(format!(
+            "{} position(s) in {} gap(s) below the $MFT's high-water mark hold no FILE record",
+            missing,
+            layout.gaps.len()
+        ));
+    }
+    summary.mft_layout = Some(layout);
+
+    // NTFS sets an entry's in-use flag and its $MFT $BITMAP bit together, so a
+    // disagreement points at records or the bitmap edited by hand
+    let mft_bitmap = match volume.as_deref_mut() {
+        Some(volume) => volume.read_mft_bitmap().unwrap_or_else(|e| {
+            warn!("Failed to read the $MFT $BITMAP from volume: {}", e);
+            None
+        }),
+        None => parser.mft_bitmap().map(<[u8]>::to_vec),
+    };
+    match mft_bitmap {
+        Some(mft_bitmap) => {
+            summary.mft_bitmap_mismatches = parser.mft_bitmap_mismatches(&mft_bitmap);
+            if !summary.mft_bitmap_mismatches.is_empty() {
+                summary.warnings.push(format!(
+                    "{} $MFT entries have an in-use flag that does not match the $MFT's $BITMAP",
+                    summary.mft_bitmap_mismatches.len()
+                ));
+            }
+        }
+        None => info!("No $MFT $BITMAP available; in-use flags were not checked against it"),
+    }
+
+    summary.count_errors(ErrorCode::Fixup, parser.get_fixup_error_count());
+    if parser.get_fixup_error_count() > 0 {
+        summary.warnings.push(format!(
+            "{} FILE record(s) failed their update sequence (fixup) check and may be torn or corrupt",
+            parser.get_fixup_error_count()
+        ));
+    }
+
+    let mut findings = wiping::mft_findings(records);
+    coarsen_times(cli, &mut findings);
+    if !findings.is_empty() {
+        summary.warnings.push(format!("{} possible wiping pattern(s) in the $MFT", findings.len()));
+    }
+    findings.extend(free_space);
+    summary.wiping_findings = findings;
+
+    // Handle specific entry dump if requested
+    if let Some(ref entry_spec) = cli.dump_entry {
+        dump_specific_entry(records, entry_spec)?;
+        return Ok(summary);
+    }
+
+    if let Some(ref cluster_map_dir) = cli.cluster_map_dir {
+        write_cluster_map(cli, &parser, cluster_map_dir, volume_clusters)?;
+    }
+
+    if let Some(ref entry_map_dir) = cli.entry_map_dir {
+        write_entry_map(cli, parser.get_records(), entry_map_dir)?;
+    }
+
+    if cli.directory_sizes
+        && let Some(ref csv_dir) = cli.csv_dir {
+        let rows = directory_sizes::compute(&parser);
+        let path = csv_dir.join(cli.get_default_filename("csv", "directories"));
+        csv::CsvOutput::write_directory_sizes(&rows, &path, &cli.csv_options())?;
+        info!("Wrote totals for {} directories to {}", rows.len(), path.display());
+    }
+
+    if cli.data_runs
+        && let Some(ref json_dir) = cli.json_dir {
+        let streams = parser.stream_run_lists();
+        let path = json_dir.join(cli.get_default_filename("json", "runs"));
+        json::JsonOutput::write_stream_run_lists(&streams, &path, cli.json_options())?;
+        info!("Wrote data runs of {} non-resident streams to {}", streams.len(), path.display());
+    }
+
+    if cli.dump_resident
+        && let Some(output_dir) = cli.csv_dir.as_ref().or(cli.json_dir.as_ref()) {
+        let resident_dir = output_dir.join("Resident");
+        let mut used_names = HashSet::new();
+        let mut dumped = dump_resident_data(&parser, &resident_dir, &mut used_names)?;
+        let files = dumped.len();
+        dumped.extend(dump_extended_attributes(&parser, &resident_dir, &mut used_names)?);
+        info!(
+            "Wrote {} resident streams and {} extended attribute values to {}",
+            files,
+            dumped.len() - files,
+            resident_dir.display()
+        );
+        if !dumped.is_empty() {
+            csv::CsvOutput::write_dump_manifest(&dumped, resident_dir.join("manifest.csv"), &cli.csv_options())?;
+            hashdeep::write_hashdeep(&dumped, &resident_dir)?;
+        }
+    }
+
+    if let Some(ref recover_dir) = cli.recover_dir {
+        let recovered = recover_deleted_files(&parser, volume, recover_dir)?;
+        info!("Recovered {} deleted files to {}", recovered.files.len(), recover_dir.display());
+        if !recovered.files.is_empty() {
+            csv::CsvOutput::write_dump_manifest(&recovered.files, recover_dir.join("manifest.csv"), &cli.csv_options())?;
+            hashdeep::write_hashdeep(&recovered.files, recover_dir)?;
+        }
+        if recovered.compressed > 0 {
+            summary.warnings.push(format!(
+                "{} recovered file(s) were stored NTFS-compressed and were decompressed",
+                recovered.compressed
+            ));
+        }
+        let mut wof_compressed: Vec<_> = recovered.wof_compressed.into_iter().collect();
+        wof_compressed.sort_unstable();
+        for (algorithm, count) in wof_compressed {
+            summary.warnings.push(format!("{} recovered file(s) were WOF {} compressed and were decompressed", count, algorithm));
+        }
+        if recovered.failed > 0 {
+            summary.warnings.push(format!("{} deleted file(s) could not be recovered", recovered.failed));
+        }
+    }
+
+    if cli.entry_reuse
+        && let Some(ref csv_dir) = cli.csv_dir {
+        let churn = entry_reuse::sequence_churn(records);
+        let path = csv_dir.join(cli.get_default_filename("csv", "sequencechurn"));
+        csv::CsvOutput::write_sequence_churn(&churn, &path, &cli.csv_options())?;
+        info!("Wrote {} entries with sequence number churn to {}", churn.len(), path.display());
+        if !churn.is_empty() {
+            summary.warnings.push(format!("{} $MFT entries reused far more often than their neighbours", churn.len()));
+        }
+    }
+
+    let mut pipeline = record_pipeline(cli, "mft", true, false)?;
+    for filter in mft_filters(cli, &parser)? {
+        pipeline.add_filter(filter);
+    }
+    // The listing, preview and statistics cover the same records as the outputs
+    let records = pipeline.retain(records);
+    if records.len() != parser.get_records().len() {
+        info!("{} of {} MFT records pass the filters", records.len(), parser.get_records().len());
+    }
+    let records = &records[..];
+    let preview_limit = if cli.export_tagged.is_some() { None } else { Some(PREVIEW_LIMIT) };
+
+    // Output results
+    output_results(pipeline, records)?;
+
+    if cli.file_listing
+        && let Some(ref csv_dir) = cli.csv_dir {
+        let mut entries = mft::file_listing(records);
+        coarsen_times(cli, &mut entries);
+        let path = csv_dir.join(cli.get_default_filename("csv", "filelisting"));
+        csv::CsvOutput::write_file_listing(&entries, &path, &cli.csv_options())?;
+        info!("Wrote a listing of {} files to {}", entries.len(), path.display());
+    }
+
+    // Show console output if requested
+    print_preview(cli, &preview_records(cli, records), "MFT records", preview_limit, table::TableOutput::print_mft_records)?;
+
+    // Summary counts
+    let stats = mft::compute_statistics(records);
+    if !cli.batch_job {
+        table::TableOutput::print_mft_statistics(&stats);
+        if !summary.record_number_shifts.is_empty() {
+            table::TableOutput::print_record_number_shifts(&summary.record_number_shifts);
+        }
+        if !parser.get_corrupt_records().is_empty() {
+            table::TableOutput::print_corrupt_records(parser.get_corrupt_records());
+        }
+        if let Some(ref layout) = summary.mft_layout {
+            table::TableOutput::print_mft_layout(layout);
+        }
+        if !summary.mft_bitmap_mismatches.is_empty() {
+            table::TableOutput::print_mft_bitmap_mismatches(&summary.mft_bitmap_mismatches);
+        }
+        if !summary.wiping_findings.is_empty() {
+            table::TableOutput::print_wiping_findings(&summary.wiping_findings);
+        }
+    }
+    summary.mft_statistics = Some(stats);
+
+    Ok(summary)
+}
+
+/// Write the cluster allocation map CSV and heatmap; without a $Bitmap the
+/// volume is assumed to end at the last mapped cluster
+fn write_cluster_map(
+    cli: &Cli,
+    parser: &mft::MftParser,
+    output_dir: &Path,
+    volume_clusters: Option<u64>,
+) -> Result<()> {
+    let map = cluster_map::ClusterMap::build(parser);
+    let rows = usage_map::rows(&map, parser);
+
+    let csv_path = output_dir.join(cli.get_default_filename("csv", "clustermap"));
+    csv::CsvOutput::write_cluster_map(&rows, &csv_path, &cli.csv_options())?;
+    info!("Wrote {} cluster map rows to {}", rows.len(), csv_path.display());
+
+    let total_clusters = volume_clusters
+        .unwrap_or_else(|| map.extents().iter().map(|extent| extent.lcn + extent.length).max().unwrap_or(0));
+    let heatmap_path = output_dir.join(cli.get_default_filename("ppm", "clustermap"));
+    usage_map::write_heatmap(&heatmap_path, &map, parser, total_clusters)?;
+    let (clusters_per_pixel, _) = usage_map::heatmap_scale(total_clusters);
+    info!(
+        "Wrote cluster heatmap of {} clusters ({} per pixel) to {}",
+        total_clusters,
+        clusters_per_pixel,
+        heatmap_path.display()
+    );
+
+    Ok(())
+}
+
+/// Write the entry number vs created time CSV and, with the png feature, its heatmap
+fn write_entry_map(cli: &Cli, records: &[MftRecord], output_dir: &Path) -> Result<()> {
+    let mut rows = entry_map::rows(records);
+    coarsen_times(cli, &mut rows);
+    let csv_path = output_dir.join(cli.get_default_filename("csv", "entrymap"));
+    csv::CsvOutput::write_entry_map(&rows, &csv_path, &cli.csv_options())?;
+    info!("Wrote {} entry map rows to {}", rows.len(), csv_path.display());
+
+    #[cfg(feature = "png")]
+    if !rows.is_empty() {
+        let heatmap_path = output_dir.join(cli.get_default_filename("png", "entrymap"));
+        entry_map::write_heatmap(&heatmap_path, &rows)?;
+        let (ticks_per_column, entries_per_row, _) = entry_map::entry_map_scale(&rows);
+        info!(
+            "Wrote entry map heatmap ({} entries per row, {} seconds per column) to {}",
+            entries_per_row,
+            ticks_per_column.div_ceil(10_000_000),
+            heatmap_path.display()
+        );
+    }
+    #[cfg(not(feature = "png"))]
+    info!("Build with the png feature for a heatmap of the entry map");
+
+    Ok(())
+}
+
+/// Save the content of every resident $DATA stream, of files in use and
+/// deleted alike, as `<entry>-<seq>_<name>`, and alternate data streams as
+/// `<entry>-<seq>_<name>_<stream>`; empty streams are left out
+fn dump_resident_data(parser: &mft::MftParser, output_dir: &Path, used_names: &mut HashSet<String>) -> Result<Vec<DumpedFile>> {
+    let mut dumped = Vec::new();
+    let records = parser.get_records().iter().filter(|record| {
+        !record.is_hard_link && record.base_record_reference.is_empty() && (record.is_ads || !record.is_directory)
+    });
+    for record in records {
+        // Stream names can't contain ':', so a stream row's name ends with its stream's
+        let stream = if record.is_ads { record.file_name.rsplit_once(':').map_or("", |(_, name)| name) } else { "" };
+        let Some(content) = parser.resident_stream(record.entry_number, stream).filter(|content| !content.is_empty()) else {
+            continue;
+        };
+        let name = format!("{}-{}_{}", record.entry_number, record.sequence_number, record.file_name);
+        let dump_name = write_dump_file(output_dir, &name, content, used_names)?;
+        let kind = if record.is_ads { "ads" } else { "data" };
+        dumped.push(dumped_file(record, dump_name, kind, stream, content));
+    }
+    Ok(dumped)
+}
+
+/// Save each $EA value as `<entry>-<seq>_<name>.ea`, e.g. `624-5_$LXMOD.ea`
+fn dump_extended_attributes(parser: &mft::MftParser, output_dir: &Path, used_names: &mut HashSet<String>) -> Result<Vec<DumpedFile>> {
+    let mut dumped = Vec::new();
+    for (record, attributes) in parser.extended_attributes() {
+        for attribute in attributes {
+            let name = format!("{}-{}_{}.ea", record.entry_number, record.sequence_number, attribute.name);
+            let dump_name = write_dump_file(output_dir, &name, &attribute.value, used_names)?;
+            dumped.push(dumped_file(record, dump_name, "ea", &attribute.name, &attribute.value));
+        }
+    }
+    Ok(dumped)
+}
+
+/// A manifest row for `content`, written as `dump_name` from `record`
+fn dumped_file(record: &MftRecord, dump_name: String, kind: &str, name: &str, content: &[u8]) -> DumpedFile {
+    let hashes = evidence::hash_content(content);
+    DumpedFile {
+        dump_name,
+        entry_number: record.entry_number,
+        sequence_number: record.sequence_number,
+        in_use: record.in_use,
+        full_path: record.full_path.clone(),
+        content: kind.to_string(),
+        name: name.to_string(),
+        size: content.len() as u64,
+        md5: hashes.md5,
+        sha1: hashes.sha1,
+        sha256: hashes.sha256,
+    }
+}
+
+/// Write `content` to `output_dir` as `name`, with characters file systems
+/// reject replaced by '_'. A name already used in the dump, compared
+/// case-insensitively as on Windows, gets `_2`, `_3`, ... before its
+/// extension. Returns the name written.
+fn write_dump_file(output_dir: &Path, name: &str, content: &[u8], used_names: &mut HashSet<String>) -> Result<String> {
+    if used_names.is_empty() {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory {}", output_dir.display()))?;
+    }
+    let name: String = name.chars().map(|c| if c.is_alphanumeric() || "$._- ".contains(c) { c } else { '_' }).collect();
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.ends_with('_') => (stem, format!(".{}", extension)),
+        _ => (name.as_str(), String::new()),
+    };
+    let mut unique = name.clone();
+    let mut copy = 1;
+    while !used_names.insert(unique.to_lowercase()) {
+        copy += 1;
+        unique = format!("{}_{}{}", stem, copy, extension);
+    }
+
+    let path = output_dir.join(&unique);
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(unique)
+}
+
+/// Files written by --recover
+#[derive(Default)]
+struct RecoveryCounts {
+    files: Vec<DumpedFile>,
+    /// Written files that were stored NTFS-compressed
+    compressed: usize,
+    /// Written files that were WOF compressed, by algorithm
+    wof_compressed: HashMap<&'static str, usize>,
+    failed: usize,
+}
+
+/// Save deleted files whose content is intact as `<entry>-<seq>_<name>`,
+/// e.g. `624-5_report.docx`, and alternate data streams as
+/// `<entry>-<seq>_<name>_<stream>`: resident content from the $MFT, and
+/// non-resident content whose clusters are all unallocated from `volume`.
+/// A WOF compressed file is saved decompressed from its WofCompressedData
+/// stream, which is not saved on its own.
+fn recover_deleted_files(
+    parser: &mft::MftParser,
+    mut volume: Option<&mut volume::VolumeReader>,
+    output_dir: &Path,
+) -> Result<RecoveryCounts> {
+    let mut counts = RecoveryCounts::default();
+    let mut used_names = HashSet::new();
+    let is_wof = |record: &MftRecord| record.reparse_tag == reparse::IO_REPARSE_TAG_WOF;
+    let wof_stream_suffix = format!(":{}", compression::WOF_STREAM_NAME);
+    // A WOF compressed file's content is only as intact as its WofCompressedData stream
+    let wof_streams: HashMap<u32, Recoverability> = parser
+        .get_records()
+        .iter()
+        .filter(|record| is_wof(record) && record.is_ads && record.file_name.ends_with(&wof_stream_suffix))
+        .map(|record| (record.entry_number, record.recoverability))
+        .collect();
+    let candidates = parser.get_records().iter().filter(|record| {
+        let recoverability = match (is_wof(record), record.is_ads) {
+            (true, false) => wof_streams.get(&record.entry_number).copied().unwrap_or(record.recoverability),
+            (true, true) if record.file_name.ends_with(&wof_stream_suffix) => Recoverability::NoData,
+            _ => record.recoverability,
+        };
+        !record.in_use
+            && !record.is_directory
+            && !record.is_hard_link
+            && record.base_record_reference.is_empty()
+            && matches!(recoverability, Recoverability::Resident | Recoverability::Recoverable)
+    });
+    if volume.is_none() {
+        info!("Only resident files can be recovered from a $MFT file; point -f at the volume to recover the rest");
+    }
+
+    for record in candidates {
+        // Stream names can't contain ':', so a stream row's name ends with its stream's
+        let stream = match (record.is_ads, is_wof(record)) {
+            (true, _) => record.file_name.rsplit_once(':').map_or("", |(_, name)| name),
+            (false, true) => compression::WOF_STREAM_NAME,
+            (false, false) => "",
+        };
+        let stream_runs = parser.stream_runs(record.entry_number, stream);
+        let content = match (parser.resident_stream(record.entry_number, stream), stream_runs, volume.as_deref_mut()) {
+            (Some(content), _, _) => Ok(content.to_vec()),
+            (None, Some(runs), Some(volume)) => volume.read_file_data(&runs.runs, runs.real_size, runs.compression_unit),
+            _ => continue,
+        };
+        let content = match content {
+            Ok(content) if stream == compression::WOF_STREAM_NAME && !record.is_ads => {
+                decompress_wof_file(parser, record, &content).map(|(content, algorithm)| {
+                    *counts.wof_compressed.entry(algorithm.name()).or_default() += 1;
+                    content
+                })
+            }
+            content => content,
+        };
+        let content = match content {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to recover entry {} ({}): {}", record.entry_number, record.file_name, e);
+                counts.failed += 1;
+                continue;
+            }
+        };
+
+        let name = format!("{}-{}_{}", record.entry_number, record.sequence_number, record.file_name);
+        let dump_name = write_dump_file(output_dir, &name, &content, &mut used_names)?;
+        let kind = if record.is_ads { "ads" } else { "data" };
+        let stream = if record.is_ads { stream } else { "" };
+        counts.files.push(dumped_file(record, dump_name, kind, stream, &content));
+        if record.is_compressed && stream_runs.is_some_and(|runs| runs.compression_unit > 0) {
+            counts.compressed += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Decompress a WOF compressed file from its WofCompressedData stream, with the
+/// algorithm its reparse point names
+fn decompress_wof_file(
+    parser: &mft::MftParser,
+    record: &MftRecord,
+    stream: &[u8],
+) -> ParseResult<(Vec<u8>, compression::WofAlgorithm)> {
+    let reparse_point = parser
+        .resident_attribute(record.entry_number, 0xC0, "")
+        .map(reparse::parse)
+        .transpose()?;
+    let Some(algorithm) = reparse_point.and_then(|reparse_point| reparse_point.wof_algorithm) else {
+        return Err(ParseError {
+            code: ErrorCode::ReparsePoint,
+            message: "WOF reparse point names no compression algorithm".to_string(),
+            offset: None,
+        });
+    };
+    compression::decompress_wof_stream(stream, algorithm, record.file_size).map(|content| (content, algorithm))
+}
+
+/// Open -f read-only and bring it into memory as --io-mode or --direct-io asks
+fn load_input(cli: &Cli) -> Result<evidence::InputData> {
+    let mut io_mode = cli.io_mode;
+    if cli.direct_io {
+        match evidence::read_direct(&cli.file) {
+            Ok(data) => return Ok(evidence::InputData::Buffered(data)),
+            Err(e) => {
+                warn!("Direct I/O unavailable for {} ({}); using buffered reads", cli.file.display(), e);
+                io_mode = IoMode::Read;
+            }
+        }
+    }
+
+    let file = evidence::open_read_only(&cli.file)?;
+    evidence::load_input(&file, io_mode, cli.populate)
+        .with_context(|| format!("Failed to read {}", cli.file.display()))
+}
+
+/// How many of an artifact's `size` bytes --max-input-mb lets through, with a
+/// notice for the summary when that cuts the artifact short
+fn input_limit(cli: &Cli, size: usize, artifact: &str) -> (usize, Option<String>) {
+    match cli.max_input_bytes() {
+        Some(limit) if size as u64 > limit => {
+            let notice = format!(
+                "Only the first {} of {} bytes of {} were parsed (--max-input-mb)",
+                limit, size, artifact
+            );
+            warn!("{}", notice);
+            (limit as usize, Some(notice))
+        }
+        _ => (size, None),
+    }
+}
+
+fn process_usn_journal(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing USN Journal file: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+
+    let file_size = input.len() as u64;
+    let (length, limit_notice) = input_limit(cli, input.len(), "$J");
+    if cli.entry_reuse && cli.mft_file.is_none() {
+        warn!("--entry-reuse needs -m to compare $J with the current $MFT records");
+    }
+    let lookup_mft = cli.mft_file.as_deref().map(parse_mft_for_lookup).transpose()?;
+    let mut summary = process_usn_data(cli, input.into_vec(length), 0, lookup_mft.as_ref())?;
+    summary.file_size = file_size;
+    summary.warnings.extend(limit_notice);
+    Ok(summary)
+}
+
+/// `lookup_mft` resolves parent paths and is what --entry-reuse compares journal entries against
+fn process_usn_data(
+    cli: &Cli,
+    data: Vec<u8>,
+    base_offset: u64,
+    lookup_mft: Option<&mft::MftParser>,
+) -> Result<json::AnalysisSummary> {
+    let file_size = data.len() as u64;
+
+    // Writers run on their own threads so serialization overlaps parsing
+    let mut records = record_pipeline(cli, "usn", true, false)?;
+
+    let mut parser = usn_journal::UsnJournalParser::new(data)
+        .with_base_offset(base_offset)
+        .raw_mode(cli.raw)
+        .with_deadline(cli.deadline())
+        .with_path_resolver(path_resolver::PathResolver::new(lookup_mft));
+    parser.parse_batched(pipeline::BATCH_SIZE, |batch| {
+        if cli.raw {
+            records.send(batch.iter().map(RawUsnJournalEntry::from));
+        } else {
+            records.send(batch.iter().cloned());
+        }
+    })?;
+    log_outputs(&records.finish()?);
+
+    let entries = parser.get_entries();
+    let resolved = entries.iter().filter(|entry| entry.path_source.is_some()).count();
+    info!("Parsed {} USN Journal entries, {} with a resolved path", entries.len(), resolved);
+
+    // Console output
+    print_preview(cli, &preview_records(cli, entries), "USN Journal entries", Some(PREVIEW_LIMIT), table::TableOutput::print_usn_journal_entries)?;
+
+    let mut summary = json::AnalysisSummary::new(FileType::UsnJournal, file_size, entries.len());
+    summary.errors_encountered = parser.get_error_count();
+    summary.count_errors(ErrorCode::UsnRecord, parser.get_error_count());
+    summary.timed_out = parser.timed_out();
+    if parser.get_skipped_bytes() > 0 {
+        summary.warnings.push(format!(
+            "Skipped {} bytes in {} corrupt USN region(s)",
+            parser.get_skipped_bytes(),
+            parser.get_error_count()
+        ));
+    }
+
+    summary.wiping_findings = wiping::usn_findings(entries);
+    coarsen_times(cli, &mut summary.wiping_findings);
+    if !summary.wiping_findings.is_empty() {
+        summary.warnings.push(format!("{} possible wiping or mass-deletion pattern(s) in $J", summary.wiping_findings.len()));
+        if !cli.batch_job {
+            table::TableOutput::print_wiping_findings(&summary.wiping_findings);
+        }
+    }
+
+    let (mut alerts, mut renames) = ransomware::detect(entries);
+    coarsen_times(cli, &mut alerts);
+    coarsen_times(cli, &mut renames);
+    if !alerts.is_empty() {
+        for alert in &alerts {
+            summary.warnings.push(format!(
+                "Possible ransomware: {} files renamed to {} between {} and {}",
+                alert.renamed_files, alert.extensions, alert.start, alert.end
+            ));
+        }
+        if let Some(ref csv_dir) = cli.csv_dir {
+            let path = csv_dir.join(cli.get_default_filename("csv", "ransomware"));
+            csv::CsvOutput::write_ransomware_renames(&renames, &path, &cli.csv_options())?;
+            info!("Wrote {} ransomware-like renames to {}", renames.len(), path.display());
+        }
+        if !cli.batch_job {
+            table::TableOutput::print_ransomware_alerts(&alerts);
+        }
+        summary.ransomware_alerts = alerts;
+    }
+
+    if let (Some(mft_parser), Some(csv_dir)) = (lookup_mft.filter(|_| cli.entry_reuse), cli.csv_dir.as_ref()) {
+        let mut occupants = entry_reuse::previous_occupants(mft_parser.get_records(), entries);
+        coarsen_times(cli, &mut occupants);
+        let path = csv_dir.join(cli.get_default_filename("csv", "entryreuse"));
+        csv::CsvOutput::write_previous_occupants(&occupants, &path, &cli.csv_options())?;
+        info!("Wrote {} previous occupants of reused entries to {}", occupants.len(), path.display());
+        if !occupants.is_empty() {
+            summary.warnings.push(format!(
+                "$J holds {} file(s) whose $MFT entries have since been reused",
+                occupants.len()
+            ));
+        }
+    }
+
+    Ok(summary)
+}
+
+fn process_volume(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing NTFS volume: {}", cli.file.display());
+
+    let mut volume = volume::VolumeReader::open(&cli.file)
+        .with_context(|| format!("Failed to open volume: {}", cli.file.display()))?;
+    let boot = volume.boot_sector();
+    info!(
+        "Volume serial {:016X}, {} bytes, {} byte clusters, $MFT at cluster {}",
+        boot.volume_serial_number,
+        volume.volume_size(),
+        volume.cluster_size(),
+        boot.mft_start_cluster
+    );
+
+    // Record IDs are derived from the serial of the volume being read, and
+    // FILE records are as large as its boot sector says
+    let mut cli = cli.clone();
+    cli.volume_serial.get_or_insert(boot.volume_serial_number);
+    cli.record_size.get_or_insert(volume.mft_record_size());
+    let cli = &cli;
+
+    let mut mft_data = volume.read_mft().context("Failed to read $MFT from volume")?;
+    info!("Read {} bytes of $MFT from volume", mft_data.len());
+
+    // Locate $J before the MFT buffer is handed to the parser
+    let usn_data = if cli.dump_entry.is_none() {
+        volume.read_usn_journal(&mft_data).context("Failed to read $UsnJrnl:$J from volume")?
+    } else {
+        None
+    };
+
+    let bitmap = match volume.read_bitmap(&mft_data) {
+        Ok(bits) => bits.map(bitmap::ClusterBitmap::new),
+        Err(e) => {
+            warn!("Failed to read $Bitmap from volume, recoverability will be unverified: {}", e);
+            None
+        }
+    };
+
+    // `mfte-rs auto` also takes $Boot, $Secure:$SDS and $LogFile from the volume
+    let all_artifacts = cli.all_artifacts && cli.dump_entry.is_none();
+    let boot_data = if all_artifacts {
+        Some(volume.read_boot().context("Failed to read $Boot from volume")?)
+    } else {
+        None
+    };
+    let sds_data = if all_artifacts {
+        volume.read_sds(&mft_data).context("Failed to read $Secure:$SDS from volume")?
+    } else {
+        None
+    };
+    let logfile_data = if all_artifacts {
+        volume.read_logfile(&mft_data).context("Failed to read $LogFile from volume")?
+    } else {
+        None
+    };
+
+    let volume_size = volume.volume_size();
+    let (length, limit_notice) = input_limit(cli, mft_data.len(), "$MFT");
+    mft_data.truncate(length);
+    // $J and $LogFile paths are resolved against a second parse of the $MFT
+    let lookup_mft = (usn_data.is_some() || logfile_data.is_some()).then(|| mft_data.clone());
+    let mut summary = process_mft_data(cli, mft_data, bitmap, Some(&mut volume))?;
+    let lookup_mft = match lookup_mft {
+        Some(mft_data) => {
+            let mut lookup = mft::MftParser::new(mft_data).with_record_size(cli.record_size);
+            lookup.parse()?;
+            Some(lookup)
+        }
+        None => None,
+    };
+    summary.file_type = FileType::Volume.to_string();
+    summary.file_size = volume_size;
+    summary.warnings.extend(limit_notice);
+
+    // Custom output names and --template belong to the $MFT results; the other artifacts use the defaults
+    let mut artifact_cli = cli.clone();
+    artifact_cli.template = None;
+    artifact_cli.csv_filename = None;
+    artifact_cli.json_filename = None;
+    artifact_cli.body_filename = None;
+
+    match usn_data {
+        Some((base_offset, mut data)) => {
+            info!("Read {} bytes of $UsnJrnl:$J from volume starting at offset 0x{:x}", data.len(), base_offset);
+            let (length, limit_notice) = input_limit(cli, data.len(), "$J");
+            data.truncate(length);
+            summary.warnings.extend(limit_notice);
+            merge_summary(&mut summary, process_usn_data(&artifact_cli, data, base_offset, lookup_mft.as_ref())?);
+        }
+        None if cli.dump_entry.is_none() => {
+            info!("No $UsnJrnl:$J stream found on volume");
+            summary.warnings.push("No $UsnJrnl:$J stream found on volume".to_string());
+        }
+        None => {}
+    }
+
+    if let Some(data) = boot_data {
+        merge_summary(&mut summary, process_boot_data(&artifact_cli, &data)?);
+    }
+
+    match sds_data {
+        Some(mut data) => {
+            info!("Read {} bytes of $Secure:$SDS from volume", data.len());
+            let (length, limit_notice) = input_limit(cli, data.len(), "$SDS");
+            data.truncate(length);
+            summary.warnings.extend(limit_notice);
+            merge_summary(&mut summary, process_sds_data(&artifact_cli, data)?);
+        }
+        None if all_artifacts => summary.warnings.push("No $Secure:$SDS stream found on volume".to_string()),
+        None => {}
+    }
+
+    match logfile_data {
+        Some(mut data) => {
+            info!("Read {} bytes of $LogFile from volume", data.len());
+            let (length, limit_notice) = input_limit(cli, data.len(), "$LogFile");
+            data.truncate(length);
+            summary.warnings.extend(limit_notice);
+            merge_summary(&mut summary, process_logfile_data(&artifact_cli, data, lookup_mft)?);
+        }
+        None if all_artifacts => summary.warnings.push("No $LogFile stream found on volume".to_string()),
+        None => {}
+    }
+
+    Ok(summary)
+}
+
+/// Catalogue code of the error processing stopped at, if it came from a parser or from I/O
+fn error_code(error: &anyhow::Error) -> Option<ErrorCode> {
+    error
+        .downcast_ref::<ParseError>()
+        .map(|e| e.code)
+        .or_else(|| error.downcast_ref::<std::io::Error>().map(|_| ErrorCode::Io))
+}
+
+/// Fold the summary of an artifact read from a volume into the volume's summary
+fn merge_summary(summary: &mut json::AnalysisSummary, artifact: json::AnalysisSummary) {
+    summary.records_processed += artifact.records_processed;
+    summary.errors_encountered += artifact.errors_encountered;
+    for (code, count) in artifact.error_codes {
+        summary.count_errors(code, count);
+    }
+    summary.timed_out |= artifact.timed_out;
+    summary.warnings.extend(artifact.warnings);
+    summary.wiping_findings.extend(artifact.wiping_findings);
+    summary.ransomware_alerts.extend(artifact.ransomware_alerts);
+}
+
+fn process_boot(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing Boot sector file: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+    process_boot_data(cli, &input)
+}
+
+fn process_boot_data(cli: &Cli, data: &[u8]) -> Result<json::AnalysisSummary> {
+    let boot_sector = boot::BootParser::parse(data)?;
+    info!("Parsed boot sector information");
+
+    // Output results
+    write_outputs(cli, [boot_sector.clone()], "boot", false, true)?;
+
+    // Console output
+    print_preview(cli, std::slice::from_ref(&boot_sector), "boot sector", None, |boot, _| {
+        table::TableOutput::print_boot_sector(&boot[0])
+    })?;
+
+    Ok(json::AnalysisSummary::new(FileType::Boot, data.len() as u64, 1))
+}
+
+fn process_sds(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing SDS file: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+
+    let file_size = input.len() as u64;
+    let (length, limit_notice) = input_limit(cli, input.len(), "$SDS");
+    let mut summary = process_sds_data(cli, input.into_vec(length))?;
+    summary.file_size = file_size;
+    summary.warnings.extend(limit_notice);
+    Ok(summary)
+}
+
+fn process_sds_data(cli: &Cli, data: Vec<u8>) -> Result<json::AnalysisSummary> {
+    let file_size = data.len() as u64;
+    let mut parser = sds::SdsParser::new(data).with_deadline(cli.deadline());
+    parser.parse()?;
+
+    let descriptors = parser.get_descriptors();
+    info!("Parsed {} security descriptors", descriptors.len());
+    let mut summary = json::AnalysisSummary::new(FileType::Sds, file_size, descriptors.len());
+    summary.errors_encountered = parser.get_error_count();
+    summary.count_errors(ErrorCode::SecurityDescriptor, parser.get_error_count());
+    summary.timed_out = parser.timed_out();
+    if parser.get_hash_mismatches() > 0 {
+        summary.warnings.push(format!("{} entries have a hash that does not match their descriptor", parser.get_hash_mismatches()));
+    }
+    if parser.get_mirror_mismatches() > 0 {
+        summary.warnings.push(format!("{} entries differ from their mirror copy", parser.get_mirror_mismatches()));
+    }
+
+    // Handle specific security descriptor dump if requested
+    if let Some(ref security_id) = cli.dump_security {
+        dump_specific_security_descriptor(descriptors, security_id)?;
+        return Ok(summary);
+    }
+
+    // Output results; binary descriptors are written as hex
+    let descriptors_hex: Vec<SecurityDescriptorHex> = descriptors.iter().map(SecurityDescriptorHex::from).collect();
+    write_outputs(cli, descriptors_hex.iter().cloned(), "sds", false, false)?;
+
+    // Console output
+    if matches!(cli.output_format, OutputFormat::Table) {
+        print_preview(cli, descriptors, "security descriptors", Some(PREVIEW_LIMIT), table::TableOutput::print_security_descriptors)?;
+    } else {
+        print_preview(cli, &descriptors_hex, "security descriptors", Some(PREVIEW_LIMIT), |_, _| {})?;
+    }
+
+    Ok(summary)
+}
+
+fn process_i30(cli: &Cli) -> Result<json::AnalysisSummary> {
+    info!("Processing I30 index file: {}", cli.file.display());
+
+    let input = load_input(cli)?;
+
+    let file_size = input.len() as u64;
+    let (length, limit_notice) = input_limit(cli, input.len(), "$I30");
+    let mut parser = i30::I30Parser::new(input.into_vec(length)).with_deadline(cli.deadline());
+    parser.parse()?;
+    let lookup_mft = cli.mft_file.as_deref().map(parse_mft_for_lookup).transpose()?;
+    let resolved = parser.resolve_paths(path_resolver::PathResolver::new(lookup_mft.as_ref()));
+
+    let entries = parser.get_entries();
+    info!("Parsed {} index entries, {} with a resolved path", entries.len(), resolved);
+
+    // Output results
+    write_outputs(cli, entries.iter().cloned(), "i30", true, false)?;
+
+    // Console output
+    print_preview(cli, &preview_records(cli, entries), "index entries", Some(PREVIEW_LIMIT), table::TableOutput::print_index_entries)?;
+
+    let mut summary = json::AnalysisSummary::new(FileType::I30, file_size, entries.len());
+    summary.timed_out = parser.timed_out();
+    summary.warnings.extend(limit_notice);
+    summary.count_errors(ErrorCode::Fixup, parser.get_fixup_error_count());
+    if parser.get_fixup_error_count() > 0 {
+        summary.errors_encountered = parser.get_fixup_error_count();
+        summary.warnings.push(format!(
+            "{} of {} INDX pages failed their update sequence (fixup) check; their entries have fixup_error set",
+            parser.get_fixup_error_count(),
+            parser.get_page_count()
+        ));
+    }
+    Ok(summary)
+}
+
+/// `mfte-rs mactime`: render bodyfiles as a day-grouped timeline
+fn run_mactime(args: &MactimeCli) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let (start, end) = args.range();
+    let mut timeline = mactime::Timeline::new().with_range(start, end);
+
+    for body_file in &args.body_files {
+        let contents = std::fs::read_to_string(body_file)
+            .with_context(|| format!("Failed to read bodyfile: {}", body_file.display()))?;
+        let skipped = timeline.add_lines(contents.lines());
+        if skipped > 0 {
+            warn!("Skipped {} malformed lines in {}", skipped, body_file.display());
+        }
+    }
+
+    match args.output {
+        Some(ref path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create timeline: {}", path.display()))?;
+            let mut output = BufWriter::new(file);
+            timeline.write(&mut output)?;
+            output.flush()?;
+            info!("Wrote {} timeline events to: {}", timeline.event_count(), path.display());
+        }
+        None => {
+            let mut output = BufWriter::new(std::io::stdout().lock());
+            timeline.write(&mut output)?;
+            output.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `mfte-rs merge`: combine outputs from many hosts into one dataset
+fn run_merge(args: &MergeCli) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let jsonl = match args.output.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+        Some("csv") => false,
+        Some("jsonl") => true,
+        _ => anyhow::bail!("Merged output must end in .csv or .jsonl: {}", args.output.display()),
+    };
+
+    let mut dataset = merge::MergedDataset::new(&args.host_column).with_dedupe(args.dedupe);
+    for (host, path) in args.hosts() {
+        let files = merge::input_files(&path, args.name_filter.as_deref())?;
+        if files.is_empty() {
+            warn!("No .csv or .jsonl outputs to merge in {}", path.display());
+        }
+        for file in files {
+            // An input directory can hold the merged output of an earlier run
+            if std::fs::canonicalize(&file).ok() == std::fs::canonicalize(&args.output).ok() {
+                continue;
+            }
+            let rows = dataset.add_file(&file, &host)?;
+            info!("Read {} rows for host {} from {}", rows, host, file.display());
+        }
+    }
+
+    if let Some(ref column) = args.sort {
+        dataset.sort_by(column)?;
+    }
+    if jsonl {
+        dataset.write_jsonl(&args.output)?;
+    } else {
+        dataset.write_csv(&args.output)?;
+    }
+
+    if args.dedupe {
+        info!("Dropped {} duplicate rows", dataset.duplicate_count());
+    }
+    info!(
+        "Wrote {} rows with {} columns to: {}",
+        dataset.row_count(),
+        dataset.columns().len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// `mfte-rs whohas`: find the streams whose data runs contain the given clusters or volume offsets
+fn run_tag(args: &TagCli) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut store = bookmarks::BookmarkStore::load(&args.tags_file)?;
+    let entries = args.entries.iter()
+        .map(|entry| entry.parse::<bookmarks::Bookmark>())
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(ref set) = args.set
+        && !entries.is_empty() {
+        if args.remove {
+            let removed = store.remove(set, entries);
+            info!("Removed {} bookmark(s) from '{}'", removed, set);
+        } else {
+            let added = store.add(set, entries);
+            info!("Added {} bookmark(s) to '{}'", added, set);
+        }
+        store.save(&args.tags_file)?;
+    }
+
+    if args.list {
+        for (name, set) in &store.sets {
+            if args.set.as_ref().is_some_and(|wanted| wanted != name) {
+                continue;
+            }
+            let entries: Vec<String> = set.iter().map(ToString::to_string).collect();
+            println!("{} ({}): {}", name, set.len(), entries.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+fn run_whohas(args: &WhohasCli) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let (mft_data, volume_cluster_size) = read_mft_for_lookup(&args.file)?;
+    let cluster_size = args.cluster_size.or(volume_cluster_size);
+    if cluster_size == Some(0) {
+        anyhow::bail!("--cluster-size must be greater than zero");
+    }
+
+    // (cluster, byte offset within that cluster when looking up an offset)
+    let mut lookups = Vec::new();
+    for cluster in &args.clusters {
+        lookups.push((parse_numeric_value(cluster)?, 0));
+    }
+    for offset in &args.offsets {
+        let offset = parse_numeric_value(offset)?;
+        let cluster_size = cluster_size
+            .ok_or_else(|| anyhow::anyhow!("--cluster-size is required to look up offsets in an extracted $MFT"))?;
+        lookups.push((offset / cluster_size, offset % cluster_size));
+    }
 
-    // Validate command line arguments
-    if let Err(e) = cli.validate() {
-        error!("Validation error: {}", e);
-        std::process::exit(1);
+    let mut parser = mft::MftParser::new(mft_data);
+    parser.parse()?;
+    let cluster_map = cluster_map::ClusterMap::build(&parser);
+    info!("Indexed {} cluster runs", cluster_map.extents().len());
+
+    let mut owners = Vec::new();
+    for (cluster, offset_in_cluster) in lookups {
+        let found = cluster_map.owners(cluster);
+        if found.is_empty() {
+            warn!("No MFT record maps cluster {}", cluster);
+        }
+        owners.extend(found.into_iter().filter_map(|extent| {
+            let record = parser.get_record(extent.entry_number)?;
+            let vcn = extent.vcn + (cluster - extent.lcn);
+            Some(ntfs::types::ClusterOwner {
+                cluster,
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                in_use: record.in_use,
+                stream: extent.stream.clone(),
+                vcn,
+                file_offset: cluster_size.map(|size| vcn * size + offset_in_cluster),
+                full_path: record.full_path.clone(),
+            })
+        }));
     }
 
-    let start_time = Instant::now();
+    table::TableOutput::print_cluster_owners(&owners);
+    Ok(())
+}
 
-    // Determine file type
-    let file_type = detect_file_type(&cli.file)?;
-    info!("Detected file type: {}", file_type);
+fn run_sdsdiff(args: &SdsDiffCli) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    // Process file based on type
-    let result = match file_type {
-        FileType::Mft => process_mft(&cli),
-        FileType::UsnJournal => process_usn_journal(&cli),
-        FileType::Boot => process_boot(&cli),
-        FileType::Sds => process_sds(&cli),
-        FileType::I30 => process_i30(&cli),
-        FileType::LogFile => {
-            warn!("LogFile processing not yet implemented");
-            Ok(())
-        }
-        FileType::Unknown => {
-            error!("Unknown file type for: {}", cli.file.display());
-            std::process::exit(1);
+    let old_sds = load_descriptors(&args.old_sds)?;
+    let new_sds = load_descriptors(&args.new_sds)?;
+    let diffs = sds_diff::diff_descriptors(&old_sds, &new_sds);
+    info!("{} of {} baseline security descriptors differ", diffs.len(), old_sds.len());
+
+    let drift = match args.mft_file {
+        Some(ref mft_file) => {
+            let new_mft = parse_mft_for_lookup(mft_file)?;
+            let old_mft = args.old_mft_file.as_deref().map(parse_mft_for_lookup).transpose()?;
+            let old_records = old_mft.as_ref().map(|parser| parser.get_records());
+            Some(sds_diff::permission_drift(&old_sds, &new_sds, old_records, new_mft.get_records()))
         }
+        None => None,
     };
 
-    let processing_time = start_time.elapsed();
-
-    match result {
-        Ok(()) => {
-            info!("Processing completed successfully in {} ms", processing_time.as_millis());
-        }
-        Err(e) => {
-            error!("Processing failed: {}", e);
-            std::process::exit(1);
+    if let Some(ref csv_dir) = args.csv_dir {
+        let options = csv::CsvOptions::default();
+        let path = csv_dir.join("sdsdiff.csv");
+        csv::CsvOutput::write_descriptor_diffs(&diffs, &path, &options)?;
+        info!("Wrote {} descriptor differences to {}", diffs.len(), path.display());
+        if let Some(ref drift) = drift {
+            let path = csv_dir.join("permission_drift.csv");
+            csv::CsvOutput::write_permission_drift(drift, &path, &options)?;
+            info!("Wrote {} files with changed permissions to {}", drift.len(), path.display());
         }
     }
 
+    table::TableOutput::print_descriptor_diffs(&diffs);
+    if let Some(ref drift) = drift {
+        table::TableOutput::print_permission_drift(drift);
+    }
     Ok(())
 }
 
-fn detect_file_type(path: &Path) -> Result<FileType> {
-    let file = File::open(path)
-        .with_context(|| format!("Failed to open file: {}", path.display()))?;
-
-    let mmap = unsafe { Mmap::map(&file)? };
+/// `mfte-rs monitor`: poll a volume's $J and stream each new change as a JSON line
+fn run_monitor(args: &MonitorCli) -> Result<()> {
+    let log_level = if args.debug { "debug" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level)).init();
 
-    if mmap.len() < 4 {
-        return Ok(FileType::Unknown);
+    let path = args.volume_path().unwrap_or_else(|e| e.exit());
+    if let Err(message) = evidence::check_access(&path) {
+        error!("{}", message);
+        std::process::exit(1);
     }
 
-    // Check first 4 bytes for signatures
-    let signature = u32::from_le_bytes([mmap[0], mmap[1], mmap[2], mmap[3]]);
+    let mut volume = volume::VolumeReader::open(&path)
+        .with_context(|| format!("Failed to open volume: {}", path.display()))?;
+    let mft_data = volume.read_mft().context("Failed to read $MFT from volume")?;
+    let location = volume
+        .locate_usn_journal(&mft_data)
+        .with_context(|| format!("No $UsnJrnl:$J stream found on {}; is the change journal enabled?", path.display()))?;
+
+    // Only the directory tree is kept, for paths and --path filters
+    let mut mft = mft::MftParser::new(mft_data).with_record_size(Some(volume.mft_record_size()));
+    mft.parse()?;
+    let mut filter = usn_filter::UsnFilter::new(&mft)
+        .with_reasons(args.reasons.iter().fold(0, |reasons, reason| reasons | reason))
+        .with_extensions(&args.extensions)
+        .with_paths(&args.include_paths, &args.exclude_paths);
+    drop(mft);
+
+    // `C:` and `/dev/sdb2` name their outputs after `C` and `sdb2`
+    let volume_name = Path::new(&args.drive)
+        .file_name()
+        .map_or_else(|| args.drive.clone(), |name| name.to_string_lossy().into_owned());
+    let volume_name = volume_name.trim_end_matches(':');
+
+    let mut output = match args.jsonl_dir {
+        Some(ref dir) => {
+            let name = format!("{}_monitor", volume_name);
+            let output = rotate::RotatingJsonl::open(dir, &name, args.rotate_mb * 1024 * 1024, args.keep)?;
+            info!("Writing changes to {}", output.path().display());
+            Some(output)
+        }
+        None => None,
+    };
+    let mut forwarder = match args.forward {
+        Some(ref address) => {
+            let spool = match args.spool_dir {
+                Some(ref dir) => {
+                    std::fs::create_dir_all(dir)
+                        .with_context(|| format!("Failed to create spool directory {}", dir.display()))?;
+                    Some(dir.join(format!("{}_monitor_spool.jsonl", volume_name)))
+                }
+                None => None,
+            };
+            let forwarder = forward::TcpForwarder::new(address)
+                .with_batching(args.batch_size as usize, Duration::from_secs(args.flush_secs))
+                .with_max_rate(args.max_rate)
+                .with_retries(args.retries)
+                .with_spool(spool, args.spool_mb * 1024 * 1024);
+            Some(forwarder)
+        }
+        None => None,
+    };
 
-    match signature {
-        0x454c4946 => Ok(FileType::Mft), // "FILE"
-        0x58444e49 => Ok(FileType::I30), // "INDX"
-        _ => {
-            // Check for other patterns
-            if mmap.len() >= 512 {
-                // Check for NTFS boot sector
-                if mmap[3..11] == *b"NTFS    " {
-                    return Ok(FileType::Boot);
+    // Resume where the last run left off on this volume
+    let state_path = args
+        .state_dir
+        .as_ref()
+        .or(args.jsonl_dir.as_ref())
+        .map(|dir| dir.join(format!("{}_monitor_state.json", volume_name)));
+    let volume_serial_number = volume.boot_sector().volume_serial_number;
+    let mut position = JournalPosition::default();
+    if let Some(ref state_path) = state_path {
+        match checkpoint::UsnCheckpoint::load(state_path)? {
+            Some(saved) if saved.volume_serial_number == volume_serial_number => {
+                info!("Resuming $J at USN {} from {}", saved.next_usn, state_path.display());
+                position = JournalPosition { next_usn: Some(saved.next_usn), journal_id: saved.journal_id };
+            }
+            Some(saved) => warn!(
+                "{} belongs to volume {:016X}, not {:016X}; starting without it",
+                state_path.display(),
+                saved.volume_serial_number,
+                volume_serial_number
+            ),
+            None => {}
+        }
+    }
+    let mut saved_usn = position.next_usn;
+
+    let interval = Duration::from_secs(args.interval);
+    let context = enrichment::context_columns(&args.context);
+    let record_id: Arc<str> = Arc::from("record_id");
+    let mut polls = 0;
+    loop {
+        let poll_start = Instant::now();
+        let lines = match poll_usn_journal(&mut volume, &location, &mut position, &mut filter, args.from_start) {
+            Ok(entries) => {
+                if !entries.is_empty() {
+                    info!("{} new $J changes, up to USN {}", entries.len(), position.next_usn.unwrap_or_default());
                 }
+                entries
+                    .into_iter()
+                    .map(|entry| {
+                        let record = ArtifactRecord::from(entry);
+                        let id = record.record_id(volume_serial_number);
+                        let record = record.with_columns(std::iter::once((record_id.clone(), id)).chain(context.iter().cloned()));
+                        serde_json::to_vec(&record).map(|mut line| {
+                            line.push(b'\n');
+                            line
+                        })
+                    })
+                    .collect::<serde_json::Result<Vec<_>>>()?
+            }
+            Err(e) => {
+                warn!("Failed to read $J: {:#}", e);
+                Vec::new()
             }
+        };
 
-            // Check for USN Journal (starts with record length)
-            if mmap.len() >= 60 {
-                let record_length = u32::from_le_bytes([mmap[0], mmap[1], mmap[2], mmap[3]]);
-                if record_length > 60 && record_length < 0x10000 {
-                    return Ok(FileType::UsnJournal);
-                }
+        if let Some(ref mut output) = output
+            && !lines.is_empty()
+        {
+            for line in &lines {
+                output.write_line(line)?;
+            }
+            output.flush()?;
+        }
+
+        polls += 1;
+        let last_poll = args.iterations.is_some_and(|iterations| polls >= iterations);
+        // Held or spooled lines are delivered even when nothing changed
+        if let Some(ref mut forwarder) = forwarder {
+            let result = if last_poll { forwarder.send(lines).and_then(|_| forwarder.flush()) } else { forwarder.send(lines) };
+            if let Err(e) = result {
+                warn!("Could not forward changes to {}: {:#}", forwarder.address(), e);
             }
+        }
 
-            // Default to unknown
-            Ok(FileType::Unknown)
+        // Lines still held for the forwarder would be lost on a restart, so
+        // the checkpoint only moves once everything read has been handed on
+        if let Some(ref state_path) = state_path
+            && position.next_usn != saved_usn
+            && forwarder.as_ref().is_none_or(|forwarder| forwarder.held() == 0)
+            && let Some(next_usn) = position.next_usn
+        {
+            let saved = checkpoint::UsnCheckpoint::new(volume_serial_number, position.journal_id, next_usn);
+            match saved.save(state_path) {
+                Ok(()) => saved_usn = position.next_usn,
+                Err(e) => warn!("Failed to save the $J position: {:#}", e),
+            }
         }
+        if last_poll {
+            return Ok(());
+        }
+        std::thread::sleep(interval.saturating_sub(poll_start.elapsed()));
     }
 }
 
-fn process_mft(cli: &Cli) -> Result<()> {
-    info!("Processing MFT file: {}", cli.file.display());
+/// How far monitor has read $J
+#[derive(Debug, Default)]
+struct JournalPosition {
+    /// Offset (USN) of the next change to read; None before the first poll
+    next_usn: Option<u64>,
+    /// ID of the journal `next_usn` belongs to
+    journal_id: Option<u64>,
+}
 
-    let file = File::open(&cli.file)?;
-    let mmap = unsafe { Mmap::map(&file)? };
+/// Changes appended to $J since `position`, advancing it to the journal's end.
+///
+/// The first poll only records where the journal ends, unless `from_start`.
+fn poll_usn_journal(
+    volume: &mut volume::VolumeReader,
+    location: &volume::UsnJournalLocation,
+    position: &mut JournalPosition,
+    filter: &mut usn_filter::UsnFilter,
+    from_start: bool,
+) -> Result<Vec<ntfs::types::UsnJournalEntry>> {
+    let cursor = &mut position.next_usn;
+    let from = match *cursor {
+        Some(from) => from,
+        None if from_start => 0,
+        None => u64::MAX,
+    };
+    let Some(mut tail) = volume.read_usn_journal_tail(location, from)? else {
+        anyhow::bail!("$UsnJrnl no longer has a $J stream; was the journal deleted?");
+    };
+
+    if cursor.is_none() && !from_start {
+        info!("Monitoring $J from USN {}", tail.end);
+        *cursor = Some(tail.end);
+        return Ok(Vec::new());
+    }
+    let recreated = matches!((position.journal_id, tail.journal_id), (Some(old), Some(new)) if old != new);
+    if cursor.is_none() {
+        // --from-start: everything still in the journal, nothing to compare against
+    } else if recreated {
+        warn!("$J was recreated (journal ID {:016X}), reading it from the start", tail.journal_id.unwrap_or_default());
+        tail = volume.read_usn_journal_tail(location, 0)?.context("$UsnJrnl no longer has a $J stream")?;
+    } else if tail.end < from {
+        warn!("$J shrank from {} to {} bytes; the journal was recreated, reading it from the start", from, tail.end);
+        tail = volume.read_usn_journal_tail(location, 0)?.context("$UsnJrnl no longer has a $J stream")?;
+    } else if tail.start > from {
+        warn!(
+            "$J wrapped between polls; changes from USN {} to {} were lost. Poll more often with --interval",
+            from, tail.start
+        );
+    }
+    *cursor = Some(tail.end);
+    position.journal_id = tail.journal_id;
+
+    let mut parser = usn_journal::UsnJournalParser::new(tail.data).with_base_offset(tail.start);
+    parser.parse_filtered(filter)?;
+    Ok(parser.get_entries().to_vec())
+}
 
-    let mut parser = mft::MftParser::new(mmap.to_vec());
+/// Heuristic weights from --anomaly-model, or the defaults
+fn load_anomaly_model(cli: &Cli) -> Result<anomaly::AnomalyModel> {
+    let Some(ref path) = cli.anomaly_model_file else {
+        return Ok(anomaly::AnomalyModel::default());
+    };
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read anomaly model: {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| format!("Invalid anomaly model: {}", path.display()))
+}
+
+/// Parsed records of an extracted $MFT or the $MFT of a volume
+fn parse_mft_for_lookup(path: &Path) -> Result<mft::MftParser> {
+    let (mft_data, _) = read_mft_for_lookup(path)?;
+    let mut parser = mft::MftParser::new(mft_data);
     parser.parse()?;
+    Ok(parser)
+}
 
-    let records = parser.get_records();
-    info!("Parsed {} MFT records", records.len());
+/// The $MFT of a volume with its cluster size, or the contents of an extracted $MFT file
+fn read_mft_for_lookup(path: &Path) -> Result<(Vec<u8>, Option<u64>)> {
+    let input = InputInfo::read(path)
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
 
-    // Handle specific entry dump if requested
-    if let Some(ref entry_spec) = cli.dump_entry {
-        dump_specific_entry(records, entry_spec)?;
+    if volume::detect(&input) > artifact::NO_MATCH {
+        let mut volume = volume::VolumeReader::open(path)
+            .with_context(|| format!("Failed to open NTFS volume: {}", path.display()))?;
+        let mft_data = volume.read_mft().context("Failed to read $MFT from volume")?;
+        return Ok((mft_data, Some(volume.cluster_size())));
+    }
+
+    let file = evidence::open_read_only(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    Ok((mmap.to_vec(), None))
+}
+
+/// Write the run summary next to the JSON output
+fn write_summary(cli: &Cli, parser: &dyn ArtifactParser, summary: &json::AnalysisSummary) -> Result<()> {
+    let Some(ref json_dir) = cli.json_dir else {
+        return Ok(());
+    };
+
+    let name = format!("{}_summary", parser.name());
+    let output_path = json_dir.join(cli.get_default_filename("json", &name));
+    json::JsonOutput::write_analysis_summary(summary, &output_path)?;
+    info!("Summary written to: {}", output_path.display());
+
+    Ok(())
+}
+
+/// Print a console preview of parsed records in the format selected by --format
+fn print_preview<T: serde::Serialize>(
+    cli: &Cli,
+    records: &[T],
+    label: &str,
+    limit: Option<usize>,
+    print_table: impl Fn(&[T], Option<usize>),
+) -> Result<()> {
+    if cli.no_preview {
         return Ok(());
     }
 
-    // Output results
-    output_results(cli, records, "mft")?;
+    let shown = &records[..limit.map_or(records.len(), |n| n.min(records.len()))];
 
-    // Show console output if requested
     match cli.output_format {
-        OutputFormat::Table => table::TableOutput::print_mft_records(records, Some(20)),
-        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+        OutputFormat::Table => print_table(records, limit),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(shown)?),
         OutputFormat::Csv => {
-            // Print CSV headers and first few records
-            println!("entry_number,sequence_number,file_name,file_size,in_use,is_directory");
-            for record in records.iter().take(10) {
-                println!("{},{},{},{},{},{}",
-                    record.entry_number,
-                    record.sequence_number,
-                    record.file_name,
-                    record.file_size,
-                    record.in_use,
-                    record.is_directory);
+            let mut writer = ::csv::Writer::from_writer(std::io::stdout());
+            for record in shown {
+                writer.serialize(record)?;
             }
+            writer.flush()?;
         }
-        OutputFormat::Minimal => {
-            println!("Processed {} MFT records", records.len());
-        }
+        OutputFormat::Minimal => println!("Processed {} {}", records.len(), label),
     }
 
     Ok(())
 }
 
-fn process_usn_journal(cli: &Cli) -> Result<()> {
-    info!("Processing USN Journal file: {}", cli.file.display());
-
-    let file = File::open(&cli.file)?;
-    let mmap = unsafe { Mmap::map(&file)? };
+fn output_results(mut pipeline: RecordPipeline, records: &[ntfs::types::MftRecord]) -> Result<()> {
+    if !pipeline.is_empty() {
+        for chunk in records.chunks(pipeline::BATCH_SIZE) {
+            pipeline.send(chunk.iter().cloned());
+        }
+    }
+    log_outputs(&pipeline.finish()?);
 
-    let mut parser = usn_journal::UsnJournalParser::new(mmap.to_vec());
-    parser.parse()?;
+    Ok(())
+}
 
-    let entries = parser.get_entries();
-    info!("Parsed {} USN Journal entries", entries.len());
+/// Every output sink requested on the command line, in the order they are reported
+fn output_sinks(
+    cli: &Cli,
+    file_type: &str,
+    bodyfile: bool,
+    single_record: bool,
+) -> Result<Vec<Box<dyn sink::OutputSink<ArtifactRecord>>>> {
+    let mut sinks: Vec<Box<dyn sink::OutputSink<ArtifactRecord>>> = Vec::new();
 
-    // Output results
     if let Some(ref json_dir) = cli.json_dir {
-        let default_filename = cli.get_default_filename("json", "usn");
-        let filename = cli.json_filename.as_deref().unwrap_or(&default_filename);
-        let output_path = json_dir.join(filename);
-        json::JsonOutput::write_usn_journal_entries(entries, &output_path)?;
-        info!("JSON output written to: {}", output_path.display());
+        let filename = get_filename_with_default(
+            cli.json_filename.as_deref(),
+            cli.get_default_filename("json", file_type)
+        );
+        let json = sink::JsonSink::new(json_dir.join(filename), cli.json_options());
+        sinks.push(Box::new(if single_record { json.single_object() } else { json }));
+    }
+
+    if let Some(ref jsonl_dir) = cli.jsonl_dir {
+        let output_path = jsonl_dir.join(cli.get_default_filename("jsonl", file_type));
+        sinks.push(Box::new(sink::JsonlSink::new(output_path).normalized(cli.normalized)));
     }
 
     if let Some(ref csv_dir) = cli.csv_dir {
         let filename = get_filename_with_default(
             cli.csv_filename.as_deref(),
-            cli.get_default_filename("csv", "usn")
+            cli.get_default_filename("csv", file_type)
         );
-        let output_path = csv_dir.join(&filename);
-        csv::CsvOutput::write_usn_journal_entries(entries, &output_path)?;
-        info!("CSV output written to: {}", output_path.display());
+        let output_path = csv_dir.join(filename);
+        match cli.template {
+            Some(ref template) => {
+                let template = template::Template::parse(template)?;
+                sinks.push(Box::new(sink::TemplateSink::new(output_path, template, cli.csv_preamble())));
+            }
+            None => sinks.push(Box::new(sink::CsvSink::new(output_path, cli.csv_options()).normalized(cli.normalized))),
+        }
     }
 
-    if let Some(ref body_dir) = cli.body_dir {
-        let filename = get_filename_with_default(
-            cli.body_filename.as_deref(),
-            cli.get_default_filename("body", "usn")
-        );
-        let output_path = body_dir.join(&filename);
-        let drive_letter = cli.body_drive_letter.as_deref().unwrap_or("C");
-        bodyfile::BodyfileOutput::write_usn_journal_entries(entries, &output_path, drive_letter, cli.body_lf)?;
-        info!("Bodyfile output written to: {}", output_path.display());
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite_dir) = cli.sqlite_dir() {
+        let output_path = sqlite_dir.join(cli.get_default_filename("sqlite", file_type));
+        sinks.push(Box::new(sqlite::SqliteSink::new(output_path, file_type)));
     }
 
-    // Console output
-    match cli.output_format {
-        OutputFormat::Table => table::TableOutput::print_usn_journal_entries(entries, Some(20)),
-        _ => println!("Processed {} USN Journal entries", entries.len()),
+    #[cfg(feature = "parquet")]
+    if let Some(parquet_dir) = cli.parquet_dir() {
+        let output_path = parquet_dir.join(cli.get_default_filename("parquet", file_type));
+        sinks.push(Box::new(parquet::ParquetSink::new(output_path)));
     }
 
-    Ok(())
-}
-
-fn process_boot(cli: &Cli) -> Result<()> {
-    info!("Processing Boot sector file: {}", cli.file.display());
-
-    let file = File::open(&cli.file)?;
-    let mmap = unsafe { Mmap::map(&file)? };
-
-    let boot_sector = boot::BootParser::parse(&mmap)?;
-    info!("Parsed boot sector information");
+    let body_options = if bodyfile && (cli.body_dir.is_some() || cli.mactime_dir.is_some()) {
+        Some(bodyfile_options(cli, file_type)?)
+    } else {
+        None
+    };
 
-    // Output results
-    if let Some(ref json_dir) = cli.json_dir {
+    if let (Some(body_dir), Some(options)) = (&cli.body_dir, body_options.clone()) {
         let filename = get_filename_with_default(
-            cli.json_filename.as_deref(),
-            cli.get_default_filename("json", "boot")
+            cli.body_filename.as_deref(),
+            cli.get_default_filename("body", file_type)
         );
-        let output_path = json_dir.join(&filename);
-        json::JsonOutput::write_boot_sector(&boot_sector, &output_path)?;
-        info!("JSON output written to: {}", output_path.display());
+        sinks.push(Box::new(sink::LineSink::new(
+            "Bodyfile",
+            body_dir.join(filename),
+            Box::new(move |record: &ArtifactRecord| record.body_line(&options)),
+        )));
     }
 
-    if let Some(ref csv_dir) = cli.csv_dir {
-        let filename = get_filename_with_default(
-            cli.csv_filename.as_deref(),
-            cli.get_default_filename("csv", "boot")
-        );
-        let output_path = csv_dir.join(&filename);
-        csv::CsvOutput::write_boot_sector(&boot_sector, &output_path)?;
-        info!("CSV output written to: {}", output_path.display());
+    if let (Some(mactime_dir), Some(options)) = (&cli.mactime_dir, body_options) {
+        let output_path = mactime_dir.join(cli.get_default_filename("mactime", file_type));
+        sinks.push(Box::new(mactime::MactimeSink::new(output_path, options)));
     }
 
-    // Console output
-    table::TableOutput::print_boot_sector(&boot_sector);
-
-    Ok(())
+    Ok(sinks)
 }
 
-fn process_sds(cli: &Cli) -> Result<()> {
-    info!("Processing SDS file: {}", cli.file.display());
-
-    let file = File::open(&cli.file)?;
-    let mmap = unsafe { Mmap::map(&file)? };
+/// Bodyfile settings; MFT records get owner RIDs as UIDs when --sds is given and
+/// $FILE_NAME rows with --bfn
+fn bodyfile_options(cli: &Cli, file_type: &str) -> Result<bodyfile::BodyfileOptions> {
+    let drive_letter = cli.body_drive_letter.as_deref().unwrap_or("C");
+    let options = bodyfile::BodyfileOptions::new(drive_letter, cli.body_lf)
+        .with_file_name_rows(cli.body_file_name);
 
-    let mut parser = sds::SdsParser::new(mmap.to_vec());
-    parser.parse()?;
+    let Some(ref sds_path) = cli.sds_file else {
+        return Ok(options);
+    };
+    if file_type != "mft" {
+        return Ok(options);
+    }
 
-    let descriptors = parser.get_descriptors();
-    info!("Parsed {} security descriptors", descriptors.len());
+    let owner_uids = load_owner_sids(sds_path)?
+        .into_iter()
+        .filter_map(|(security_id, sid)| sds::sid_rid(&sid).map(|rid| (security_id, rid)))
+        .collect();
+    Ok(options.with_owner_uids(owner_uids))
+}
 
-    // Handle specific security descriptor dump if requested
-    if let Some(ref security_id) = cli.dump_security {
-        dump_specific_security_descriptor(descriptors, security_id)?;
-        return Ok(());
+/// Record pipeline writing to every requested output
+fn record_pipeline(cli: &Cli, file_type: &str, bodyfile: bool, single_record: bool) -> Result<RecordPipeline> {
+    let mut pipeline = RecordPipeline::new();
+    pipeline.add_enricher(enrichment::record_id_enricher(cli.volume_serial.unwrap_or_default()));
+    if !cli.context.is_empty() {
+        pipeline.add_enricher(enrichment::context_enricher(&cli.context));
     }
-
-    // Output results
-    if let Some(ref json_dir) = cli.json_dir {
-        let filename = get_filename_with_default(
-            cli.json_filename.as_deref(),
-            cli.get_default_filename("json", "sds")
-        );
-        let output_path = json_dir.join(&filename);
-        json::JsonOutput::write_security_descriptors(descriptors, &output_path)?;
-        info!("JSON output written to: {}", output_path.display());
+    for path in &cli.lookup_tables {
+        pipeline.add_enricher(LookupTable::load(path)?.into_enricher());
+    }
+    // Before any time coarsening, so changes are matched at the times they happened
+    if file_type == "usn"
+        && let Some(ref path) = cli.process_hints_file {
+        pipeline.add_enricher(enrichment::ProcessHints::load(path)?.into_enricher());
     }
+    if let Some(coarsening) = time_coarsening(cli) {
+        pipeline.add_enricher(enrichment::time_coarsening_enricher(coarsening));
+    }
+    for sink in output_sinks(cli, file_type, bodyfile, single_record)? {
+        pipeline.add_sink(sink)?;
+    }
+    Ok(pipeline)
+}
 
-    if let Some(ref csv_dir) = cli.csv_dir {
-        let filename = get_filename_with_default(
-            cli.csv_filename.as_deref(),
-            cli.get_default_filename("csv", "sds")
-        );
-        let output_path = csv_dir.join(&filename);
-        csv::CsvOutput::write_security_descriptors(descriptors, &output_path)?;
-        info!("CSV output written to: {}", output_path.display());
+/// --round-times and --jitter-times, applied to every output that carries a timestamp
+fn time_coarsening(cli: &Cli) -> Option<filetime::TimeCoarsening> {
+    (cli.round_times.is_some() || cli.jitter_times.is_some()).then(|| filetime::TimeCoarsening {
+        granularity_seconds: cli.round_times.map_or(0, |unit| unit.seconds()),
+        shift_seconds: cli.jitter_times.map_or(0, enrichment::run_time_shift),
+    })
+}
+
+/// Coarsen the timestamps of rows and findings written outside the record pipeline
+fn coarsen_times<T: MapTimes>(cli: &Cli, items: &mut [T]) {
+    if let Some(coarsening) = time_coarsening(cli) {
+        coarsening.apply_to(items);
     }
+}
 
-    // Console output
-    match cli.output_format {
-        OutputFormat::Table => table::TableOutput::print_security_descriptors(descriptors, Some(20)),
-        _ => println!("Processed {} security descriptors", descriptors.len()),
+/// Records as the console preview shows them, coarsened like the written outputs
+fn preview_records<'a, T: MapTimes + Clone>(cli: &Cli, records: &'a [T]) -> Cow<'a, [T]> {
+    match time_coarsening(cli) {
+        Some(coarsening) if !cli.no_preview => {
+            let mut records = records.to_vec();
+            coarsening.apply_to(&mut records);
+            Cow::Owned(records)
+        }
+        _ => Cow::Borrowed(records),
     }
+}
 
+/// Write already parsed records to every requested output
+fn write_outputs<R: Into<ArtifactRecord>>(
+    cli: &Cli,
+    records: impl IntoIterator<Item = R>,
+    file_type: &str,
+    bodyfile: bool,
+    single_record: bool,
+) -> Result<()> {
+    let mut pipeline = record_pipeline(cli, file_type, bodyfile, single_record)?;
+    pipeline.send(records);
+    log_outputs(&pipeline.finish()?);
     Ok(())
 }
 
-fn process_i30(cli: &Cli) -> Result<()> {
-    info!("Processing I30 index file: {}", cli.file.display());
-
-    let file = File::open(&cli.file)?;
-    let mmap = unsafe { Mmap::map(&file)? };
-
-    let mut parser = i30::I30Parser::new(mmap.to_vec());
-    parser.parse()?;
+fn log_outputs(outputs: &[(&str, PathBuf)]) {
+    for (format, path) in outputs {
+        info!("{} output written to: {}", format, path.display());
+    }
+}
 
-    let entries = parser.get_entries();
-    info!("Parsed {} index entries", entries.len());
+/// The $MFT record filters requested on the command line
+fn mft_filters(cli: &Cli, parser: &mft::MftParser) -> Result<Vec<RecordFilter>> {
+    let mut filters = Vec::new();
 
-    // Output results
-    if let Some(ref json_dir) = cli.json_dir {
-        let filename = get_filename_with_default(
-            cli.json_filename.as_deref(),
-            cli.get_default_filename("json", "i30")
-        );
-        let output_path = json_dir.join(&filename);
-        json::JsonOutput::write_index_entries(entries, &output_path)?;
-        info!("JSON output written to: {}", output_path.display());
+    // Restrict to files owned by the requested SID
+    if let Some(ref sid) = cli.owner_sid {
+        filters.push(owner_sid_filter(cli, sid)?);
     }
 
-    if let Some(ref csv_dir) = cli.csv_dir {
-        let filename = get_filename_with_default(
-            cli.csv_filename.as_deref(),
-            cli.get_default_filename("csv", "i30")
-        );
-        let output_path = csv_dir.join(&filename);
-        csv::CsvOutput::write_index_entries(entries, &output_path)?;
-        info!("CSV output written to: {}", output_path.display());
+    // Restrict to records bookmarked in the requested set
+    if let Some(ref set) = cli.export_tagged {
+        filters.push(bookmark_filter(cli, set)?);
     }
 
-    if let Some(ref body_dir) = cli.body_dir {
-        let filename = get_filename_with_default(
-            cli.body_filename.as_deref(),
-            cli.get_default_filename("body", "i30")
-        );
-        let output_path = body_dir.join(&filename);
-        let drive_letter = cli.body_drive_letter.as_deref().unwrap_or("C");
-        bodyfile::BodyfileOutput::write_index_entries(entries, &output_path, drive_letter, cli.body_lf)?;
-        info!("Bodyfile output written to: {}", output_path.display());
+    // Leave out $MFT, $Bitmap, $Secure, the $Extend subtree and the other metadata files
+    if cli.exclude_system {
+        let metadata: HashSet<(u32, u32)> = parser
+            .get_records()
+            .iter()
+            .filter(|r| parser.is_metadata_file(r))
+            .map(|r| (r.entry_number, r.parent_entry_number))
+            .collect();
+        info!("Excluding {} NTFS metadata records", metadata.len());
+        filters.push(record::mft_filter(move |r| !metadata.contains(&(r.entry_number, r.parent_entry_number))));
     }
 
-    // Console output
-    match cli.output_format {
-        OutputFormat::Table => table::TableOutput::print_index_entries(entries, Some(20)),
-        _ => println!("Processed {} index entries", entries.len()),
+    // Extension records only hold attributes already shown on their base record's row
+    if cli.exclude_extensions {
+        filters.push(record::mft_filter(|r| r.base_record_reference.is_empty()));
     }
 
-    Ok(())
+    Ok(filters)
 }
 
-fn output_results(cli: &Cli, records: &[ntfs::types::MftRecord], file_type: &str) -> Result<()> {
-    // JSON output
-    if let Some(ref json_dir) = cli.json_dir {
-        let filename = get_filename_with_default(
-            cli.json_filename.as_deref(),
-            cli.get_default_filename("json", file_type)
-        );
-        let output_path = json_dir.join(&filename);
-        json::JsonOutput::write_mft_records(records, &output_path)?;
-        info!("JSON output written to: {}", output_path.display());
-    }
+fn owner_sid_filter(cli: &Cli, sid: &str) -> Result<RecordFilter> {
+    let sds_path = cli.sds_file.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("--sds is required when using --owner-sid"))?;
+    let owners = load_owner_sids(sds_path)?;
+    let sid = sid.to_string();
 
-    // CSV output
-    if let Some(ref csv_dir) = cli.csv_dir {
-        let filename = get_filename_with_default(
-            cli.csv_filename.as_deref(),
-            cli.get_default_filename("csv", file_type)
-        );
-        let output_path = csv_dir.join(&filename);
-        csv::CsvOutput::write_mft_records(records, &output_path)?;
-        info!("CSV output written to: {}", output_path.display());
-    }
+    Ok(record::mft_filter(move |r| owners.get(&(r.security_id as u32))
+        .is_some_and(|owner| owner.eq_ignore_ascii_case(&sid))))
+}
 
-    // Bodyfile output
-    if let Some(ref body_dir) = cli.body_dir {
-        let filename = get_filename_with_default(
-            cli.body_filename.as_deref(),
-            cli.get_default_filename("body", file_type)
-        );
-        let output_path = body_dir.join(&filename);
-        let drive_letter = cli.body_drive_letter.as_deref().unwrap_or("C");
-        bodyfile::BodyfileOutput::write_mft_records(records, &output_path, drive_letter, cli.body_lf)?;
-        info!("Bodyfile output written to: {}", output_path.display());
-    }
+fn bookmark_filter(cli: &Cli, set: &str) -> Result<RecordFilter> {
+    let tags_path = cli.tags_file.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("--tags is required when using --export-tagged"))?;
+    let store = bookmarks::BookmarkStore::load(tags_path)?;
+    let bookmarks = store.get(set)
+        .ok_or_else(|| anyhow::anyhow!("No bookmark set '{}' in {}", set, tags_path.display()))?
+        .clone();
 
-    Ok(())
+    Ok(record::mft_filter(move |r| bookmarks.iter().any(|b| b.matches(r.entry_number, r.sequence_number))))
+}
+
+/// Owner SID of every security descriptor in an $SDS file, by security ID
+fn load_owner_sids(sds_path: &Path) -> Result<HashMap<u32, String>> {
+    let owners = sds::owner_sid_map(&load_descriptors(sds_path)?);
+    info!("Resolved owner SIDs for {} security descriptors", owners.len());
+    Ok(owners)
+}
+
+/// Every security descriptor in an $SDS file
+fn load_descriptors(sds_path: &Path) -> Result<Vec<ntfs::types::SecurityDescriptor>> {
+    let file = evidence::open_read_only(sds_path)
+        .with_context(|| format!("Failed to open SDS file: {}", sds_path.display()))?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    let mut parser = sds::SdsParser::new(mmap.to_vec());
+    parser.parse()?;
+    Ok(parser.get_descriptors().to_vec())
 }
 
 fn dump_specific_entry(records: &[ntfs::types::MftRecord], entry_spec: &str) -> Result<()> {
@@ -403,17 +2203,84 @@ fn dump_specific_entry(records: &[ntfs::types::MftRecord], entry_spec: &str) ->
     println!("Entry Number:       {}", record.entry_number);
     println!("Sequence Number:    {}", record.sequence_number);
     println!("In Use:             {}", record.in_use);
+    if !record.in_use {
+        println!("Recoverability:     {:?}", record.recoverability);
+    }
     println!("File Name:          {}", record.file_name);
+    if !record.short_name.is_empty() {
+        println!("Short Name:         {}", record.short_name);
+    }
     println!("Parent Path:        {}", record.parent_path);
+    println!("Full Path:          {}", record.full_path);
+    for link in records.iter().filter(|r| r.is_hard_link && r.entry_number == record.entry_number && r.sequence_number == record.sequence_number) {
+        println!("Hard Link:          {}", link.full_path);
+    }
+    println!("Path Confidence:    {:?}", record.path_confidence);
     println!("File Size:          {}", record.file_size);
+    println!("Allocated Size:     {}", record.allocated_size);
+    if let Some(initialized_size) = record.initialized_size {
+        println!("Initialized Size:   {}", initialized_size);
+    }
     println!("Is Directory:       {}", record.is_directory);
     println!("Has ADS:            {}", record.has_ads);
+    if !record.ads_names.is_empty() {
+        println!("ADS Names:          {}", record.ads_names);
+    }
+    if !record.index_names.is_empty() {
+        println!("Indexes:            {}", record.index_names);
+    }
+    if let Some(zone_id) = record.zone_id {
+        println!("Zone ID:            {}", zone_id);
+    }
+    if !record.host_url.is_empty() {
+        println!("Host URL:           {}", record.host_url);
+    }
+    if !record.referrer_url.is_empty() {
+        println!("Referrer URL:       {}", record.referrer_url);
+    }
+    if record.ea_size > 0 {
+        println!("EA Size:            {}", record.ea_size);
+    }
+    if !record.extended_attributes.is_empty() {
+        println!("Extended Attrs:     {}", record.extended_attributes);
+    }
+    if !record.logged_util_stream.is_empty() {
+        println!("Logged Util Stream: {}", record.logged_util_stream);
+    }
+    if record.is_encrypted {
+        println!("EFS Encrypted:      true");
+    }
+    for key in record.efs_users.split('|').filter(|key| !key.is_empty()) {
+        println!("EFS User:           {}", key);
+    }
+    for key in record.efs_recovery_agents.split('|').filter(|key| !key.is_empty()) {
+        println!("EFS Recovery Agent: {}", key);
+    }
+    if record.reparse_tag != 0 {
+        println!("Reparse Tag:        0x{:08X} {}", record.reparse_tag, record.reparse_type);
+    }
+    if !record.reparse_target.is_empty() {
+        println!("Reparse Target:     {}", record.reparse_target);
+    }
+    if !record.reparse_print_name.is_empty() {
+        println!("Reparse Print Name: {}", record.reparse_print_name);
+    }
+    println!("Reference Count:    {}", record.reference_count);
+    if !record.base_record_reference.is_empty() {
+        println!("Base Record:        {}", record.base_record_reference);
+    }
+    if record.fixup_error {
+        println!("Fixup Error:        update sequence check failed; the record may be torn");
+    }
+    if record.entry_number_from_offset {
+        println!("Entry Number:       taken from the record's position; the header has none or its high bits are set");
+    }
 
     if let Some(created) = record.created_0x10 {
-        println!("Created (0x10):     {}", created.format("%Y-%m-%d %H:%M:%S%.6f"));
+        println!("Created (0x10):     {}", created);
     }
     if let Some(modified) = record.last_modified_0x10 {
-        println!("Modified (0x10):    {}", modified.format("%Y-%m-%d %H:%M:%S%.6f"));
+        println!("Modified (0x10):    {}", modified);
     }
 
     Ok(())
@@ -430,6 +2297,7 @@ fn dump_specific_security_descriptor(descriptors: &[ntfs::types::SecurityDescrip
     println!("{}", "-".repeat(50));
     println!("ID:                 {}", descriptor.id);
     println!("Hash:               0x{:08X}", descriptor.hash);
+    println!("Hash Valid:         {}", descriptor.hash_valid);
     println!("Offset:             0x{:016X}", descriptor.offset);
     println!("Length:             {}", descriptor.length);
     println!("Descriptor (hex):   {}", hex::encode(&descriptor.descriptor));
@@ -461,3 +2329,150 @@ fn parse_numeric_value(value: &str) -> Result<u64> {
             .with_context(|| format!("Invalid decimal value: {}", value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2023-06-15T13:45:12.3456789Z; every event below falls within minutes of it
+    const EXACT: u64 = 133_313_103_123_456_789;
+    const TICKS_PER_SECOND: u64 = 10_000_000;
+
+    /// Resident attribute with `content` right after its 0x18-byte header
+    fn resident_attribute(attr_type: u32, content: &[u8]) -> Vec<u8> {
+        let length = (0x18 + content.len()).next_multiple_of(8);
+        let mut attribute = vec![0u8; length];
+        attribute[0..4].copy_from_slice(&attr_type.to_le_bytes());
+        attribute[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+        attribute[0x10..0x14].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        attribute[0x14..0x16].copy_from_slice(&0x18u16.to_le_bytes());
+        attribute[0x18..0x18 + content.len()].copy_from_slice(content);
+        attribute
+    }
+
+    /// In-use FILE record with $STANDARD_INFORMATION and $FILE_NAME, all four times `time`
+    fn file_record(entry: u32, sequence: u16, name: &str, time: u64) -> Vec<u8> {
+        let times: Vec<u8> = (0..4).flat_map(|_| time.to_le_bytes()).collect();
+        let mut standard_information = times.clone();
+        standard_information.resize(72, 0);
+
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut file_name = (5u64 | 5 << 48).to_le_bytes().to_vec();
+        file_name.extend_from_slice(&times);
+        file_name.extend_from_slice(&[0u8; 24]);
+        file_name.extend_from_slice(&[(name.len() / 2) as u8, 1]);
+        file_name.extend_from_slice(&name);
+
+        // The zero update sequence array matches the zero stride ends
+        let mut record = vec![0u8; mft::DEFAULT_RECORD_SIZE];
+        record[0..4].copy_from_slice(b"FILE");
+        record[4..6].copy_from_slice(&0x30u16.to_le_bytes());
+        record[6..8].copy_from_slice(&3u16.to_le_bytes());
+        record[0x10..0x12].copy_from_slice(&sequence.to_le_bytes());
+        record[0x12..0x14].copy_from_slice(&1u16.to_le_bytes());
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+        record[0x16..0x18].copy_from_slice(&1u16.to_le_bytes());
+        record[0x1C..0x20].copy_from_slice(&(mft::DEFAULT_RECORD_SIZE as u32).to_le_bytes());
+        record[0x2C..0x30].copy_from_slice(&entry.to_le_bytes());
+
+        let mut offset = 0x38;
+        for attribute in [resident_attribute(0x10, &standard_information), resident_attribute(0x30, &file_name)] {
+            record[offset..offset + attribute.len()].copy_from_slice(&attribute);
+            offset += attribute.len();
+        }
+        record[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        record[0x18..0x1C].copy_from_slice(&(offset as u32 + 8).to_le_bytes());
+        record
+    }
+
+    fn usn_record(usn: usize, entry: u32, sequence: u16, reason: u32, name: &str, time: u64) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let length = (60 + name.len()).next_multiple_of(8);
+        let mut record = vec![0u8; length];
+        record[0..4].copy_from_slice(&(length as u32).to_le_bytes());
+        record[4..6].copy_from_slice(&2u16.to_le_bytes());
+        record[8..16].copy_from_slice(&(entry as u64 | (sequence as u64) << 48).to_le_bytes());
+        record[16..24].copy_from_slice(&(5u64 | 5 << 48).to_le_bytes());
+        record[24..32].copy_from_slice(&(usn as u64).to_le_bytes());
+        record[32..40].copy_from_slice(&time.to_le_bytes());
+        record[40..44].copy_from_slice(&reason.to_le_bytes());
+        record[52..56].copy_from_slice(&0x20u32.to_le_bytes());
+        record[56..58].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        record[58..60].copy_from_slice(&60u16.to_le_bytes());
+        record[60..60 + name.len()].copy_from_slice(&name);
+        record
+    }
+
+    fn read_outputs(dir: &Path, contents: &mut Vec<(PathBuf, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                read_outputs(&path, contents);
+            } else {
+                let text = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+                contents.push((path, text));
+            }
+        }
+    }
+
+    #[test]
+    fn round_times_leaves_no_exact_timestamp_in_any_output() {
+        let dir = std::env::temp_dir().join(format!("mfte-rs-round-times-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (input, output) = (dir.join("MFT"), dir.join("out"));
+        std::fs::create_dir_all(&output).unwrap();
+        let (file, out) = (input.to_str().unwrap(), output.to_str().unwrap());
+        let cli = Cli::try_parse_from([
+            "mfte-rs", "-f", file, "--csv", out, "--json", out, "--body", out, "--bdl", "C", "--mactime", out,
+            "--fl", "--entry-map", out, "--entry-reuse", "--round-times", "day", "--no-preview",
+        ])
+        .unwrap();
+
+        // Entries 40 to 44, each created a second after the previous one
+        let mut mft_data = vec![0u8; 45 * mft::DEFAULT_RECORD_SIZE];
+        for entry in 40..45u32 {
+            let offset = entry as usize * mft::DEFAULT_RECORD_SIZE;
+            let time = EXACT + entry as u64 * TICKS_PER_SECOND;
+            mft_data[offset..offset + mft::DEFAULT_RECORD_SIZE].copy_from_slice(&file_record(entry, 5, &format!("file{}.txt", entry), time));
+        }
+        std::fs::write(&input, &mft_data).unwrap();
+        assert_eq!(cli.validate(), Ok(()));
+        let mut lookup_mft = mft::MftParser::new(mft_data.clone());
+        lookup_mft.parse().unwrap();
+
+        // A mass deletion, a burst of renames to .locked and an earlier occupant of entry 40
+        let mut journal = Vec::new();
+        let mut add = |entry: u32, sequence: u16, reason: u32, name: &str, seconds: u64| {
+            let record = usn_record(journal.len(), entry, sequence, reason, name, EXACT + seconds * TICKS_PER_SECOND);
+            journal.extend_from_slice(&record);
+        };
+        for i in 0..200 {
+            add(100 + i, 1, 0x200, &format!("deleted{}.txt", i), i as u64);
+        }
+        for i in 0..25 {
+            add(400 + i, 1, 0x2000, &format!("report{}.locked", i), i as u64);
+        }
+        add(40, 2, 0x100, "old.txt", 0);
+
+        let mft_summary = process_mft_data(&cli, mft_data, None, None).unwrap();
+        let usn_summary = process_usn_data(&cli, journal, 0, Some(&lookup_mft)).unwrap();
+        assert!(!usn_summary.wiping_findings.is_empty());
+        assert!(!usn_summary.ransomware_alerts.is_empty());
+
+        let mut contents = Vec::new();
+        read_outputs(&output, &mut contents);
+        for name in ["filelisting", "entrymap", "ransomware", "entryreuse", ".body", ".mactime"] {
+            assert!(contents.iter().any(|(path, _)| path.to_string_lossy().contains(name)), "no {} output", name);
+        }
+        for summary in [&mft_summary, &usn_summary] {
+            contents.push((PathBuf::from("summary"), serde_json::to_string(summary).unwrap()));
+        }
+
+        // Rounded to the day, every time reads midnight; 168683xxxx are the Unix seconds of the exact times
+        for (path, text) in &contents {
+            assert!(!text.contains("13:4") && !text.contains("168683"), "exact timestamp in {}", path.display());
+        }
+        assert!(contents.iter().any(|(_, text)| text.contains("2023-06-15T00:00:00")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
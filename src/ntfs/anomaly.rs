@@ -0,0 +1,102 @@
+use super::filetime::FileTime;
+use super::types::MftRecord;
+use serde::{Deserialize, Serialize};
+
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Weights and thresholds of the timestomping heuristics behind `anomaly_score`.
+///
+/// Each heuristic a record trips adds its weight to the score; a weight of 0
+/// turns the heuristic off. Read from a JSON file given with --anomaly-model,
+/// in which any field left out keeps its default, e.g.
+/// `{"si_zero_fraction": 10, "threshold": 60}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnomalyModel {
+    /// $STANDARD_INFORMATION created is earlier than $FILE_NAME created, which
+    /// SetFileTime can do but the file system itself never does
+    pub si_created_before_fn: u32,
+    /// $STANDARD_INFORMATION last record change is earlier than $FILE_NAME
+    /// created, though the change time moves whenever the record does
+    pub si_changed_before_fn: u32,
+    /// Every $STANDARD_INFORMATION timestamp falls on a whole second while the
+    /// $FILE_NAME created timestamp does not, as tools setting times from a
+    /// date string leave them
+    pub si_zero_fraction: u32,
+    /// $STANDARD_INFORMATION last modified is earlier than its created. Also
+    /// left by copies, which keep the source's modified time, so weighted low
+    pub si_modified_before_created: u32,
+    /// A timestamp is past year 9999 or later than the time of the run
+    pub invalid_timestamp: u32,
+    /// Differences smaller than this many seconds are not counted as one
+    /// timestamp being earlier than another
+    pub tolerance_seconds: u64,
+    /// Score at or above which a record counts as anomalous in the summary
+    pub threshold: u32,
+}
+
+impl Default for AnomalyModel {
+    fn default() -> Self {
+        Self {
+            si_created_before_fn: 40,
+            si_changed_before_fn: 30,
+            si_zero_fraction: 30,
+            si_modified_before_created: 10,
+            invalid_timestamp: 20,
+            tolerance_seconds: 1,
+            threshold: 50,
+        }
+    }
+}
+
+impl AnomalyModel {
+    /// Score of `record` and the names of the heuristics it tripped, separated by '|'
+    pub fn score(&self, record: &MftRecord) -> (u32, String) {
+        let si_created = record.created_0x10;
+        let si_modified = record.last_modified_0x10;
+        let si_changed = record.last_record_change_0x10;
+        let si_accessed = record.last_access_0x10;
+        let fn_created = record.created_0x30;
+
+        let whole_second = |time: Option<FileTime>| time.is_none_or(|time| time.raw().is_multiple_of(TICKS_PER_SECOND));
+        let si_times = [si_created, si_modified, si_changed, si_accessed];
+
+        let checks = [
+            ("si_created_before_fn", self.si_created_before_fn, self.earlier(si_created, fn_created)),
+            ("si_changed_before_fn", self.si_changed_before_fn, self.earlier(si_changed, fn_created)),
+            (
+                "si_zero_fraction",
+                self.si_zero_fraction,
+                si_times.iter().any(Option::is_some) && si_times.into_iter().all(whole_second) && !whole_second(fn_created),
+            ),
+            ("si_modified_before_created", self.si_modified_before_created, self.earlier(si_modified, si_created)),
+            ("invalid_timestamp", self.invalid_timestamp, !record.timestamp_anomalies.is_empty()),
+        ];
+
+        let mut score = 0u32;
+        let mut tripped = Vec::new();
+        for (name, weight, hit) in checks {
+            if hit && weight > 0 {
+                score = score.saturating_add(weight);
+                tripped.push(name);
+            }
+        }
+        (score, tripped.join("|"))
+    }
+
+    /// Whether a record scoring `score` counts as anomalous
+    pub fn is_anomalous(&self, score: u32) -> bool {
+        score > 0 && score >= self.threshold
+    }
+
+    /// Whether `time` lies before `other` by at least the tolerance
+    fn earlier(&self, time: Option<FileTime>, other: Option<FileTime>) -> bool {
+        match (time, other) {
+            (Some(time), Some(other)) if time.raw() != 0 && other.raw() != 0 => {
+                time.raw().saturating_add(self.tolerance_seconds.saturating_mul(TICKS_PER_SECOND)) <= other.raw()
+                    && time < other
+            }
+            _ => false,
+        }
+    }
+}
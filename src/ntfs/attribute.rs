@@ -0,0 +1,204 @@
+use super::names::decode_utf16le_lossy;
+use super::types::{ErrorCode, ParseError, ParseResult};
+
+/// Size of the header fields shared by resident and non-resident attributes
+const COMMON_HEADER_SIZE: usize = 0x10;
+const RESIDENT_HEADER_SIZE: usize = 0x18;
+const NON_RESIDENT_HEADER_SIZE: usize = 0x40;
+
+/// Attributes are laid out on 8-byte boundaries within a FILE record
+const ATTRIBUTE_ALIGNMENT: u32 = 8;
+
+/// Attribute record header from a FILE record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeHeader {
+    pub attr_type: u32,
+    pub length: u32,
+    pub name_length: u8,
+    pub name_offset: u16,
+    pub flags: u16,
+    pub attribute_id: u16,
+    pub form: AttributeForm,
+}
+
+/// Layout-specific part of the header, selected by the non-resident flag at 0x08
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeForm {
+    Resident {
+        content_size: u32,
+        content_offset: u16,
+    },
+    NonResident(NonResidentHeader),
+}
+
+/// Header of an attribute whose content lives in clusters described by a runlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonResidentHeader {
+    pub starting_vcn: u64,
+    pub last_vcn: u64,
+    pub runlist_offset: u16,
+    /// Compression unit as a power of two of clusters; 0 when uncompressed
+    pub compression_unit: u16,
+    pub allocated_size: u64,
+    pub real_size: u64,
+    pub initialized_size: u64,
+}
+
+impl AttributeHeader {
+    /// Parse the header at the start of `attribute`, which must cover the whole attribute.
+    ///
+    /// The on-disk length is checked against the header size and the bytes
+    /// available, so callers can step by it without stalling or over-reading.
+    pub fn parse(attribute: &[u8]) -> ParseResult<Self> {
+        if attribute.len() < COMMON_HEADER_SIZE {
+            return Err(header_error("Attribute header truncated", 0));
+        }
+
+        let attr_type = read_u32(attribute, 0x00);
+        let length = read_u32(attribute, 0x04);
+        let non_resident = attribute[0x08] != 0;
+        let name_length = attribute[0x09];
+        let name_offset = read_u16(attribute, 0x0A);
+        let flags = read_u16(attribute, 0x0C);
+        let attribute_id = read_u16(attribute, 0x0E);
+
+        let header_size = if non_resident { NON_RESIDENT_HEADER_SIZE } else { RESIDENT_HEADER_SIZE };
+        if (length as usize) < header_size || !length.is_multiple_of(ATTRIBUTE_ALIGNMENT) {
+            return Err(header_error(&format!("Invalid attribute length {}", length), 0x04));
+        }
+        if length as usize > attribute.len() {
+            return Err(header_error(&format!("Attribute length {} runs past the end of the record", length), 0x04));
+        }
+
+        let form = if non_resident {
+            if attribute.len() < NON_RESIDENT_HEADER_SIZE {
+                return Err(header_error("Non-resident attribute header truncated", 0x10));
+            }
+            AttributeForm::NonResident(NonResidentHeader {
+                starting_vcn: read_u64(attribute, 0x10),
+                last_vcn: read_u64(attribute, 0x18),
+                runlist_offset: read_u16(attribute, 0x20),
+                compression_unit: read_u16(attribute, 0x22),
+                allocated_size: read_u64(attribute, 0x28),
+                real_size: read_u64(attribute, 0x30),
+                initialized_size: read_u64(attribute, 0x38),
+            })
+        } else {
+            if attribute.len() < RESIDENT_HEADER_SIZE {
+                return Err(header_error("Resident attribute header truncated", 0x10));
+            }
+            AttributeForm::Resident {
+                content_size: read_u32(attribute, 0x10),
+                content_offset: read_u16(attribute, 0x14),
+            }
+        };
+
+        Ok(Self {
+            attr_type,
+            length,
+            name_length,
+            name_offset,
+            flags,
+            attribute_id,
+            form,
+        })
+    }
+
+    pub fn is_resident(&self) -> bool {
+        matches!(self.form, AttributeForm::Resident { .. })
+    }
+
+    pub fn non_resident(&self) -> Option<&NonResidentHeader> {
+        match self.form {
+            AttributeForm::NonResident(ref header) => Some(header),
+            AttributeForm::Resident { .. } => None,
+        }
+    }
+
+    /// Attribute name (e.g. "Zone.Identifier" or "$I30"); empty for unnamed attributes
+    pub fn name(&self, attribute: &[u8]) -> String {
+        let start = self.name_offset as usize;
+        let end = start + self.name_length as usize * 2;
+        attribute
+            .get(start..end)
+            .map(decode_utf16le_lossy)
+            .unwrap_or_default()
+    }
+
+    /// Resident content, or None for non-resident attributes and out-of-bounds content
+    pub fn resident_content<'a>(&self, attribute: &'a [u8]) -> Option<&'a [u8]> {
+        let AttributeForm::Resident { content_size, content_offset } = self.form else {
+            return None;
+        };
+        let start = content_offset as usize;
+        attribute.get(start..start.checked_add(content_size as usize)?)
+    }
+
+    /// Encoded runlist of a non-resident attribute
+    pub fn runlist<'a>(&self, attribute: &'a [u8]) -> Option<&'a [u8]> {
+        let header = self.non_resident()?;
+        attribute.get(header.runlist_offset as usize..)
+    }
+}
+
+fn header_error(message: &str, offset: u64) -> ParseError {
+    ParseError {
+        code: ErrorCode::AttributeHeader,
+        message: message.to_string(),
+        offset: Some(offset),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A resident $DATA attribute of `size` bytes whose header claims `length`
+    fn resident_attribute(length: u32, size: usize) -> Vec<u8> {
+        let mut attribute = vec![0u8; size];
+        attribute[0x00..0x04].copy_from_slice(&0x80u32.to_le_bytes());
+        attribute[0x04..0x08].copy_from_slice(&length.to_le_bytes());
+        attribute
+    }
+
+    #[test]
+    fn rejects_zero_length() {
+        let error = AttributeHeader::parse(&resident_attribute(0, 0x20)).unwrap_err();
+        assert_eq!(error.code, ErrorCode::AttributeHeader);
+    }
+
+    #[test]
+    fn rejects_length_past_record() {
+        let error = AttributeHeader::parse(&resident_attribute(0x100, 0x20)).unwrap_err();
+        assert_eq!(error.code, ErrorCode::AttributeHeader);
+    }
+
+    #[test]
+    fn rejects_unaligned_length() {
+        assert!(AttributeHeader::parse(&resident_attribute(0x1C, 0x20)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        assert!(AttributeHeader::parse(&[0x80, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn accepts_length_inside_record() {
+        let header = AttributeHeader::parse(&resident_attribute(0x18, 0x20)).unwrap();
+        assert_eq!(header.length, 0x18);
+        assert!(header.is_resident());
+    }
+}
@@ -0,0 +1,71 @@
+use super::types::DataRun;
+
+/// The volume's $Bitmap: one bit per cluster, set when the cluster is allocated
+#[derive(Debug, Clone)]
+pub struct ClusterBitmap {
+    bits: Vec<u8>,
+}
+
+impl ClusterBitmap {
+    pub fn new(bits: Vec<u8>) -> Self {
+        Self { bits }
+    }
+
+    /// Number of clusters the bitmap covers
+    pub fn cluster_count(&self) -> u64 {
+        self.bits.len() as u64 * 8
+    }
+
+    /// Clusters past the end of the bitmap are reported as allocated, since
+    /// nothing can be recovered from outside the volume
+    pub fn is_allocated(&self, lcn: u64) -> bool {
+        self.bits
+            .get((lcn / 8) as usize)
+            .is_none_or(|byte| byte & (1 << (lcn % 8)) != 0)
+    }
+
+    /// Start and length of the longest run of free clusters, if any cluster is free
+    pub fn largest_free_extent(&self) -> Option<(u64, u64)> {
+        let mut largest: Option<(u64, u64)> = None;
+        let mut run_start = 0;
+        let mut run_length = 0u64;
+
+        for (index, &byte) in self.bits.iter().enumerate() {
+            let first = index as u64 * 8;
+            // Whole bytes of free or allocated clusters skip the per-bit loop
+            if byte == 0 {
+                if run_length == 0 {
+                    run_start = first;
+                }
+                run_length += 8;
+                continue;
+            }
+            for bit in 0..8 {
+                if byte & (1 << bit) == 0 {
+                    if run_length == 0 {
+                        run_start = first + bit;
+                    }
+                    run_length += 1;
+                } else {
+                    if run_length > 0 && largest.is_none_or(|(_, length)| run_length > length) {
+                        largest = Some((run_start, run_length));
+                    }
+                    run_length = 0;
+                }
+            }
+        }
+        if run_length > 0 && largest.is_none_or(|(_, length)| run_length > length) {
+            largest = Some((run_start, run_length));
+        }
+
+        largest
+    }
+
+    /// Allocated clusters among the non-sparse clusters of `runs`
+    pub fn count_allocated(&self, runs: &[DataRun]) -> u64 {
+        runs.iter()
+            .filter_map(|run| run.lcn.map(|lcn| (lcn, run.length)))
+            .map(|(lcn, length)| (lcn..lcn.saturating_add(length)).filter(|&c| self.is_allocated(c)).count() as u64)
+            .sum()
+    }
+}
@@ -1,13 +1,18 @@
-use super::types::{BootSector, ParseError, ParseResult};
+use super::types::{BootSector, ErrorCode, ParseError, ParseResult};
+use crate::artifact::{InputInfo, NO_MATCH, SIGNATURE_MATCH};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::{Cursor, Read};
 
+/// Size of an extracted $Boot file; larger inputs with a boot sector are whole volumes
+pub const BOOT_FILE_SIZE: u64 = 8192;
+
 pub struct BootParser;
 
 impl BootParser {
     pub fn parse(data: &[u8]) -> ParseResult<BootSector> {
         if data.len() < 512 {
             return Err(ParseError {
+                code: ErrorCode::BootSector,
                 message: "Boot sector data too small".to_string(),
                 offset: None,
             });
@@ -64,4 +69,18 @@ impl BootParser {
             volume_label: String::new(), // Volume label is typically in MFT, not boot sector
         })
     }
-}
\ No newline at end of file
+}
+
+/// True when the header starts with an NTFS boot sector
+pub fn has_boot_signature(header: &[u8]) -> bool {
+    header.len() >= 512 && header[3..11] == *b"NTFS    "
+}
+
+/// A $Boot extract is a regular file of at most 8 KiB starting with a boot sector
+pub fn detect(input: &InputInfo) -> u8 {
+    if has_boot_signature(&input.header) && input.is_regular_file && input.size <= BOOT_FILE_SIZE {
+        SIGNATURE_MATCH
+    } else {
+        NO_MATCH
+    }
+}
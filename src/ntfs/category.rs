@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Directory,
+    Executable,
+    Library,
+    Script,
+    Document,
+    Archive,
+    Image,
+    Audio,
+    Video,
+    Database,
+    Email,
+    Shortcut,
+    Config,
+    Log,
+    DiskImage,
+    Other,
+}
+
+impl fmt::Display for FileCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FileCategory::Directory => "directory",
+            FileCategory::Executable => "executable",
+            FileCategory::Library => "library",
+            FileCategory::Script => "script",
+            FileCategory::Document => "document",
+            FileCategory::Archive => "archive",
+            FileCategory::Image => "image",
+            FileCategory::Audio => "audio",
+            FileCategory::Video => "video",
+            FileCategory::Database => "database",
+            FileCategory::Email => "email",
+            FileCategory::Shortcut => "shortcut",
+            FileCategory::Config => "config",
+            FileCategory::Log => "log",
+            FileCategory::DiskImage => "diskimage",
+            FileCategory::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Classify a file by its extension
+pub fn from_extension(extension: &str, is_directory: bool) -> FileCategory {
+    if is_directory {
+        return FileCategory::Directory;
+    }
+
+    // Compound extensions classify by their last component (tar.gz -> gz)
+    let last = extension.rsplit('.').next().unwrap_or(extension).to_ascii_lowercase();
+
+    match last.as_str() {
+        "exe" | "com" | "scr" | "msi" | "msp" | "cpl" | "pif" | "elf" | "app" => FileCategory::Executable,
+        "dll" | "sys" | "ocx" | "drv" | "so" | "dylib" | "efi" => FileCategory::Library,
+        "ps1" | "psm1" | "psd1" | "bat" | "cmd" | "vbs" | "vbe" | "js" | "jse" | "wsf" | "wsh"
+        | "hta" | "py" | "pyw" | "sh" | "pl" | "rb" | "php" | "lua" => FileCategory::Script,
+        "doc" | "docx" | "docm" | "dot" | "dotx" | "xls" | "xlsx" | "xlsm" | "xlsb" | "csv" | "ppt"
+        | "pptx" | "pptm" | "pdf" | "rtf" | "odt" | "ods" | "odp" | "txt" | "md" | "one" => FileCategory::Document,
+        "zip" | "rar" | "7z" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "tar" | "cab" | "lz" | "lzma"
+        | "z" | "arj" | "lz4" | "br" => FileCategory::Archive,
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tif" | "tiff" | "ico" | "webp" | "heic" | "svg" => FileCategory::Image,
+        "mp3" | "wav" | "flac" | "aac" | "ogg" | "wma" | "m4a" => FileCategory::Audio,
+        "mp4" | "avi" | "mkv" | "mov" | "wmv" | "flv" | "webm" | "m4v" => FileCategory::Video,
+        "db" | "sqlite" | "sqlite3" | "mdb" | "accdb" | "edb" | "sdf" | "ldf" | "mdf" => FileCategory::Database,
+        "pst" | "ost" | "eml" | "msg" | "mbox" => FileCategory::Email,
+        "lnk" | "url" | "website" => FileCategory::Shortcut,
+        "ini" | "inf" | "cfg" | "conf" | "config" | "xml" | "json" | "yaml" | "yml" | "reg" | "toml" => FileCategory::Config,
+        "log" | "etl" | "evtx" | "evt" => FileCategory::Log,
+        "iso" | "img" | "vhd" | "vhdx" | "vmdk" | "dmg" | "e01" | "dd" | "raw" => FileCategory::DiskImage,
+        _ => FileCategory::Other,
+    }
+}
+
+/// Classify a file by the magic bytes at the start of its content
+pub fn from_magic(data: &[u8]) -> Option<FileCategory> {
+    const SIGNATURES: &[(&[u8], FileCategory)] = &[
+        (b"MZ", FileCategory::Executable),
+        (b"\x7fELF", FileCategory::Executable),
+        (b"#!", FileCategory::Script),
+        (b"%PDF", FileCategory::Document),
+        (b"{\\rtf", FileCategory::Document),
+        (b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1", FileCategory::Document),
+        (b"PK\x03\x04", FileCategory::Archive),
+        (b"Rar!\x1A\x07", FileCategory::Archive),
+        (b"7z\xBC\xAF\x27\x1C", FileCategory::Archive),
+        (b"\x1F\x8B", FileCategory::Archive),
+        (b"MSCF", FileCategory::Archive),
+        (b"\x89PNG", FileCategory::Image),
+        (b"\xFF\xD8\xFF", FileCategory::Image),
+        (b"GIF8", FileCategory::Image),
+        (b"SQLite format 3\0", FileCategory::Database),
+        (b"L\0\0\0\x01\x14\x02\0", FileCategory::Shortcut),
+    ];
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|&(_, category)| category)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_extensions_to_categories() {
+        let table = [
+            ("exe", FileCategory::Executable),
+            ("msi", FileCategory::Executable),
+            ("dll", FileCategory::Library),
+            ("sys", FileCategory::Library),
+            ("ps1", FileCategory::Script),
+            ("bat", FileCategory::Script),
+            ("js", FileCategory::Script),
+            ("docx", FileCategory::Document),
+            ("pdf", FileCategory::Document),
+            ("txt", FileCategory::Document),
+            ("zip", FileCategory::Archive),
+            ("7z", FileCategory::Archive),
+            ("png", FileCategory::Image),
+            ("mp3", FileCategory::Audio),
+            ("mkv", FileCategory::Video),
+            ("sqlite", FileCategory::Database),
+            ("pst", FileCategory::Email),
+            ("lnk", FileCategory::Shortcut),
+            ("ini", FileCategory::Config),
+            ("evtx", FileCategory::Log),
+            ("vhdx", FileCategory::DiskImage),
+            ("e01", FileCategory::DiskImage),
+        ];
+        for (extension, category) in table {
+            assert_eq!(from_extension(extension, false), category, "{}", extension);
+        }
+    }
+
+    #[test]
+    fn ignores_extension_case() {
+        for extension in ["EXE", "Exe", "eXe"] {
+            assert_eq!(from_extension(extension, false), FileCategory::Executable, "{}", extension);
+        }
+        assert_eq!(from_extension("TAR.GZ", false), FileCategory::Archive);
+    }
+
+    #[test]
+    fn classifies_compound_extensions_by_their_last_part() {
+        assert_eq!(from_extension("tar.gz", false), FileCategory::Archive);
+        assert_eq!(from_extension("tar.zst", false), FileCategory::Archive);
+        assert_eq!(from_extension("user.js", false), FileCategory::Script);
+    }
+
+    #[test]
+    fn unknown_and_missing_extensions_are_other() {
+        for extension in ["", "xyz", "docxx", "ex", "d.ts", "."] {
+            assert_eq!(from_extension(extension, false), FileCategory::Other, "{:?}", extension);
+        }
+    }
+
+    #[test]
+    fn directories_are_directories_whatever_their_extension() {
+        assert_eq!(from_extension("exe", true), FileCategory::Directory);
+        assert_eq!(from_extension("", true), FileCategory::Directory);
+    }
+
+    #[test]
+    fn classifies_content_by_magic_bytes() {
+        assert_eq!(from_magic(b"MZ\x90\0"), Some(FileCategory::Executable));
+        assert_eq!(from_magic(b"%PDF-1.7"), Some(FileCategory::Document));
+        assert_eq!(from_magic(b"PK\x03\x04\x14\0"), Some(FileCategory::Archive));
+        assert_eq!(from_magic(b"SQLite format 3\0"), Some(FileCategory::Database));
+        assert_eq!(from_magic(b"plain text"), None);
+        assert_eq!(from_magic(b""), None);
+    }
+
+    #[test]
+    fn displays_lowercase_names() {
+        assert_eq!(FileCategory::DiskImage.to_string(), "diskimage");
+        assert_eq!(FileCategory::Other.to_string(), "other");
+    }
+}
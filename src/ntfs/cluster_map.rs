@@ -0,0 +1,75 @@
+use super::mft::MftParser;
+
+/// A run of clusters and the $DATA stream that maps it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterExtent {
+    /// First logical cluster of the run
+    pub lcn: u64,
+    pub length: u64,
+    /// Virtual cluster within the stream that `lcn` maps to
+    pub vcn: u64,
+    pub entry_number: u32,
+    /// ADS name, empty for the unnamed stream
+    pub stream: String,
+}
+
+impl ClusterExtent {
+    pub fn contains(&self, cluster: u64) -> bool {
+        cluster >= self.lcn && cluster - self.lcn < self.length
+    }
+}
+
+/// Reverse index from clusters to the streams whose data runs cover them.
+///
+/// Deleted records are included, so a cluster can have several owners: the
+/// file that uses it now and any deleted files that used it before.
+#[derive(Debug, Clone, Default)]
+pub struct ClusterMap {
+    /// Sorted by starting cluster
+    extents: Vec<ClusterExtent>,
+    /// Longest extent, which bounds how far back a lookup has to scan
+    max_length: u64,
+}
+
+impl ClusterMap {
+    pub fn build(parser: &MftParser) -> Self {
+        let mut extents: Vec<ClusterExtent> = parser
+            .data_streams()
+            .flat_map(|(record, stream)| {
+                stream.runs.iter().filter_map(move |run| {
+                    Some(ClusterExtent {
+                        lcn: run.lcn?,
+                        length: run.length,
+                        vcn: run.vcn,
+                        entry_number: record.entry_number,
+                        stream: stream.name.clone(),
+                    })
+                })
+            })
+            .filter(|extent| extent.length > 0)
+            .collect();
+        extents.sort_by_key(|extent| (extent.lcn, extent.entry_number));
+
+        let max_length = extents.iter().map(|extent| extent.length).max().unwrap_or(0);
+        Self { extents, max_length }
+    }
+
+    pub fn extents(&self) -> &[ClusterExtent] {
+        &self.extents
+    }
+
+    /// Every extent containing `cluster`, in record order
+    pub fn owners(&self, cluster: u64) -> Vec<&ClusterExtent> {
+        let end = self.extents.partition_point(|extent| extent.lcn <= cluster);
+        let earliest_start = cluster.saturating_sub(self.max_length);
+
+        let mut owners: Vec<&ClusterExtent> = self.extents[..end]
+            .iter()
+            .rev()
+            .take_while(|extent| extent.lcn >= earliest_start)
+            .filter(|extent| extent.contains(cluster))
+            .collect();
+        owners.sort_by_key(|extent| extent.entry_number);
+        owners
+    }
+}
@@ -0,0 +1,1261 @@
+use super::types::{ErrorCode, ParseError, ParseResult};
+
+/// Uncompressed size of an LZNT1 chunk; only the last chunk of a stream is shorter
+pub const LZNT1_CHUNK_SIZE: usize = 4096;
+/// NTFS compresses 16 clusters at a time, 64 KB with the usual 4 KB clusters
+pub const COMPRESSION_UNIT_SIZE: usize = 16 * 4096;
+
+/// Alternate data stream a WOF compressed file keeps its content in; the unnamed
+/// $DATA stream is sparse
+pub const WOF_STREAM_NAME: &str = "WofCompressedData";
+
+/// Algorithms a WofCompressedData stream without its reparse point is tried
+/// as, the largest chunks first
+const WOF_ALGORITHMS: [WofAlgorithm; 4] = [WofAlgorithm::Lzx, WofAlgorithm::Xpress16K, WofAlgorithm::Xpress8K, WofAlgorithm::Xpress4K];
+/// LZX, the largest WOF chunk size
+const WOF_LZX_CHUNK_SIZE: usize = 32768;
+/// Chunk table entries that have to agree before an input is taken for a WofCompressedData stream
+const WOF_TABLE_PROBES: usize = 8;
+
+/// XPRESS Huffman output is coded in blocks of 64 KB, each with its own code
+const XPRESS_BLOCK_SIZE: usize = 65536;
+/// 512 symbols: 256 literals and 256 match headers
+const XPRESS_SYMBOLS: usize = 512;
+/// The code lengths, two 4-bit lengths per byte, that open each block
+const XPRESS_TABLE_SIZE: usize = XPRESS_SYMBOLS / 2;
+const XPRESS_MAX_CODE_LENGTH: u32 = 15;
+/// The decoder reads 32 bits ahead; a stream may end before the last of them
+const XPRESS_READ_AHEAD: usize = 4;
+
+/// WOF uses the LZX of WIM archives: a 32 KB window, which takes 30 offset slots
+const LZX_OFFSET_SLOTS: usize = 30;
+/// 256 literals and a match header for each offset slot and 3-bit length
+const LZX_MAIN_SYMBOLS: usize = 256 + 8 * LZX_OFFSET_SLOTS;
+/// Lengths beyond the 7 a match header holds
+const LZX_LENGTH_SYMBOLS: usize = 249;
+/// The code the main and length code lengths are themselves coded with
+const LZX_PRETREE_SYMBOLS: usize = 20;
+const LZX_ALIGNED_SYMBOLS: usize = 8;
+const LZX_MAX_CODE_LENGTH: u32 = 16;
+const LZX_MAX_PRETREE_CODE_LENGTH: u32 = 15;
+const LZX_MAX_ALIGNED_CODE_LENGTH: u32 = 7;
+const LZX_BLOCK_VERBATIM: u32 = 1;
+const LZX_BLOCK_ALIGNED: u32 = 2;
+const LZX_BLOCK_UNCOMPRESSED: u32 = 3;
+/// Size of a block whose header doesn't give one
+const LZX_DEFAULT_BLOCK_SIZE: usize = 32768;
+const LZX_MIN_MATCH: usize = 2;
+/// Matches repeating one of the last three offsets use the first offset slots
+const LZX_RECENT_OFFSETS: usize = 3;
+/// Offset slots count from 2 so the recent offsets fit below them
+const LZX_OFFSET_ADJUSTMENT: usize = 2;
+/// File size WIM's LZX translates E8 (x86 CALL) targets against
+const LZX_E8_FILE_SIZE: i32 = 12_000_000;
+/// The decoder reads 16 bits ahead
+const LZX_READ_AHEAD: usize = 2;
+
+/// How an extract wraps the data it should hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrapper {
+    /// Clusters of a file in an NTFS-compressed folder, copied without decompressing:
+    /// LZNT1 compression units, each padded to its full size
+    Lznt1,
+    /// The WofCompressedData stream of a file compressed with `compact /exe`:
+    /// a chunk offset table followed by XPRESS Huffman or LZX chunks
+    Wof,
+}
+
+/// How the Windows Overlay Filter compressed a file (`compact /exe:<algorithm>`),
+/// as its WOF reparse point records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WofAlgorithm {
+    Xpress4K,
+    Lzx,
+    Xpress8K,
+    Xpress16K,
+}
+
+impl WofAlgorithm {
+    /// From the FILE_PROVIDER_COMPRESSION_* value of a WOF reparse point
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(WofAlgorithm::Xpress4K),
+            1 => Some(WofAlgorithm::Lzx),
+            2 => Some(WofAlgorithm::Xpress8K),
+            3 => Some(WofAlgorithm::Xpress16K),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WofAlgorithm::Xpress4K => "XPRESS4K",
+            WofAlgorithm::Lzx => "LZX",
+            WofAlgorithm::Xpress8K => "XPRESS8K",
+            WofAlgorithm::Xpress16K => "XPRESS16K",
+        }
+    }
+
+    /// Uncompressed size of every chunk but the last
+    pub fn chunk_size(&self) -> usize {
+        match self {
+            WofAlgorithm::Xpress4K => 4096,
+            WofAlgorithm::Lzx => WOF_LZX_CHUNK_SIZE,
+            WofAlgorithm::Xpress8K => 8192,
+            WofAlgorithm::Xpress16K => 16384,
+        }
+    }
+
+    /// Decompress one chunk to `size` bytes; a chunk that didn't shrink is stored as it is
+    fn decompress_chunk(&self, chunk: &[u8], size: usize) -> ParseResult<Vec<u8>> {
+        if chunk.len() == size {
+            Ok(chunk.to_vec())
+        } else if *self == WofAlgorithm::Lzx {
+            decompress_lzx(chunk, size)
+        } else {
+            decompress_xpress_huffman(chunk, size)
+        }
+    }
+}
+
+impl Wrapper {
+    /// Recognize a wrapper from the first bytes of an extract; `signature` is
+    /// what the unwrapped data starts with, e.g. `FILE` for a $MFT
+    pub fn detect(header: &[u8], signature: &[u8]) -> Option<Self> {
+        if lznt1_wraps(header, signature) {
+            Some(Wrapper::Lznt1)
+        } else if looks_like_wof_table(header) {
+            Some(Wrapper::Wof)
+        } else {
+            None
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Wrapper::Lznt1 => "NTFS (LZNT1) compression",
+            Wrapper::Wof => "a WofCompressedData (XPRESS or LZX) stream",
+        }
+    }
+
+    /// The data inside; `size_multiple` is what the unwrapped size is a multiple
+    /// of, such as the FILE record size, which sizes a WOF stream's last chunk
+    pub fn unwrap(&self, data: &[u8], size_multiple: usize) -> ParseResult<Vec<u8>> {
+        match self {
+            Wrapper::Lznt1 => decompress_compression_units(data, COMPRESSION_UNIT_SIZE),
+            Wrapper::Wof => decompress_wof(data, size_multiple),
+        }
+    }
+}
+
+/// A compressed NTFS stream starts with an LZNT1 chunk header: bits 12-14 hold
+/// the signature 3 and bit 15 marks a compressed chunk. A compressed chunk opens
+/// with a flag byte whose clear bits leave the first bytes literal, and a stored
+/// chunk holds its bytes as they are, so `signature` still shows two or three
+/// bytes in.
+pub fn lznt1_wraps(header: &[u8], signature: &[u8]) -> bool {
+    let Some(&[low, high, ..]) = header.get(..2) else {
+        return false;
+    };
+    match u16::from_le_bytes([low, high]) & 0xF000 {
+        0xB000 => header.get(3..3 + signature.len()) == Some(signature),
+        0x3000 => header.get(2..2 + signature.len()) == Some(signature),
+        _ => false,
+    }
+}
+
+/// Decompress one run of LZNT1 chunks, up to a zero chunk header or the end of `data`
+pub fn decompress_lznt1(data: &[u8]) -> ParseResult<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut position = 0;
+    let mut chunks = 0;
+    while position + 2 <= data.len() {
+        let header = u16::from_le_bytes([data[position], data[position + 1]]);
+        if header == 0 {
+            break;
+        }
+        let length = (header & 0x0FFF) as usize + 1;
+        let Some(chunk) = data.get(position + 2..position + 2 + length) else {
+            return Err(ParseError {
+                code: ErrorCode::Decompression,
+                message: format!("LZNT1 chunk of {} bytes runs past the end of the data", length),
+                offset: Some(position as u64),
+            });
+        };
+        // Every chunk but the last stands for a full 4 KB, which a chunk ending
+        // in zeros may leave out
+        output.resize(chunks * LZNT1_CHUNK_SIZE, 0);
+        if header & 0x8000 == 0 {
+            output.extend_from_slice(chunk);
+        } else {
+            decompress_lznt1_chunk(chunk, &mut output).map_err(|message| ParseError {
+                code: ErrorCode::Decompression,
+                message,
+                offset: Some(position as u64),
+            })?;
+        }
+        chunks += 1;
+        position += 2 + length;
+    }
+    Ok(output)
+}
+
+fn decompress_lznt1_chunk(chunk: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
+    let start = output.len();
+    let mut position = 0;
+    while position < chunk.len() {
+        let flags = chunk[position];
+        position += 1;
+        for bit in 0..8 {
+            if position >= chunk.len() {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                output.push(chunk[position]);
+                position += 1;
+                continue;
+            }
+
+            let Some(token) = chunk.get(position..position + 2) else {
+                return Err("LZNT1 back-reference cut off at the end of its chunk".to_string());
+            };
+            let token = u16::from_le_bytes([token[0], token[1]]) as usize;
+            position += 2;
+
+            // The further into the chunk, the more of the token goes to the displacement
+            let written = output.len() - start;
+            let mut length_bits = 12;
+            let mut remaining = written.saturating_sub(1);
+            while remaining >= 0x10 {
+                length_bits -= 1;
+                remaining >>= 1;
+            }
+            let length = (token & ((1 << length_bits) - 1)) + 3;
+            let displacement = (token >> length_bits) + 1;
+            if displacement > written {
+                return Err(format!(
+                    "LZNT1 back-reference {} bytes back with only {} bytes decompressed",
+                    displacement, written
+                ));
+            }
+            for _ in 0..length {
+                output.push(output[output.len() - displacement]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decompress a file copied cluster for cluster out of a compressed folder.
+/// Each compression unit holds its LZNT1 chunks followed by zeros where the
+/// unit's unallocated clusters were filled in; a unit that did not compress is
+/// stored as it is and an all-zero (sparse) unit stays zeros.
+pub fn decompress_compression_units(data: &[u8], unit_size: usize) -> ParseResult<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len());
+    for (index, unit) in data.chunks(unit_size).enumerate() {
+        output.resize(index * unit_size, 0);
+        let header = u16::from_le_bytes([unit[0], *unit.get(1).unwrap_or(&0)]);
+        let chunk_length = (header & 0x0FFF) as usize + 3;
+        if header & 0x7000 != 0x3000 || chunk_length > unit.len() {
+            output.extend_from_slice(unit);
+            continue;
+        }
+        let mut decompressed = decompress_lznt1(unit).map_err(|error| ParseError {
+            code: error.code,
+            message: error.message,
+            offset: Some((index * unit_size) as u64 + error.offset.unwrap_or_default()),
+        })?;
+        decompressed.truncate(unit_size);
+        output.extend_from_slice(&decompressed);
+    }
+    Ok(output)
+}
+
+/// A WofCompressedData stream opens with a table of 32-bit offsets, one per
+/// chunk after the first, counted from the end of the table. They rise by at
+/// most one chunk at a time, which an artifact's own header rarely does.
+fn looks_like_wof_table(header: &[u8]) -> bool {
+    let mut previous = 0;
+    let mut agreeing = 0;
+    for entry in header.chunks_exact(4).take(WOF_TABLE_PROBES) {
+        let offset = u32::from_le_bytes(entry.try_into().unwrap()) as usize;
+        if offset <= previous || offset - previous > WOF_LZX_CHUNK_SIZE {
+            return false;
+        }
+        previous = offset;
+        agreeing += 1;
+    }
+    agreeing == WOF_TABLE_PROBES
+}
+
+/// Byte ranges of the chunks of a WofCompressedData stream. The table's length
+/// isn't stored: offsets keep rising until the chunks begin, and the table is
+/// the longest run of them after which the last chunk fits the rest of the stream.
+fn wof_chunks(data: &[u8]) -> Option<Vec<std::ops::Range<usize>>> {
+    let mut offsets = Vec::new();
+    let mut table_length = None;
+    let mut previous = 0;
+    for entry in data.chunks_exact(4) {
+        let offset = u32::from_le_bytes(entry.try_into().unwrap()) as usize;
+        let last_start = (offsets.len() + 1) * 4 + offset;
+        if offset <= previous || offset - previous > WOF_LZX_CHUNK_SIZE || last_start >= data.len() {
+            break;
+        }
+        offsets.push(offset);
+        previous = offset;
+        if data.len() - last_start <= WOF_LZX_CHUNK_SIZE {
+            table_length = Some(offsets.len());
+        }
+    }
+
+    let offsets = &offsets[..table_length?];
+    let table_size = offsets.len() * 4;
+    let starts = std::iter::once(0).chain(offsets.iter().copied());
+    let ends = offsets.iter().copied().chain(std::iter::once(data.len() - table_size));
+    Some(starts.zip(ends).map(|(start, end)| table_size + start..table_size + end).collect())
+}
+
+/// Decompress a WofCompressedData stream copied without its file's reparse
+/// point. The algorithm is the one chunk 0 decompresses with, to its chunk
+/// size; a chunk as long as that was stored uncompressed. The last chunk's
+/// size, which only the file's own $DATA attribute records, is taken as the
+/// largest multiple of `size_multiple` it decompresses to.
+pub fn decompress_wof(data: &[u8], size_multiple: usize) -> ParseResult<Vec<u8>> {
+    let Some(chunks) = wof_chunks(data) else {
+        return Err(ParseError {
+            code: ErrorCode::Decompression,
+            message: "no WofCompressedData chunk table found".to_string(),
+            offset: Some(0),
+        });
+    };
+
+    let first = &data[chunks[0].clone()];
+    let largest = chunks.iter().map(|chunk| chunk.len()).max().unwrap_or_default();
+    let algorithm = WOF_ALGORITHMS.into_iter().filter(|algorithm| algorithm.chunk_size() >= largest).find(|algorithm| {
+        let chunk_size = algorithm.chunk_size();
+        first.len() == chunk_size || (first.len() < chunk_size && algorithm.decompress_chunk(first, chunk_size).is_ok())
+    });
+    let Some(algorithm) = algorithm else {
+        return Err(ParseError {
+            code: ErrorCode::Decompression,
+            message: "the first WofCompressedData chunk does not decompress as XPRESS or LZX".to_string(),
+            offset: Some(chunks[0].start as u64),
+        });
+    };
+    let chunk_size = algorithm.chunk_size();
+
+    let mut output = Vec::with_capacity(chunks.len() * chunk_size);
+    for (index, range) in chunks.iter().enumerate() {
+        let chunk = &data[range.clone()];
+        let decompressed = if index + 1 < chunks.len() {
+            algorithm.decompress_chunk(chunk, chunk_size)
+        } else {
+            let step = size_multiple.clamp(1, chunk_size);
+            (1..=chunk_size / step)
+                .rev()
+                .map(|multiple| algorithm.decompress_chunk(chunk, multiple * step))
+                .find(Result::is_ok)
+                .unwrap_or_else(|| algorithm.decompress_chunk(chunk, chunk_size))
+        };
+        output.extend_from_slice(&decompressed.map_err(|error| ParseError {
+            code: error.code,
+            message: format!("WofCompressedData chunk {}: {}", index, error.message),
+            offset: Some(range.start as u64),
+        })?);
+    }
+    Ok(output)
+}
+
+/// Decompress the WofCompressedData stream of a file whose WOF reparse point
+/// names `algorithm` and whose unnamed $DATA, which holds no clusters of its
+/// own, is `size` bytes. The chunk table has an entry for each chunk after the
+/// first, 64-bit for files of 4 GB or more.
+pub fn decompress_wof_stream(data: &[u8], algorithm: WofAlgorithm, size: u64) -> ParseResult<Vec<u8>> {
+    let chunk_size = algorithm.chunk_size();
+    let chunk_count = size.div_ceil(chunk_size as u64) as usize;
+    let entry_size = if size > u64::from(u32::MAX) { 8 } else { 4 };
+    let table_size = chunk_count.saturating_sub(1) * entry_size;
+    if data.len() < table_size {
+        return Err(ParseError {
+            code: ErrorCode::Decompression,
+            message: format!(
+                "WofCompressedData stream of {} bytes is shorter than the chunk table of a {} byte file",
+                data.len(),
+                size
+            ),
+            offset: Some(0),
+        });
+    }
+    // Offsets count from the end of the table; the first chunk starts there
+    let chunk_start = |index: usize| -> usize {
+        match index {
+            0 => table_size,
+            _ if index == chunk_count => data.len(),
+            _ => {
+                let entry = &data[(index - 1) * entry_size..index * entry_size];
+                let offset = match entry_size {
+                    8 => u64::from_le_bytes(entry.try_into().unwrap()),
+                    _ => u64::from(u32::from_le_bytes(entry.try_into().unwrap())),
+                };
+                table_size.saturating_add(usize::try_from(offset).unwrap_or(usize::MAX))
+            }
+        }
+    };
+
+    let mut output = Vec::new();
+    for index in 0..chunk_count {
+        let (start, end) = (chunk_start(index), chunk_start(index + 1));
+        if start > end || end > data.len() {
+            return Err(ParseError {
+                code: ErrorCode::Decompression,
+                message: format!("WofCompressedData chunk {} lies outside the stream's {} bytes", index, data.len()),
+                offset: Some(((index.max(1) - 1) * entry_size) as u64),
+            });
+        }
+        let chunk_output = (size - output.len() as u64).min(chunk_size as u64) as usize;
+        let decompressed = algorithm.decompress_chunk(&data[start..end], chunk_output).map_err(|error| ParseError {
+            code: error.code,
+            message: format!("WofCompressedData {} chunk {}: {}", algorithm.name(), index, error.message),
+            offset: Some(start as u64 + error.offset.unwrap_or_default()),
+        })?;
+        output.extend_from_slice(&decompressed);
+    }
+    Ok(output)
+}
+
+/// Decompress XPRESS Huffman (MS-XCA "LZ77+Huffman") data to `output_size` bytes
+pub fn decompress_xpress_huffman(data: &[u8], output_size: usize) -> ParseResult<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_size);
+    let mut position = 0;
+    while output.len() < output_size {
+        let Some(table) = data.get(position..position + XPRESS_TABLE_SIZE) else {
+            return Err(ParseError {
+                code: ErrorCode::Decompression,
+                message: format!("XPRESS Huffman table cut off after {} bytes of output", output.len()),
+                offset: Some(position as u64),
+            });
+        };
+        let lengths: Vec<u8> = (0..XPRESS_SYMBOLS).map(|symbol| (table[symbol / 2] >> (4 * (symbol % 2))) & 0x0F).collect();
+        let decoder = match HuffmanDecoder::new(&lengths, XPRESS_MAX_CODE_LENGTH) {
+            Ok(_) if lengths.iter().all(|&length| length == 0) => Err("XPRESS Huffman table has no symbols".to_string()),
+            decoder => decoder,
+        }
+        .map_err(|message| ParseError {
+            code: ErrorCode::Decompression,
+            message,
+            offset: Some(position as u64),
+        })?;
+        let mut bits = BitReader::new(data, position + XPRESS_TABLE_SIZE);
+        let block_end = (output.len() + XPRESS_BLOCK_SIZE).min(output_size);
+
+        while output.len() < block_end {
+            let symbol = decoder.decode(&mut bits) as usize;
+            if symbol < 256 {
+                output.push(symbol as u8);
+                continue;
+            }
+
+            let mut length = symbol & 0x0F;
+            let offset_bits = ((symbol - 256) >> 4) as u32;
+            if length == 15 {
+                length = bits.read_byte() as usize;
+                if length == 255 {
+                    length = bits.read_u16() as usize;
+                    if length == 0 {
+                        length = bits.read_u32() as usize;
+                    }
+                    if length < 15 {
+                        return Err(ParseError {
+                            code: ErrorCode::Decompression,
+                            message: format!("XPRESS match length {} is below its 15-byte minimum", length),
+                            offset: Some(bits.position as u64),
+                        });
+                    }
+                    length -= 15;
+                }
+                length += 15;
+            }
+            length += 3;
+            let displacement = bits.take(offset_bits) as usize + (1 << offset_bits);
+
+            if displacement > output.len() {
+                return Err(ParseError {
+                    code: ErrorCode::Decompression,
+                    message: format!(
+                        "XPRESS match {} bytes back with only {} bytes decompressed",
+                        displacement,
+                        output.len()
+                    ),
+                    offset: Some(bits.position as u64),
+                });
+            }
+            for _ in 0..length.min(output_size - output.len()) {
+                output.push(output[output.len() - displacement]);
+            }
+        }
+
+        if bits.position > data.len() + XPRESS_READ_AHEAD {
+            return Err(ParseError {
+                code: ErrorCode::Decompression,
+                message: format!(
+                    "XPRESS data ends {} bytes short of {} bytes of output",
+                    bits.position - data.len(),
+                    output_size
+                ),
+                offset: Some(data.len() as u64),
+            });
+        }
+        position = bits.position;
+    }
+    Ok(output)
+}
+
+/// Decompress one WIM-style LZX chunk to `output_size` bytes. Blocks are
+/// verbatim (literals and matches), aligned (the low 3 bits of long offsets
+/// get a code of their own) or stored; code lengths carry over from block to
+/// block as differences. E8 (x86 CALL) targets are translated back last.
+pub fn decompress_lzx(data: &[u8], output_size: usize) -> ParseResult<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_size);
+    let mut bits = LzxBitReader { data, position: 0, buffer: 0, available: 0 };
+    let mut main_lengths = [0u8; LZX_MAIN_SYMBOLS];
+    let mut length_lengths = [0u8; LZX_LENGTH_SYMBOLS];
+    let mut recent_offsets = [1usize; LZX_RECENT_OFFSETS];
+    let error = |message: String, position: usize| ParseError { code: ErrorCode::Decompression, message, offset: Some(position as u64) };
+
+    while output.len() < output_size {
+        bits.ensure(4);
+        let block_type = bits.take(3);
+        let block_size = match bits.take(1) {
+            1 => LZX_DEFAULT_BLOCK_SIZE,
+            _ => bits.take(16) as usize,
+        };
+        if block_size == 0 {
+            return Err(error(format!("empty LZX block after {} bytes of output", output.len()), bits.position));
+        }
+        let block_end = (output.len() + block_size).min(output_size);
+
+        if block_type == LZX_BLOCK_UNCOMPRESSED {
+            // Stored blocks start on a 16-bit boundary, after 1 to 16 bits of padding
+            bits.ensure(1);
+            bits.buffer = 0;
+            bits.available = 0;
+            let Some(header) = data.get(bits.position..bits.position + 4 * LZX_RECENT_OFFSETS) else {
+                return Err(error("LZX stored block header cut off".to_string(), bits.position));
+            };
+            for (offset, value) in recent_offsets.iter_mut().zip(header.chunks_exact(4)) {
+                *offset = u32::from_le_bytes(value.try_into().unwrap()) as usize;
+            }
+            bits.position += header.len();
+            let Some(stored) = data.get(bits.position..bits.position + (block_end - output.len())) else {
+                return Err(error(format!("LZX stored block of {} bytes cut off", block_size), bits.position));
+            };
+            output.extend_from_slice(stored);
+            // Stored blocks are padded to an even length
+            bits.position += block_size + block_size % 2;
+            continue;
+        }
+        if block_type != LZX_BLOCK_VERBATIM && block_type != LZX_BLOCK_ALIGNED {
+            return Err(error(format!("invalid LZX block type {}", block_type), bits.position));
+        }
+
+        let codes = (|| {
+            let aligned = if block_type == LZX_BLOCK_ALIGNED {
+                let lengths: Vec<u8> = (0..LZX_ALIGNED_SYMBOLS).map(|_| bits.take(3) as u8).collect();
+                Some(HuffmanDecoder::new(&lengths, LZX_MAX_ALIGNED_CODE_LENGTH)?)
+            } else {
+                None
+            };
+            read_lzx_code_lengths(&mut bits, &mut main_lengths[..256])?;
+            read_lzx_code_lengths(&mut bits, &mut main_lengths[256..])?;
+            read_lzx_code_lengths(&mut bits, &mut length_lengths)?;
+            let main = HuffmanDecoder::new(&main_lengths, LZX_MAX_CODE_LENGTH)?;
+            let lengths = HuffmanDecoder::new(&length_lengths, LZX_MAX_CODE_LENGTH)?;
+            Ok::<_, String>((main, lengths, aligned))
+        })();
+        let (main, lengths, aligned) = codes.map_err(|message| error(message, bits.position))?;
+
+        while output.len() < block_end {
+            let symbol = main.decode(&mut bits) as usize;
+            if symbol < 256 {
+                output.push(symbol as u8);
+                continue;
+            }
+
+            let header = symbol - 256;
+            let mut length = header & 7;
+            if length == 7 {
+                length += lengths.decode(&mut bits) as usize;
+            }
+            length += LZX_MIN_MATCH;
+
+            let slot = header >> 3;
+            let offset = if slot < LZX_RECENT_OFFSETS {
+                recent_offsets.swap(slot, 0);
+                recent_offsets[0]
+            } else {
+                let (base, extra_bits) = lzx_offset_slot(slot);
+                let extra = match aligned {
+                    Some(ref aligned) if extra_bits >= 3 => {
+                        let high = bits.take(extra_bits - 3) as usize;
+                        (high << 3) + aligned.decode(&mut bits) as usize
+                    }
+                    _ => bits.take(extra_bits) as usize,
+                };
+                let offset = base + extra - LZX_OFFSET_ADJUSTMENT;
+                recent_offsets[2] = recent_offsets[1];
+                recent_offsets[1] = recent_offsets[0];
+                recent_offsets[0] = offset;
+                offset
+            };
+
+            if offset == 0 || offset > output.len() {
+                return Err(error(
+                    format!("LZX match {} bytes back with only {} bytes decompressed", offset, output.len()),
+                    bits.position,
+                ));
+            }
+            for _ in 0..length.min(output_size - output.len()) {
+                output.push(output[output.len() - offset]);
+            }
+        }
+
+        if bits.position > data.len() + LZX_READ_AHEAD {
+            return Err(error(
+                format!("LZX data ends {} bytes short of {} bytes of output", bits.position - data.len(), output_size),
+                data.len(),
+            ));
+        }
+    }
+
+    undo_e8_translation(&mut output);
+    Ok(output)
+}
+
+/// Position base and extra bits of an LZX offset slot: slots 0-3 take no extra
+/// bits, then each pair of slots takes one more
+fn lzx_offset_slot(slot: usize) -> (usize, u32) {
+    let extra_bits = |slot: usize| if slot < 4 { 0 } else { (slot as u32 - 2) / 2 };
+    let base = (0..slot).map(|previous| 1usize << extra_bits(previous)).sum();
+    (base, extra_bits(slot))
+}
+
+/// Read code lengths coded with a pretree, as differences from the lengths of
+/// the previous block: 0-16 change one length, 17 and 18 give runs of zeros and
+/// 19 a run of one changed length
+fn read_lzx_code_lengths(bits: &mut LzxBitReader, lengths: &mut [u8]) -> Result<(), String> {
+    let pretree_lengths: Vec<u8> = (0..LZX_PRETREE_SYMBOLS).map(|_| bits.take(4) as u8).collect();
+    let pretree = HuffmanDecoder::new(&pretree_lengths, LZX_MAX_PRETREE_CODE_LENGTH)?;
+
+    let mut index = 0;
+    while index < lengths.len() {
+        let (run, delta) = match pretree.decode(bits) {
+            delta @ 0..=16 => (1, Some(delta)),
+            17 => (4 + bits.take(4) as usize, None),
+            18 => (20 + bits.take(5) as usize, None),
+            _ => {
+                let run = 4 + bits.take(1) as usize;
+                match pretree.decode(bits) {
+                    delta @ 0..=17 => (run, Some(delta)),
+                    symbol => return Err(format!("LZX pretree symbol {} can't follow a run", symbol)),
+                }
+            }
+        };
+        let length = delta.map_or(0, |delta| ((u16::from(lengths[index]) + 17 - delta) % 17) as u8);
+        // A run may overshoot the last symbol; the extra lengths are dropped
+        let end = (index + run).min(lengths.len());
+        lengths[index..end].fill(length);
+        index = end;
+    }
+    Ok(())
+}
+
+/// The compressor turned the relative targets of E8 (x86 CALL) instructions
+/// into absolute ones, which repeat and so compress better; turn them back
+fn undo_e8_translation(data: &mut [u8]) {
+    if data.len() <= 10 {
+        return;
+    }
+    let mut position = 0;
+    while position < data.len() - 10 {
+        if data[position] != 0xE8 {
+            position += 1;
+            continue;
+        }
+        let target = &mut data[position + 1..position + 5];
+        let absolute = i32::from_le_bytes((&*target).try_into().unwrap());
+        let position_i32 = position as i32;
+        if (0..LZX_E8_FILE_SIZE).contains(&absolute) {
+            target.copy_from_slice(&(absolute - position_i32).to_le_bytes());
+        } else if absolute < 0 && absolute >= -position_i32 {
+            target.copy_from_slice(&(absolute + LZX_E8_FILE_SIZE).to_le_bytes());
+        }
+        position += 5;
+    }
+}
+
+/// Canonical Huffman code of an XPRESS or LZX block, looked up as many bits
+/// at a time as its longest code
+struct HuffmanDecoder {
+    /// Symbol and code length for every prefix of `bits` bits
+    table: Vec<(u16, u8)>,
+    bits: u32,
+    /// Bits the reader makes sure it holds before decoding, for LZX's alignment
+    max_length: u32,
+}
+
+impl HuffmanDecoder {
+    /// `lengths` holds each symbol's code length, 0 for symbols without a code
+    fn new(lengths: &[u8], max_length: u32) -> Result<Self, String> {
+        let bits = lengths.iter().copied().max().unwrap_or_default().max(1) as u32;
+        if bits > max_length {
+            return Err(format!("Huffman code length {} is over the maximum of {}", bits, max_length));
+        }
+
+        let mut table = vec![(0u16, 0u8); 1 << bits];
+        let mut next = 0usize;
+        // Codes go to symbols by length, then by symbol value
+        for length in 1..=bits as u8 {
+            for symbol in (0..lengths.len()).filter(|&symbol| lengths[symbol] == length) {
+                let span = 1 << (bits - length as u32);
+                if next + span > table.len() {
+                    return Err("Huffman code lengths overflow the code space".to_string());
+                }
+                table[next..next + span].fill((symbol as u16, length));
+                next += span;
+            }
+        }
+        Ok(Self { table, bits, max_length })
+    }
+
+    fn decode(&self, bits: &mut impl BitSource) -> u16 {
+        bits.ensure(self.max_length);
+        let (symbol, length) = self.table[bits.peek(self.bits) as usize];
+        // Prefixes no code covers only come up in corrupt data; consume a bit so decoding moves on
+        bits.take(u32::from(length.max(1)));
+        symbol
+    }
+}
+
+/// A bit stream read most significant bit first, which both XPRESS and LZX use
+trait BitSource {
+    /// Make sure `count` bits are buffered, as far as the reader buffers ahead
+    fn ensure(&mut self, count: u32);
+    fn peek(&self, count: u32) -> u32;
+    fn take(&mut self, count: u32) -> u32;
+}
+
+/// The XPRESS bit stream: 16-bit little-endian words read most significant bit
+/// first, with the extra length bytes interleaved at the word position
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    next_bits: u32,
+    extra_bits: i32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        let mut reader = Self { data, position, next_bits: 0, extra_bits: 16 };
+        reader.next_bits = (reader.read_u16() as u32) << 16;
+        reader.next_bits |= reader.read_u16() as u32;
+        reader
+    }
+
+    /// Bytes past the end read as zero; the caller checks how far it overran
+    fn read_byte(&mut self) -> u8 {
+        let byte = self.data.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        u16::from_le_bytes([self.read_byte(), self.read_byte()])
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        u32::from_le_bytes([self.read_byte(), self.read_byte(), self.read_byte(), self.read_byte()])
+    }
+}
+
+impl BitSource for BitReader<'_> {
+    /// The next 32 bits are always buffered
+    fn ensure(&mut self, _count: u32) {}
+
+    fn peek(&self, count: u32) -> u32 {
+        self.next_bits >> (32 - count)
+    }
+
+    fn take(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = self.peek(count);
+        self.next_bits <<= count;
+        self.extra_bits -= count as i32;
+        if self.extra_bits < 0 {
+            self.next_bits |= (self.read_u16() as u32) << -self.extra_bits;
+            self.extra_bits += 16;
+        }
+        value
+    }
+}
+
+/// The LZX bit stream: 16-bit little-endian words read most significant bit
+/// first, one word at a time as bits are needed. How far it has read decides
+/// where a stored block starts, so it reads no further ahead than that.
+struct LzxBitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Buffered bits, the next one at the top
+    buffer: u64,
+    available: u32,
+}
+
+impl BitSource for LzxBitReader<'_> {
+    /// Words past the end read as zero; the caller checks how far it overran
+    fn ensure(&mut self, count: u32) {
+        if self.available >= count {
+            return;
+        }
+        let word = match self.data.get(self.position..self.position + 2) {
+            Some(word) => u16::from_le_bytes([word[0], word[1]]),
+            None => 0,
+        };
+        self.buffer |= u64::from(word) << (48 - self.available);
+        self.position += 2;
+        self.available += 16;
+    }
+
+    fn peek(&self, count: u32) -> u32 {
+        (self.buffer >> (64 - count)) as u32
+    }
+
+    fn take(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        self.ensure(count);
+        let value = self.peek(count);
+        self.buffer <<= count;
+        self.available -= count;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compressed LZNT1 chunk for "abcabcabcabc": flag byte 0x08 leaves three
+    /// literals, then token 0x2006 goes 3 bytes back for 9 bytes
+    const LZNT1_ABC: [u8; 8] = [0x05, 0xB0, 0x08, b'a', b'b', b'c', 0x06, 0x20];
+
+    /// The MS-XCA LZ77+Huffman example: "abcdefghijklmnopqrstuvwxyz" coded with
+    /// 5-bit codes for a-v and 4-bit codes for w-z and the end-of-stream symbol
+    fn xpress_alphabet() -> Vec<u8> {
+        let mut data = vec![0u8; XPRESS_TABLE_SIZE];
+        data[0x30] = 0x50;
+        data[0x31..=0x3A].fill(0x55);
+        data[0x3B] = 0x45;
+        data[0x3C] = 0x44;
+        data[0x3D] = 0x04;
+        data[0x80] = 0x04;
+        data.extend_from_slice(&[
+            0xD8, 0x52, 0x3E, 0xD7, 0x94, 0x11, 0x5B, 0xE9, 0x19, 0x5F, 0xF9, 0xD6, 0x7C, 0xDF, 0x8D, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        data
+    }
+
+    /// An XPRESS Huffman table giving each `(symbol, length)` its code length
+    fn xpress_table(lengths: &[(usize, u8)]) -> Vec<u8> {
+        let mut table = vec![0u8; XPRESS_TABLE_SIZE];
+        for &(symbol, length) in lengths {
+            table[symbol / 2] |= length << (4 * (symbol % 2));
+        }
+        table
+    }
+
+    /// Bits packed most significant first into 16-bit little-endian words, as LZX reads them
+    #[derive(Default)]
+    struct LzxWriter {
+        bytes: Vec<u8>,
+        word: u16,
+        count: u32,
+    }
+
+    impl LzxWriter {
+        fn put(&mut self, value: u32, count: u32) {
+            for bit in (0..count).rev() {
+                self.word = (self.word << 1) | ((value >> bit) & 1) as u16;
+                self.count += 1;
+                if self.count == 16 {
+                    self.bytes.extend_from_slice(&self.word.to_le_bytes());
+                    self.word = 0;
+                    self.count = 0;
+                }
+            }
+        }
+
+        /// Pad to the end of the word
+        fn finish(mut self) -> Vec<u8> {
+            if self.count > 0 {
+                self.put(0, 16 - self.count);
+            }
+            self.bytes
+        }
+
+        /// Code lengths of 0, 1 or 2 against all-zero previous lengths, with a
+        /// pretree of 18 (long zero run) as 0, 15 (length 2) as 10, 16 (length 1)
+        /// as 110 and 17 (short zero run) as 111
+        fn put_code_lengths(&mut self, lengths: &[u8]) {
+            for symbol in 0..LZX_PRETREE_SYMBOLS {
+                self.put(
+                    match symbol {
+                        18 => 1,
+                        15 => 2,
+                        16 | 17 => 3,
+                        _ => 0,
+                    },
+                    4,
+                );
+            }
+            let mut index = 0;
+            while index < lengths.len() {
+                let zeros = lengths[index..].iter().take_while(|&&length| length == 0).count();
+                let run = match zeros.min(51) {
+                    run if (1..4).contains(&(zeros - run)) => run - 4,
+                    run => run,
+                };
+                match (lengths[index], run) {
+                    (0, 20..) => {
+                        self.put(0b0, 1);
+                        self.put(run as u32 - 20, 5);
+                    }
+                    (0, _) => {
+                        assert!(run >= 4, "zero run of {} can't be coded", run);
+                        self.put(0b111, 3);
+                        self.put(run as u32 - 4, 4);
+                    }
+                    (2, _) => self.put(0b10, 2),
+                    (1, _) => self.put(0b110, 3),
+                    (length, _) => panic!("code length {} can't be coded", length),
+                }
+                index += if lengths[index] == 0 { run } else { 1 };
+            }
+        }
+    }
+
+    /// A verbatim LZX block of the default size whose main code gives a, b, c
+    /// and match header 295 (offset slot 4, length 7 plus a length code) 2-bit
+    /// codes 00, 01, 10 and 11, and whose length code gives 0 and 1 1-bit codes;
+    /// `tokens` writes the coded data
+    fn lzx_verbatim(tokens: impl FnOnce(&mut LzxWriter)) -> Vec<u8> {
+        let mut main = [0u8; LZX_MAIN_SYMBOLS];
+        for symbol in [b'a' as usize, b'b' as usize, b'c' as usize, 295] {
+            main[symbol] = 2;
+        }
+        let mut lengths = [0u8; LZX_LENGTH_SYMBOLS];
+        lengths[..2].fill(1);
+
+        let mut bits = LzxWriter::default();
+        bits.put(LZX_BLOCK_VERBATIM, 3);
+        bits.put(1, 1);
+        bits.put_code_lengths(&main[..256]);
+        bits.put_code_lengths(&main[256..]);
+        bits.put_code_lengths(&lengths);
+        tokens(&mut bits);
+        bits.finish()
+    }
+
+    /// "abcabcabcabc": three literals, then header 295 with length code 0 for 9
+    /// bytes and offset slot 4's extra bit 1 for 4 + 1 - 2 = 3 bytes back
+    fn lzx_abc() -> Vec<u8> {
+        lzx_verbatim(|bits| {
+            bits.put(0b00_01_10_11, 8);
+            bits.put(0, 1);
+            bits.put(1, 1);
+        })
+    }
+
+    /// A stored LZX block of `content`, after the recent offsets 1, 1, 1
+    fn lzx_stored(content: &[u8]) -> Vec<u8> {
+        let mut bits = LzxWriter::default();
+        bits.put(LZX_BLOCK_UNCOMPRESSED, 3);
+        bits.put(0, 1);
+        bits.put(content.len() as u32, 16);
+        let mut data = bits.finish();
+        for _ in 0..LZX_RECENT_OFFSETS {
+            data.extend_from_slice(&1u32.to_le_bytes());
+        }
+        data.extend_from_slice(content);
+        if content.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn lznt1_decompresses_a_compressed_chunk() {
+        assert_eq!(decompress_lznt1(&LZNT1_ABC).unwrap(), b"abcabcabcabc");
+    }
+
+    #[test]
+    fn lznt1_gives_the_displacement_more_bits_further_into_a_chunk() {
+        // After 17 bytes a token splits 5:11, so 0x8000 goes 17 bytes back for 3
+        let mut data = vec![0x15, 0xB0, 0x00];
+        data.extend_from_slice(b"abcdefgh");
+        data.push(0x00);
+        data.extend_from_slice(b"ijklmnop");
+        data.extend_from_slice(&[0x02, b'q', 0x00, 0x80]);
+        assert_eq!(decompress_lznt1(&data).unwrap(), b"abcdefghijklmnopqabc");
+    }
+
+    #[test]
+    fn lznt1_pads_every_chunk_but_the_last_and_stops_at_a_zero_header() {
+        let data = [0x03, 0x30, b'w', b'x', b'y', b'z', 0x01, 0x30, b'a', b'b', 0x00, 0x00, 0xFF, 0xFF];
+        let output = decompress_lznt1(&data).unwrap();
+        assert_eq!(output.len(), LZNT1_CHUNK_SIZE + 2);
+        assert_eq!(&output[..4], b"wxyz");
+        assert!(output[4..LZNT1_CHUNK_SIZE].iter().all(|&byte| byte == 0));
+        assert_eq!(&output[LZNT1_CHUNK_SIZE..], b"ab");
+    }
+
+    #[test]
+    fn lznt1_rejects_a_chunk_longer_than_the_data() {
+        let error = decompress_lznt1(&[0x0F, 0xB0, 0x00, b'a']).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Decompression);
+        assert_eq!(error.offset, Some(0));
+    }
+
+    #[test]
+    fn lznt1_rejects_a_back_reference_before_the_chunk() {
+        assert!(decompress_lznt1(&[0x02, 0xB0, 0x01, 0x00, 0x00]).is_err());
+        // One byte written, two bytes back
+        assert!(decompress_lznt1(&[0x03, 0xB0, 0x02, b'a', 0x00, 0x10]).is_err());
+    }
+
+    #[test]
+    fn lznt1_rejects_a_token_cut_off_by_its_chunk() {
+        assert!(decompress_lznt1(&[0x01, 0xB0, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn lznt1_rejects_every_truncation_of_a_chunk() {
+        for end in 2..LZNT1_ABC.len() {
+            assert!(decompress_lznt1(&LZNT1_ABC[..end]).is_err(), "{} bytes", end);
+        }
+    }
+
+    #[test]
+    fn compression_units_decompress_store_and_keep_sparse_units() {
+        let unit_size = 16;
+        let mut data = LZNT1_ABC.to_vec();
+        data.resize(unit_size, 0);
+        data.extend_from_slice(b"0123456789abcdef");
+        data.resize(3 * unit_size, 0);
+
+        let output = decompress_compression_units(&data, unit_size).unwrap();
+        assert_eq!(output.len(), 3 * unit_size);
+        assert_eq!(&output[..12], b"abcabcabcabc");
+        assert_eq!(&output[12..16], [0; 4]);
+        assert_eq!(&output[16..32], b"0123456789abcdef");
+        assert_eq!(&output[32..], [0; 16]);
+    }
+
+    #[test]
+    fn compression_units_place_errors_in_their_unit() {
+        let mut data = vec![b'x'; 16];
+        data.extend_from_slice(&[0x02, 0xB0, 0x01, 0x00, 0x00]);
+        data.resize(32, 0);
+        let error = decompress_compression_units(&data, 16).unwrap_err();
+        assert_eq!(error.offset, Some(16));
+    }
+
+    #[test]
+    fn lznt1_wrapping_is_recognized_by_the_signature_inside() {
+        assert!(lznt1_wraps(&[0x2F, 0xB0, 0x00, b'F', b'I', b'L', b'E'], b"FILE"));
+        assert!(lznt1_wraps(&[0xFF, 0x3F, b'F', b'I', b'L', b'E'], b"FILE"));
+        assert!(!lznt1_wraps(b"FILE0\0", b"FILE"));
+        assert!(!lznt1_wraps(&[0x2F], b"FILE"));
+        assert_eq!(Wrapper::detect(&[0x2F, 0xB0, 0x00, b'F', b'I', b'L', b'E'], b"FILE"), Some(Wrapper::Lznt1));
+    }
+
+    #[test]
+    fn xpress_decodes_the_ms_xca_example() {
+        assert_eq!(decompress_xpress_huffman(&xpress_alphabet(), 26).unwrap(), b"abcdefghijklmnopqrstuvwxyz");
+    }
+
+    #[test]
+    fn xpress_decodes_matches_with_offset_bits() {
+        // a, b, c, then symbol 278 (1 offset bit, length 9) with offset bit 1: 3 back
+        let mut data = xpress_table(&[(b'a' as usize, 2), (b'b' as usize, 2), (b'c' as usize, 2), (278, 2)]);
+        data.extend_from_slice(&[0x80, 0x1B, 0x00, 0x00]);
+        assert_eq!(decompress_xpress_huffman(&data, 12).unwrap(), b"abcabcabcabc");
+    }
+
+    #[test]
+    fn xpress_reads_long_match_lengths_from_the_byte_stream() {
+        // 'a', then symbol 271 (length 15+) whose extra length byte 21 follows the first two words
+        let mut data = xpress_table(&[(b'a' as usize, 1), (271, 1)]);
+        data.extend_from_slice(&[0x00, 0x40, 0x00, 0x00, 21]);
+        assert_eq!(decompress_xpress_huffman(&data, 40).unwrap(), [b'a'; 40]);
+    }
+
+    #[test]
+    fn xpress_rejects_a_cut_off_table() {
+        let data = xpress_alphabet();
+        let error = decompress_xpress_huffman(&data[..100], 26).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Decompression);
+        assert!(decompress_xpress_huffman(&[], 1).is_err());
+    }
+
+    #[test]
+    fn xpress_rejects_a_table_without_symbols() {
+        let data = vec![0u8; XPRESS_TABLE_SIZE + 4];
+        assert!(decompress_xpress_huffman(&data, 1).is_err());
+    }
+
+    #[test]
+    fn xpress_rejects_code_lengths_that_overflow_the_code_space() {
+        let data = vec![0x11u8; XPRESS_TABLE_SIZE + 4];
+        assert!(decompress_xpress_huffman(&data, 1).is_err());
+    }
+
+    #[test]
+    fn xpress_rejects_a_match_before_any_output() {
+        let mut data = xpress_table(&[(b'a' as usize, 1), (278, 1)]);
+        data.extend_from_slice(&[0x00, 0xC0, 0x00, 0x00]);
+        let error = decompress_xpress_huffman(&data, 12).unwrap_err();
+        assert!(error.message.contains("back"), "{}", error.message);
+    }
+
+    #[test]
+    fn xpress_rejects_data_that_ends_short_of_its_output() {
+        let data = xpress_alphabet();
+        for end in XPRESS_TABLE_SIZE..XPRESS_TABLE_SIZE + 8 {
+            assert!(decompress_xpress_huffman(&data[..end], 26).is_err(), "{} bytes", end);
+        }
+        // Every other prefix fails the same way or decodes, but never panics
+        for end in 0..data.len() {
+            let _ = decompress_xpress_huffman(&data[..end], 26);
+        }
+    }
+
+    #[test]
+    fn huffman_code_lengths_over_the_maximum_are_rejected() {
+        assert!(HuffmanDecoder::new(&[16, 16], 15).is_err());
+        assert!(HuffmanDecoder::new(&[1, 1, 1], 15).is_err());
+        assert!(HuffmanDecoder::new(&[1, 2, 2], 15).is_ok());
+    }
+
+    #[test]
+    fn wof_decompresses_stored_and_xpress_chunks() {
+        // Chunk 0 stored at full size, as neither an XPRESS table nor an LZX
+        // block header would read it; chunk 1 the 26-byte MS-XCA example
+        let mut data = 4096u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0x11; 4096]);
+        data.extend_from_slice(&xpress_alphabet());
+        let output = decompress_wof(&data, 26).unwrap();
+        assert_eq!(output.len(), 4096 + 26);
+        assert!(output[..4096].iter().all(|&byte| byte == 0x11));
+        assert_eq!(&output[4096..], b"abcdefghijklmnopqrstuvwxyz");
+    }
+
+    #[test]
+    fn wof_rejects_data_without_a_chunk_table() {
+        assert!(decompress_wof(&[], 4096).is_err());
+        assert!(decompress_wof(&[0xFF; 64], 4096).is_err());
+        assert!(decompress_wof(&[0x00, 0x00, 0x00, 0x00, 0x01], 4096).is_err());
+    }
+
+    #[test]
+    fn lzx_decodes_a_verbatim_block() {
+        assert_eq!(decompress_lzx(&lzx_abc(), 12).unwrap(), b"abcabcabcabc");
+    }
+
+    #[test]
+    fn lzx_copies_a_stored_block() {
+        assert_eq!(decompress_lzx(&lzx_stored(b"hello"), 5).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn lzx_turns_e8_targets_back_into_relative_ones() {
+        // The last 10 bytes are never translated
+        let mut content = [0u8; 24];
+        content[2] = 0xE8;
+        content[3..7].copy_from_slice(&100i32.to_le_bytes());
+        content[8] = 0xE8;
+        content[9..13].copy_from_slice(&(-4i32).to_le_bytes());
+        let output = decompress_lzx(&lzx_stored(&content), 24).unwrap();
+        assert_eq!(output[3..7], 98i32.to_le_bytes());
+        assert_eq!(output[9..13], (LZX_E8_FILE_SIZE - 4).to_le_bytes());
+    }
+
+    #[test]
+    fn lzx_rejects_invalid_block_types() {
+        for block_type in [0u8, 4, 7] {
+            let data = [0x00, (block_type << 5) | 0x10, 0x00, 0x00];
+            let error = decompress_lzx(&data, 16).unwrap_err();
+            assert!(error.message.contains("block type"), "{}", error.message);
+        }
+    }
+
+    #[test]
+    fn lzx_rejects_an_empty_block() {
+        assert!(decompress_lzx(&[0x00, 0x20, 0x00, 0x00], 16).is_err());
+    }
+
+    #[test]
+    fn lzx_rejects_a_pretree_that_overflows_the_code_space() {
+        let mut bits = LzxWriter::default();
+        bits.put(LZX_BLOCK_VERBATIM, 3);
+        bits.put(1, 1);
+        for _ in 0..LZX_PRETREE_SYMBOLS {
+            bits.put(1, 4);
+        }
+        assert!(decompress_lzx(&bits.finish(), 16).is_err());
+    }
+
+    #[test]
+    fn lzx_rejects_a_match_before_any_output() {
+        let data = lzx_verbatim(|bits| {
+            bits.put(0b11, 2);
+            bits.put(0, 1);
+            bits.put(1, 1);
+        });
+        let error = decompress_lzx(&data, 12).unwrap_err();
+        assert!(error.message.contains("back"), "{}", error.message);
+    }
+
+    #[test]
+    fn lzx_rejects_truncated_blocks() {
+        let stored = lzx_stored(b"hello");
+        for end in 0..stored.len() - 1 {
+            assert!(decompress_lzx(&stored[..end], 5).is_err(), "stored, {} bytes", end);
+        }
+        let verbatim = lzx_abc();
+        for end in 0..verbatim.len() - LZX_READ_AHEAD - 2 {
+            assert!(decompress_lzx(&verbatim[..end], 12).is_err(), "verbatim, {} bytes", end);
+        }
+    }
+
+    #[test]
+    fn wof_stream_decompresses_chunks_by_the_reparse_point_algorithm() {
+        assert_eq!(decompress_wof_stream(&lzx_abc(), WofAlgorithm::Lzx, 12).unwrap(), b"abcabcabcabc");
+
+        let mut data = 4096u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0x11; 4096]);
+        data.extend_from_slice(&xpress_alphabet());
+        let output = decompress_wof_stream(&data, WofAlgorithm::Xpress4K, 4096 + 26).unwrap();
+        assert_eq!(&output[4096..], b"abcdefghijklmnopqrstuvwxyz");
+    }
+
+    #[test]
+    fn wof_stream_rejects_a_short_or_out_of_range_chunk_table() {
+        assert!(decompress_wof_stream(&[0x00; 4], WofAlgorithm::Xpress4K, 3 * 4096).is_err());
+        let mut data = 10_000u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0x11; 64]);
+        let error = decompress_wof_stream(&data, WofAlgorithm::Xpress4K, 4096 + 26).unwrap_err();
+        assert!(error.message.contains("outside"), "{}", error.message);
+    }
+}
@@ -0,0 +1,46 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Polls between clock reads, so checking once per record stays cheap
+const POLLS_PER_CLOCK_READ: u32 = 1024;
+
+/// Point in time after which a parser stops and keeps the records it has so
+/// far, so a pathological input can't hold up an automated pipeline
+#[derive(Debug, Clone, Default)]
+pub struct Deadline {
+    at: Option<Instant>,
+    polls: Cell<u32>,
+    expired: Cell<bool>,
+}
+
+impl Deadline {
+    /// No time limit
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn at(at: Option<Instant>) -> Self {
+        Self { at, ..Self::default() }
+    }
+
+    pub fn after(timeout: Duration) -> Self {
+        Self::at(Some(Instant::now() + timeout))
+    }
+
+    /// True once the deadline has passed; meant to be polled from parse loops
+    pub fn expired(&self) -> bool {
+        let Some(at) = self.at else {
+            return false;
+        };
+        if self.expired.get() {
+            return true;
+        }
+
+        let polls = self.polls.get().wrapping_add(1);
+        self.polls.set(polls);
+        if polls.is_multiple_of(POLLS_PER_CLOCK_READ) && Instant::now() >= at {
+            self.expired.set(true);
+        }
+        self.expired.get()
+    }
+}
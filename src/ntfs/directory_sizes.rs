@@ -0,0 +1,79 @@
+use super::mft::MftParser;
+use super::types::{DirectorySize, MftRecord};
+use std::collections::HashMap;
+
+/// Deepest parent chain followed before giving up, so a reference loop in a
+/// damaged $MFT can't stall the rollup
+const MAX_DEPTH: usize = 1024;
+
+/// Recursive file counts and logical sizes for every in-use directory, largest first.
+///
+/// Files count toward their parent and every directory above it. Deleted files
+/// are totalled separately, so data staged and then removed still shows up
+/// under the directory that held it.
+pub fn compute(parser: &MftParser) -> Vec<DirectorySize> {
+    let mut rows = Vec::new();
+    let mut index = HashMap::new();
+
+    for record in parser.get_records() {
+        if record.is_directory && record.in_use && !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty() {
+            index.insert(record.entry_number, rows.len());
+            rows.push(DirectorySize {
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                full_path: record.full_path.clone(),
+                ..DirectorySize::default()
+            });
+        }
+    }
+
+    for record in parser.get_records() {
+        if !record.base_record_reference.is_empty() || record.is_ads || record.is_hard_link {
+            continue;
+        }
+
+        for (depth, entry_number) in ancestors(parser, record).enumerate() {
+            // Deleted directories have no row, but the chain continues above them
+            let Some(&row) = index.get(&entry_number) else {
+                continue;
+            };
+            let row = &mut rows[row];
+            let direct = depth == 0;
+
+            match (record.is_directory, record.in_use) {
+                (true, true) => row.total_directory_count += 1,
+                (true, false) => {}
+                (false, true) => {
+                    if direct {
+                        row.file_count += 1;
+                        row.size += record.file_size;
+                    }
+                    row.total_file_count += 1;
+                    row.total_size += record.file_size;
+                }
+                (false, false) => {
+                    row.total_deleted_file_count += 1;
+                    row.total_deleted_size += record.file_size;
+                }
+            }
+        }
+    }
+
+    rows.sort_by(|a, b| b.total_size.cmp(&a.total_size).then_with(|| a.full_path.cmp(&b.full_path)));
+    rows
+}
+
+/// Entry numbers of the directories above `record`, nearest first, ending at the root
+fn ancestors<'a>(parser: &'a MftParser, record: &MftRecord) -> impl Iterator<Item = u32> + 'a {
+    let mut current = (record.entry_number, record.parent_entry_number);
+    std::iter::from_fn(move || {
+        let (entry_number, parent) = current;
+        // The root directory is its own parent
+        if parent == entry_number {
+            return None;
+        }
+        current = (parent, parser.get_record(parent)?.parent_entry_number);
+        Some(parent)
+    })
+    .take(MAX_DEPTH)
+}
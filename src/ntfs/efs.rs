@@ -0,0 +1,131 @@
+use super::names::decode_utf16le_lossy;
+use super::sds::format_sid;
+use super::types::{ErrorCode, ParseError, ParseResult};
+
+/// Name of the $LOGGED_UTILITY_STREAM an EFS encrypted file keeps its keys in
+pub const EFS_STREAM_NAME: &str = "$EFS";
+
+/// Length, state, versions and three MD5 checksums, then the DDF and DRF offsets
+const HEADER_SIZE: usize = 0x4C;
+const DDF_OFFSET: usize = 0x40;
+const DRF_OFFSET: usize = 0x44;
+/// Length, credential offset, FEK size and offset, reserved
+const FIELD_HEADER_SIZE: usize = 0x14;
+/// Credential length, SID offset, type and four type-specific dwords
+const CREDENTIAL_HEADER_SIZE: usize = 0x1C;
+/// A credential identified by its certificate's thumbprint, as EFS writes for
+/// every key since Windows XP; older CryptoAPI container credentials only name a key container
+const CREDENTIAL_CERTIFICATE_THUMBPRINT: u32 = 3;
+/// Thumbprint offset and size, then container, provider and user name offsets
+const THUMBPRINT_HEADER_SIZE: usize = 0x14;
+
+/// Someone who can decrypt an EFS encrypted file: a user the file's encryption
+/// key was encrypted for (data decryption field, DDF) or a recovery agent (data
+/// recovery field, DRF)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EfsKey {
+    /// SID of the key's owner; empty when the field carries none, as for most recovery agents
+    pub sid: String,
+    /// SHA-1 thumbprint of the key's certificate as uppercase hex
+    pub thumbprint: String,
+    /// Display name stored with the certificate, e.g. `alice(alice@CONTOSO)`
+    pub user_name: String,
+}
+
+impl EfsKey {
+    /// `sid:thumbprint:user name`, the form output columns list keys in
+    pub fn summary(&self) -> String {
+        format!("{}:{}:{}", self.sid, self.thumbprint, self.user_name)
+    }
+}
+
+/// The keys of a $EFS stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EfsMetadata {
+    pub users: Vec<EfsKey>,
+    pub recovery_agents: Vec<EfsKey>,
+}
+
+/// Decode the data decryption and data recovery fields of a $EFS stream
+pub fn parse(content: &[u8]) -> ParseResult<EfsMetadata> {
+    if content.len() < HEADER_SIZE {
+        return Err(ParseError {
+            code: ErrorCode::EfsMetadata,
+            message: format!("$EFS too short: {} bytes", content.len()),
+            offset: None,
+        });
+    }
+    // A stream without recovery agents has a DRF offset of 0
+    let key_list = |offset_field: usize| match dword(content, offset_field)? as usize {
+        0 => Ok(Vec::new()),
+        offset => parse_key_list(content, offset),
+    };
+    Ok(EfsMetadata {
+        users: key_list(DDF_OFFSET)?,
+        recovery_agents: key_list(DRF_OFFSET)?,
+    })
+}
+
+/// A count followed by that many data decryption or recovery fields
+fn parse_key_list(content: &[u8], offset: usize) -> ParseResult<Vec<EfsKey>> {
+    let count = dword(content, offset)?;
+    let mut keys = Vec::new();
+    let mut field_offset = offset + 4;
+    for _ in 0..count {
+        let field_length = dword(content, field_offset)? as usize;
+        if field_length < FIELD_HEADER_SIZE {
+            return Err(ParseError {
+                code: ErrorCode::EfsMetadata,
+                message: format!("$EFS key field of {} bytes is shorter than its header", field_length),
+                offset: Some(field_offset as u64),
+            });
+        }
+        keys.push(parse_key(content, field_offset)?);
+        field_offset += field_length;
+    }
+    Ok(keys)
+}
+
+fn parse_key(content: &[u8], field_offset: usize) -> ParseResult<EfsKey> {
+    let credential = field_offset + dword(content, field_offset + 4)? as usize;
+    let mut key = EfsKey::default();
+
+    let sid_offset = dword(content, credential + 4)? as usize;
+    if sid_offset != 0 {
+        key.sid = content.get(credential + sid_offset..).and_then(format_sid).unwrap_or_default();
+    }
+    if dword(content, credential + 8)? != CREDENTIAL_CERTIFICATE_THUMBPRINT || content.len() < credential + CREDENTIAL_HEADER_SIZE {
+        return Ok(key);
+    }
+
+    let header = credential + dword(content, credential + 0x10)? as usize;
+    let thumbprint_offset = header + dword(content, header)? as usize;
+    let thumbprint_size = dword(content, header + 4)? as usize;
+    if let Some(thumbprint) = content.get(thumbprint_offset..thumbprint_offset + thumbprint_size) {
+        key.thumbprint = thumbprint.iter().map(|byte| format!("{:02X}", byte)).collect();
+    }
+    if content.len() >= header + THUMBPRINT_HEADER_SIZE {
+        let user_name_offset = dword(content, header + 0x10)? as usize;
+        if user_name_offset != 0 {
+            key.user_name = nul_terminated(content.get(header + user_name_offset..).unwrap_or_default());
+        }
+    }
+    Ok(key)
+}
+
+fn dword(content: &[u8], offset: usize) -> ParseResult<u32> {
+    content
+        .get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| ParseError {
+            code: ErrorCode::EfsMetadata,
+            message: format!("$EFS field at offset {} runs past the stream's {} bytes", offset, content.len()),
+            offset: Some(offset as u64),
+        })
+}
+
+/// A NUL terminated UTF-16 string
+fn nul_terminated(data: &[u8]) -> String {
+    let length = data.chunks_exact(2).position(|unit| unit == [0, 0]).unwrap_or(data.len() / 2);
+    decode_utf16le_lossy(&data[..length * 2])
+}
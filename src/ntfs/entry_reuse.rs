@@ -0,0 +1,129 @@
+use super::types::{MftRecord, PreviousOccupant, SequenceChurn, UsnJournalEntry};
+use std::collections::HashMap;
+
+/// Entries on either side that make up an entry's neighbourhood
+const CHURN_WINDOW: usize = 16;
+
+/// An entry is reported once its sequence number is this many times the neighbourhood median...
+const CHURN_FACTOR: u32 = 4;
+
+/// ...and at least this far above it, so low sequence numbers don't trip the ratio
+const MIN_CHURN_EXCESS: u16 = 32;
+
+const USN_REASON_FILE_CREATE: u32 = 0x0000_0100;
+const USN_REASON_FILE_DELETE: u32 = 0x0000_0200;
+
+/// Entries whose sequence number is far above those of the entries around them.
+///
+/// Every reuse of an entry bumps its sequence number, so an entry that has
+/// churned through many short-lived files (temp files, staging, wiping) stands
+/// out against neighbours allocated at the same time.
+pub fn sequence_churn(records: &[MftRecord]) -> Vec<SequenceChurn> {
+    let base: Vec<&MftRecord> = records
+        .iter()
+        .filter(|record| !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty())
+        .collect();
+
+    let mut churn = Vec::new();
+    for (index, record) in base.iter().enumerate() {
+        let neighbours = base[index.saturating_sub(CHURN_WINDOW)..(index + CHURN_WINDOW + 1).min(base.len())]
+            .iter()
+            .filter(|neighbour| neighbour.entry_number != record.entry_number && neighbour.sequence_number > 0)
+            .map(|neighbour| neighbour.sequence_number);
+        let Some(median) = median(neighbours) else {
+            continue;
+        };
+
+        if u32::from(record.sequence_number) >= u32::from(median.max(1)) * CHURN_FACTOR
+            && record.sequence_number - median >= MIN_CHURN_EXCESS
+        {
+            churn.push(SequenceChurn {
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                neighbor_median: median,
+                in_use: record.in_use,
+                full_path: record.full_path.clone(),
+            });
+        }
+    }
+    churn
+}
+
+fn median(values: impl Iterator<Item = u16>) -> Option<u16> {
+    let mut values: Vec<u16> = values.collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+/// Files $J saw in an entry under an earlier sequence number than the record
+/// now in the $MFT, i.e. files whose record has since been reused.
+///
+/// Journal entries for a newer sequence number than the $MFT holds (a $MFT
+/// captured before the journal) and for entries beyond the end of the $MFT are
+/// left out; there is no current record to compare them with.
+pub fn previous_occupants(records: &[MftRecord], entries: &[UsnJournalEntry]) -> Vec<PreviousOccupant> {
+    let current: HashMap<u32, &MftRecord> = records
+        .iter()
+        .filter(|record| !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty())
+        .map(|record| (record.entry_number, record))
+        .collect();
+
+    let mut index: HashMap<(u32, u16), usize> = HashMap::new();
+    let mut occupants: Vec<PreviousOccupant> = Vec::new();
+    let mut names: Vec<Vec<&str>> = Vec::new();
+
+    for entry in entries {
+        let Some(record) = current.get(&entry.entry_number) else {
+            continue;
+        };
+        if entry.sequence_number >= record.sequence_number {
+            continue;
+        }
+
+        let slot = *index.entry((entry.entry_number, entry.sequence_number)).or_insert_with(|| {
+            occupants.push(PreviousOccupant {
+                entry_number: entry.entry_number,
+                sequence_number: entry.sequence_number,
+                current_sequence_number: record.sequence_number,
+                current_name: record.file_name.clone(),
+                file_name: String::new(),
+                previous_names: String::new(),
+                parent_entry_number: entry.parent_entry_number,
+                parent_sequence_number: entry.parent_sequence_number,
+                first_seen: entry.timestamp,
+                last_seen: entry.timestamp,
+                created: false,
+                deleted: false,
+                usn_records: 0,
+            });
+            names.push(Vec::new());
+            occupants.len() - 1
+        });
+
+        let occupant = &mut occupants[slot];
+        occupant.first_seen = occupant.first_seen.min(entry.timestamp);
+        occupant.last_seen = occupant.last_seen.max(entry.timestamp);
+        occupant.created |= entry.reason_flags & USN_REASON_FILE_CREATE != 0;
+        occupant.deleted |= entry.reason_flags & USN_REASON_FILE_DELETE != 0;
+        occupant.usn_records += 1;
+        occupant.parent_entry_number = entry.parent_entry_number;
+        occupant.parent_sequence_number = entry.parent_sequence_number;
+
+        let seen = &mut names[slot];
+        if seen.last() != Some(&entry.file_name.as_str()) {
+            seen.retain(|name| *name != entry.file_name);
+            seen.push(&entry.file_name);
+        }
+    }
+
+    for (occupant, mut seen) in occupants.iter_mut().zip(names) {
+        occupant.file_name = seen.pop().unwrap_or_default().to_string();
+        occupant.previous_names = seen.join("|");
+    }
+
+    occupants.sort_by_key(|occupant| (occupant.entry_number, occupant.sequence_number));
+    occupants
+}
@@ -0,0 +1,78 @@
+use super::types::{ErrorCode, ParseError, ParseResult};
+
+/// FILE_NEED_EA: the file can't be understood without this attribute
+pub const NEED_EA: u8 = 0x80;
+
+/// NextEntryOffset, Flags, EaNameLength and EaValueLength of FILE_FULL_EA_INFORMATION
+const ENTRY_HEADER_SIZE: usize = 8;
+/// Size of $EA_INFORMATION (0xD0)
+const INFORMATION_SIZE: usize = 8;
+
+/// One extended attribute from $EA (0xE0), e.g. the `$LXUID`, `$LXGID` and
+/// `$LXMOD` attributes WSL keeps Linux ownership and modes in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedAttribute {
+    pub name: String,
+    pub flags: u8,
+    pub value: Vec<u8>,
+}
+
+/// $EA_INFORMATION (0xD0), which sizes the $EA attribute next to it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EaInformation {
+    /// Size of the attributes in their packed (FILE_FULL_EA_INFORMATION without padding) form
+    pub packed_size: u16,
+    /// Attributes flagged NEED_EA
+    pub need_ea_count: u16,
+    /// Size of the $EA attribute's content
+    pub unpacked_size: u32,
+}
+
+/// Decode the FILE_FULL_EA_INFORMATION entries of a resident $EA
+pub fn parse(content: &[u8]) -> ParseResult<Vec<ExtendedAttribute>> {
+    let mut attributes = Vec::new();
+    let mut offset = 0;
+    while offset + ENTRY_HEADER_SIZE <= content.len() {
+        let entry = &content[offset..];
+        let next_entry_offset = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+        let flags = entry[4];
+        let name_length = entry[5] as usize;
+        let value_length = u16::from_le_bytes([entry[6], entry[7]]) as usize;
+
+        // The name is NUL terminated, the value follows the terminator
+        let value_start = ENTRY_HEADER_SIZE + name_length + 1;
+        let Some(value) = entry.get(value_start..value_start + value_length) else {
+            return Err(ParseError {
+                code: ErrorCode::ExtendedAttribute,
+                message: format!("$EA entry at offset {} runs past the attribute's {} bytes", offset, content.len()),
+                offset: None,
+            });
+        };
+        attributes.push(ExtendedAttribute {
+            name: String::from_utf8_lossy(&entry[ENTRY_HEADER_SIZE..ENTRY_HEADER_SIZE + name_length]).into_owned(),
+            flags,
+            value: value.to_vec(),
+        });
+
+        if next_entry_offset == 0 {
+            break;
+        }
+        offset += next_entry_offset;
+    }
+    Ok(attributes)
+}
+
+pub fn parse_information(content: &[u8]) -> ParseResult<EaInformation> {
+    if content.len() < INFORMATION_SIZE {
+        return Err(ParseError {
+            code: ErrorCode::ExtendedAttribute,
+            message: format!("$EA_INFORMATION too short: {} bytes", content.len()),
+            offset: None,
+        });
+    }
+    Ok(EaInformation {
+        packed_size: u16::from_le_bytes([content[0], content[1]]),
+        need_ea_count: u16::from_le_bytes([content[2], content[3]]),
+        unpacked_size: u32::from_le_bytes(content[4..8].try_into().unwrap()),
+    })
+}
@@ -0,0 +1,44 @@
+/// FILE_ATTRIBUTE_* bits of $STANDARD_INFORMATION and $FILE_NAME and their names, in bit order
+const FILE_ATTRIBUTES: &[(u32, &str)] = &[
+    (0x0000_0001, "ReadOnly"),
+    (0x0000_0002, "Hidden"),
+    (0x0000_0004, "System"),
+    (0x0000_0010, "Directory"),
+    (0x0000_0020, "Archive"),
+    (0x0000_0040, "Device"),
+    (0x0000_0080, "Normal"),
+    (0x0000_0100, "Temporary"),
+    (0x0000_0200, "Sparse"),
+    (0x0000_0400, "ReparsePoint"),
+    (0x0000_0800, "Compressed"),
+    (0x0000_1000, "Offline"),
+    (0x0000_2000, "NotContentIndexed"),
+    (0x0000_4000, "Encrypted"),
+    (0x0000_8000, "IntegrityStream"),
+    (0x0001_0000, "Virtual"),
+    (0x0002_0000, "NoScrubData"),
+    (0x0004_0000, "RecallOnOpen"),
+    (0x0008_0000, "Pinned"),
+    (0x0010_0000, "Unpinned"),
+    (0x0040_0000, "RecallOnDataAccess"),
+    // Only NTFS itself sets these two, on directories and on view indexes such as $Secure
+    (0x1000_0000, "IsDirectory"),
+    (0x2000_0000, "IsIndexView"),
+];
+
+/// Names of the attribute bits set in `flags`, separated by " | ", e.g.
+/// "Hidden | System | Archive"; bits without a name are kept as hex.
+/// Empty when no bit is set.
+pub fn format_file_attributes(flags: u32) -> String {
+    let mut names: Vec<String> = FILE_ATTRIBUTES
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+
+    let unknown = FILE_ATTRIBUTES.iter().fold(flags, |rest, (flag, _)| rest & !flag);
+    if unknown != 0 {
+        names.push(format!("0x{:08x}", unknown));
+    }
+    names.join(" | ")
+}
@@ -0,0 +1,247 @@
+use chrono::format::{DelayedFormat, StrftimeItems};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// 100-nanosecond intervals between 1601-01-01 and 1970-01-01
+const UNIX_EPOCH_FILETIME: u64 = 116_444_736_000_000_000;
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// First FILETIME past 9999-12-31T23:59:59.9999999Z, the last instant RFC 3339 can write
+const MAX_VALID_FILETIME: u64 = 2_650_467_744_000_000_000;
+
+/// How far past the time of the run a timestamp may lie before it is flagged,
+/// leaving room for clock skew between the examined system and this one
+const FUTURE_TOLERANCE_SECONDS: i64 = 24 * 60 * 60;
+
+/// Whether a FILETIME is a plausible point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    Valid,
+    /// Later than the time of the run; still written, but flagged
+    Future,
+    /// Beyond year 9999, e.g. 0xFFFFFFFFFFFFFFFF; written as null
+    Invalid,
+}
+
+impl fmt::Display for Validity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Validity::Valid => "valid",
+            Validity::Future => "future",
+            Validity::Invalid => "invalid",
+        })
+    }
+}
+
+/// Out-of-range timestamps among `times`, as `name=0xRAW flag` separated by '|';
+/// empty when every timestamp is valid
+pub fn anomalies<'a>(times: impl IntoIterator<Item = (&'a str, Option<FileTime>)>) -> String {
+    times
+        .into_iter()
+        .filter_map(|(name, time)| {
+            let time = time?;
+            match time.validity() {
+                Validity::Valid => None,
+                validity => Some(format!("{}=0x{:016X} {}", name, time.0, validity)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Coarsens timestamps for datasets shared outside an investigation: each is
+/// moved by `shift_seconds`, then rounded down to a multiple of
+/// `granularity_seconds` (0 keeps full precision). The shift is the same for
+/// every timestamp, so events keep their order and, up to the rounding, the
+/// gaps between them. Unset and out-of-range values are left alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeCoarsening {
+    pub granularity_seconds: u64,
+    pub shift_seconds: i64,
+}
+
+impl TimeCoarsening {
+    pub fn apply(self, time: FileTime) -> FileTime {
+        if time.0 == 0 || time.0 >= MAX_VALID_FILETIME {
+            return time;
+        }
+        let shifted = time.0 as i128 + self.shift_seconds as i128 * TICKS_PER_SECOND as i128;
+        let shifted = shifted.clamp(1, MAX_VALID_FILETIME as i128 - 1) as u64;
+        // FILETIME counts from midnight, so multiples of a day, hour or minute fall on their boundaries
+        let granularity = self.granularity_seconds * TICKS_PER_SECOND;
+        FileTime(if granularity == 0 { shifted } else { shifted - shifted % granularity })
+    }
+
+    /// Coarsen every timestamp of each item
+    pub fn apply_to<T: MapTimes>(self, items: &mut [T]) {
+        for item in items {
+            item.map_times(|time| self.apply(time));
+        }
+    }
+}
+
+/// Records, rows and findings holding timestamps, so a [`TimeCoarsening`]
+/// reaches every output written from them
+pub trait MapTimes {
+    /// Pass every timestamp through `map`
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime);
+}
+
+/// A Windows FILETIME: 100-nanosecond intervals since 1601-01-01 UTC.
+///
+/// Parsers store the raw integer and only build a calendar date when the value is
+/// displayed or serialized, which keeps date arithmetic out of the parsing hot path.
+/// Zero means "not set" and is shown as the Unix epoch.
+///
+/// Every writer shows all seven 100-nanosecond digits, so no precision the
+/// volume recorded is lost and column widths never depend on the value.
+/// Values past year 9999 cannot be written as a date and serialize as null;
+/// records list them with their raw value in `timestamp_anomalies`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileTime(pub u64);
+
+impl FileTime {
+    pub fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Whole seconds since the Unix epoch, rounded toward negative infinity; integer math only.
+    /// 0 when not set or invalid, as bodyfiles expect for missing times
+    pub fn timestamp(self) -> i64 {
+        if self.0 == 0 || self.0 >= MAX_VALID_FILETIME {
+            return 0;
+        }
+        (self.0 / TICKS_PER_SECOND) as i64 - (UNIX_EPOCH_FILETIME / TICKS_PER_SECOND) as i64
+    }
+
+    /// Nanoseconds past the whole second
+    pub fn subsec_nanos(self) -> u32 {
+        ((self.0 % TICKS_PER_SECOND) * 100) as u32
+    }
+
+    pub fn to_datetime(self) -> DateTime<Utc> {
+        DateTime::<Utc>::from_timestamp(self.timestamp(), self.subsec_nanos())
+            .unwrap_or(DateTime::<Utc>::UNIX_EPOCH)
+    }
+
+    pub fn from_datetime(datetime: DateTime<Utc>) -> Self {
+        let ticks = datetime.timestamp() as i128 * TICKS_PER_SECOND as i128
+            + (datetime.timestamp_subsec_nanos() / 100) as i128
+            + UNIX_EPOCH_FILETIME as i128;
+        Self(ticks.clamp(0, u64::MAX as i128) as u64)
+    }
+
+    /// RFC 3339 in UTC with all seven 100-nanosecond digits, e.g. 2024-03-01T12:00:00.1234567Z
+    pub fn to_fixed_precision(self) -> String {
+        format!("{}.{:07}Z", self.format("%Y-%m-%dT%H:%M:%S"), self.0 % TICKS_PER_SECOND)
+    }
+
+    /// Invalid past year 9999, future once later than the time of the run
+    pub fn validity(self) -> Validity {
+        static RUN_STARTED: OnceLock<i64> = OnceLock::new();
+
+        if self.0 >= MAX_VALID_FILETIME {
+            return Validity::Invalid;
+        }
+        let now = *RUN_STARTED.get_or_init(|| Utc::now().timestamp());
+        if self.timestamp() > now + FUTURE_TOLERANCE_SECONDS {
+            Validity::Future
+        } else {
+            Validity::Valid
+        }
+    }
+
+    /// Format with a strftime pattern, as DateTime::format does
+    pub fn format<'a>(self, pattern: &'a str) -> DelayedFormat<StrftimeItems<'a>> {
+        self.to_datetime().format(pattern)
+    }
+}
+
+impl From<u64> for FileTime {
+    fn from(raw: u64) -> Self {
+        Self(raw)
+    }
+}
+
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= MAX_VALID_FILETIME {
+            return write!(f, "invalid (0x{:016X})", self.0);
+        }
+        f.write_str(&self.to_fixed_precision())
+    }
+}
+
+impl Serialize for FileTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0 >= MAX_VALID_FILETIME {
+            return serializer.serialize_none();
+        }
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FileTime {
+    /// Null (an invalid timestamp) reads back as "not set"
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<DateTime<Utc>>::deserialize(deserializer).map(|datetime| datetime.map(Self::from_datetime).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(time: FileTime) -> String {
+        serde_json::to_string(&time).unwrap()
+    }
+
+    #[test]
+    fn zero_is_unix_epoch() {
+        assert_eq!(FileTime(0).to_string(), "1970-01-01T00:00:00.0000000Z");
+        assert_eq!(FileTime(0).timestamp(), 0);
+    }
+
+    #[test]
+    fn unix_epoch() {
+        let time = FileTime(UNIX_EPOCH_FILETIME);
+        assert_eq!(time.to_string(), "1970-01-01T00:00:00.0000000Z");
+        assert_eq!(json(time), "\"1970-01-01T00:00:00.0000000Z\"");
+    }
+
+    #[test]
+    fn keeps_100ns_ticks() {
+        assert_eq!(FileTime(UNIX_EPOCH_FILETIME + 1).to_string(), "1970-01-01T00:00:00.0000001Z");
+        let time = FileTime(132_223_104_001_234_567);
+        assert_eq!(time.to_string(), "2020-01-01T00:00:00.1234567Z");
+        assert_eq!(time.timestamp(), 1_577_836_800);
+        assert_eq!(time.subsec_nanos(), 123_456_700);
+    }
+
+    #[test]
+    fn last_representable_instant() {
+        let time = FileTime(MAX_VALID_FILETIME - 1);
+        assert_eq!(time.to_string(), "9999-12-31T23:59:59.9999999Z");
+        assert_eq!(time.validity(), Validity::Future);
+    }
+
+    #[test]
+    fn unrepresentable_value() {
+        let time = FileTime(u64::MAX);
+        assert_eq!(time.validity(), Validity::Invalid);
+        assert_eq!(time.to_string(), "invalid (0xFFFFFFFFFFFFFFFF)");
+        assert_eq!(json(time), "null");
+        assert_eq!(time.timestamp(), 0);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        for raw in [UNIX_EPOCH_FILETIME, 132_223_104_001_234_567, MAX_VALID_FILETIME - 1] {
+            let time: FileTime = serde_json::from_str(&json(FileTime(raw))).unwrap();
+            assert_eq!(time, FileTime(raw));
+        }
+        assert_eq!(serde_json::from_str::<FileTime>("null").unwrap(), FileTime(0));
+    }
+}
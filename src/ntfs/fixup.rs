@@ -0,0 +1,91 @@
+use super::types::ErrorCode;
+
+/// Update sequence arrays protect the last two bytes of every 512-byte stride,
+/// whatever the sector size of the volume
+pub const FIXUP_STRIDE: usize = 512;
+
+/// Offset of the update sequence array's offset and count in FILE, INDX and RCRD headers
+const USA_OFFSET: usize = 4;
+const USA_COUNT: usize = 6;
+
+/// Restore the stride-end bytes of a FILE record, INDX page or RCRD page from
+/// its update sequence array.
+///
+/// Every stride whose check value matches is restored, even when another one
+/// doesn't, so a torn record still yields its intact sectors. Fails with
+/// `ErrorCode::Fixup` when the array lies outside `buf`, names strides past its
+/// end, or any check value doesn't match.
+pub fn apply(buf: &mut [u8]) -> Result<(), ErrorCode> {
+    if buf.len() < USA_COUNT + 2 {
+        return Err(ErrorCode::Fixup);
+    }
+    let usa_offset = u16::from_le_bytes([buf[USA_OFFSET], buf[USA_OFFSET + 1]]) as usize;
+    let usa_count = u16::from_le_bytes([buf[USA_COUNT], buf[USA_COUNT + 1]]) as usize;
+    if usa_count == 0 || usa_offset + usa_count * 2 > buf.len() || (usa_count - 1) * FIXUP_STRIDE > buf.len() {
+        return Err(ErrorCode::Fixup);
+    }
+
+    let check = [buf[usa_offset], buf[usa_offset + 1]];
+    let mut intact = true;
+    for stride in 1..usa_count {
+        let end = stride * FIXUP_STRIDE;
+        if buf[end - 2..end] != check {
+            intact = false;
+            continue;
+        }
+        let replacement = usa_offset + stride * 2;
+        buf[end - 2] = buf[replacement];
+        buf[end - 1] = buf[replacement + 1];
+    }
+
+    if intact { Ok(()) } else { Err(ErrorCode::Fixup) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-stride record whose array sits at 0x30 with check value 0x0007,
+    /// stride ends carrying `ends` and the saved bytes 0xAAAA and 0xBBBB
+    fn record(ends: [u16; 2]) -> Vec<u8> {
+        let mut buf = vec![0u8; 2 * FIXUP_STRIDE];
+        buf[0..4].copy_from_slice(b"FILE");
+        buf[USA_OFFSET..USA_OFFSET + 2].copy_from_slice(&0x30u16.to_le_bytes());
+        buf[USA_COUNT..USA_COUNT + 2].copy_from_slice(&3u16.to_le_bytes());
+        buf[0x30..0x36].copy_from_slice(&[0x07, 0x00, 0xAA, 0xAA, 0xBB, 0xBB]);
+        buf[FIXUP_STRIDE - 2..FIXUP_STRIDE].copy_from_slice(&ends[0].to_le_bytes());
+        buf[2 * FIXUP_STRIDE - 2..].copy_from_slice(&ends[1].to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn restores_every_stride() {
+        let mut buf = record([7, 7]);
+        assert_eq!(apply(&mut buf), Ok(()));
+        assert_eq!(buf[FIXUP_STRIDE - 2..FIXUP_STRIDE], [0xAA, 0xAA]);
+        assert_eq!(buf[2 * FIXUP_STRIDE - 2..], [0xBB, 0xBB]);
+    }
+
+    #[test]
+    fn restores_intact_strides_of_a_torn_record() {
+        let mut buf = record([7, 9]);
+        assert_eq!(apply(&mut buf), Err(ErrorCode::Fixup));
+        assert_eq!(buf[FIXUP_STRIDE - 2..FIXUP_STRIDE], [0xAA, 0xAA]);
+        assert_eq!(buf[2 * FIXUP_STRIDE - 2..], [9, 0]);
+    }
+
+    #[test]
+    fn rejects_array_outside_record() {
+        let mut buf = record([7, 7]);
+        buf[USA_OFFSET..USA_OFFSET + 2].copy_from_slice(&0xFFF0u16.to_le_bytes());
+        assert_eq!(apply(&mut buf), Err(ErrorCode::Fixup));
+    }
+
+    #[test]
+    fn rejects_strides_past_record() {
+        let mut buf = record([7, 7]);
+        buf.truncate(FIXUP_STRIDE + 0x100);
+        assert_eq!(apply(&mut buf), Err(ErrorCode::Fixup));
+        assert!(apply(&mut [0u8; 4]).is_err());
+    }
+}
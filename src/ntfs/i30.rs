@@ -1,11 +1,33 @@
-use super::types::{IndexEntry, ParseError, ParseResult};
+use super::category;
+use super::deadline::Deadline;
+use crate::artifact::{InputInfo, NO_MATCH, SIGNATURE_MATCH};
+use super::filetime::{self, FileTime};
+use super::fixup;
+use super::names::{decode_utf16le, extract_extension};
+use super::path_resolver::PathResolver;
+use super::types::{IndexEntry, NameNamespace, ErrorCode, ParseError, ParseResult};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::{DateTime, Utc};
 use std::io::{Cursor, Read};
 
+/// INDX record header plus the index node header that follows it
+const INDX_HEADER_SIZE: usize = 0x28;
+
+/// Offset of the index node header, which entry offsets are relative to
+const INDEX_NODE_HEADER_OFFSET: usize = 0x18;
+
+/// Size of an index allocation page, each with its own update sequence array
+const INDX_PAGE_SIZE: usize = 4096;
+
+/// Index entry header (16 bytes) plus the fixed part of its $FILE_NAME key
+const MIN_INDEX_ENTRY_LENGTH: u64 = 0x52;
+
 pub struct I30Parser {
     data: Vec<u8>,
     entries: Vec<IndexEntry>,
+    pages: usize,
+    fixup_errors: usize,
+    deadline: Deadline,
+    timed_out: bool,
 }
 
 impl I30Parser {
@@ -13,64 +35,110 @@ impl I30Parser {
         Self {
             data,
             entries: Vec::new(),
+            pages: 0,
+            fixup_errors: 0,
+            deadline: Deadline::none(),
+            timed_out: false,
         }
     }
 
-    pub fn parse(&mut self) -> ParseResult<()> {
-        let mut cursor = Cursor::new(&self.data);
+    /// Stop reading INDX pages once `deadline` passes, keeping the entries parsed so far
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
 
-        // Parse INDX header
-        let signature = cursor.read_u32::<LittleEndian>()
-            .map_err(|_| ParseError {
-                message: "Failed to read INDX signature".to_string(),
+    pub fn parse(&mut self) -> ParseResult<()> {
+        if self.data.len() < INDX_HEADER_SIZE {
+            return Err(ParseError {
+                code: ErrorCode::InputTooSmall,
+                message: format!("INDX record of {} bytes is shorter than its header", self.data.len()),
                 offset: Some(0),
-            })?;
+            });
+        }
 
-        if signature != 0x58444e49 { // "INDX"
+        if !self.data.starts_with(b"INDX") {
             return Err(ParseError {
+                code: ErrorCode::BadSignature,
                 message: "Invalid INDX signature".to_string(),
                 offset: Some(0),
             });
         }
 
-        let _fixup_offset = cursor.read_u16::<LittleEndian>().unwrap();
-        let _fixup_count = cursor.read_u16::<LittleEndian>().unwrap();
-        let _lsn = cursor.read_u64::<LittleEndian>().unwrap();
-        let _vcn = cursor.read_u64::<LittleEndian>().unwrap();
+        let mut offset = 0;
+        while offset + INDX_HEADER_SIZE <= self.data.len() {
+            if self.deadline.expired() {
+                log::warn!("Timed out at offset 0x{:x}; keeping {} index entries parsed so far", offset, self.entries.len());
+                self.timed_out = true;
+                break;
+            }
+            let end = (offset + INDX_PAGE_SIZE).min(self.data.len());
+            // Pages past the last allocated one are often zeroed or stale
+            if !self.data[offset..].starts_with(b"INDX") {
+                log::debug!("No INDX signature at offset 0x{:x}; skipping page", offset);
+                offset += INDX_PAGE_SIZE;
+                continue;
+            }
+
+            self.pages += 1;
+            let mut page = self.data[offset..end].to_vec();
+            let fixup_ok = fixup::apply(&mut page).is_ok();
+            if !fixup_ok {
+                log::warn!("{}: INDX page at offset 0x{:x} failed its update sequence check", ErrorCode::Fixup, offset);
+                self.fixup_errors += 1;
+            }
+            self.parse_page(&page, offset as u64, fixup_ok);
+            offset += INDX_PAGE_SIZE;
+        }
+
+        log::info!("Parsed {} I30 index entries from {} INDX pages", self.entries.len(), self.pages);
+        Ok(())
+    }
+
+    /// Parse the entries of one fixed-up INDX page
+    fn parse_page(&mut self, page: &[u8], page_offset: u64, fixup_ok: bool) {
+        let mut cursor = Cursor::new(page);
+        cursor.set_position(INDEX_NODE_HEADER_OFFSET as u64);
 
         // Parse index header
         let entries_offset = cursor.read_u32::<LittleEndian>().unwrap();
-        let _total_size = cursor.read_u32::<LittleEndian>().unwrap();
+        let total_size = cursor.read_u32::<LittleEndian>().unwrap();
         let _allocated_size = cursor.read_u32::<LittleEndian>().unwrap();
         let _flags = cursor.read_u32::<LittleEndian>().unwrap();
 
-        // Jump to entries
-        cursor.set_position(24 + entries_offset as u64);
+        // Entries end where the node's used size says, never past the page
+        let used_end = (INDEX_NODE_HEADER_OFFSET + total_size as usize).min(page.len());
+        let entries = &page[..used_end];
+        let mut cursor = Cursor::new(entries);
+        cursor.set_position((INDEX_NODE_HEADER_OFFSET + entries_offset as usize) as u64);
 
-        while (cursor.position() as usize) < self.data.len() {
-            match self.parse_entry(&mut cursor) {
-                Ok(Some(entry)) => self.entries.push(entry),
+        while (cursor.position() as usize) < entries.len() {
+            match Self::parse_entry(&mut cursor) {
+                Ok(Some(mut entry)) => {
+                    entry.fixup_error = !fixup_ok;
+                    entry.error_code = (!fixup_ok).then_some(ErrorCode::Fixup);
+                    self.entries.push(entry);
+                }
                 Ok(None) => break, // End of entries
                 Err(e) => {
-                    log::warn!("Failed to parse I30 entry at offset 0x{:x}: {}", cursor.position(), e);
+                    log::warn!("Failed to parse I30 entry at offset 0x{:x}: {}", page_offset + cursor.position(), e);
                     break;
                 }
             }
         }
-
-        log::info!("Parsed {} I30 index entries", self.entries.len());
-        Ok(())
     }
 
-    fn parse_entry(&self, cursor: &mut Cursor<&Vec<u8>>) -> ParseResult<Option<IndexEntry>> {
+    fn parse_entry(cursor: &mut Cursor<&[u8]>) -> ParseResult<Option<IndexEntry>> {
         let start_pos = cursor.position();
+        let data_len = cursor.get_ref().len() as u64;
 
-        if start_pos + 16 > self.data.len() as u64 {
+        if start_pos + 16 > data_len {
             return Ok(None); // Not enough data for index entry header
         }
 
         let file_reference = cursor.read_u64::<LittleEndian>()
             .map_err(|_| ParseError {
+                code: ErrorCode::IndexEntry,
                 message: "Failed to read file reference".to_string(),
                 offset: Some(start_pos),
             })?;
@@ -79,13 +147,22 @@ impl I30Parser {
         let sequence_number = (file_reference >> 48) as u16;
 
         let entry_length = cursor.read_u16::<LittleEndian>().unwrap();
-        let filename_length = cursor.read_u16::<LittleEndian>().unwrap();
+        let _filename_length = cursor.read_u16::<LittleEndian>().unwrap();
         let flags = cursor.read_u32::<LittleEndian>().unwrap();
 
         if entry_length == 0 || (flags & 0x02) != 0 {
             return Ok(None); // End entry or invalid entry
         }
 
+        // Every read below stays inside the entry, and the next one starts past it
+        if (entry_length as u64) < MIN_INDEX_ENTRY_LENGTH || start_pos + entry_length as u64 > data_len {
+            return Err(ParseError {
+                code: ErrorCode::IndexEntry,
+                message: format!("Invalid index entry length {}", entry_length),
+                offset: Some(start_pos),
+            });
+        }
+
         // Parse filename attribute
         let parent_file_reference = cursor.read_u64::<LittleEndian>().unwrap();
         let parent_entry_number = (parent_file_reference & 0xFFFFFFFFFFFF) as u32;
@@ -102,32 +179,56 @@ impl I30Parser {
         let _reparse_value = cursor.read_u32::<LittleEndian>().unwrap();
 
         let name_length = cursor.read_u8().unwrap();
-        let _name_type = cursor.read_u8().unwrap();
+        let name_namespace = NameNamespace::from(cursor.read_u8().unwrap());
+
+        if MIN_INDEX_ENTRY_LENGTH + name_length as u64 * 2 > entry_length as u64 {
+            return Err(ParseError {
+                code: ErrorCode::IndexEntry,
+                message: format!("File name of {} characters overflows its index entry", name_length),
+                offset: Some(start_pos),
+            });
+        }
 
         // Read filename (UTF-16)
         let mut name_bytes = vec![0u8; (name_length as usize) * 2];
         cursor.read_exact(&mut name_bytes)
             .map_err(|_| ParseError {
+                code: ErrorCode::IndexEntry,
                 message: "Failed to read filename".to_string(),
                 offset: Some(start_pos),
             })?;
 
-        let file_name = string_from_utf16le(&name_bytes)
+        let file_name = decode_utf16le(&name_bytes)
             .unwrap_or_else(|_| String::from("INVALID_NAME"));
 
+        let extension_info = extract_extension(&file_name);
+        let file_category = category::from_extension(&extension_info.extension, (attributes & 0x10) != 0);
+
         let entry = IndexEntry {
             entry_number,
             sequence_number,
             parent_entry_number,
             parent_sequence_number,
             file_name,
+            name_namespace,
             full_path: String::new(), // Will be resolved later
+            path_source: None,
+            extension: extension_info.extension,
+            suspicious_name: extension_info.suspicious,
+            category: file_category,
             file_size,
             is_directory: (attributes & 0x10) != 0,
-            created: windows_filetime_to_datetime(created),
-            modified: windows_filetime_to_datetime(modified),
-            accessed: windows_filetime_to_datetime(accessed),
+            created: FileTime(created),
+            modified: FileTime(modified),
+            accessed: FileTime(accessed),
+            timestamp_anomalies: filetime::anomalies([
+                ("created", Some(FileTime(created))),
+                ("modified", Some(FileTime(modified))),
+                ("accessed", Some(FileTime(accessed))),
+            ]),
             attributes,
+            fixup_error: false,
+            error_code: None,
         };
 
         // Move to next entry
@@ -136,24 +237,86 @@ impl I30Parser {
         Ok(Some(entry))
     }
 
+    /// Fill `full_path` from `paths`, falling back to the directories among
+    /// the carved entries themselves. Returns how many entries were resolved.
+    pub fn resolve_paths(&mut self, mut paths: PathResolver) -> usize {
+        paths.learn_i30(&self.entries);
+        let mut resolved = 0;
+        for entry in &mut self.entries {
+            if let Some((path, source)) = paths.resolve(entry.parent_entry_number, entry.parent_sequence_number, &entry.file_name) {
+                entry.full_path = path;
+                entry.path_source = Some(source);
+                resolved += 1;
+            }
+        }
+        resolved
+    }
+
     pub fn get_entries(&self) -> &[IndexEntry] {
         &self.entries
     }
-}
 
-fn windows_filetime_to_datetime(filetime: u64) -> DateTime<Utc> {
-    const FILETIME_UNIX_DIFF: u64 = 11644473600;
-    let seconds = filetime / 10_000_000 - FILETIME_UNIX_DIFF;
-    let nanos = ((filetime % 10_000_000) * 100) as u32;
+    pub fn get_page_count(&self) -> usize {
+        self.pages
+    }
 
-    DateTime::<Utc>::from_timestamp(seconds as i64, nanos)
-        .unwrap_or_else(|| DateTime::<Utc>::from_timestamp(0, 0).unwrap())
+    /// True when parsing stopped early at the deadline
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// INDX pages whose update sequence array didn't match
+    pub fn get_fixup_error_count(&self) -> usize {
+        self.fixup_errors
+    }
 }
 
-fn string_from_utf16le(bytes: &[u8]) -> Result<String, std::string::FromUtf16Error> {
-    let utf16_chars: Vec<u16> = bytes
-        .chunks_exact(2)
-        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
-    String::from_utf16(&utf16_chars)
-}
\ No newline at end of file
+/// $I30 index allocations are a run of "INDX" pages
+pub fn detect(input: &InputInfo) -> u8 {
+    if input.header.starts_with(b"INDX") {
+        SIGNATURE_MATCH
+    } else {
+        NO_MATCH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An index entry header in a buffer of `size` bytes, claiming `entry_length`
+    fn index_entry(entry_length: u16, size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[0x00..0x08].copy_from_slice(&0x0001_0000_0000_0040u64.to_le_bytes());
+        data[0x08..0x0A].copy_from_slice(&entry_length.to_le_bytes());
+        data[0x0A..0x0C].copy_from_slice(&0x42u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn rejects_entry_shorter_than_its_key() {
+        let data = index_entry(0x18, 0x60);
+        let error = I30Parser::parse_entry(&mut Cursor::new(&data[..])).unwrap_err();
+        assert_eq!(error.code, ErrorCode::IndexEntry);
+    }
+
+    #[test]
+    fn rejects_entry_past_page() {
+        let data = index_entry(0x200, 0x60);
+        let error = I30Parser::parse_entry(&mut Cursor::new(&data[..])).unwrap_err();
+        assert_eq!(error.code, ErrorCode::IndexEntry);
+    }
+
+    #[test]
+    fn rejects_name_overflowing_entry() {
+        let mut data = index_entry(0x58, 0x60);
+        data[0x50] = 0x20; // 32 characters in an entry with room for 3
+        assert!(I30Parser::parse_entry(&mut Cursor::new(&data[..])).is_err());
+    }
+
+    #[test]
+    fn rejects_input_shorter_than_header() {
+        let error = I30Parser::new(b"INDX".to_vec()).parse().unwrap_err();
+        assert_eq!(error.code, ErrorCode::InputTooSmall);
+    }
+}
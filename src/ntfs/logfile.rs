@@ -0,0 +1,391 @@
+use super::deadline::Deadline;
+use super::filetime::{FileTime, Validity};
+use super::fixup;
+use super::mft::MftParser;
+use super::names::{decode_utf16le, extract_extension};
+use super::types::{LogFileName, NameNamespace, ErrorCode, ParseError, ParseResult};
+use crate::artifact::{InputInfo, NO_MATCH, SIGNATURE_MATCH};
+use std::collections::{HashMap, HashSet};
+
+/// Log page size when the restart page doesn't give a usable one
+const DEFAULT_LOG_PAGE_SIZE: usize = 4096;
+
+/// Offset of the log page size in a restart page header
+const RESTART_LOG_PAGE_SIZE_OFFSET: usize = 0x14;
+
+/// Update sequence arrays protect the last two bytes of every 512-byte sector
+const SECTOR_SIZE: usize = 512;
+
+/// RCRD page header up to its update sequence array
+const RCRD_HEADER_SIZE: usize = 0x28;
+
+/// Fixed part of a $FILE_NAME attribute, before the UTF-16 name
+const FILE_NAME_HEADER_SIZE: usize = 0x42;
+
+/// Earliest timestamp taken for a carved name (1990-01-01); zeroes and small
+/// integers that happen to sit where a FILETIME would are rejected
+const MIN_PLAUSIBLE_FILETIME: u64 = 122_756_256_000_000_000;
+
+/// Attribute bits a $FILE_NAME can carry, including the directory and index view bits
+const KNOWN_NAME_FLAGS: u32 = 0x3047_FFFF;
+
+/// $FILE_NAME flag set on directories
+const FILE_NAME_DIRECTORY: u32 = 0x1000_0000;
+
+/// Largest file size NTFS can describe
+const MAX_FILE_SIZE: u64 = 1 << 48;
+
+/// Carves the $FILE_NAME attributes held in $LogFile log records.
+///
+/// Redo and undo data for index and FILE record updates embed whole
+/// $FILE_NAME structures, so names of files created, renamed or deleted in
+/// the log's window survive here after their FILE records have been reused.
+/// Each 8-byte aligned position of every RCRD page is checked for a plausible
+/// $FILE_NAME: known flags, timestamps between 1990 and the time of the run,
+/// and a name without control or reserved characters.
+pub struct LogFileParser {
+    data: Vec<u8>,
+    names: Vec<LogFileName>,
+    pages: usize,
+    error_count: usize,
+    deadline: Deadline,
+    timed_out: bool,
+}
+
+impl LogFileParser {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            names: Vec::new(),
+            pages: 0,
+            error_count: 0,
+            deadline: Deadline::none(),
+            timed_out: false,
+        }
+    }
+
+    /// Stop scanning pages once `deadline` passes, keeping the names carved so far
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn parse(&mut self) -> ParseResult<()> {
+        if detect_signature(&self.data) == NO_MATCH {
+            return Err(ParseError {
+                code: ErrorCode::BadSignature,
+                message: "Missing $LogFile restart page signature (RSTR or CHKD)".to_string(),
+                offset: Some(0),
+            });
+        }
+
+        let page_size = self.log_page_size();
+        let mut seen: HashMap<(u64, String, u64), usize> = HashMap::new();
+        let mut offset = 0;
+
+        while offset + page_size <= self.data.len() {
+            if self.deadline.expired() {
+                log::warn!("Timed out at offset 0x{:x}; keeping {} $LogFile names carved so far", offset, self.names.len());
+                self.timed_out = true;
+                break;
+            }
+
+            if &self.data[offset..offset + 4] == b"RCRD" {
+                self.pages += 1;
+                let mut page = self.data[offset..offset + page_size].to_vec();
+                if fixup::apply(&mut page).is_err() {
+                    // A torn page still holds intact names away from the sector ends
+                    log::debug!("{}: RCRD page at offset 0x{:x} failed its update sequence check", ErrorCode::Fixup, offset);
+                    self.error_count += 1;
+                }
+                self.carve_page(&page, offset as u64, &mut seen);
+            }
+            offset += page_size;
+        }
+
+        log::info!("Carved {} file names from {} $LogFile pages", self.names.len(), self.pages);
+        Ok(())
+    }
+
+    fn log_page_size(&self) -> usize {
+        let size = self
+            .data
+            .get(RESTART_LOG_PAGE_SIZE_OFFSET..RESTART_LOG_PAGE_SIZE_OFFSET + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+            .unwrap_or(0);
+        if size.is_power_of_two() && (SECTOR_SIZE..=1 << 16).contains(&size) {
+            size
+        } else {
+            DEFAULT_LOG_PAGE_SIZE
+        }
+    }
+
+    fn carve_page(&mut self, page: &[u8], page_offset: u64, seen: &mut HashMap<(u64, String, u64), usize>) {
+        let usa_offset = u16::from_le_bytes([page[4], page[5]]) as usize;
+        let usa_count = u16::from_le_bytes([page[6], page[7]]) as usize;
+        let start = (usa_offset + usa_count * 2).max(RCRD_HEADER_SIZE).next_multiple_of(8);
+
+        let mut position = start;
+        while position + FILE_NAME_HEADER_SIZE <= page.len() {
+            if let Some(name) = carve_file_name(&page[position..], page_offset + position as u64) {
+                let key = (
+                    (name.parent_entry_number as u64) << 16 | name.parent_sequence_number as u64,
+                    name.file_name.clone(),
+                    name.created.raw(),
+                );
+                match seen.get(&key) {
+                    Some(&index) => self.names[index].occurrences += 1,
+                    None => {
+                        seen.insert(key, self.names.len());
+                        self.names.push(name);
+                    }
+                }
+            }
+            position += 8;
+        }
+    }
+
+    /// Fill in parent paths from a $MFT and whether each name is still in use there
+    pub fn resolve_with_mft(&mut self, mft: &MftParser) {
+        let live: HashSet<(u32, String)> = mft
+            .get_records()
+            .iter()
+            .filter(|record| record.in_use && !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty())
+            .map(|record| (record.parent_entry_number, record.file_name.to_lowercase()))
+            .collect();
+
+        for name in &mut self.names {
+            if let Some(parent) = mft.get_record(name.parent_entry_number)
+                && parent.sequence_number == name.parent_sequence_number
+            {
+                name.parent_path = parent.full_path.clone();
+            }
+            // The $MFT output keeps one name per file, never the 8.3 alias
+            name.in_mft = (name.name_namespace != NameNamespace::Dos)
+                .then(|| live.contains(&(name.parent_entry_number, name.file_name.to_lowercase())));
+        }
+    }
+
+    pub fn get_names(&self) -> &[LogFileName] {
+        &self.names
+    }
+
+    /// RCRD pages that failed their update sequence check
+    pub fn get_error_count(&self) -> usize {
+        self.error_count
+    }
+
+    pub fn get_page_count(&self) -> usize {
+        self.pages
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+/// $LogFile opens with a restart page ("RSTR", or "CHKD" after chkdsk)
+pub fn detect(input: &InputInfo) -> u8 {
+    detect_signature(&input.header)
+}
+
+fn detect_signature(header: &[u8]) -> u8 {
+    if header.starts_with(b"RSTR") || header.starts_with(b"CHKD") {
+        SIGNATURE_MATCH
+    } else {
+        NO_MATCH
+    }
+}
+
+/// A $FILE_NAME at the start of `data`, if the bytes there look like one
+fn carve_file_name(data: &[u8], source_offset: u64) -> Option<LogFileName> {
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+    let parent_reference = u64_at(0);
+    let parent_entry = parent_reference & 0xFFFF_FFFF_FFFF;
+    let parent_sequence = (parent_reference >> 48) as u16;
+    if parent_entry > u32::MAX as u64 || parent_sequence == 0 {
+        return None;
+    }
+
+    let times = [u64_at(0x08), u64_at(0x10), u64_at(0x18), u64_at(0x20)].map(FileTime);
+    if !times.iter().all(|time| time.raw() >= MIN_PLAUSIBLE_FILETIME && time.validity() == Validity::Valid) {
+        return None;
+    }
+
+    let allocated_size = u64_at(0x28);
+    let file_size = u64_at(0x30);
+    if allocated_size >= MAX_FILE_SIZE || file_size >= MAX_FILE_SIZE {
+        return None;
+    }
+
+    let flags = u32::from_le_bytes(data[0x38..0x3C].try_into().unwrap());
+    if flags & !KNOWN_NAME_FLAGS != 0 {
+        return None;
+    }
+
+    let name_length = data[0x40] as usize;
+    let namespace = data[0x41];
+    let name_end = FILE_NAME_HEADER_SIZE + name_length * 2;
+    if name_length == 0 || namespace > 3 || name_end > data.len() {
+        return None;
+    }
+
+    let file_name = decode_utf16le(&data[FILE_NAME_HEADER_SIZE..name_end]).ok()?;
+    if !file_name.chars().all(plausible_name_char) {
+        return None;
+    }
+
+    let is_directory = flags & FILE_NAME_DIRECTORY != 0;
+    let extension = if is_directory { String::new() } else { extract_extension(&file_name).extension };
+    Some(LogFileName {
+        source_offset,
+        parent_entry_number: parent_entry as u32,
+        parent_sequence_number: parent_sequence,
+        parent_path: String::new(),
+        file_name,
+        name_namespace: NameNamespace::from(namespace),
+        extension,
+        is_directory,
+        file_size,
+        created: times[0],
+        modified: times[1],
+        record_changed: times[2],
+        accessed: times[3],
+        attributes: flags,
+        occurrences: 1,
+        in_mft: None,
+    })
+}
+
+/// Rejects control characters, the characters Win32 reserves in names and
+/// noncharacters, which random bytes decode to far more often than real names do
+fn plausible_name_char(c: char) -> bool {
+    !c.is_control() && !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '\u{FFFE}' | '\u{FFFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 4096;
+    /// 2023-06-15 13:45:12 UTC
+    const TIME: u64 = 133_313_103_123_456_789;
+    /// Where carving starts on a page whose update sequence array covers 8 sectors
+    const FIRST_SLOT: usize = 0x40;
+
+    /// A $FILE_NAME of `name` under entry 5 (sequence 5), claiming `name_length` characters
+    fn file_name(name: &str, name_length: u8) -> Vec<u8> {
+        let mut data = vec![0u8; FILE_NAME_HEADER_SIZE];
+        data[0x00..0x08].copy_from_slice(&(5u64 | 5 << 48).to_le_bytes());
+        for offset in [0x08, 0x10, 0x18, 0x20] {
+            data[offset..offset + 8].copy_from_slice(&TIME.to_le_bytes());
+        }
+        data[0x28..0x30].copy_from_slice(&4096u64.to_le_bytes());
+        data[0x30..0x38].copy_from_slice(&1234u64.to_le_bytes());
+        data[0x38..0x3C].copy_from_slice(&0x20u32.to_le_bytes());
+        data[0x40] = name_length;
+        data[0x41] = 1;
+        data.extend(name.encode_utf16().flat_map(u16::to_le_bytes));
+        data
+    }
+
+    /// A restart page followed by one RCRD page holding `structures` at their offsets
+    fn log_file(structures: &[(usize, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0u8; 2 * PAGE_SIZE];
+        data[0..4].copy_from_slice(b"RSTR");
+        data[RESTART_LOG_PAGE_SIZE_OFFSET..RESTART_LOG_PAGE_SIZE_OFFSET + 4].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+
+        let page = &mut data[PAGE_SIZE..];
+        page[0..4].copy_from_slice(b"RCRD");
+        page[4..6].copy_from_slice(&(RCRD_HEADER_SIZE as u16).to_le_bytes());
+        page[6..8].copy_from_slice(&(1 + (PAGE_SIZE / SECTOR_SIZE) as u16).to_le_bytes());
+        page[RCRD_HEADER_SIZE..RCRD_HEADER_SIZE + 2].copy_from_slice(&1u16.to_le_bytes());
+        for (offset, structure) in structures {
+            page[*offset..*offset + structure.len()].copy_from_slice(structure);
+        }
+        for sector_end in (SECTOR_SIZE..=PAGE_SIZE).step_by(SECTOR_SIZE) {
+            page[sector_end - 2..sector_end].copy_from_slice(&1u16.to_le_bytes());
+        }
+        data
+    }
+
+    fn carve(data: Vec<u8>) -> LogFileParser {
+        let mut parser = LogFileParser::new(data);
+        parser.parse().unwrap();
+        parser
+    }
+
+    #[test]
+    fn carves_a_file_name_from_an_rcrd_page() {
+        let parser = carve(log_file(&[(FIRST_SLOT, file_name("report.txt", 10))]));
+        assert_eq!(parser.get_page_count(), 1);
+        assert_eq!(parser.get_error_count(), 0);
+
+        let [name] = parser.get_names() else {
+            panic!("expected one name, got {:?}", parser.get_names());
+        };
+        assert_eq!(name.file_name, "report.txt");
+        assert_eq!(name.extension, "txt");
+        assert_eq!(name.name_namespace, NameNamespace::Win32);
+        assert_eq!((name.parent_entry_number, name.parent_sequence_number), (5, 5));
+        assert_eq!(name.file_size, 1234);
+        assert_eq!(name.created.raw(), TIME);
+        assert_eq!(name.source_offset, (PAGE_SIZE + FIRST_SLOT) as u64);
+        assert!(!name.is_directory);
+        assert_eq!(name.occurrences, 1);
+    }
+
+    #[test]
+    fn counts_a_repeated_name_once() {
+        let parser = carve(log_file(&[(FIRST_SLOT, file_name("report.txt", 10)), (0x100, file_name("report.txt", 10))]));
+        assert_eq!(parser.get_names().len(), 1);
+        assert_eq!(parser.get_names()[0].occurrences, 2);
+    }
+
+    #[test]
+    fn carves_names_from_a_torn_page() {
+        let mut data = log_file(&[(FIRST_SLOT, file_name("report.txt", 10))]);
+        data[2 * PAGE_SIZE - 2] = 9;
+        let parser = carve(data);
+        assert_eq!(parser.get_error_count(), 1);
+        assert_eq!(parser.get_names().len(), 1);
+    }
+
+    #[test]
+    fn corrupted_name_lengths_carve_nothing() {
+        // No name, a name running into the zeros after it, and one running off the page
+        for (offset, name_length) in [(FIRST_SLOT, 0), (FIRST_SLOT, 40), (PAGE_SIZE - 0x48, 0xFF)] {
+            let parser = carve(log_file(&[(offset, file_name("", name_length))]));
+            assert!(parser.get_names().is_empty(), "length {} at 0x{:x}: {:?}", name_length, offset, parser.get_names());
+        }
+        let parser = carve(log_file(&[(FIRST_SLOT, file_name("report.txt", 40))]));
+        assert!(parser.get_names().is_empty(), "{:?}", parser.get_names());
+    }
+
+    #[test]
+    fn name_length_past_the_data_is_rejected() {
+        let mut data = file_name("report.txt", 10);
+        assert!(carve_file_name(&data, 0).is_some());
+        data[0x40] = 11;
+        assert!(carve_file_name(&data, 0).is_none());
+        assert!(carve_file_name(&data[..FILE_NAME_HEADER_SIZE], 0).is_none());
+    }
+
+    #[test]
+    fn implausible_fields_are_rejected() {
+        let valid = file_name("report.txt", 10);
+        let corrupt = |offset: usize, bytes: &[u8]| {
+            let mut data = valid.clone();
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            carve_file_name(&data, 0)
+        };
+        assert!(corrupt(0x06, &[0, 0]).is_none(), "parent sequence 0");
+        assert!(corrupt(0x08, &0u64.to_le_bytes()).is_none(), "zero timestamp");
+        assert!(corrupt(0x30, &u64::MAX.to_le_bytes()).is_none(), "file size");
+        assert!(corrupt(0x3B, &[0x80]).is_none(), "unknown flag");
+        assert!(corrupt(0x41, &[4]).is_none(), "namespace");
+        assert!(corrupt(0x42, &[b'a', 0x00, 0x3A]).is_none(), "reserved character");
+        assert!(corrupt(0x42, &[0x00, 0xD8]).is_none(), "unpaired surrogate");
+    }
+}
@@ -1,60 +1,398 @@
-use super::types::{MftRecord, ParseError, ParseResult};
+use super::anomaly::AnomalyModel;
+use super::attribute::AttributeHeader;
+use super::bitmap::ClusterBitmap;
+use super::category::{self, FileCategory};
+use super::compression::Wrapper;
+use super::deadline::Deadline;
+use super::efs;
+use super::extended_attributes::{self, ExtendedAttribute};
+use super::file_attributes::format_file_attributes;
+use super::filetime::{self, FileTime};
+use super::fixup;
+use super::names::{decode_utf16le, extract_extension};
+use super::reparse;
+use super::zone_identifier::{self, ZONE_IDENTIFIER_STREAM};
+use super::runlist::decode_runlist;
+use super::types::{AdsNameCount, CorruptRecord, DataRun, ErrorCode, FileListEntry, MftRecord, MftBitmapMismatch, MftGap, MftLayout, MftStatistics, NameNamespace, ParseError, ParseResult, PathConfidence, RecordNumberShift, Recoverability, StreamRunList};
+use crate::artifact::{InputInfo, LIKELY_MATCH, NO_MATCH, SIGNATURE_MATCH};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::{DateTime, Utc};
-use std::collections::HashMap;
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
 
-const MFT_RECORD_SIZE: usize = 1024;
+/// FILE record size on 512e volumes; 4Kn volumes use 4096
+pub const DEFAULT_RECORD_SIZE: usize = 1024;
+/// Offset of the allocated size in the FILE record header, which equals the record size
+const ALLOCATED_SIZE_OFFSET: usize = 0x1C;
+/// FILE records examined when detecting the record size
+const RECORD_SIZE_PROBES: usize = 16;
+/// Size of an NTFS 3.1 FILE record header, which ends with the record number
+const MFT_HEADER_SIZE_V31: u16 = 0x30;
 const MFT_SIGNATURE: u32 = 0x454c4946; // "FILE"
+/// Signature NTFS writes over a record that failed its multi-sector transfer check
+const BAD_RECORD_SIGNATURE: &[u8] = b"BAAD";
+/// Header fields holding the sequence number, the offset of the first
+/// attribute, the in-use and directory flags and the base record reference
+const SEQUENCE_NUMBER_OFFSET: usize = 0x10;
+const FIRST_ATTRIBUTE_OFFSET: usize = 0x14;
+const FLAGS_OFFSET: usize = 0x16;
+const BASE_RECORD_OFFSET: usize = 0x20;
+/// $ATTRIBUTE_LIST, which names the FILE records holding each of a file's attributes
+const ATTRIBUTE_LIST: u32 = 0x20;
+/// $BITMAP, which in $MFT record 0 has a bit for each entry in use
+const BITMAP_ATTRIBUTE: u32 = 0xB0;
+/// $LOGGED_UTILITY_STREAM, named $TXF_DATA for Transactional NTFS and $EFS for encrypted files
+const LOGGED_UTILITY_STREAM: u32 = 0x100;
+/// $DATA attribute header flag of an NTFS-compressed (LZNT1) stream
+const ATTRIBUTE_FLAG_COMPRESSED: u16 = 0x0001;
+/// $DATA attribute header flag of an EFS encrypted stream
+const ATTRIBUTE_FLAG_ENCRYPTED: u16 = 0x4000;
+/// $DATA attribute header flag of a sparse stream
+const ATTRIBUTE_FLAG_SPARSE: u16 = 0x8000;
+const FILE_ATTRIBUTE_SPARSE: u32 = 0x0200;
+/// $STANDARD_INFORMATION flag of a file Windows shows as compressed
+const FILE_ATTRIBUTE_COMPRESSED: u32 = 0x0800;
+const FILE_ATTRIBUTE_ENCRYPTED: u32 = 0x4000;
+/// $ATTRIBUTE_LIST entry up to its name: type, length, name fields, starting VCN,
+/// file reference and attribute id
+const ATTRIBUTE_LIST_ENTRY_SIZE: usize = 0x1A;
+const ORPHAN_DIRECTORY: &str = "$OrphanFiles";
+const ROOT_ENTRY: u32 = 5;
+/// $Extend holds $UsnJrnl, $ObjId, $Quota, $Reparse and $RmMetadata
+const EXTEND_ENTRY: u32 = 11;
+/// Entries below this are reserved for metadata files; user files start here
+const FIRST_USER_ENTRY: u32 = 24;
+/// Deepest parent chain followed when looking for $Extend
+const MAX_METADATA_DEPTH: usize = 64;
+
+/// Alternate data stream names Windows, browsers and common software write;
+/// any other name is flagged as unusual in the statistics
+const KNOWN_ADS_NAMES: &[&str] = &[
+    "Zone.Identifier",
+    "SmartScreen",
+    "OECustomProperty",
+    "encryptable",
+    "favicon",
+    "ms-properties",
+    "Win32App_1",
+    "AFP_AfpInfo",
+    "AFP_Resource",
+    "com.dropbox.attrs",
+    "com.dropbox.attributes",
+    "{4c8cc155-6c1e-11d1-8e41-00c04fb9386d}",
+];
+
+/// Data runs of one $DATA attribute; `name` is empty for the unnamed stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamRuns {
+    pub name: String,
+    pub runs: Vec<DataRun>,
+    /// Size of the stream's content, from its first fragment
+    pub real_size: u64,
+    /// Compression unit as a power of two of clusters; 0 when the stream isn't NTFS-compressed
+    pub compression_unit: u16,
+}
+
+/// Size and state of one alternate data stream, for its own output row
+#[derive(Debug, Clone)]
+struct AlternateStream {
+    name: String,
+    size: u64,
+    allocated_size: u64,
+    initialized_size: Option<u64>,
+    recoverability: Recoverability,
+}
+
+/// What a FILE record holds besides the fields of its MftRecord
+#[derive(Default)]
+struct RecordParts {
+    runs: Vec<StreamRuns>,
+    alternate_streams: Vec<AlternateStream>,
+    extended_attributes: Vec<ExtendedAttribute>,
+    /// The record's $ATTRIBUTE_LIST, if it has one
+    attribute_list: Option<AttributeList>,
+    /// Every $FILE_NAME attribute, in attribute order
+    file_names: Vec<FileName>,
+    /// Win32 and POSIX names besides the one the record is named by
+    hard_links: Vec<FileName>,
+}
+
+/// One $FILE_NAME attribute: a name of the file in one directory
+#[derive(Debug, Clone)]
+struct FileName {
+    parent_entry_number: u32,
+    parent_sequence_number: u16,
+    created: FileTime,
+    modified: FileTime,
+    record_changed: FileTime,
+    accessed: FileTime,
+    allocated_size: u64,
+    real_size: u64,
+    /// FILE_ATTRIBUTE_* bits as of the name's last update
+    flags: u32,
+    name_type: u8,
+    name: String,
+    /// 8.3 alias of this name, filled in with --sn
+    short_name: String,
+}
+
+impl FileName {
+    /// Which name a record is shown under: a long (Win32) name, then one valid
+    /// in both namespaces, then a POSIX one, then an 8.3 alias
+    fn preference(&self) -> u8 {
+        match NameNamespace::from(self.name_type) {
+            NameNamespace::Win32 => 4,
+            NameNamespace::Win32AndDos => 3,
+            NameNamespace::Posix => 2,
+            NameNamespace::Dos => 1,
+            NameNamespace::Unknown => 0,
+        }
+    }
+}
+
+enum AttributeList {
+    /// Records holding the file's attributes, as (entry, sequence), the base record included
+    Resident(Vec<(u32, u16)>),
+    /// The list lives in clusters outside the $MFT, so its entries can't be read
+    NonResident,
+}
 
 pub struct MftParser {
     data: Vec<u8>,
     records: Vec<MftRecord>,
     entry_map: HashMap<u32, usize>, // Maps entry number to record index
+    error_count: usize,
+    /// Clusters of each record's $DATA streams, for recoverability and cluster lookups
+    data_runs: HashMap<u32, Vec<StreamRuns>>,
+    bitmap: Option<ClusterBitmap>,
+    record_number_shifts: Vec<RecordNumberShift>,
+    /// FILE records whose update sequence array check failed
+    fixup_errors: usize,
+    /// BAAD records and FILE records that could not be parsed
+    corrupt_records: Vec<CorruptRecord>,
+    /// Offset of each entry's FILE record, for merging extension records
+    record_offsets: HashMap<u32, usize>,
+    /// Size of every FILE record in `data`
+    record_size: usize,
+    attribute_lists: HashMap<u32, AttributeList>,
+    alternate_streams: HashMap<u32, Vec<AlternateStream>>,
+    /// Names and values of each record's extended attributes, for --dr
+    extended_attributes: HashMap<u32, Vec<ExtendedAttribute>>,
+    /// Each record's other hard links
+    hard_links: HashMap<u32, Vec<FileName>>,
+    hard_link_rows: bool,
+    short_names: bool,
+    deadline: Deadline,
+    timed_out: bool,
+    anomaly_model: AnomalyModel,
 }
 
 impl MftParser {
     pub fn new(data: Vec<u8>) -> Self {
+        let record_size = detect_record_size(&data);
         Self {
             data,
             records: Vec::new(),
             entry_map: HashMap::new(),
+            error_count: 0,
+            data_runs: HashMap::new(),
+            bitmap: None,
+            record_number_shifts: Vec::new(),
+            fixup_errors: 0,
+            corrupt_records: Vec::new(),
+            record_offsets: HashMap::new(),
+            record_size,
+            attribute_lists: HashMap::new(),
+            alternate_streams: HashMap::new(),
+            extended_attributes: HashMap::new(),
+            hard_links: HashMap::new(),
+            hard_link_rows: false,
+            short_names: false,
+            deadline: Deadline::none(),
+            timed_out: false,
+            anomaly_model: AnomalyModel::default(),
+        }
+    }
+
+    /// Check deleted files' clusters against the volume's $Bitmap instead of only
+    /// against the clusters claimed by records still in use
+    pub fn with_bitmap(mut self, bitmap: Option<ClusterBitmap>) -> Self {
+        self.bitmap = bitmap;
+        self
+    }
+
+    /// Read FILE records of `record_size` bytes, e.g. from the volume's boot
+    /// sector, instead of the size detected from the records themselves
+    pub fn with_record_size(mut self, record_size: Option<usize>) -> Self {
+        if let Some(record_size) = record_size {
+            self.record_size = record_size;
         }
+        self
+    }
+
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Follow a file with several hard links with a row for each of its other
+    /// names, in the directory that name is in
+    pub fn with_hard_link_rows(mut self, hard_link_rows: bool) -> Self {
+        self.hard_link_rows = hard_link_rows;
+        self
+    }
+
+    /// Fill `short_name` with the 8.3 alias Windows made for each long name
+    pub fn with_short_names(mut self, short_names: bool) -> Self {
+        self.short_names = short_names;
+        self
+    }
+
+    /// Stop reading records once `deadline` passes, keeping those parsed so far
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Score each record's timestamps with `anomaly_model` instead of the default weights
+    pub fn with_anomaly_model(mut self, anomaly_model: AnomalyModel) -> Self {
+        self.anomaly_model = anomaly_model;
+        self
+    }
+
+    pub fn anomaly_model(&self) -> &AnomalyModel {
+        &self.anomaly_model
     }
 
     pub fn parse(&mut self) -> ParseResult<()> {
+        let record_size = self.record_size;
+        if self.data.len() < record_size {
+            return Err(ParseError {
+                code: ErrorCode::InputTooSmall,
+                message: format!(
+                    "$MFT is only {} bytes, less than one {}-byte FILE record: the extract is truncated; re-extract it",
+                    self.data.len(),
+                    record_size
+                ),
+                offset: None,
+            });
+        }
+        if record_size != DEFAULT_RECORD_SIZE {
+            log::info!("Reading {} byte FILE records", record_size);
+        }
         let mut offset = 0;
 
         // First pass: Parse all records and build entry map
-        while offset + MFT_RECORD_SIZE <= self.data.len() {
-            match self.parse_record(&self.data[offset..offset + MFT_RECORD_SIZE], offset) {
-                Ok(Some(record)) => {
+        while offset + record_size <= self.data.len() {
+            if self.deadline.expired() {
+                log::warn!("Timed out at offset 0x{:x}; keeping {} MFT records parsed so far", offset, self.records.len());
+                self.timed_out = true;
+                break;
+            }
+            let position = (offset / record_size) as u32;
+            if self.data[offset..].starts_with(BAD_RECORD_SIGNATURE) {
+                self.corrupt_records.push(CorruptRecord {
+                    position,
+                    offset: offset as u64,
+                    baad: true,
+                    code: ErrorCode::BaadRecord,
+                    reason: "BAAD signature: NTFS found the record torn".to_string(),
+                });
+                offset += record_size;
+                continue;
+            }
+
+            // Restore the sector-end bytes before any attribute is read
+            let raw = &mut self.data[offset..offset + record_size];
+            let fixup_ok = !raw.starts_with(b"FILE") || fixup::apply(raw).is_ok();
+            if !fixup_ok {
+                log::debug!("{}: FILE record at offset 0x{:x} failed its update sequence check", ErrorCode::Fixup, offset);
+                self.fixup_errors += 1;
+            }
+
+            match self.parse_record(&self.data[offset..offset + record_size], offset, fixup_ok, &[]) {
+                Ok(Some((record, parts))) => {
                     let entry_number = record.entry_number;
+                    self.track_record_number(entry_number, position);
                     let record_index = self.records.len();
                     self.entry_map.insert(entry_number, record_index);
+                    self.record_offsets.insert(entry_number, offset);
                     self.records.push(record);
+                    if !parts.runs.is_empty() {
+                        self.data_runs.insert(entry_number, parts.runs);
+                    }
+                    if let Some(attribute_list) = parts.attribute_list {
+                        self.attribute_lists.insert(entry_number, attribute_list);
+                    }
+                    if !parts.alternate_streams.is_empty() {
+                        self.alternate_streams.insert(entry_number, parts.alternate_streams);
+                    }
+                    if !parts.extended_attributes.is_empty() {
+                        self.extended_attributes.insert(entry_number, parts.extended_attributes);
+                    }
+                    if !parts.hard_links.is_empty() {
+                        self.hard_links.insert(entry_number, parts.hard_links);
+                    }
                 },
                 Ok(None) => {}, // Skip invalid/unused records
                 Err(e) => {
                     log::warn!("Failed to parse MFT record at offset 0x{:x}: {}", offset, e);
+                    self.error_count += 1;
+                    self.corrupt_records.push(CorruptRecord {
+                        position,
+                        offset: offset as u64,
+                        baad: false,
+                        code: e.code,
+                        reason: e.message,
+                    });
                 }
             }
-            offset += MFT_RECORD_SIZE;
+            offset += record_size;
         }
 
-        // Second pass: Resolve parent paths
+        // Second pass: bring attributes held in extension records into their base records
+        let merged = self.merge_extension_records();
+        self.group_extension_rows(&merged);
+
+        // Third pass: Resolve parent paths
         self.resolve_parent_paths();
 
+        // Fourth pass: give each alternate data stream, and with hard link rows
+        // each other name of a file, a row of its own
+        if self.hard_link_rows {
+            self.add_hard_link_rows();
+        }
+        self.add_stream_rows();
+
+        // Fifth pass: check whether deleted files' clusters were reused
+        self.assess_recoverability();
+
+        // Sixth pass: score the timestamps of every row for signs of timestomping
+        for record in &mut self.records {
+            (record.anomaly_score, record.anomaly_indicators) = self.anomaly_model.score(record);
+        }
+
         log::info!("Parsed {} MFT records", self.records.len());
         Ok(())
     }
 
-    fn parse_record(&self, data: &[u8], offset: usize) -> ParseResult<Option<MftRecord>> {
+    /// Parse one FILE record, returning it with the data runs of its $DATA streams.
+    ///
+    /// `fixup_ok` is false when the record's update sequence check failed; it is
+    /// still parsed, since a torn write usually leaves most attributes intact.
+    /// The attributes of `extensions`, the base record's extension records, are
+    /// parsed along with its own.
+    fn parse_record(
+        &self,
+        data: &[u8],
+        offset: usize,
+        fixup_ok: bool,
+        extensions: &[&[u8]],
+    ) -> ParseResult<Option<(MftRecord, RecordParts)>> {
         let mut cursor = Cursor::new(data);
 
         // Read MFT record header
         let signature = cursor.read_u32::<LittleEndian>()
             .map_err(|_| ParseError {
+                code: ErrorCode::Truncated,
                 message: "Failed to read MFT signature".to_string(),
                 offset: Some(offset as u64),
             })?;
@@ -63,22 +401,41 @@ impl MftParser {
             return Ok(None); // Not a valid MFT record
         }
 
-        let _fixup_offset = cursor.read_u16::<LittleEndian>().unwrap();
+        let fixup_offset = cursor.read_u16::<LittleEndian>().unwrap();
         let _fixup_count = cursor.read_u16::<LittleEndian>().unwrap();
-        let _lsn = cursor.read_u64::<LittleEndian>().unwrap();
+        let lsn = cursor.read_u64::<LittleEndian>().unwrap();
         let sequence_number = cursor.read_u16::<LittleEndian>().unwrap();
-        let _link_count = cursor.read_u16::<LittleEndian>().unwrap();
+        let link_count = cursor.read_u16::<LittleEndian>().unwrap();
         let first_attribute_offset = cursor.read_u16::<LittleEndian>().unwrap();
         let flags = cursor.read_u16::<LittleEndian>().unwrap();
         let _used_size = cursor.read_u32::<LittleEndian>().unwrap();
         let _allocated_size = cursor.read_u32::<LittleEndian>().unwrap();
-        let _base_record = cursor.read_u64::<LittleEndian>().unwrap();
+        let base_record = cursor.read_u64::<LittleEndian>().unwrap();
         let _next_attribute_id = cursor.read_u16::<LittleEndian>().unwrap();
+        let header_record_number_high = cursor.read_u16::<LittleEndian>().unwrap();
+        let header_record_number = cursor.read_u32::<LittleEndian>().unwrap();
 
         let in_use = (flags & 0x01) != 0;
         let is_directory = (flags & 0x02) != 0;
 
-        let entry_number = (offset / MFT_RECORD_SIZE) as u32;
+        // NTFS 3.1 headers store the record's own number at 0x2C, ahead of the
+        // fixup array; it stays correct for extracts that don't start at entry 0.
+        // Older headers put the fixup array there, so fall back to the position.
+        // Entry numbers are 32-bit, so a header whose high 16 bits at 0x2A are
+        // set also falls back rather than being truncated.
+        let entry_number_from_offset = fixup_offset < MFT_HEADER_SIZE_V31 || header_record_number_high != 0;
+        let entry_number = if entry_number_from_offset {
+            (offset / self.record_size) as u32
+        } else {
+            header_record_number
+        };
+
+        // Extension records point back at the base record that owns them
+        let base_record_reference = if base_record & 0xFFFFFFFFFFFF == 0 {
+            String::new()
+        } else {
+            format!("{}-{}", base_record & 0xFFFFFFFFFFFF, base_record >> 48)
+        };
 
         // Create a basic MFT record
         let mut record = MftRecord {
@@ -87,13 +444,23 @@ impl MftParser {
             parent_entry_number: 0,
             parent_sequence_number: None,
             in_use,
+            recoverability: Recoverability::NoData,
             parent_path: String::new(),
             file_name: String::new(),
+            full_path: String::new(),
+            path_confidence: PathConfidence::Unknown,
             extension: String::new(),
+            suspicious_name: false,
+            category: FileCategory::Other,
             is_directory,
             has_ads: false,
             is_ads: false,
+            is_hard_link: false,
+            ads_names: String::new(),
+            index_names: String::new(),
             file_size: 0,
+            allocated_size: 0,
+            initialized_size: None,
             created_0x10: None,
             created_0x30: None,
             last_modified_0x10: None,
@@ -102,224 +469,1207 @@ impl MftParser {
             last_record_change_0x30: None,
             last_access_0x10: None,
             last_access_0x30: None,
+            timestamp_anomalies: String::new(),
+            anomaly_score: 0,
+            anomaly_indicators: String::new(),
             update_sequence_number: 0,
-            logfile_sequence_number: 0,
+            logfile_sequence_number: lsn as i64,
             security_id: 0,
             zone_id_contents: String::new(),
+            zone_id: None,
+            referrer_url: String::new(),
+            host_url: String::new(),
             si_flags: 0,
+            si_flag_names: String::new(),
+            fn_flags: 0,
+            fn_flag_names: String::new(),
+            is_compressed: false,
+            is_sparse: false,
+            is_encrypted: false,
             object_id_file_droid: String::new(),
             reparse_target: String::new(),
-            reference_count: 0,
+            reparse_print_name: String::new(),
+            reparse_tag: 0,
+            reparse_type: String::new(),
+            extended_attributes: String::new(),
+            ea_size: 0,
+            reference_count: link_count as i32,
+            base_record_reference,
+            extension_records: String::new(),
             name_type: 0,
+            name_namespace: None,
+            short_name: String::new(),
             logged_util_stream: String::new(),
+            efs_users: String::new(),
+            efs_recovery_agents: String::new(),
+            fixup_error: !fixup_ok,
+            error_code: (!fixup_ok).then_some(ErrorCode::Fixup),
+            entry_number_from_offset,
         };
 
-        // Parse attributes
-        cursor.seek(SeekFrom::Start(first_attribute_offset as u64)).unwrap();
-        self.parse_attributes(&mut cursor, &mut record)?;
-
-        Ok(Some(record))
-    }
+        // Parse attributes; those of extension records slot in by type, the
+        // order $ATTRIBUTE_LIST keeps them in, so names come before data sizes
+        let mut attributes = split_attributes(data, first_attribute_offset as usize, entry_number)?;
+        for extension in extensions {
+            let first_attribute_offset = u16::from_le_bytes([extension[FIRST_ATTRIBUTE_OFFSET], extension[FIRST_ATTRIBUTE_OFFSET + 1]]);
+            attributes.extend(split_attributes(extension, first_attribute_offset as usize, entry_number)?);
+        }
+        attributes.sort_by_key(|(header, _)| header.attr_type);
+        let mut parts = RecordParts::default();
+        self.parse_attributes(&attributes, &mut record, &mut parts)?;
+        record.timestamp_anomalies = timestamp_anomalies(&record);
 
-    fn parse_attributes(&self, cursor: &mut Cursor<&[u8]>, record: &mut MftRecord) -> ParseResult<()> {
-        loop {
-            let pos = cursor.position();
-            if pos + 4 > cursor.get_ref().len() as u64 {
-                break;
-            }
+        // An 8.3 alias names the same link as its long name, in the same directory
+        let file_names = std::mem::take(&mut parts.file_names);
+        let alias = |parent_entry_number: u32| -> String {
+            file_names
+                .iter()
+                .filter(|_| self.short_names)
+                .find(|named| NameNamespace::from(named.name_type) == NameNamespace::Dos && named.parent_entry_number == parent_entry_number)
+                .map(|named| named.name.clone())
+                .unwrap_or_default()
+        };
+        if record.name_namespace != Some(NameNamespace::Dos) {
+            record.short_name = alias(record.parent_entry_number);
+        }
+        parts.hard_links = file_names
+            .iter()
+            .filter(|link| NameNamespace::from(link.name_type) != NameNamespace::Dos)
+            .filter(|link| link.parent_entry_number != record.parent_entry_number || link.name != record.file_name)
+            .map(|link| FileName { short_name: alias(link.parent_entry_number), ..link.clone() })
+            .collect();
 
-            let attr_type = cursor.read_u32::<LittleEndian>().unwrap();
+        Ok(Some((record, parts)))
+    }
 
-            if attr_type == 0xFFFFFFFF {
-                break; // End of attributes
-            }
+    fn parse_attributes(&self, attributes: &[(AttributeHeader, &[u8])], record: &mut MftRecord, parts: &mut RecordParts) -> ParseResult<()> {
+        for (header, attribute) in attributes {
+            let name = header.name(attribute);
 
-            let attr_length = cursor.read_u32::<LittleEndian>().unwrap();
-            let _non_resident = cursor.read_u8().unwrap();
-            let _name_length = cursor.read_u8().unwrap();
-            let _name_offset = cursor.read_u16::<LittleEndian>().unwrap();
-            let _flags = cursor.read_u16::<LittleEndian>().unwrap();
-            let _attribute_id = cursor.read_u16::<LittleEndian>().unwrap();
-
-            match attr_type {
-                0x10 => self.parse_standard_info(cursor, record)?,
-                0x30 => self.parse_file_name(cursor, record)?,
-                0x80 => self.parse_data_attribute(cursor, record)?,
+            match (header.attr_type, header.resident_content(attribute)) {
+                // $STANDARD_INFORMATION and $FILE_NAME are always resident
+                (0x10, Some(content)) => self.parse_standard_info(content, record)?,
+                (0x30, Some(content)) => {
+                    let file_name = parse_file_name(content)?;
+                    // Name the record by the first of its most preferred names,
+                    // whichever record or order they are stored in
+                    if parts.file_names.iter().all(|named| file_name.preference() > named.preference()) {
+                        apply_file_name(&file_name, record);
+                    }
+                    parts.file_names.push(file_name);
+                }
+                (0x10 | 0x30, None) => {
+                    log::debug!("Entry {}: ignoring non-resident attribute 0x{:x}", record.entry_number, header.attr_type);
+                }
+                (ATTRIBUTE_LIST, Some(content)) => {
+                    parts.attribute_list = Some(AttributeList::Resident(parse_attribute_list(content)));
+                }
+                (ATTRIBUTE_LIST, None) => parts.attribute_list = Some(AttributeList::NonResident),
+                (0x80, content) if name.is_empty() => {
+                    Self::parse_data(header, content, record);
+                    Self::collect_runs(header, attribute, name, record, &mut parts.runs);
+                }
+                // Named $DATA attributes are alternate data streams
+                (0x80, content) => {
+                    if !record.ads_names.split('|').any(|existing| existing == name) {
+                        append_name(&mut record.ads_names, &name);
+                    }
+                    record.has_ads = true;
+                    if let Some(content) = content
+                        && name.eq_ignore_ascii_case(ZONE_IDENTIFIER_STREAM)
+                    {
+                        let zone = zone_identifier::parse(content);
+                        record.zone_id_contents = zone.contents;
+                        record.zone_id = zone.zone_id;
+                        record.referrer_url = zone.referrer_url;
+                        record.host_url = zone.host_url;
+                    }
+                    Self::collect_stream(header, content, &name, &mut parts.alternate_streams);
+                    Self::collect_runs(header, attribute, name, record, &mut parts.runs);
+                }
+                // $INDEX_ROOT is present for every index, resident or not
+                (0x90, _) => append_name(&mut record.index_names, &name),
+                (0xC0, Some(content)) => match reparse::parse(content) {
+                    Ok(reparse_point) => {
+                        record.reparse_tag = reparse_point.tag;
+                        record.reparse_type = reparse::tag_name(reparse_point.tag).to_string();
+                        record.reparse_target = reparse_point.substitute_name;
+                        record.reparse_print_name = reparse_point.print_name;
+                    }
+                    Err(e) => log::debug!("Entry {}: unreadable $REPARSE_POINT: {}", record.entry_number, e),
+                },
+                (0xC0, None) => {
+                    log::debug!("Entry {}: ignoring non-resident $REPARSE_POINT", record.entry_number);
+                }
+                (0xD0, Some(content)) => match extended_attributes::parse_information(content) {
+                    Ok(information) => record.ea_size = information.unpacked_size,
+                    Err(e) => log::debug!("Entry {}: unreadable $EA_INFORMATION: {}", record.entry_number, e),
+                },
+                (0xE0, Some(content)) => match extended_attributes::parse(content) {
+                    Ok(attributes) => {
+                        for attribute in &attributes {
+                            append_name(&mut record.extended_attributes, &format!("{}:{}", attribute.name, attribute.value.len()));
+                        }
+                        parts.extended_attributes = attributes;
+                    }
+                    Err(e) => log::debug!("Entry {}: unreadable $EA: {}", record.entry_number, e),
+                },
+                (0xE0, None) => {
+                    log::debug!("Entry {}: ignoring non-resident $EA", record.entry_number);
+                }
+                (LOGGED_UTILITY_STREAM, content) => {
+                    // Only the first fragment of a non-resident stream carries its size
+                    let size = match (content, header.non_resident()) {
+                        (Some(content), _) => Some(content.len() as u64),
+                        (None, Some(non_resident)) if non_resident.starting_vcn == 0 => Some(non_resident.real_size),
+                        _ => None,
+                    };
+                    if let Some(size) = size {
+                        append_name(&mut record.logged_util_stream, &format!("{}:{}", name, size));
+                    }
+                    if name == efs::EFS_STREAM_NAME {
+                        record.is_encrypted = true;
+                        // A large $EFS is non-resident, and its keys then can't be read from the $MFT
+                        match content.map(efs::parse) {
+                            Some(Ok(metadata)) => {
+                                for key in &metadata.users {
+                                    append_name(&mut record.efs_users, &key.summary());
+                                }
+                                for key in &metadata.recovery_agents {
+                                    append_name(&mut record.efs_recovery_agents, &key.summary());
+                                }
+                            }
+                            Some(Err(e)) => log::debug!("Entry {}: unreadable $EFS: {}", record.entry_number, e),
+                            None => {}
+                        }
+                    }
+                }
                 _ => {
                     // Skip unknown attributes
                 }
             }
-
-            // Move to next attribute
-            cursor.seek(SeekFrom::Start(pos + attr_length as u64)).unwrap();
         }
 
         Ok(())
     }
 
-    fn parse_standard_info(&self, cursor: &mut Cursor<&[u8]>, record: &mut MftRecord) -> ParseResult<()> {
-        let _resident_size = cursor.read_u32::<LittleEndian>().unwrap();
-        let _resident_offset = cursor.read_u16::<LittleEndian>().unwrap();
-        cursor.seek(SeekFrom::Current(2)).unwrap(); // Reserved
+    fn parse_standard_info(&self, content: &[u8], record: &mut MftRecord) -> ParseResult<()> {
+        let mut cursor = Cursor::new(content);
 
-        let created = cursor.read_u64::<LittleEndian>().unwrap();
-        let modified = cursor.read_u64::<LittleEndian>().unwrap();
-        let record_changed = cursor.read_u64::<LittleEndian>().unwrap();
-        let accessed = cursor.read_u64::<LittleEndian>().unwrap();
+        let created = cursor.read_u64::<LittleEndian>()?;
+        let modified = cursor.read_u64::<LittleEndian>()?;
+        let record_changed = cursor.read_u64::<LittleEndian>()?;
+        let accessed = cursor.read_u64::<LittleEndian>()?;
 
-        // Convert Windows FILETIME to DateTime<Utc>
-        record.created_0x10 = Some(windows_filetime_to_datetime(created));
-        record.last_modified_0x10 = Some(windows_filetime_to_datetime(modified));
-        record.last_record_change_0x10 = Some(windows_filetime_to_datetime(record_changed));
-        record.last_access_0x10 = Some(windows_filetime_to_datetime(accessed));
+        // Raw FILETIMEs; calendar dates are only built when records are written
+        record.created_0x10 = Some(FileTime(created));
+        record.last_modified_0x10 = Some(FileTime(modified));
+        record.last_record_change_0x10 = Some(FileTime(record_changed));
+        record.last_access_0x10 = Some(FileTime(accessed));
 
-        record.si_flags = cursor.read_u32::<LittleEndian>().unwrap();
+        record.si_flags = cursor.read_u32::<LittleEndian>()?;
+        record.si_flag_names = format_file_attributes(record.si_flags);
+        if record.si_flags & FILE_ATTRIBUTE_COMPRESSED != 0 {
+            record.is_compressed = true;
+        }
+        if record.si_flags & FILE_ATTRIBUTE_SPARSE != 0 {
+            record.is_sparse = true;
+        }
+        if record.si_flags & FILE_ATTRIBUTE_ENCRYPTED != 0 {
+            record.is_encrypted = true;
+        }
+
+        let _max_versions = cursor.read_u32::<LittleEndian>()?;
+        let _version = cursor.read_u32::<LittleEndian>()?;
+        let _class_id = cursor.read_u32::<LittleEndian>()?;
+
+        // NTFS 3.x extends $STANDARD_INFORMATION to 72 bytes with owner/security ids, quota and USN
+        if content.len() >= 72 {
+            let _owner_id = cursor.read_u32::<LittleEndian>()?;
+            record.security_id = cursor.read_u32::<LittleEndian>()? as i32;
+            let _quota_charged = cursor.read_u64::<LittleEndian>()?;
+            record.update_sequence_number = cursor.read_u64::<LittleEndian>()? as i64;
+        }
 
         Ok(())
     }
 
-    fn parse_file_name(&self, cursor: &mut Cursor<&[u8]>, record: &mut MftRecord) -> ParseResult<()> {
-        let _resident_size = cursor.read_u32::<LittleEndian>().unwrap();
-        let _resident_offset = cursor.read_u16::<LittleEndian>().unwrap();
-        cursor.seek(SeekFrom::Current(2)).unwrap(); // Reserved
+    /// Take the file size from the unnamed $DATA stream, which is kept current unlike $FILE_NAME
+    fn parse_data(header: &AttributeHeader, content: Option<&[u8]>, record: &mut MftRecord) {
+        if header.flags & ATTRIBUTE_FLAG_COMPRESSED != 0 {
+            record.is_compressed = true;
+        }
+        if header.flags & ATTRIBUTE_FLAG_SPARSE != 0 {
+            record.is_sparse = true;
+        }
+        if header.flags & ATTRIBUTE_FLAG_ENCRYPTED != 0 {
+            record.is_encrypted = true;
+        }
+        match (content, header.non_resident()) {
+            (Some(content), _) => {
+                record.file_size = content.len() as u64;
+                record.allocated_size = content.len() as u64;
+                record.initialized_size = None;
+                record.recoverability = if content.is_empty() { Recoverability::NoData } else { Recoverability::Resident };
+
+                // Content signatures take precedence over the extension
+                if let Some(magic_category) = category::from_magic(&content[..content.len().min(16)]) {
+                    record.category = magic_category;
+                }
+            }
+            // Only the first fragment of a non-resident stream carries its sizes
+            (None, Some(non_resident)) if non_resident.starting_vcn == 0 => {
+                record.file_size = non_resident.real_size;
+                record.allocated_size = non_resident.allocated_size;
+                record.initialized_size = Some(non_resident.initialized_size);
+                if non_resident.real_size > 0 {
+                    record.recoverability = Recoverability::Unverified;
+                }
+            }
+            _ => {}
+        }
+    }
 
-        let parent_reference = cursor.read_u64::<LittleEndian>().unwrap();
-        record.parent_entry_number = (parent_reference & 0xFFFFFFFFFFFF) as u32;
-        record.parent_sequence_number = Some((parent_reference >> 48) as u16);
+    /// Record the size of an alternate data stream; like the unnamed stream, only
+    /// the first fragment of a non-resident one carries its sizes
+    fn collect_stream(header: &AttributeHeader, content: Option<&[u8]>, name: &str, streams: &mut Vec<AlternateStream>) {
+        let sizes = match (content, header.non_resident()) {
+            (Some(content), _) => Some((content.len() as u64, content.len() as u64, None)),
+            (None, Some(non_resident)) if non_resident.starting_vcn == 0 => {
+                Some((non_resident.real_size, non_resident.allocated_size, Some(non_resident.initialized_size)))
+            }
+            _ => None,
+        };
+        let index = match streams.iter().position(|stream| stream.name == name) {
+            Some(index) => index,
+            None => {
+                streams.push(AlternateStream {
+                    name: name.to_string(),
+                    size: 0,
+                    allocated_size: 0,
+                    initialized_size: None,
+                    recoverability: Recoverability::NoData,
+                });
+                streams.len() - 1
+            }
+        };
+        if let Some((size, allocated_size, initialized_size)) = sizes {
+            let stream = &mut streams[index];
+            stream.size = size;
+            stream.allocated_size = allocated_size;
+            stream.initialized_size = initialized_size;
+            stream.recoverability = match (size, content.is_some()) {
+                (0, _) => Recoverability::NoData,
+                (_, true) => Recoverability::Resident,
+                (_, false) => Recoverability::Unverified,
+            };
+        }
+    }
 
-        let created = cursor.read_u64::<LittleEndian>().unwrap();
-        let modified = cursor.read_u64::<LittleEndian>().unwrap();
-        let record_changed = cursor.read_u64::<LittleEndian>().unwrap();
-        let accessed = cursor.read_u64::<LittleEndian>().unwrap();
+    /// Decode the runlist of a non-resident $DATA attribute
+    fn collect_runs(
+        header: &AttributeHeader,
+        attribute: &[u8],
+        name: String,
+        record: &MftRecord,
+        runs: &mut Vec<StreamRuns>,
+    ) {
+        let Some(non_resident) = header.non_resident() else {
+            return;
+        };
+        let Some(runlist) = header.runlist(attribute) else {
+            return;
+        };
+        match decode_runlist(runlist, non_resident.starting_vcn) {
+            // A fragment held in an extension record continues the stream from its starting VCN
+            Ok(decoded) if !decoded.is_empty() => {
+                let index = match runs.iter().position(|stream| stream.name == name) {
+                    Some(index) => index,
+                    None => {
+                        runs.push(StreamRuns { name, runs: Vec::new(), real_size: 0, compression_unit: 0 });
+                        runs.len() - 1
+                    }
+                };
+                let stream = &mut runs[index];
+                stream.runs.extend(decoded);
+                stream.runs.sort_by_key(|run| run.vcn);
+                if non_resident.starting_vcn == 0 {
+                    stream.real_size = non_resident.real_size;
+                    stream.compression_unit = non_resident.compression_unit;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::debug!("Entry {}: unreadable $DATA runlist: {}", record.entry_number, e),
+        }
+    }
 
-        // Set 0x30 timestamps
-        record.created_0x30 = Some(windows_filetime_to_datetime(created));
-        record.last_modified_0x30 = Some(windows_filetime_to_datetime(modified));
-        record.last_record_change_0x30 = Some(windows_filetime_to_datetime(record_changed));
-        record.last_access_0x30 = Some(windows_filetime_to_datetime(accessed));
+    /// Parse files whose attributes spill into extension records again, with
+    /// those records' attributes included.
+    ///
+    /// A file with more attributes than fit one FILE record (many hard links, a
+    /// heavily fragmented $DATA) keeps the rest in extension records named by
+    /// its $ATTRIBUTE_LIST, so on its own the base record can lack names,
+    /// timestamps or data sizes. An extension record is only merged when it
+    /// points back at the base with the base's sequence number and, for a
+    /// resident list, is named by it. The extension records keep their own rows;
+    /// returns those merged, by base entry.
+    fn merge_extension_records(&mut self) -> HashMap<u32, Vec<u32>> {
+        let mut extensions: HashMap<(u32, u16), Vec<u32>> = HashMap::new();
+        for record in self.records.iter().filter(|record| !record.base_record_reference.is_empty()) {
+            let offset = self.record_offsets[&record.entry_number] + BASE_RECORD_OFFSET;
+            let base = u64::from_le_bytes(self.data[offset..offset + 8].try_into().unwrap());
+            extensions
+                .entry(((base & 0xFFFFFFFFFFFF) as u32, (base >> 48) as u16))
+                .or_default()
+                .push(record.entry_number);
+        }
 
-        let _allocated_size = cursor.read_u64::<LittleEndian>().unwrap();
-        let real_size = cursor.read_u64::<LittleEndian>().unwrap();
-        record.file_size = real_size;
+        let mut merged = HashMap::new();
+        for ((base_entry, base_sequence), mut entries) in extensions {
+            let Some(&index) = self.entry_map.get(&base_entry) else {
+                continue; // Base record not in this $MFT
+            };
+            let base = &self.records[index];
+            if base.sequence_number != base_sequence || !base.base_record_reference.is_empty() {
+                continue; // Entry reused since the extension records were written
+            }
+            if let Some(AttributeList::Resident(listed)) = self.attribute_lists.get(&base_entry) {
+                entries.retain(|&entry| {
+                    self.get_record(entry)
+                        .is_some_and(|extension| listed.contains(&(entry, extension.sequence_number)))
+                });
+            }
+            if entries.is_empty() {
+                continue;
+            }
+            entries.sort_unstable();
 
-        let _flags = cursor.read_u32::<LittleEndian>().unwrap();
-        let _reparse_value = cursor.read_u32::<LittleEndian>().unwrap();
+            let fixup_ok = !base.fixup_error && entries.iter().all(|&entry| self.get_record(entry).is_some_and(|extension| !extension.fixup_error));
+            let record_data = |entry: u32| {
+                let offset = self.record_offsets[&entry];
+                &self.data[offset..offset + self.record_size]
+            };
+            let extension_data: Vec<&[u8]> = entries.iter().map(|&entry| record_data(entry)).collect();
+            match self.parse_record(record_data(base_entry), self.record_offsets[&base_entry], fixup_ok, &extension_data) {
+                Ok(Some((mut record, parts))) => {
+                    record.extension_records = entries
+                        .iter()
+                        .filter_map(|&entry| self.get_record(entry).map(|extension| format!("{}-{}", entry, extension.sequence_number)))
+                        .collect::<Vec<_>>()
+                        .join("|");
+                    self.records[index] = record;
+                    if parts.runs.is_empty() {
+                        self.data_runs.remove(&base_entry);
+                    } else {
+                        self.data_runs.insert(base_entry, parts.runs);
+                    }
+                    if parts.alternate_streams.is_empty() {
+                        self.alternate_streams.remove(&base_entry);
+                    } else {
+                        self.alternate_streams.insert(base_entry, parts.alternate_streams);
+                    }
+                    if parts.extended_attributes.is_empty() {
+                        self.extended_attributes.remove(&base_entry);
+                    } else {
+                        self.extended_attributes.insert(base_entry, parts.extended_attributes);
+                    }
+                    if parts.hard_links.is_empty() {
+                        self.hard_links.remove(&base_entry);
+                    } else {
+                        self.hard_links.insert(base_entry, parts.hard_links);
+                    }
+                    merged.insert(base_entry, entries);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Entry {}: failed to merge extension records {:?}: {}", base_entry, entries, e),
+            }
+        }
+        if !merged.is_empty() {
+            log::info!("Merged extension records into {} base records", merged.len());
+        }
+        merged
+    }
 
-        let name_length = cursor.read_u8().unwrap();
-        record.name_type = cursor.read_u8().unwrap();
+    /// Move the rows of merged extension records to directly after their base
+    /// record's row, so each file's records read as one group
+    fn group_extension_rows(&mut self, merged: &HashMap<u32, Vec<u32>>) {
+        if merged.is_empty() {
+            return;
+        }
+        let grouped: HashSet<usize> = merged.values().flatten().filter_map(|entry| self.entry_map.get(entry).copied()).collect();
 
-        // Read filename (UTF-16)
-        let mut name_bytes = vec![0u8; (name_length as usize) * 2];
-        cursor.read_exact(&mut name_bytes).unwrap();
+        let mut order = Vec::with_capacity(self.records.len());
+        for (index, record) in self.records.iter().enumerate() {
+            if grouped.contains(&index) {
+                continue;
+            }
+            order.push(index);
+            if record.base_record_reference.is_empty()
+                && let Some(entries) = merged.get(&record.entry_number) {
+                order.extend(entries.iter().filter_map(|entry| self.entry_map.get(entry).copied()));
+            }
+        }
 
-        let name = string_from_utf16le(&name_bytes)
-            .unwrap_or_else(|_| String::from("INVALID_NAME"));
+        let mut index_map = vec![0; self.records.len()];
+        for (position, &index) in order.iter().enumerate() {
+            index_map[index] = position;
+        }
+        let mut records: Vec<Option<MftRecord>> = std::mem::take(&mut self.records).into_iter().map(Some).collect();
+        self.records = order.iter().filter_map(|&index| records[index].take()).collect();
+        for index in self.entry_map.values_mut() {
+            *index = index_map[*index];
+        }
+    }
 
-        record.file_name = name.clone();
+    /// Follow each base record that has alternate data streams with one row per
+    /// stream, as MFTECmd does.
+    ///
+    /// A stream's row copies its file's row, with `file:stream` as the name,
+    /// the stream's size, and the extension taken from the stream name; only
+    /// the Zone.Identifier row keeps the file's Mark-of-the-Web fields, and
+    /// extended attributes and logged utility streams stay with the file.
+    fn add_stream_rows(&mut self) {
+        if self.alternate_streams.is_empty() {
+            return;
+        }
 
-        // Extract extension
-        if let Some(dot_pos) = name.rfind('.') {
-            record.extension = name[dot_pos + 1..].to_string();
+        let mut records = Vec::with_capacity(self.records.len() + self.alternate_streams.len());
+        let mut index_map = vec![0; self.records.len()];
+        for (index, record) in std::mem::take(&mut self.records).into_iter().enumerate() {
+            index_map[index] = records.len();
+            let streams = if record.base_record_reference.is_empty() && !record.is_hard_link {
+                self.alternate_streams.get(&record.entry_number)
+            } else {
+                None
+            };
+            let rows: Vec<MftRecord> = streams
+                .into_iter()
+                .flatten()
+                .map(|stream| {
+                    let mut row = record.clone();
+                    let extension_info = extract_extension(&stream.name);
+                    row.file_name = format!("{}:{}", record.file_name, stream.name);
+                    if !row.full_path.is_empty() {
+                        row.full_path = format!("{}:{}", record.full_path, stream.name);
+                    }
+                    row.extension = extension_info.extension;
+                    row.suspicious_name = extension_info.suspicious;
+                    row.category = category::from_extension(&row.extension, false);
+                    row.file_size = stream.size;
+                    row.allocated_size = stream.allocated_size;
+                    row.initialized_size = stream.initialized_size;
+                    row.recoverability = stream.recoverability;
+                    row.has_ads = false;
+                    row.is_ads = true;
+                    row.ads_names = String::new();
+                    row.index_names = String::new();
+                    if !stream.name.eq_ignore_ascii_case(ZONE_IDENTIFIER_STREAM) {
+                        row.zone_id_contents = String::new();
+                        row.zone_id = None;
+                        row.referrer_url = String::new();
+                        row.host_url = String::new();
+                    }
+                    row.extended_attributes = String::new();
+                    row.ea_size = 0;
+                    row.logged_util_stream = String::new();
+                    row.efs_users = String::new();
+                    row.efs_recovery_agents = String::new();
+                    row
+                })
+                .collect();
+            records.push(record);
+            records.extend(rows);
         }
 
-        Ok(())
+        for index in self.entry_map.values_mut() {
+            *index = index_map[*index];
+        }
+        self.records = records;
     }
 
-    fn parse_data_attribute(&self, _cursor: &mut Cursor<&[u8]>, _record: &mut MftRecord) -> ParseResult<()> {
-        // Data attribute parsing - for now just skip
-        Ok(())
+    /// Grade every deleted record with non-resident data by how many of its
+    /// clusters are allocated again.
+    ///
+    /// With a $Bitmap the volume's allocation state is authoritative. Without one,
+    /// only clusters claimed by records still in use can be detected, so files
+    /// with no such overlap stay `Unverified`.
+    fn assess_recoverability(&mut self) {
+        let live_clusters = match self.bitmap {
+            Some(_) => Vec::new(),
+            None => self.live_cluster_ranges(),
+        };
+
+        for record in &mut self.records {
+            if record.in_use {
+                record.recoverability = Recoverability::NotDeleted;
+                continue;
+            }
+            if record.recoverability != Recoverability::Unverified {
+                continue;
+            }
+            // Stream names can't contain ':', so a stream row's name ends with its stream's
+            let stream_name = if record.is_ads {
+                record.file_name.rsplit_once(':').map_or("", |(_, name)| name)
+            } else {
+                ""
+            };
+            let runs: Vec<DataRun> = self
+                .data_runs
+                .get(&record.entry_number)
+                .into_iter()
+                .flatten()
+                .filter(|stream| stream.name == stream_name)
+                .flat_map(|stream| stream.runs.iter().copied())
+                .collect();
+            if runs.is_empty() {
+                continue;
+            }
+
+            let total: u64 = runs.iter().filter(|run| run.lcn.is_some()).map(|run| run.length).sum();
+            let reused = match self.bitmap {
+                Some(ref bitmap) => bitmap.count_allocated(&runs),
+                None => overlapping_clusters(&runs, &live_clusters),
+            };
+
+            record.recoverability = match (reused, self.bitmap.is_some()) {
+                (0, true) => Recoverability::Recoverable,
+                (0, false) => Recoverability::Unverified,
+                (reused, _) if reused >= total => Recoverability::Overwritten,
+                _ => Recoverability::Partial,
+            };
+        }
+    }
+
+    /// Sorted, merged cluster ranges (start, end exclusive) used by in-use records
+    fn live_cluster_ranges(&self) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = self
+            .records
+            .iter()
+            .filter(|record| record.in_use && !record.is_ads && !record.is_hard_link)
+            .filter_map(|record| self.data_runs.get(&record.entry_number))
+            .flatten()
+            .flat_map(|stream| &stream.runs)
+            .filter_map(|run| run.lcn.map(|lcn| (lcn, lcn.saturating_add(run.length))))
+            .collect();
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
     }
 
     pub fn get_records(&self) -> &[MftRecord] {
         &self.records
     }
 
+    /// Data runs of a record's non-resident $DATA stream `name` ("" for the unnamed one)
+    pub fn stream_runs(&self, entry_number: u32, name: &str) -> Option<&StreamRuns> {
+        self.data_runs.get(&entry_number)?.iter().find(|stream| stream.name == name)
+    }
+
+    /// Content of a record's resident $DATA stream `name` ("" for the unnamed one)
+    pub fn resident_stream(&self, entry_number: u32, name: &str) -> Option<&[u8]> {
+        self.resident_attribute(entry_number, 0x80, name)
+    }
+
+    /// Content of a resident attribute of a record's base record, e.g. its $REPARSE_POINT (0xC0)
+    pub fn resident_attribute(&self, entry_number: u32, attr_type: u32, name: &str) -> Option<&[u8]> {
+        let offset = *self.record_offsets.get(&entry_number)?;
+        let data = &self.data[offset..offset + self.record_size];
+        let first_attribute_offset = u16::from_le_bytes([data[FIRST_ATTRIBUTE_OFFSET], data[FIRST_ATTRIBUTE_OFFSET + 1]]);
+        let attributes = split_attributes(data, first_attribute_offset as usize, entry_number).ok()?;
+        attributes
+            .into_iter()
+            .filter(|(header, attribute)| header.attr_type == attr_type && header.name(attribute) == name)
+            .find_map(|(header, attribute)| header.resident_content(attribute))
+    }
+
+    /// The $MFT's own $BITMAP from record 0, when it is resident; a real $MFT's
+    /// is non-resident and has to be read from the volume
+    pub fn mft_bitmap(&self) -> Option<&[u8]> {
+        self.resident_attribute(0, BITMAP_ATTRIBUTE, "")
+    }
+
+    /// Entries whose in-use flag disagrees with their bit in `bitmap`, the
+    /// $MFT's $BITMAP, a sign that records or the bitmap were edited by hand.
+    /// Entries are taken by position, and only those both cover are compared;
+    /// a position without a FILE record counts as not in use.
+    pub fn mft_bitmap_mismatches(&self, bitmap: &[u8]) -> Vec<MftBitmapMismatch> {
+        self.data
+            .chunks_exact(self.record_size)
+            .take(bitmap.len() * 8)
+            .enumerate()
+            .filter_map(|(position, record)| {
+                let bitmap_allocated = bitmap[position / 8] & (1 << (position % 8)) != 0;
+                let header = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+                let (sequence_number, record_in_use) = match record.starts_with(b"FILE") {
+                    true => (header(SEQUENCE_NUMBER_OFFSET), header(FLAGS_OFFSET) & 0x01 != 0),
+                    false => (0, false),
+                };
+                (record_in_use != bitmap_allocated).then_some(MftBitmapMismatch {
+                    entry_number: position as u32,
+                    sequence_number,
+                    record_in_use,
+                    bitmap_allocated,
+                })
+            })
+            .collect()
+    }
+
+    /// Where records have ever been written: runs of positions without a FILE
+    /// record, the zeroed tail NTFS never reached, and whether the input is
+    /// shorter than record 0 says the $MFT is
+    pub fn layout(&self) -> MftLayout {
+        let mut layout = MftLayout {
+            record_count: self.data.len() / self.record_size,
+            actual_size: self.data.len() as u64,
+            ..Default::default()
+        };
+        for (position, record) in self.data.chunks_exact(self.record_size).enumerate() {
+            let position = position as u32;
+            // A BAAD record was a FILE record before NTFS marked it bad
+            if record.starts_with(b"FILE") || record.starts_with(BAD_RECORD_SIGNATURE) {
+                layout.high_water_mark = Some(position);
+                continue;
+            }
+            let zeroed = record.iter().all(|&byte| byte == 0);
+            match layout.gaps.last_mut() {
+                Some(gap) if gap.last_position + 1 == position && gap.zeroed == zeroed => {
+                    gap.last_position = position;
+                    gap.record_count += 1;
+                }
+                _ => layout.gaps.push(MftGap {
+                    first_position: position,
+                    last_position: position,
+                    record_count: 1,
+                    zeroed,
+                }),
+            }
+        }
+
+        // A zeroed run after the last FILE record is space the $MFT never used
+        if let Some(gap) = layout.gaps.last()
+            && gap.zeroed
+            && layout.high_water_mark.is_none_or(|mark| gap.first_position > mark)
+        {
+            layout.never_used_records = gap.record_count;
+            layout.gaps.pop();
+        }
+
+        layout.expected_size = self
+            .record_offsets
+            .get(&0)
+            .filter(|&&offset| offset == 0)
+            .and_then(|_| self.stream_runs(0, ""))
+            .map(|stream| stream.real_size);
+        layout.truncated = !self.data.len().is_multiple_of(self.record_size)
+            || layout.expected_size.is_some_and(|size| size > layout.actual_size);
+        layout
+    }
+
+    pub fn get_record(&self, entry_number: u32) -> Option<&MftRecord> {
+        self.entry_map.get(&entry_number).map(|&index| &self.records[index])
+    }
+
+    /// True for NTFS metadata files: the reserved entries other than the root
+    /// directory ($MFT, $Bitmap, $Secure, ...) and everything under $Extend
+    pub fn is_metadata_file(&self, record: &MftRecord) -> bool {
+        if record.entry_number < FIRST_USER_ENTRY {
+            return record.entry_number != ROOT_ENTRY;
+        }
+
+        let mut parent = record.parent_entry_number;
+        for _ in 0..MAX_METADATA_DEPTH {
+            match parent {
+                EXTEND_ENTRY => return true,
+                ROOT_ENTRY => return false,
+                _ => match self.get_record(parent) {
+                    Some(next) if next.parent_entry_number != parent => parent = next.parent_entry_number,
+                    _ => return false,
+                },
+            }
+        }
+        false
+    }
+
+    /// Every non-resident $DATA stream with the record that holds it
+    pub fn data_streams(&self) -> impl Iterator<Item = (&MftRecord, &StreamRuns)> {
+        self.records.iter().filter(|record| !record.is_ads && !record.is_hard_link).flat_map(move |record| {
+            self.data_runs
+                .get(&record.entry_number)
+                .into_iter()
+                .flatten()
+                .map(move |stream| (record, stream))
+        })
+    }
+
+    /// Extended attributes of each file that has them, in record order
+    pub fn extended_attributes(&self) -> impl Iterator<Item = (&MftRecord, &[ExtendedAttribute])> {
+        self.records
+            .iter()
+            .filter(|record| !record.is_ads && !record.is_hard_link)
+            .filter_map(|record| Some((record, self.extended_attributes.get(&record.entry_number)?.as_slice())))
+    }
+
+    /// Run list of every non-resident $DATA stream, in record order
+    pub fn stream_run_lists(&self) -> Vec<StreamRunList> {
+        self.data_streams()
+            .map(|(record, stream)| StreamRunList {
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                in_use: record.in_use,
+                full_path: record.full_path.clone(),
+                stream: stream.name.clone(),
+                allocated_clusters: stream.runs.iter().filter(|run| run.lcn.is_some()).map(|run| run.length).sum(),
+                runs: stream.runs.clone(),
+            })
+            .collect()
+    }
+
+    pub fn get_error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// FILE records whose update sequence array didn't match, i.e. torn or corrupt records
+    pub fn get_fixup_error_count(&self) -> usize {
+        self.fixup_errors
+    }
+
+    /// BAAD records and FILE records that failed to parse, in $MFT order
+    pub fn get_corrupt_records(&self) -> &[CorruptRecord] {
+        &self.corrupt_records
+    }
+
+    /// True when parsing stopped early at the deadline
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Regions whose header record numbers don't match their position, a sign
+    /// of a carved, mis-extracted or spliced $MFT
+    pub fn get_record_number_shifts(&self) -> &[RecordNumberShift] {
+        &self.record_number_shifts
+    }
+
+    /// Extend the current shifted region or start a new one
+    fn track_record_number(&mut self, entry_number: u32, position: u32) {
+        let shift = entry_number as i64 - position as i64;
+        if shift == 0 {
+            return;
+        }
+
+        // Only extend the region if the previous valid record belongs to it
+        if let Some(region) = self.record_number_shifts.last_mut()
+            && region.shift == shift
+            && self.records.last().is_some_and(|last| last.entry_number as i64 - shift == region.last_position as i64)
+        {
+            region.last_position = position;
+            region.record_count += 1;
+            return;
+        }
+
+        self.record_number_shifts.push(RecordNumberShift {
+            first_position: position,
+            last_position: position,
+            record_count: 1,
+            shift,
+        });
+    }
+
     fn resolve_parent_paths(&mut self) {
         // Clone the entry map for borrowing purposes
         let entry_map = self.entry_map.clone();
 
         for i in 0..self.records.len() {
-            let parent_entry = self.records[i].parent_entry_number;
-            if parent_entry == 5 {
-                // Entry 5 is the root directory
-                self.records[i].parent_path = String::new();
-            } else if parent_entry != self.records[i].entry_number {
-                // Build path by following parent chain
-                let path = self.build_path(parent_entry, &entry_map, 0);
-                self.records[i].parent_path = path;
+            let (parent_path, confidence, full_path) = self.locate(&self.records[i], &entry_map);
+            let record = &mut self.records[i];
+            // A record that is its own parent keeps whatever parent path it had
+            if let Some(parent_path) = parent_path {
+                record.parent_path = parent_path;
             }
+            record.path_confidence = confidence;
+            record.full_path = full_path;
         }
     }
 
-    fn build_path(&self, entry_number: u32, entry_map: &HashMap<u32, usize>, depth: usize) -> String {
+    /// Parent path, its confidence and the full path of a record, from the
+    /// parent and name it carries
+    fn locate(&self, record: &MftRecord, entry_map: &HashMap<u32, usize>) -> (Option<String>, PathConfidence, String) {
+        let entry_number = record.entry_number;
+        let parent_entry = record.parent_entry_number;
+
+        let (parent_path, confidence) = if entry_number == 5 || parent_entry == 5 {
+            // Entry 5 is the root directory
+            (Some(String::new()), PathConfidence::Exact)
+        } else if parent_entry != entry_number {
+            // Build path by following parent chain
+            let (path, confidence) = self.build_path(parent_entry, record.parent_sequence_number, entry_map, 0);
+            (Some(path), confidence)
+        } else {
+            (None, PathConfidence::Unknown)
+        };
+
+        let parent = parent_path.as_deref().unwrap_or(&record.parent_path);
+        let full_path = if record.file_name.is_empty() {
+            String::new()
+        } else if entry_number == 5 {
+            String::from("/")
+        } else if confidence == PathConfidence::Unknown {
+            format!("/{}/{}", ORPHAN_DIRECTORY, record.file_name)
+        } else if parent.is_empty() {
+            format!("/{}", record.file_name)
+        } else {
+            format!("/{}/{}", parent, record.file_name)
+        };
+        (parent_path, confidence, full_path)
+    }
+
+    /// Follow each file that has several hard links with one row per other
+    /// link, carrying that link's name, parent directory and $FILE_NAME
+    /// timestamps. Streams stay with the file's own row.
+    fn add_hard_link_rows(&mut self) {
+        if self.hard_links.is_empty() {
+            return;
+        }
+
+        let entry_map = self.entry_map.clone();
+        let mut records = Vec::with_capacity(self.records.len() + self.hard_links.len());
+        let mut index_map = vec![0; self.records.len()];
+        for (index, record) in self.records.iter().enumerate() {
+            index_map[index] = records.len();
+            records.push(record.clone());
+            if !record.base_record_reference.is_empty() {
+                continue;
+            }
+            for link in self.hard_links.get(&record.entry_number).into_iter().flatten() {
+                let mut row = record.clone();
+                apply_file_name(link, &mut row);
+                row.file_size = record.file_size;
+                row.allocated_size = record.allocated_size;
+                row.is_hard_link = true;
+                row.timestamp_anomalies = timestamp_anomalies(&row);
+                let (parent_path, confidence, full_path) = self.locate(&row, &entry_map);
+                row.parent_path = parent_path.unwrap_or_default();
+                row.path_confidence = confidence;
+                row.full_path = full_path;
+                records.push(row);
+            }
+        }
+
+        for index in self.entry_map.values_mut() {
+            *index = index_map[*index];
+        }
+        self.records = records;
+    }
+
+    /// Prefix every full path with a drive letter (e.g. C:/Users)
+    pub fn apply_drive_letter(&mut self, drive_letter: &str) {
+        let prefix = format!("{}:", drive_letter.trim_end_matches([':', '/', '\\']));
+        for record in self.records.iter_mut().filter(|r| !r.full_path.is_empty()) {
+            record.full_path.insert_str(0, &prefix);
+        }
+    }
+
+    /// Follow the parent chain, returning the path and how far it can be trusted
+    fn build_path(
+        &self,
+        entry_number: u32,
+        expected_sequence: Option<u16>,
+        entry_map: &HashMap<u32, usize>,
+        depth: usize,
+    ) -> (String, PathConfidence) {
         // Prevent infinite recursion
         if depth > 100 {
-            return String::from("...[path too deep]");
+            return (String::from("...[path too deep]"), PathConfidence::Unknown);
         }
 
         if entry_number == 5 {
-            return String::new(); // Root directory
+            return (String::new(), PathConfidence::Exact); // Root directory
         }
 
         if let Some(&record_index) = entry_map.get(&entry_number) {
             if record_index < self.records.len() {
                 let record = &self.records[record_index];
-                let parent_path = if record.parent_entry_number == 5 {
-                    String::new()
+                let hop_confidence = match expected_sequence {
+                    // Deleting an entry bumps its sequence number by one
+                    Some(sequence) if sequence != record.sequence_number => {
+                        if !record.in_use && record.sequence_number == sequence.wrapping_add(1) {
+                            PathConfidence::ParentDeleted
+                        } else {
+                            PathConfidence::SequenceMismatch
+                        }
+                    }
+                    _ if !record.in_use => PathConfidence::ParentDeleted,
+                    _ => PathConfidence::Exact,
+                };
+
+                let (parent_path, parent_confidence) = if record.parent_entry_number == 5 {
+                    (String::new(), PathConfidence::Exact)
                 } else {
-                    self.build_path(record.parent_entry_number, entry_map, depth + 1)
+                    self.build_path(record.parent_entry_number, record.parent_sequence_number, entry_map, depth + 1)
                 };
+                let confidence = hop_confidence.max(parent_confidence);
 
                 if parent_path.is_empty() {
-                    record.file_name.clone()
+                    (record.file_name.clone(), confidence)
                 } else {
-                    format!("{}/{}", parent_path, record.file_name)
+                    (format!("{}/{}", parent_path, record.file_name), confidence)
                 }
             } else {
-                String::from("...[invalid index]")
+                (String::from("...[invalid index]"), PathConfidence::Unknown)
             }
         } else {
-            String::from("...[parent not found]")
+            (String::from("...[parent not found]"), PathConfidence::Unknown)
         }
     }
 }
 
-// Helper function to convert UTF-16LE bytes to String
-fn string_from_utf16le(bytes: &[u8]) -> Result<String, std::string::FromUtf16Error> {
-    let utf16_chars: Vec<u16> = bytes
-        .chunks_exact(2)
-        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
-    String::from_utf16(&utf16_chars)
+fn parse_file_name(content: &[u8]) -> ParseResult<FileName> {
+    let mut cursor = Cursor::new(content);
+
+    let parent_reference = cursor.read_u64::<LittleEndian>()?;
+    let created = cursor.read_u64::<LittleEndian>()?;
+    let modified = cursor.read_u64::<LittleEndian>()?;
+    let record_changed = cursor.read_u64::<LittleEndian>()?;
+    let accessed = cursor.read_u64::<LittleEndian>()?;
+    let allocated_size = cursor.read_u64::<LittleEndian>()?;
+    let real_size = cursor.read_u64::<LittleEndian>()?;
+    let flags = cursor.read_u32::<LittleEndian>()?;
+    let _reparse_value = cursor.read_u32::<LittleEndian>()?;
+
+    let name_length = cursor.read_u8()?;
+    let name_type = cursor.read_u8()?;
+
+    // Read filename (UTF-16)
+    let mut name_bytes = vec![0u8; (name_length as usize) * 2];
+    cursor.read_exact(&mut name_bytes)?;
+    let name = decode_utf16le(&name_bytes).unwrap_or_else(|_| String::from("INVALID_NAME"));
+
+    Ok(FileName {
+        parent_entry_number: (parent_reference & 0xFFFFFFFFFFFF) as u32,
+        parent_sequence_number: (parent_reference >> 48) as u16,
+        created: FileTime(created),
+        modified: FileTime(modified),
+        record_changed: FileTime(record_changed),
+        accessed: FileTime(accessed),
+        allocated_size,
+        real_size,
+        flags,
+        name_type,
+        name,
+        short_name: String::new(),
+    })
 }
 
-trait StringFromUtf16Le {
-    fn from_utf16le(&self) -> Result<String, std::string::FromUtf16Error>;
+/// Name a record, or one of its hard link rows, by a $FILE_NAME: its parent,
+/// name, extension and 0x30 timestamps
+fn apply_file_name(file_name: &FileName, record: &mut MftRecord) {
+    record.parent_entry_number = file_name.parent_entry_number;
+    record.parent_sequence_number = Some(file_name.parent_sequence_number);
+    record.created_0x30 = Some(file_name.created);
+    record.last_modified_0x30 = Some(file_name.modified);
+    record.last_record_change_0x30 = Some(file_name.record_changed);
+    record.last_access_0x30 = Some(file_name.accessed);
+    record.file_size = file_name.real_size;
+    record.allocated_size = file_name.allocated_size;
+    record.fn_flags = file_name.flags;
+    record.fn_flag_names = format_file_attributes(file_name.flags);
+    record.name_type = file_name.name_type;
+    record.name_namespace = Some(NameNamespace::from(file_name.name_type));
+    record.short_name = file_name.short_name.clone();
+
+    let extension_info = extract_extension(&file_name.name);
+    record.extension = extension_info.extension;
+    record.suspicious_name = extension_info.suspicious;
+    record.category = category::from_extension(&record.extension, record.is_directory);
+    record.file_name = file_name.name.clone();
+}
+
+fn timestamp_anomalies(record: &MftRecord) -> String {
+    filetime::anomalies([
+        ("created_0x10", record.created_0x10),
+        ("created_0x30", record.created_0x30),
+        ("last_modified_0x10", record.last_modified_0x10),
+        ("last_modified_0x30", record.last_modified_0x30),
+        ("last_record_change_0x10", record.last_record_change_0x10),
+        ("last_record_change_0x30", record.last_record_change_0x30),
+        ("last_access_0x10", record.last_access_0x10),
+        ("last_access_0x30", record.last_access_0x30),
+    ])
+}
+
+/// FILE record size the first FILE records declare, or 1024 when none declares a plausible one
+pub fn detect_record_size(data: &[u8]) -> usize {
+    let declared = data
+        .chunks(DEFAULT_RECORD_SIZE)
+        .filter(|record| record.starts_with(b"FILE") && record.len() >= ALLOCATED_SIZE_OFFSET + 4)
+        .take(RECORD_SIZE_PROBES)
+        .map(|record| u32::from_le_bytes(record[ALLOCATED_SIZE_OFFSET..ALLOCATED_SIZE_OFFSET + 4].try_into().unwrap()) as usize)
+        .find(|&size| is_valid_record_size(size));
+    declared.unwrap_or(DEFAULT_RECORD_SIZE)
+}
+
+/// FILE records are a power of two between a 512-byte sector and 64 KB
+pub fn is_valid_record_size(size: usize) -> bool {
+    size.is_power_of_two() && (512..=65536).contains(&size)
 }
 
-impl StringFromUtf16Le for [u8] {
-    fn from_utf16le(&self) -> Result<String, std::string::FromUtf16Error> {
-        string_from_utf16le(self)
+/// An $MFT starts with the "FILE" signature of record 0
+pub fn detect(input: &InputInfo) -> u8 {
+    if input.header.len() >= 4 && input.header[..4] == MFT_SIGNATURE.to_le_bytes() {
+        SIGNATURE_MATCH
+    } else if Wrapper::detect(&input.header, b"FILE").is_some() {
+        LIKELY_MATCH
+    } else {
+        NO_MATCH
     }
 }
 
-fn windows_filetime_to_datetime(filetime: u64) -> DateTime<Utc> {
-    // Windows FILETIME is 100-nanosecond intervals since January 1, 1601
-    // Unix timestamp is seconds since January 1, 1970
-    const FILETIME_UNIX_DIFF: u64 = 11644473600; // seconds between 1601 and 1970
+/// One --fl row for each file and directory in use, with its $STANDARD_INFORMATION times;
+/// alternate data streams are left out
+pub fn file_listing(records: &[MftRecord]) -> Vec<FileListEntry> {
+    records
+        .iter()
+        .filter(|record| record.in_use && !record.is_ads)
+        .map(|record| FileListEntry {
+            entry_number: record.entry_number,
+            sequence_number: record.sequence_number,
+            file_name: record.file_name.clone(),
+            full_path: record.full_path.clone(),
+            extension: record.extension.clone(),
+            file_size: record.file_size,
+            is_directory: record.is_directory,
+            created: record.created_0x10.unwrap_or_default(),
+            modified: record.last_modified_0x10.unwrap_or_default(),
+        })
+        .collect()
+}
 
-    if filetime == 0 {
-        return DateTime::<Utc>::from_timestamp(0, 0).unwrap();
+/// Summarize records into allocation, type, path resolution and stream name counts
+pub fn compute_statistics(records: &[MftRecord]) -> MftStatistics {
+    let mut stats = MftStatistics {
+        total_records: records.len(),
+        ..Default::default()
+    };
+    let mut ads_names: HashMap<&str, usize> = HashMap::new();
+
+    for record in records {
+        if record.is_ads {
+            stats.ads_streams += 1;
+            continue;
+        }
+        if record.is_hard_link {
+            continue;
+        }
+
+        // Extension records carry overflow attributes for their base record,
+        // which has their stream names too once merged
+        if !record.base_record_reference.is_empty() {
+            stats.extension_records += 1;
+            continue;
+        }
+
+        for name in record.ads_names.split('|').filter(|name| !name.is_empty()) {
+            *ads_names.entry(name).or_default() += 1;
+        }
+
+        match (record.in_use, record.is_directory) {
+            (true, false) => stats.active_files += 1,
+            (true, true) => stats.active_directories += 1,
+            (false, false) => stats.deleted_files += 1,
+            (false, true) => stats.deleted_directories += 1,
+        }
+
+        if record.path_confidence == PathConfidence::Unknown && !record.file_name.is_empty() {
+            stats.orphans += 1;
+        }
     }
 
-    let seconds = filetime / 10_000_000;
-    if seconds < FILETIME_UNIX_DIFF {
-        return DateTime::<Utc>::from_timestamp(0, 0).unwrap();
+    stats.ads_names = ads_names
+        .into_iter()
+        .map(|(name, count)| AdsNameCount {
+            name: name.to_string(),
+            count,
+            unusual: !KNOWN_ADS_NAMES.iter().any(|known| known.eq_ignore_ascii_case(name)),
+        })
+        .collect();
+    stats.ads_names.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    stats
+}
+
+/// Attributes of a FILE record from `start` up to the end marker, with the bytes of each.
+///
+/// The header checks its length against the rest of the record, so every step
+/// moves forward and stays inside it.
+fn split_attributes(data: &[u8], start: usize, entry_number: u32) -> ParseResult<Vec<(AttributeHeader, &[u8])>> {
+    let mut attributes = Vec::new();
+    let mut pos = start;
+
+    while pos + 4 <= data.len() {
+        if data[pos..pos + 4] == [0xFF; 4] {
+            break; // End of attributes
+        }
+        let header = AttributeHeader::parse(&data[pos..]).map_err(|e| ParseError {
+            code: e.code,
+            message: format!("Entry {}: {}", entry_number, e.message),
+            offset: e.offset.map(|offset| offset + pos as u64),
+        })?;
+        let length = header.length as usize;
+        attributes.push((header, &data[pos..pos + length]));
+        pos += length;
     }
+    Ok(attributes)
+}
 
-    let unix_seconds = seconds - FILETIME_UNIX_DIFF;
-    let nanos = ((filetime % 10_000_000) * 100) as u32;
+/// Records named by a resident $ATTRIBUTE_LIST, as (entry, sequence), each once
+fn parse_attribute_list(content: &[u8]) -> Vec<(u32, u16)> {
+    let mut references = Vec::new();
+    let mut pos = 0;
+
+    while pos + ATTRIBUTE_LIST_ENTRY_SIZE <= content.len() {
+        let length = u16::from_le_bytes([content[pos + 4], content[pos + 5]]) as usize;
+        if length < ATTRIBUTE_LIST_ENTRY_SIZE {
+            break;
+        }
+        let reference = u64::from_le_bytes(content[pos + 0x10..pos + 0x18].try_into().unwrap());
+        let record = ((reference & 0xFFFFFFFFFFFF) as u32, (reference >> 48) as u16);
+        if !references.contains(&record) {
+            references.push(record);
+        }
+        pos += length;
+    }
+    references
+}
 
-    DateTime::<Utc>::from_timestamp(unix_seconds as i64, nanos)
-        .unwrap_or_else(|| DateTime::<Utc>::from_timestamp(0, 0).unwrap())
-}
\ No newline at end of file
+/// Add a name to a '|' separated list, once
+fn append_name(list: &mut String, name: &str) {
+    if name.is_empty() || list.split('|').any(|existing| existing == name) {
+        return;
+    }
+    if !list.is_empty() {
+        list.push('|');
+    }
+    list.push_str(name);
+}
+
+/// Clusters of `runs` that fall inside the sorted, merged `ranges`
+fn overlapping_clusters(runs: &[DataRun], ranges: &[(u64, u64)]) -> u64 {
+    let mut overlap = 0;
+    for run in runs {
+        let Some(lcn) = run.lcn else {
+            continue;
+        };
+        let (start, end) = (lcn, lcn.saturating_add(run.length));
+        let first = ranges.partition_point(|&(_, range_end)| range_end <= start);
+        for &(range_start, range_end) in ranges[first..].iter().take_while(|&&(range_start, _)| range_start < end) {
+            overlap += end.min(range_end) - start.max(range_start);
+        }
+    }
+    overlap
+}
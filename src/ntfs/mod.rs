@@ -1,8 +1,33 @@
 pub mod mft;
 pub mod usn_journal;
+pub mod usn_filter;
 pub mod boot;
 pub mod sds;
+pub mod sds_diff;
 pub mod i30;
+pub mod runlist;
+pub mod anomaly;
+pub mod attribute;
+pub mod bitmap;
+pub mod cluster_map;
+pub mod compression;
+pub mod deadline;
+pub mod directory_sizes;
+pub mod efs;
+pub mod entry_reuse;
+pub mod extended_attributes;
+pub mod file_attributes;
+pub mod filetime;
+pub mod fixup;
+pub mod logfile;
+pub mod volume;
+pub mod wiping;
+pub mod category;
+pub mod names;
+pub mod path_resolver;
+pub mod ransomware;
+pub mod reparse;
 pub mod types;
+pub mod zone_identifier;
 
 pub use types::*;
\ No newline at end of file
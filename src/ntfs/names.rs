@@ -0,0 +1,208 @@
+// Compound extensions that should be reported as a single unit
+const COMPOUND_EXTENSIONS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz", "tar.lzma", "tar.z",
+    "tar.lz4", "tar.br", "user.js", "d.ts", "min.js", "min.css",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionInfo {
+    pub extension: String,
+    pub suspicious: bool,
+}
+
+/// Extract the extension from a file name
+///
+/// Known compound extensions (e.g. tar.gz) are kept together regardless of case,
+/// and names ending in dots or spaces are flagged as suspicious since the Win32
+/// API strips them and only raw NT paths can create them.
+pub fn extract_extension(name: &str) -> ExtensionInfo {
+    let trimmed = name.trim_end_matches(['.', ' ']);
+    let suspicious = trimmed.len() != name.len() && !trimmed.is_empty();
+
+    // Leading dots mark hidden-style names (.bashrc), not extensions
+    let stem_start = trimmed.len() - trimmed.trim_start_matches('.').len();
+    let body = &trimmed[stem_start..];
+
+    let extension = match body.rfind('.') {
+        Some(dot_pos) => COMPOUND_EXTENSIONS
+            .iter()
+            .map(|compound| body.len().saturating_sub(compound.len()))
+            .zip(COMPOUND_EXTENSIONS)
+            .find(|&(split, compound)| {
+                split > 1
+                    && body.is_char_boundary(split)
+                    && body[split..].eq_ignore_ascii_case(compound)
+                    && body[..split].ends_with('.')
+            })
+            .map(|(split, _)| body[split..].to_string())
+            .unwrap_or_else(|| body[dot_pos + 1..].to_string()),
+        None => String::new(),
+    };
+
+    ExtensionInfo { extension, suspicious }
+}
+
+/// Decode a UTF-16LE name, as String::from_utf16 does
+///
+/// NTFS names are overwhelmingly ASCII, so leading ASCII code units are checked
+/// four at a time in a u64 and narrowed directly; only the remainder after the
+/// first non-ASCII unit goes through the general decoder.
+pub fn decode_utf16le(bytes: &[u8]) -> Result<String, std::string::FromUtf16Error> {
+    let bytes = &bytes[..bytes.len() & !1];
+    let ascii_len = ascii_prefix_len(bytes);
+    let mut decoded = narrow_ascii(&bytes[..ascii_len], bytes.len());
+
+    let tail = bytes[ascii_len..]
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]));
+    for unit in char::decode_utf16(tail) {
+        match unit {
+            Ok(ch) => decoded.push(ch),
+            // Let the standard decoder build the error for the unpaired surrogate
+            Err(_) => return String::from_utf16(&[0xD800]),
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Decode a UTF-16LE name, replacing invalid surrogates with U+FFFD
+pub fn decode_utf16le_lossy(bytes: &[u8]) -> String {
+    decode_utf16le(bytes).unwrap_or_else(|_| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    })
+}
+
+/// Length in bytes of the leading run of ASCII code units
+fn ascii_prefix_len(bytes: &[u8]) -> usize {
+    // A code unit is ASCII when its high byte is zero and its low byte is below 0x80
+    const NON_ASCII_MASK: u64 = 0xFF80_FF80_FF80_FF80;
+
+    let mut len = 0;
+    for word in bytes.chunks_exact(8) {
+        if u64::from_le_bytes(word.try_into().unwrap()) & NON_ASCII_MASK != 0 {
+            break;
+        }
+        len += 8;
+    }
+
+    while len + 2 <= bytes.len() && bytes[len] < 0x80 && bytes[len + 1] == 0 {
+        len += 2;
+    }
+    len
+}
+
+fn narrow_ascii(bytes: &[u8], capacity: usize) -> String {
+    let mut narrowed = Vec::with_capacity(capacity);
+    narrowed.extend(bytes.iter().step_by(2));
+    String::from_utf8(narrowed).expect("ASCII is valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn extension(name: &str) -> (String, bool) {
+        let info = extract_extension(name);
+        (info.extension, info.suspicious)
+    }
+
+    #[test]
+    fn keeps_compound_extensions_together() {
+        assert_eq!(extension("archive.tar.gz"), ("tar.gz".to_string(), false));
+        assert_eq!(extension("ARCHIVE.TAR.GZ"), ("TAR.GZ".to_string(), false));
+        assert_eq!(extension("types.d.ts"), ("d.ts".to_string(), false));
+        assert_eq!(extension("backup.gz"), ("gz".to_string(), false));
+        // Nothing before the compound extension: it's the whole name
+        assert_eq!(extension("tar.gz"), ("gz".to_string(), false));
+        assert_eq!(extension("mytar.gz"), ("gz".to_string(), false));
+    }
+
+    #[test]
+    fn leading_dots_are_not_extensions() {
+        assert_eq!(extension(".bashrc"), (String::new(), false));
+        assert_eq!(extension("..hidden"), (String::new(), false));
+        assert_eq!(extension(".bashrc.bak"), ("bak".to_string(), false));
+    }
+
+    #[test]
+    fn trailing_dots_and_spaces_are_suspicious() {
+        assert_eq!(extension("file."), (String::new(), true));
+        assert_eq!(extension("file. "), (String::new(), true));
+        assert_eq!(extension("report.pdf "), ("pdf".to_string(), true));
+        assert_eq!(extension("report.pdf.."), ("pdf".to_string(), true));
+        // "." and ".." are directory entries, not hidden names
+        assert_eq!(extension("."), (String::new(), false));
+        assert_eq!(extension(".."), (String::new(), false));
+    }
+
+    #[test]
+    fn names_without_a_dot_have_no_extension() {
+        assert_eq!(extension("README"), (String::new(), false));
+        assert_eq!(extension(""), (String::new(), false));
+    }
+
+    #[test]
+    fn extensions_of_non_ascii_names() {
+        assert_eq!(extension("résumé.pdf"), ("pdf".to_string(), false));
+        assert_eq!(extension("отчёт.докс"), ("докс".to_string(), false));
+        assert_eq!(extension("архив.tar.gz"), ("tar.gz".to_string(), false));
+        // A compound extension's length mustn't split a multi-byte character
+        assert_eq!(extension("xé.ts"), ("ts".to_string(), false));
+    }
+
+    #[test]
+    fn decodes_ascii_through_the_fast_path() {
+        for name in ["", "a", "abc", "abcd", "$MFT", "a_longer_file_name.txt"] {
+            assert_eq!(decode_utf16le(&utf16le(name)).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn decodes_non_ascii_wherever_it_starts() {
+        for name in ["é", "éabcdefgh", "abcé", "abcdefghé.txt", "abcdefgh\u{7F}\u{80}", "文件.txt", "smile😀.png"] {
+            assert_eq!(decode_utf16le(&utf16le(name)).unwrap(), name);
+        }
+        // A low byte under 0x80 with a high byte set isn't ASCII
+        assert_eq!(decode_utf16le(&[b'a', 0, 0x41, 0x01]).unwrap(), "aŁ");
+    }
+
+    #[test]
+    fn drops_an_odd_trailing_byte() {
+        let mut bytes = utf16le("name");
+        bytes.push(b'x');
+        assert_eq!(decode_utf16le(&bytes).unwrap(), "name");
+    }
+
+    #[test]
+    fn rejects_unpaired_surrogates() {
+        let mut high = utf16le("abcdefgh");
+        high.extend_from_slice(&0xD800u16.to_le_bytes());
+        assert!(decode_utf16le(&high).is_err());
+
+        let mut low = 0xDC00u16.to_le_bytes().to_vec();
+        low.extend_from_slice(&utf16le("txt"));
+        assert!(decode_utf16le(&low).is_err());
+
+        // High surrogate followed by something other than a low one
+        let mut broken = 0xD83Du16.to_le_bytes().to_vec();
+        broken.extend_from_slice(&utf16le("a"));
+        assert!(decode_utf16le(&broken).is_err());
+    }
+
+    #[test]
+    fn lossy_decoding_replaces_unpaired_surrogates() {
+        let mut bytes = utf16le("ab");
+        bytes.extend_from_slice(&0xD800u16.to_le_bytes());
+        bytes.extend_from_slice(&utf16le(".txt"));
+        assert_eq!(decode_utf16le_lossy(&bytes), "ab\u{FFFD}.txt");
+    }
+}
@@ -0,0 +1,138 @@
+use super::mft::MftParser;
+use super::types::{IndexEntry, PathSource};
+use super::usn_journal::UsnRecord;
+use std::collections::HashMap;
+
+const ROOT_ENTRY: u32 = 5;
+/// Deepest parent chain followed when building a path
+const MAX_PATH_DEPTH: usize = 64;
+
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const USN_REASON_FILE_CREATE: u32 = 0x0000_0100;
+const USN_REASON_RENAME_NEW_NAME: u32 = 0x0000_2000;
+
+/// Best-effort paths for $J and $I30 entries.
+///
+/// Directories come first from a $MFT, when one is given. A parent the $MFT
+/// no longer holds, or holds under a different sequence number, falls back to
+/// directory names recovered from other artifacts: directory creates and
+/// renames seen in $J, and directory entries carved from $I30. Each resolved
+/// path carries the least certain source any of its components came from.
+#[derive(Default)]
+pub struct PathResolver {
+    /// Directories of the $MFT by entry number
+    mft: HashMap<u32, Directory>,
+    /// Directories recovered from other artifacts by entry and sequence number
+    recovered: HashMap<(u32, u16), Directory>,
+}
+
+struct Directory {
+    sequence_number: u16,
+    in_use: bool,
+    parent_entry_number: u32,
+    parent_sequence_number: Option<u16>,
+    name: String,
+    source: PathSource,
+}
+
+impl PathResolver {
+    /// A resolver knowing the directories of `mft`, or none
+    pub fn new(mft: Option<&MftParser>) -> Self {
+        let mft = mft
+            .map(|mft| {
+                mft.get_records()
+                    .iter()
+                    .filter(|record| record.is_directory && !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty())
+                    .map(|record| {
+                        let directory = Directory {
+                            sequence_number: record.sequence_number,
+                            in_use: record.in_use,
+                            parent_entry_number: record.parent_entry_number,
+                            parent_sequence_number: record.parent_sequence_number,
+                            name: record.file_name.clone(),
+                            source: PathSource::Mft,
+                        };
+                        (record.entry_number, directory)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { mft, recovered: HashMap::new() }
+    }
+
+    /// Remember the name a $J record gives a directory when it is created or
+    /// renamed, replacing any earlier name for the same entry and sequence,
+    /// the $MFT's included, so paths follow the journal as it is read.
+    /// Deleted directories keep their names, as later records may still name them.
+    pub fn learn_usn(&mut self, record: &UsnRecord) {
+        if record.file_attributes & FILE_ATTRIBUTE_DIRECTORY == 0
+            || record.reason & (USN_REASON_FILE_CREATE | USN_REASON_RENAME_NEW_NAME) == 0
+        {
+            return;
+        }
+        let directory = Directory {
+            sequence_number: record.sequence_number,
+            in_use: true,
+            parent_entry_number: record.parent_entry_number,
+            parent_sequence_number: Some(record.parent_sequence_number),
+            name: record.file_name.clone(),
+            source: PathSource::Usn,
+        };
+        match self.mft.get_mut(&record.entry_number) {
+            Some(known) if known.sequence_number == record.sequence_number => *known = directory,
+            _ => {
+                self.recovered.insert((record.entry_number, record.sequence_number), directory);
+            }
+        }
+    }
+
+    /// Remember the directories among carved $I30 entries, keeping names
+    /// already learned from $J
+    pub fn learn_i30(&mut self, entries: &[IndexEntry]) {
+        for entry in entries.iter().filter(|entry| entry.is_directory) {
+            self.recovered.entry((entry.entry_number, entry.sequence_number)).or_insert_with(|| Directory {
+                sequence_number: entry.sequence_number,
+                in_use: true,
+                parent_entry_number: entry.parent_entry_number,
+                parent_sequence_number: Some(entry.parent_sequence_number),
+                name: entry.file_name.clone(),
+                source: PathSource::I30,
+            });
+        }
+    }
+
+    /// Full path of `name` in the directory `parent_entry_number`, with the
+    /// source of its least certain component
+    pub fn resolve(&self, parent_entry_number: u32, parent_sequence_number: u16, name: &str) -> Option<(String, PathSource)> {
+        let mut names = vec![name];
+        let mut source = PathSource::Mft;
+        let mut entry = parent_entry_number;
+        let mut sequence = Some(parent_sequence_number);
+        for _ in 0..MAX_PATH_DEPTH {
+            if entry == ROOT_ENTRY {
+                names.reverse();
+                return Some((format!("/{}", names.join("/")), source));
+            }
+            let directory = self.directory(entry, sequence)?;
+            names.push(directory.name.as_str());
+            source = source.max(directory.source);
+            entry = directory.parent_entry_number;
+            sequence = directory.parent_sequence_number;
+        }
+        None
+    }
+
+    /// The directory an entry and sequence number refer to, preferring the $MFT
+    fn directory(&self, entry_number: u32, sequence_number: Option<u16>) -> Option<&Directory> {
+        let Some(sequence_number) = sequence_number else {
+            return self.mft.get(&entry_number);
+        };
+        let from_mft = self.mft.get(&entry_number).filter(|directory| {
+            // Deleting an entry bumps its sequence number by one
+            directory.sequence_number == sequence_number
+                || (!directory.in_use && directory.sequence_number == sequence_number.wrapping_add(1))
+        });
+        from_mft.or_else(|| self.recovered.get(&(entry_number, sequence_number)))
+    }
+}
@@ -0,0 +1,169 @@
+use super::names::extract_extension;
+use super::types::{RansomwareAlert, RansomwareIndicator, RansomwareRename, UsnJournalEntry};
+use std::collections::HashMap;
+
+/// Extensions appended or substituted by common ransomware families
+const KNOWN_EXTENSIONS: &[&str] = &[
+    "locked", "lock", "encrypted", "enc", "crypt", "crypted", "crypto", "cry", "cryp1", "crypz",
+    "wncry", "wnry", "wcry", "locky", "zepto", "odin", "thor", "aesir", "osiris", "cerber", "cerber3",
+    "lockbit", "ryk", "conti", "hive", "akira", "blackcat", "clop", "cl0p", "phobos", "dharma", "djvu",
+    "makop", "medusa", "royal", "basta", "crab", "krab", "gdcb", "ecc", "ezz", "exx", "micro", "vvv",
+    "ccc", "ttt", "zzzzz", "petya", "sage", "globe", "rapid", "nemty", "sodinokibi", "revil", "maze",
+    "ragnar", "darkside", "babuk", "play", "rhysida", "8base", "blackbasta", "quantum",
+];
+
+/// Renames this close together belong to the same burst
+const BURST_GAP_SECONDS: i64 = 60;
+
+/// Ransomware-like renames a burst needs before it raises an alert
+const MIN_ALERT_RENAMES: usize = 20;
+
+/// Random-looking extensions are at least this long...
+const MIN_RANDOM_EXTENSION_LENGTH: usize = 5;
+const MAX_RANDOM_EXTENSION_LENGTH: usize = 16;
+
+/// ...and carry at least this much Shannon entropy per character
+const MIN_RANDOM_EXTENSION_ENTROPY: f64 = 2.5;
+
+/// Paths kept in an alert; the CSV lists every rename
+const MAX_SAMPLE_PATHS: usize = 20;
+
+/// New extensions listed in an alert
+const MAX_LISTED_EXTENSIONS: usize = 5;
+
+const USN_REASON_FILE_DELETE: u32 = 0x0000_0200;
+const USN_REASON_RENAME_OLD_NAME: u32 = 0x0000_1000;
+const USN_REASON_RENAME_NEW_NAME: u32 = 0x0000_2000;
+
+/// Why `extension` looks like it was added by ransomware, if it does
+pub fn classify_extension(extension: &str) -> Option<RansomwareIndicator> {
+    let extension = extension.to_ascii_lowercase();
+    if KNOWN_EXTENSIONS.contains(&extension.as_str()) {
+        return Some(RansomwareIndicator::KnownExtension);
+    }
+
+    let random = (MIN_RANDOM_EXTENSION_LENGTH..=MAX_RANDOM_EXTENSION_LENGTH).contains(&extension.len())
+        && extension.bytes().all(|b| b.is_ascii_alphanumeric())
+        && extension.bytes().any(|b| b.is_ascii_digit())
+        && extension.bytes().any(|b| b.is_ascii_alphabetic())
+        && entropy(&extension) >= MIN_RANDOM_EXTENSION_ENTROPY;
+    random.then_some(RansomwareIndicator::RandomExtension)
+}
+
+/// Shannon entropy in bits per byte
+fn entropy(value: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for b in value.bytes() {
+        counts[b as usize] += 1;
+    }
+    let length = value.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Find bursts of renames to ransomware-like extensions, returning the alerts
+/// and every rename that belongs to one
+pub fn detect(entries: &[UsnJournalEntry]) -> (Vec<RansomwareAlert>, Vec<RansomwareRename>) {
+    // RENAME_OLD_NAME carries the name before the change, RENAME_NEW_NAME the one after
+    let mut old_names: HashMap<(u32, u16), &str> = HashMap::new();
+    let mut renames = Vec::new();
+
+    for entry in entries {
+        let key = (entry.entry_number, entry.sequence_number);
+        if entry.reason_flags & USN_REASON_RENAME_OLD_NAME != 0 {
+            old_names.insert(key, &entry.file_name);
+            continue;
+        }
+        if entry.reason_flags & USN_REASON_RENAME_NEW_NAME == 0 {
+            continue;
+        }
+
+        let old_name = old_names.remove(&key).unwrap_or_default();
+        let new_extension = extract_extension(&entry.file_name).extension;
+        if new_extension.is_empty() || new_extension.eq_ignore_ascii_case(&extract_extension(old_name).extension) {
+            continue;
+        }
+        let Some(indicator) = classify_extension(&new_extension) else {
+            continue;
+        };
+
+        renames.push(RansomwareRename {
+            alert: 0,
+            timestamp: entry.timestamp,
+            entry_number: entry.entry_number,
+            sequence_number: entry.sequence_number,
+            parent_entry_number: entry.parent_entry_number,
+            old_name: old_name.to_string(),
+            new_name: entry.file_name.clone(),
+            new_extension: new_extension.to_ascii_lowercase(),
+            indicator,
+            path: if entry.full_path.is_empty() { entry.file_name.clone() } else { entry.full_path.clone() },
+        });
+    }
+    renames.sort_by_key(|rename| rename.timestamp);
+
+    let mut deletions: Vec<i64> = entries
+        .iter()
+        .filter(|entry| entry.reason_flags & USN_REASON_FILE_DELETE != 0)
+        .map(|entry| entry.timestamp.timestamp())
+        .collect();
+    deletions.sort_unstable();
+    deletions.dedup();
+
+    let mut alerts = Vec::new();
+    let mut alerted = Vec::new();
+    let mut burst_start = 0;
+    for index in 1..=renames.len() {
+        let burst_ends = index == renames.len()
+            || renames[index].timestamp.timestamp() - renames[index - 1].timestamp.timestamp() > BURST_GAP_SECONDS;
+        if !burst_ends {
+            continue;
+        }
+
+        let burst = &mut renames[burst_start..index];
+        if burst.len() >= MIN_ALERT_RENAMES {
+            for rename in burst.iter_mut() {
+                rename.alert = alerts.len();
+            }
+            alerts.push(alert(burst, &deletions));
+            alerted.extend_from_slice(burst);
+        }
+        burst_start = index;
+    }
+
+    (alerts, alerted)
+}
+
+fn alert(burst: &[RansomwareRename], deletions: &[i64]) -> RansomwareAlert {
+    let (start, end) = (burst[0].timestamp, burst[burst.len() - 1].timestamp);
+
+    let deleted_files = deletions.partition_point(|&time| time <= end.timestamp())
+        - deletions.partition_point(|&time| time < start.timestamp());
+
+    let mut extensions: HashMap<&str, usize> = HashMap::new();
+    for rename in burst {
+        *extensions.entry(&rename.new_extension).or_default() += 1;
+    }
+    let mut extensions: Vec<(&str, usize)> = extensions.into_iter().collect();
+    extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    RansomwareAlert {
+        start,
+        end,
+        renamed_files: burst.len(),
+        deleted_files,
+        extensions: extensions
+            .iter()
+            .take(MAX_LISTED_EXTENSIONS)
+            .map(|(extension, count)| format!("{} ({})", extension, count))
+            .collect::<Vec<_>>()
+            .join("|"),
+        sample_paths: burst.iter().take(MAX_SAMPLE_PATHS).map(|rename| rename.path.clone()).collect(),
+    }
+}
@@ -0,0 +1,137 @@
+use super::compression::WofAlgorithm;
+use super::names::decode_utf16le;
+use super::types::{ErrorCode, ParseError, ParseResult};
+
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+pub const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000_000C;
+pub const IO_REPARSE_TAG_APPEXECLINK: u32 = 0x8000_001B;
+/// Windows Overlay Filter: a file compressed with `compact /exe`, or backed by a WIM
+pub const IO_REPARSE_TAG_WOF: u32 = 0x8000_0017;
+
+/// Size of the REPARSE_DATA_BUFFER header: tag, data length and reserved
+const HEADER_SIZE: usize = 8;
+/// Substitute and print name offsets and lengths
+const NAME_FIELDS_SIZE: usize = 8;
+/// WOF_PROVIDER_FILE, whose files keep their compressed content in a WofCompressedData stream
+const WOF_PROVIDER_FILE: u32 = 2;
+
+/// Tags worth naming in output; cloud files use the 0x9000x01A range for their
+/// per-provider subtypes
+const REPARSE_TAGS: &[(u32, &str)] = &[
+    (IO_REPARSE_TAG_MOUNT_POINT, "MOUNT_POINT"),
+    (IO_REPARSE_TAG_SYMLINK, "SYMLINK"),
+    (0xC000_0004, "HSM"),
+    (0x8000_0006, "HSM2"),
+    (0x8000_0007, "SIS"),
+    (0x8000_0008, "WIM"),
+    (0x8000_0009, "CSV"),
+    (0x8000_000A, "DFS"),
+    (0x8000_0012, "DFSR"),
+    (0x8000_0013, "DEDUP"),
+    (0x8000_0014, "NFS"),
+    (IO_REPARSE_TAG_WOF, "WOF"),
+    (0x8000_0018, "WCI"),
+    (0x8000_0019, "GLOBAL_REPARSE"),
+    (IO_REPARSE_TAG_APPEXECLINK, "APPEXECLINK"),
+    (0x8000_001E, "STORAGE_SYNC"),
+    (0x8000_0021, "ONEDRIVE"),
+    (0x8000_0023, "AF_UNIX"),
+    (0x8000_0024, "LX_FIFO"),
+    (0x8000_0025, "LX_CHR"),
+    (0x8000_0026, "LX_BLK"),
+    (0xA000_001D, "LX_SYMLINK"),
+    (0xA000_0027, "WCI_LINK"),
+    (0xC000_0014, "APPXSTRM"),
+];
+
+/// The parts of a $REPARSE_POINT attribute (0xC0) shown in output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReparsePoint {
+    pub tag: u32,
+    /// NT path the link resolves to, e.g. `\??\C:\Users\Public`; for an app
+    /// execution alias, the executable it starts
+    pub substitute_name: String,
+    /// Path as shown to users, e.g. `C:\Users\Public`
+    pub print_name: String,
+    /// How a WOF compressed file's WofCompressedData stream is compressed
+    pub wof_algorithm: Option<WofAlgorithm>,
+}
+
+/// Decode the tag and, for symbolic links, junctions, mount points and app
+/// execution aliases, the names the reparse data holds
+pub fn parse(content: &[u8]) -> ParseResult<ReparsePoint> {
+    if content.len() < HEADER_SIZE {
+        return Err(ParseError {
+            code: ErrorCode::ReparsePoint,
+            message: format!("$REPARSE_POINT too short: {} bytes", content.len()),
+            offset: None,
+        });
+    }
+    let tag = u32::from_le_bytes(content[0..4].try_into().unwrap());
+    let data_length = u16::from_le_bytes([content[4], content[5]]) as usize;
+    let data = &content[HEADER_SIZE..content.len().min(HEADER_SIZE + data_length)];
+
+    let mut reparse = ReparsePoint { tag, ..ReparsePoint::default() };
+    match tag {
+        // Symbolic links carry a flags field ahead of the path buffer that junctions lack
+        IO_REPARSE_TAG_MOUNT_POINT => read_names(data, NAME_FIELDS_SIZE, &mut reparse)?,
+        IO_REPARSE_TAG_SYMLINK => read_names(data, NAME_FIELDS_SIZE + 4, &mut reparse)?,
+        IO_REPARSE_TAG_APPEXECLINK => read_app_exec_link(data, &mut reparse),
+        IO_REPARSE_TAG_WOF => reparse.wof_algorithm = read_wof_algorithm(data),
+        _ => {}
+    }
+    Ok(reparse)
+}
+
+/// Name for a reparse tag, e.g. "SYMLINK"; empty for tags not in the table
+pub fn tag_name(tag: u32) -> &'static str {
+    if tag & 0xFFFF_0FFF == 0x9000_001A {
+        return "CLOUD";
+    }
+    REPARSE_TAGS.iter().find(|(known, _)| *known == tag).map_or("", |(_, name)| name)
+}
+
+/// Substitute and print names, given as offsets and lengths into the path
+/// buffer that starts at `buffer_start`
+fn read_names(data: &[u8], buffer_start: usize, reparse: &mut ReparsePoint) -> ParseResult<()> {
+    if data.len() < buffer_start {
+        return Err(ParseError {
+            code: ErrorCode::ReparsePoint,
+            message: format!("Reparse data too short for tag 0x{:08X}: {} bytes", reparse.tag, data.len()),
+            offset: None,
+        });
+    }
+    let field = |index: usize| u16::from_le_bytes([data[index * 2], data[index * 2 + 1]]) as usize;
+    let buffer = &data[buffer_start..];
+    let name = |offset: usize, length: usize| {
+        buffer
+            .get(offset..offset + length)
+            .map(|bytes| decode_utf16le(bytes).unwrap_or_else(|_| String::from("INVALID_NAME")))
+            .unwrap_or_default()
+    };
+    reparse.substitute_name = name(field(0), field(1));
+    reparse.print_name = name(field(2), field(3));
+    Ok(())
+}
+
+/// An app execution alias (WindowsApps\*.exe) holds a version, then NUL
+/// terminated package name, app user model ID and target executable
+fn read_app_exec_link(data: &[u8], reparse: &mut ReparsePoint) {
+    let Some(strings) = data.get(4..) else {
+        return;
+    };
+    let units: Vec<u16> = strings.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    if let Some(target) = units.split(|&unit| unit == 0).nth(2) {
+        reparse.substitute_name = String::from_utf16_lossy(target);
+    }
+}
+
+/// WOF_EXTERNAL_INFO (version, provider) followed, for the file provider, by
+/// FILE_PROVIDER_EXTERNAL_INFO_V1 (version, algorithm, flags)
+fn read_wof_algorithm(data: &[u8]) -> Option<WofAlgorithm> {
+    let field = |index: usize| data.get(index * 4..index * 4 + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+    if field(1)? != WOF_PROVIDER_FILE {
+        return None;
+    }
+    WofAlgorithm::from_id(field(3)?)
+}
@@ -0,0 +1,60 @@
+use super::types::{DataRun, ErrorCode, ParseError, ParseResult};
+
+/// Decode the mapping pairs of a non-resident attribute into cluster runs
+///
+/// Each run starts with a header byte whose low nibble is the size of the length
+/// field and high nibble the size of the signed LCN delta; a zero header ends the list.
+pub fn decode_runlist(data: &[u8], starting_vcn: u64) -> ParseResult<Vec<DataRun>> {
+    let mut runs = Vec::new();
+    let mut pos = 0usize;
+    let mut vcn = starting_vcn;
+    let mut lcn: i64 = 0;
+
+    while pos < data.len() && data[pos] != 0 {
+        let header = data[pos];
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        pos += 1;
+
+        if length_size == 0 || length_size > 8 || offset_size > 8 || pos + length_size + offset_size > data.len() {
+            return Err(ParseError {
+                code: ErrorCode::DataRun,
+                message: format!("Invalid data run header 0x{:02x}", header),
+                offset: Some(pos as u64 - 1),
+            });
+        }
+
+        let length = read_unsigned(&data[pos..pos + length_size]);
+        pos += length_size;
+
+        let run_lcn = if offset_size == 0 {
+            None // Sparse run
+        } else {
+            lcn = lcn.wrapping_add(read_signed(&data[pos..pos + offset_size]));
+            pos += offset_size;
+            if lcn < 0 {
+                return Err(ParseError {
+                    code: ErrorCode::DataRun,
+                    message: format!("Data run points before the start of the volume (LCN {})", lcn),
+                    offset: Some(pos as u64),
+                });
+            }
+            Some(lcn as u64)
+        };
+
+        runs.push(DataRun { vcn, lcn: run_lcn, length });
+        vcn = vcn.saturating_add(length);
+    }
+
+    Ok(runs)
+}
+
+fn read_unsigned(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn read_signed(bytes: &[u8]) -> i64 {
+    let value = read_unsigned(bytes);
+    let shift = 64 - bytes.len() * 8;
+    ((value << shift) as i64) >> shift
+}
@@ -1,10 +1,29 @@
-use super::types::{SecurityDescriptor, ParseError, ParseResult};
+use super::deadline::Deadline;
+use super::types::{SecurityDescriptor, ErrorCode, ParseError, ParseResult};
+use crate::artifact::{InputInfo, LIKELY_MATCH, NO_MATCH};
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Cursor, Read};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+const SDS_HEADER_SIZE: usize = 20;
+
+/// Self-relative SECURITY_DESCRIPTOR header: revision, control and the four offsets
+const SECURITY_DESCRIPTOR_HEADER_SIZE: usize = 20;
+
+/// $SDS is written in 256 KiB blocks, each immediately followed by a mirror copy
+const SDS_BLOCK_SIZE: u64 = 0x40000;
+
+/// Entries start on 16-byte boundaries
+const SDS_ALIGNMENT: u64 = 16;
 
 pub struct SdsParser {
     data: Vec<u8>,
     descriptors: Vec<SecurityDescriptor>,
+    error_count: usize,
+    mirror_mismatches: usize,
+    hash_mismatches: usize,
+    deadline: Deadline,
+    timed_out: bool,
 }
 
 impl SdsParser {
@@ -12,72 +31,315 @@ impl SdsParser {
         Self {
             data,
             descriptors: Vec::new(),
+            error_count: 0,
+            mirror_mismatches: 0,
+            hash_mismatches: 0,
+            deadline: Deadline::none(),
+            timed_out: false,
         }
     }
 
+    /// Stop reading descriptors once `deadline` passes, keeping those parsed so far
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
     pub fn parse(&mut self) -> ParseResult<()> {
-        let mut cursor = Cursor::new(&self.data);
-
-        while (cursor.position() as usize) < self.data.len() {
-            match self.parse_descriptor(&mut cursor) {
-                Ok(Some(descriptor)) => self.descriptors.push(descriptor),
-                Ok(None) => break, // End of valid descriptors
-                Err(e) => {
-                    log::warn!("Failed to parse SDS descriptor at offset 0x{:x}: {}", cursor.position(), e);
-                    break;
+        let data_len = self.data.len() as u64;
+        let mut seen_ids = HashSet::new();
+        let mut block_start = 0;
+
+        'blocks: while block_start < data_len {
+            let block_end = (block_start + SDS_BLOCK_SIZE).min(data_len);
+            let mut position = block_start;
+
+            while position + SDS_HEADER_SIZE as u64 <= block_end {
+                if self.deadline.expired() {
+                    log::warn!("Timed out at offset 0x{:x}; keeping {} security descriptors parsed so far", position, self.descriptors.len());
+                    self.timed_out = true;
+                    break 'blocks;
+                }
+
+                match self.parse_descriptor(position, block_end) {
+                    Ok(Some(descriptor)) => {
+                        position = (position + descriptor.length as u64).next_multiple_of(SDS_ALIGNMENT);
+                        self.check_mirror(&descriptor);
+                        if !descriptor.hash_valid {
+                            log::debug!("Security id {} hash 0x{:08x} does not match its descriptor", descriptor.id, descriptor.hash);
+                            self.hash_mismatches += 1;
+                        }
+                        if seen_ids.insert(descriptor.id) {
+                            self.descriptors.push(descriptor);
+                        } else {
+                            log::debug!("Ignoring duplicate security id {} at offset 0x{:x}", descriptor.id, descriptor.offset);
+                        }
+                    }
+                    Ok(None) => position += SDS_ALIGNMENT, // Padding between or after entries
+                    Err(e) => {
+                        log::debug!("Skipping invalid SDS entry: {}", e);
+                        self.error_count += 1;
+                        position += SDS_ALIGNMENT;
+                    }
                 }
             }
+
+            // Skip the mirror copy that follows every block
+            block_start += 2 * SDS_BLOCK_SIZE;
         }
 
+        if self.error_count > 0 {
+            log::warn!("Skipped {} invalid SDS entries", self.error_count);
+        }
+        if self.hash_mismatches > 0 {
+            log::warn!("{} SDS entries have a hash that does not match their descriptor", self.hash_mismatches);
+        }
+        if self.mirror_mismatches > 0 {
+            log::warn!("{} SDS entries differ from their mirror copy", self.mirror_mismatches);
+        }
         log::info!("Parsed {} security descriptors", self.descriptors.len());
         Ok(())
     }
 
-    fn parse_descriptor(&self, cursor: &mut Cursor<&Vec<u8>>) -> ParseResult<Option<SecurityDescriptor>> {
-        let start_pos = cursor.position();
-
-        if start_pos + 20 > self.data.len() as u64 {
-            return Ok(None); // Not enough data for SDS header
+    /// Parse the entry at `position`; Ok(None) means zero padding rather than an entry
+    fn parse_descriptor(&self, position: u64, block_end: u64) -> ParseResult<Option<SecurityDescriptor>> {
+        let header = &self.data[position as usize..position as usize + SDS_HEADER_SIZE];
+        if header.iter().all(|&b| b == 0) {
+            return Ok(None);
         }
 
-        let hash = cursor.read_u32::<LittleEndian>()
-            .map_err(|_| ParseError {
-                message: "Failed to read SDS hash".to_string(),
-                offset: Some(start_pos),
-            })?;
-
+        let mut cursor = Cursor::new(header);
+        let hash = cursor.read_u32::<LittleEndian>().unwrap();
         let id = cursor.read_u32::<LittleEndian>().unwrap();
-        let offset = cursor.read_u64::<LittleEndian>().unwrap();
+        let embedded_offset = cursor.read_u64::<LittleEndian>().unwrap();
         let length = cursor.read_u32::<LittleEndian>().unwrap();
 
-        if length == 0 || length > 0x10000 { // Sanity check
-            return Ok(None);
+        // Every entry records its own position in the stream
+        if embedded_offset != position {
+            return Err(ParseError {
+                code: ErrorCode::SecurityDescriptor,
+                message: format!("Embedded offset 0x{:x} does not match position", embedded_offset),
+                offset: Some(position),
+            });
         }
 
-        // Read the security descriptor data
-        let mut descriptor_data = vec![0u8; length as usize];
-        cursor.read_exact(&mut descriptor_data)
-            .map_err(|_| ParseError {
-                message: "Failed to read security descriptor data".to_string(),
-                offset: Some(start_pos),
-            })?;
+        if length < SDS_HEADER_SIZE as u32 || position + length as u64 > block_end {
+            return Err(ParseError {
+                code: ErrorCode::SecurityDescriptor,
+                message: format!("Entry length {} runs outside its block", length),
+                offset: Some(position),
+            });
+        }
 
-        let descriptor = SecurityDescriptor {
+        if (length as usize) < SDS_HEADER_SIZE + SECURITY_DESCRIPTOR_HEADER_SIZE {
+            return Err(ParseError {
+                code: ErrorCode::SecurityDescriptor,
+                message: format!("Entry length {} leaves no room for a security descriptor", length),
+                offset: Some(position),
+            });
+        }
+
+        // The descriptor follows the header; length includes the header
+        let start = position as usize + SDS_HEADER_SIZE;
+        let end = position as usize + length as usize;
+        let descriptor = self.data[start..end].to_vec();
+
+        // Owner, group, SACL and DACL offsets are relative to the descriptor and must stay inside it
+        for field in (4..SECURITY_DESCRIPTOR_HEADER_SIZE).step_by(4) {
+            let offset = u32::from_le_bytes(descriptor[field..field + 4].try_into().unwrap()) as usize;
+            if offset >= descriptor.len() {
+                return Err(ParseError {
+                    code: ErrorCode::SecurityDescriptor,
+                    message: format!("Security descriptor offset 0x{:x} runs past its {} bytes", offset, descriptor.len()),
+                    offset: Some(position + SDS_HEADER_SIZE as u64 + field as u64),
+                });
+            }
+        }
+
+        Ok(Some(SecurityDescriptor {
             id,
             hash,
-            offset: start_pos,
+            hash_valid: descriptor_hash(&descriptor) == hash,
+            offset: position,
             length,
-            descriptor: descriptor_data,
-        };
+            descriptor,
+        }))
+    }
+
+    /// Compare an entry with its mirror copy, when the mirror is present
+    fn check_mirror(&mut self, descriptor: &SecurityDescriptor) {
+        let start = (descriptor.offset + SDS_BLOCK_SIZE) as usize;
+        let end = start + descriptor.length as usize;
+        let primary = &self.data[descriptor.offset as usize..descriptor.offset as usize + descriptor.length as usize];
 
-        Ok(Some(descriptor))
+        if let Some(mirror) = self.data.get(start..end)
+            && mirror != primary {
+            log::debug!("Security id {} differs from its mirror at 0x{:x}", descriptor.id, start);
+            self.mirror_mismatches += 1;
+        }
     }
 
     pub fn get_descriptors(&self) -> &[SecurityDescriptor] {
         &self.descriptors
     }
 
+    /// Number of invalid entries skipped while parsing
+    pub fn get_error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// True when parsing stopped early at the deadline
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Number of entries whose mirror copy differs from the primary
+    pub fn get_mirror_mismatches(&self) -> usize {
+        self.mirror_mismatches
+    }
+
+    /// Number of entries whose stored hash does not match their descriptor
+    pub fn get_hash_mismatches(&self) -> usize {
+        self.hash_mismatches
+    }
+
     pub fn find_by_id(&self, id: u32) -> Option<&SecurityDescriptor> {
         self.descriptors.iter().find(|desc| desc.id == id)
     }
-}
\ No newline at end of file
+}
+
+/// $SDS has no signature: the first entry records offset 0 and ids start at 0x100
+pub fn detect(input: &InputInfo) -> u8 {
+    let header = &input.header;
+    if header.len() < SDS_HEADER_SIZE {
+        return NO_MATCH;
+    }
+
+    let id = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let embedded_offset = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let length = u32::from_le_bytes(header[16..20].try_into().unwrap());
+    if embedded_offset == 0 && id >= 0x100 && (40..0x10000).contains(&length) {
+        LIKELY_MATCH
+    } else {
+        NO_MATCH
+    }
+}
+
+/// NTFS security descriptor hash: each little-endian dword is added to the running hash rotated left by 3
+pub fn descriptor_hash(descriptor: &[u8]) -> u32 {
+    descriptor
+        .chunks_exact(4)
+        .fold(0u32, |hash, dword| {
+            u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]]).wrapping_add(hash.rotate_left(3))
+        })
+}
+
+/// Build a lookup of security id to owner SID string for every parsed descriptor
+pub fn owner_sid_map(descriptors: &[SecurityDescriptor]) -> HashMap<u32, String> {
+    descriptors
+        .iter()
+        .filter_map(|desc| owner_sid(&desc.descriptor).map(|sid| (desc.id, sid)))
+        .collect()
+}
+
+/// Extract the owner SID from a self-relative security descriptor
+pub fn owner_sid(descriptor: &[u8]) -> Option<String> {
+    if descriptor.len() < 20 {
+        return None;
+    }
+
+    // Self-relative layout: revision, sbz1, control, then owner/group/SACL/DACL offsets
+    let owner_offset = u32::from_le_bytes(descriptor[4..8].try_into().ok()?) as usize;
+    if owner_offset == 0 || owner_offset >= descriptor.len() {
+        return None;
+    }
+
+    format_sid(&descriptor[owner_offset..])
+}
+
+/// Format a binary SID as its string form (e.g. S-1-5-21-...-1001)
+pub fn format_sid(data: &[u8]) -> Option<String> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let revision = data[0];
+    let sub_authority_count = data[1] as usize;
+    if data.len() < 8 + sub_authority_count * 4 {
+        return None;
+    }
+
+    // Identifier authority is a 48-bit big-endian value
+    let authority = data[2..8]
+        .iter()
+        .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+
+    let mut sid = format!("S-{}-{}", revision, authority);
+    for chunk in data[8..8 + sub_authority_count * 4].chunks_exact(4) {
+        let sub_authority = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        sid.push_str(&format!("-{}", sub_authority));
+    }
+
+    Some(sid)
+}
+
+/// Relative identifier of a SID string: its last sub-authority (1001 in S-1-5-21-...-1001)
+pub fn sid_rid(sid: &str) -> Option<u32> {
+    let (prefix, rid) = sid.rsplit_once('-')?;
+    // S-1-0 style SIDs without sub-authorities have no RID
+    if prefix.matches('-').count() < 2 {
+        return None;
+    }
+    rid.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An $SDS entry at offset 0 claiming `length`, padded to `size` bytes
+    fn sds_entry(length: u32, size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[4..8].copy_from_slice(&0x100u32.to_le_bytes());
+        data[16..20].copy_from_slice(&length.to_le_bytes());
+        data[20] = 1; // Descriptor revision
+        data
+    }
+
+    #[test]
+    fn rejects_length_below_header() {
+        let parser = SdsParser::new(sds_entry(0x10, 0x40));
+        let error = parser.parse_descriptor(0, 0x40).unwrap_err();
+        assert_eq!(error.code, ErrorCode::SecurityDescriptor);
+    }
+
+    #[test]
+    fn rejects_length_past_block() {
+        let parser = SdsParser::new(sds_entry(0x80, 0x40));
+        assert!(parser.parse_descriptor(0, 0x40).is_err());
+    }
+
+    #[test]
+    fn rejects_descriptor_offset_past_entry() {
+        let mut data = sds_entry(0x28, 0x40);
+        data[24..28].copy_from_slice(&0x100u32.to_le_bytes()); // Owner
+        let parser = SdsParser::new(data);
+        assert!(parser.parse_descriptor(0, 0x40).is_err());
+    }
+
+    #[test]
+    fn skips_invalid_entries_without_stalling() {
+        let mut parser = SdsParser::new(sds_entry(0x10, 0x40));
+        parser.parse().unwrap();
+        assert!(parser.get_descriptors().is_empty());
+        assert!(parser.get_error_count() > 0);
+    }
+
+    #[test]
+    fn accepts_minimal_descriptor() {
+        let mut parser = SdsParser::new(sds_entry(0x28, 0x40));
+        parser.parse().unwrap();
+        assert_eq!(parser.get_descriptors().len(), 1);
+        assert_eq!(parser.get_error_count(), 0);
+    }
+}
@@ -0,0 +1,168 @@
+use super::sds::owner_sid;
+use super::types::{DescriptorChange, MftRecord, PermissionDrift, SecurityDescriptor, SecurityDescriptorDiff};
+use std::collections::HashMap;
+
+/// Self-relative descriptor header flags (SE_DACL_PRESENT, SE_SELF_RELATIVE, ...)
+const CONTROL_FIELD: usize = 2;
+
+/// Self-relative descriptor fields holding the offset of each part
+const OWNER_OFFSET_FIELD: usize = 4;
+const GROUP_OFFSET_FIELD: usize = 8;
+const SACL_OFFSET_FIELD: usize = 12;
+const DACL_OFFSET_FIELD: usize = 16;
+
+/// Parts compared between two descriptors, in the order they are reported
+const PARTS: &[(&str, usize, Part)] = &[
+    ("owner", OWNER_OFFSET_FIELD, Part::Sid),
+    ("group", GROUP_OFFSET_FIELD, Part::Sid),
+    ("dacl", DACL_OFFSET_FIELD, Part::Acl),
+    ("sacl", SACL_OFFSET_FIELD, Part::Acl),
+];
+
+#[derive(Clone, Copy)]
+enum Part {
+    Sid,
+    Acl,
+}
+
+/// Descriptors added, removed or rewritten between a baseline $SDS and a later one, by security ID
+pub fn diff_descriptors(old: &[SecurityDescriptor], new: &[SecurityDescriptor]) -> Vec<SecurityDescriptorDiff> {
+    let old_by_id = by_id(old);
+    let new_by_id = by_id(new);
+    let mut diffs = Vec::new();
+
+    for (&id, old_descriptor) in &old_by_id {
+        match new_by_id.get(&id) {
+            None => diffs.push(diff(id, DescriptorChange::Removed, Some(old_descriptor), None)),
+            Some(new_descriptor) if new_descriptor.descriptor != old_descriptor.descriptor => {
+                diffs.push(diff(id, DescriptorChange::Changed, Some(old_descriptor), Some(new_descriptor)));
+            }
+            Some(_) => {}
+        }
+    }
+    for (&id, new_descriptor) in &new_by_id {
+        if !old_by_id.contains_key(&id) {
+            diffs.push(diff(id, DescriptorChange::Added, None, Some(new_descriptor)));
+        }
+    }
+
+    diffs.sort_by_key(|diff| diff.security_id);
+    diffs
+}
+
+/// Files whose effective descriptor differs from the baseline.
+///
+/// With a baseline $MFT, a file present in both (same entry and sequence
+/// number) is compared through whichever security IDs it used at each point.
+/// Without one, files using a security ID that is new or rewritten in the
+/// later $SDS are reported.
+pub fn permission_drift(
+    old_sds: &[SecurityDescriptor],
+    new_sds: &[SecurityDescriptor],
+    old_records: Option<&[MftRecord]>,
+    new_records: &[MftRecord],
+) -> Vec<PermissionDrift> {
+    let old_by_id = by_id(old_sds);
+    let new_by_id = by_id(new_sds);
+    let old_ids: Option<HashMap<(u32, u16), u32>> = old_records.map(|records| {
+        files(records)
+            .map(|record| ((record.entry_number, record.sequence_number), record.security_id as u32))
+            .collect()
+    });
+
+    let mut drift = Vec::new();
+    for record in files(new_records) {
+        let new_id = record.security_id as u32;
+        let old_id = match old_ids {
+            Some(ref old_ids) => match old_ids.get(&(record.entry_number, record.sequence_number)) {
+                Some(&old_id) => Some(old_id),
+                // Created after the baseline; nothing to drift from
+                None => continue,
+            },
+            None => None,
+        };
+
+        let old_descriptor = old_by_id.get(&old_id.unwrap_or(new_id)).map(|descriptor| &descriptor.descriptor[..]);
+        let new_descriptor = new_by_id.get(&new_id).map(|descriptor| &descriptor.descriptor[..]);
+        let changed_parts = match (old_descriptor, new_descriptor) {
+            (Some(old), Some(new)) if old == new => continue,
+            (Some(old), Some(new)) => changed_parts(old, new),
+            (None, Some(_)) => "new".to_string(),
+            // Neither $SDS describes the file's security ID, or the later one lost it
+            (_, None) => continue,
+        };
+
+        drift.push(PermissionDrift {
+            entry_number: record.entry_number,
+            sequence_number: record.sequence_number,
+            full_path: record.full_path.clone(),
+            old_security_id: old_id,
+            new_security_id: new_id,
+            old_owner: old_descriptor.and_then(owner_sid).unwrap_or_default(),
+            new_owner: new_descriptor.and_then(owner_sid).unwrap_or_default(),
+            changed_parts,
+        });
+    }
+    drift
+}
+
+fn by_id(descriptors: &[SecurityDescriptor]) -> HashMap<u32, &SecurityDescriptor> {
+    descriptors.iter().map(|descriptor| (descriptor.id, descriptor)).collect()
+}
+
+/// In-use base records, the ones a security ID applies to
+fn files(records: &[MftRecord]) -> impl Iterator<Item = &MftRecord> {
+    records
+        .iter()
+        .filter(|record| record.in_use && !record.is_ads && !record.is_hard_link && record.base_record_reference.is_empty())
+}
+
+fn diff(
+    security_id: u32,
+    change: DescriptorChange,
+    old: Option<&SecurityDescriptor>,
+    new: Option<&SecurityDescriptor>,
+) -> SecurityDescriptorDiff {
+    SecurityDescriptorDiff {
+        security_id,
+        change,
+        old_owner: old.and_then(|descriptor| owner_sid(&descriptor.descriptor)).unwrap_or_default(),
+        new_owner: new.and_then(|descriptor| owner_sid(&descriptor.descriptor)).unwrap_or_default(),
+        changed_parts: match (old, new) {
+            (Some(old), Some(new)) => changed_parts(&old.descriptor, &new.descriptor),
+            _ => String::new(),
+        },
+        old_hash: old.map(|descriptor| descriptor.hash),
+        new_hash: new.map(|descriptor| descriptor.hash),
+    }
+}
+
+/// Names of the descriptor parts that differ, '|' separated; "control" when only
+/// the header flags do, "other" when only padding or unreferenced bytes do
+fn changed_parts(old: &[u8], new: &[u8]) -> String {
+    let parts: Vec<&str> = PARTS
+        .iter()
+        .filter(|(_, field, kind)| part(old, *field, *kind) != part(new, *field, *kind))
+        .map(|(name, _, _)| *name)
+        .collect();
+    if parts.is_empty() {
+        let control = old.get(CONTROL_FIELD..CONTROL_FIELD + 2) != new.get(CONTROL_FIELD..CONTROL_FIELD + 2);
+        return if control { "control" } else { "other" }.to_string();
+    }
+    parts.join("|")
+}
+
+/// Raw bytes of one SID or ACL of a self-relative descriptor; None when absent or out of bounds
+fn part(descriptor: &[u8], field: usize, kind: Part) -> Option<&[u8]> {
+    let offset = u32::from_le_bytes(descriptor.get(field..field + 4)?.try_into().ok()?) as usize;
+    if offset == 0 {
+        return None;
+    }
+    let length = match kind {
+        // Revision, sub-authority count, 6-byte authority, then 4 bytes per sub-authority
+        Part::Sid => 8 + 4 * *descriptor.get(offset + 1)? as usize,
+        // ACL header: revision, padding, then the total ACL size
+        Part::Acl => u16::from_le_bytes(descriptor.get(offset + 2..offset + 4)?.try_into().ok()?) as usize,
+    };
+    descriptor.get(offset..offset + length)
+}
@@ -1,4 +1,5 @@
-use chrono::{DateTime, Utc};
+use super::category::FileCategory;
+use super::filetime::{FileTime, MapTimes};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -10,6 +11,7 @@ pub enum FileType {
     Boot = 3,
     Sds = 4,
     I30 = 5,
+    Volume = 6,
     Unknown = 99,
 }
 
@@ -22,11 +24,88 @@ impl fmt::Display for FileType {
             FileType::Boot => write!(f, "Boot"),
             FileType::Sds => write!(f, "SDS"),
             FileType::I30 => write!(f, "I30"),
+            FileType::Volume => write!(f, "Volume"),
             FileType::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
+/// How much a reconstructed path can be trusted, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathConfidence {
+    /// Every parent in the chain matched its expected sequence number
+    Exact,
+    /// A parent in the chain is deleted but still matches the referenced entry
+    ParentDeleted,
+    /// A parent entry has been reused by a different file since the reference was made
+    SequenceMismatch,
+    /// The chain could not be followed back to the root
+    Unknown,
+}
+
+/// Artifact a reconstructed $J or $I30 path came from, ordered from most to least certain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathSource {
+    /// Every directory in the path is in the $MFT
+    Mft,
+    /// A directory was named by a create or rename in $J
+    Usn,
+    /// A directory was named by an entry carved from $I30
+    I30,
+}
+
+/// $FILE_NAME namespace, decoded from the name type byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameNamespace {
+    /// Case-sensitive name allowing any character but '/' and NUL
+    #[serde(rename = "POSIX")]
+    Posix,
+    /// Long Windows name, paired with a separate DOS name
+    Win32,
+    /// 8.3 short name, paired with a separate Win32 name
+    #[serde(rename = "DOS")]
+    Dos,
+    /// Name that is valid in both namespaces, so no separate short name exists
+    #[serde(rename = "Win32&DOS")]
+    Win32AndDos,
+    /// Name type outside the four NTFS defines
+    Unknown,
+}
+
+impl From<u8> for NameNamespace {
+    fn from(name_type: u8) -> Self {
+        match name_type {
+            0 => NameNamespace::Posix,
+            1 => NameNamespace::Win32,
+            2 => NameNamespace::Dos,
+            3 => NameNamespace::Win32AndDos,
+            _ => NameNamespace::Unknown,
+        }
+    }
+}
+
+/// How likely the content of a file is to be recoverable, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Recoverability {
+    /// The record is in use; nothing to recover
+    NotDeleted,
+    /// The data is resident in the FILE record and fully recoverable from the $MFT
+    Resident,
+    /// Every data cluster is unallocated, so the content is likely intact
+    Recoverable,
+    /// Some data clusters have been allocated again
+    Partial,
+    /// Every data cluster has been allocated again
+    Overwritten,
+    /// Non-resident data whose clusters could not be checked without a $Bitmap
+    Unverified,
+    /// No unnamed $DATA content (directories and empty files)
+    NoData,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MftRecord {
     pub entry_number: u32,
@@ -34,49 +113,300 @@ pub struct MftRecord {
     pub parent_entry_number: u32,
     pub parent_sequence_number: Option<u16>,
     pub in_use: bool,
+    /// For deleted records, whether the file content can still be recovered
+    pub recoverability: Recoverability,
     pub parent_path: String,
     pub file_name: String,
+    pub full_path: String,
+    pub path_confidence: PathConfidence,
     pub extension: String,
+    pub suspicious_name: bool,
+    pub category: FileCategory,
     pub is_directory: bool,
     pub has_ads: bool,
     pub is_ads: bool,
+    /// Row for another hard link of the file (one of its other $FILE_NAME attributes), added with --hl
+    pub is_hard_link: bool,
+    /// Names of the alternate data streams (named $DATA attributes), separated by '|'
+    pub ads_names: String,
+    /// Names of the indexes held by the record, e.g. "$I30" for directories or "$SDH|$SII" for $Secure
+    pub index_names: String,
     pub file_size: u64,
-    pub created_0x10: Option<DateTime<Utc>>,
-    pub created_0x30: Option<DateTime<Utc>>,
-    pub last_modified_0x10: Option<DateTime<Utc>>,
-    pub last_modified_0x30: Option<DateTime<Utc>>,
-    pub last_record_change_0x10: Option<DateTime<Utc>>,
-    pub last_record_change_0x30: Option<DateTime<Utc>>,
-    pub last_access_0x10: Option<DateTime<Utc>>,
-    pub last_access_0x30: Option<DateTime<Utc>>,
+    /// Bytes allocated to the stream on disk, from its $DATA header, else from $FILE_NAME.
+    /// Far above `file_size` means slack worth carving
+    pub allocated_size: u64,
+    /// Bytes of a non-resident stream written so far; below `file_size` the
+    /// rest reads as zeros, as left by a truncated or preallocated file
+    pub initialized_size: Option<u64>,
+    pub created_0x10: Option<FileTime>,
+    pub created_0x30: Option<FileTime>,
+    pub last_modified_0x10: Option<FileTime>,
+    pub last_modified_0x30: Option<FileTime>,
+    pub last_record_change_0x10: Option<FileTime>,
+    pub last_record_change_0x30: Option<FileTime>,
+    pub last_access_0x10: Option<FileTime>,
+    pub last_access_0x30: Option<FileTime>,
+    /// Timestamps past year 9999 or the time of the run, as "name=0xRAW invalid|future" separated by '|'
+    pub timestamp_anomalies: String,
+    /// Sum of the weights of the timestomping heuristics the record trips; 0 when it trips none
+    pub anomaly_score: u32,
+    /// Heuristics behind `anomaly_score`, e.g. "si_created_before_fn|si_zero_fraction"
+    pub anomaly_indicators: String,
+    /// $J USN of the file's last change, from $STANDARD_INFORMATION; 0 when the journal is off
     pub update_sequence_number: i64,
+    /// $LogFile sequence number of the record's last change, from the FILE record header
     pub logfile_sequence_number: i64,
+    /// Key of the file's security descriptor in $Secure:$SDS, from $STANDARD_INFORMATION
     pub security_id: i32,
+    /// Text of the file's resident Zone.Identifier stream (Mark-of-the-Web)
     pub zone_id_contents: String,
+    /// ZoneId from Zone.Identifier: 0 local machine, 1 intranet, 2 trusted, 3 internet, 4 restricted
+    pub zone_id: Option<u32>,
+    /// Page the file was downloaded from, per Zone.Identifier
+    pub referrer_url: String,
+    /// URL the file itself was downloaded from, per Zone.Identifier
+    pub host_url: String,
     pub si_flags: u32,
+    /// `si_flags` as attribute names, e.g. "Hidden | System | Archive"
+    pub si_flag_names: String,
+    /// File attribute flags of the $FILE_NAME the record is shown under
+    pub fn_flags: u32,
+    /// `fn_flags` as attribute names
+    pub fn_flag_names: String,
+    /// $DATA is NTFS (LZNT1) compressed, or $STANDARD_INFORMATION marks the file compressed
+    pub is_compressed: bool,
+    /// $DATA is sparse, or $STANDARD_INFORMATION marks the file sparse
+    pub is_sparse: bool,
+    /// $DATA is EFS encrypted, $STANDARD_INFORMATION marks the file encrypted, or it has a $EFS stream
+    pub is_encrypted: bool,
     pub object_id_file_droid: String,
+    /// Substitute name of a symbolic link, junction or mount point, or an app execution alias's executable
     pub reparse_target: String,
+    /// Print name of a symbolic link, junction or mount point
+    pub reparse_print_name: String,
+    /// Raw reparse tag from $REPARSE_POINT; 0 when the record has none
+    pub reparse_tag: u32,
+    /// Name of the reparse tag (SYMLINK, MOUNT_POINT, WOF, CLOUD, ...); empty for none or an unknown tag
+    pub reparse_type: String,
+    /// Extended attributes from $EA as "name:size" separated by '|', e.g. "$LXUID:4|$LXGID:4|$LXMOD:4" for a file created under WSL
+    pub extended_attributes: String,
+    /// Size of the $EA attribute according to $EA_INFORMATION; 0 when the record has none
+    pub ea_size: u32,
     pub reference_count: i32,
+    /// Base record as "entry-sequence" when this is an extension record, empty otherwise.
+    /// An extension record's row only holds attributes that overflowed its base
+    /// record; they are merged into the base record's row, which it then follows
+    pub base_record_reference: String,
+    /// Extension records merged into this base record, as "entry-sequence" separated by '|'
+    pub extension_records: String,
     pub name_type: u8,
+    /// Namespace of `file_name`; None when the record has no $FILE_NAME
+    pub name_namespace: Option<NameNamespace>,
+    /// 8.3 alias of `file_name` from its DOS $FILE_NAME, with --sn; empty otherwise
+    pub short_name: String,
+    /// $LOGGED_UTILITY_STREAM attributes as "name:size" separated by '|', e.g. "$EFS:584" for an
+    /// encrypted file or "$TXF_DATA:56" for one changed in an NTFS transaction
     pub logged_util_stream: String,
+    /// Users who can decrypt an EFS encrypted file, as "sid:certificate thumbprint:user name" separated by '|'
+    pub efs_users: String,
+    /// EFS recovery agents who can decrypt the file, in the same form as `efs_users`
+    pub efs_recovery_agents: String,
+    /// The update sequence (fixup) check failed, so at least one sector of the record is torn or corrupt
+    pub fixup_error: bool,
+    /// Catalogue code of the error the record was read despite, e.g. MFTE-E014 for a failed fixup
+    pub error_code: Option<ErrorCode>,
+    /// The header holds no record number (pre-NTFS 3.1) or one with its high 16
+    /// bits set, so `entry_number` is the record's position in the $MFT
+    pub entry_number_from_offset: bool,
+}
+
+impl MapTimes for MftRecord {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        for time in [
+            &mut self.created_0x10,
+            &mut self.created_0x30,
+            &mut self.last_modified_0x10,
+            &mut self.last_modified_0x30,
+            &mut self.last_record_change_0x10,
+            &mut self.last_record_change_0x30,
+            &mut self.last_access_0x10,
+            &mut self.last_access_0x30,
+        ] {
+            *time = time.map(&map);
+        }
+    }
+}
+
+/// A run of consecutive FILE records whose header record number differs from
+/// their position in the $MFT by the same amount
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordNumberShift {
+    /// Positional entry number (offset / record size) of the first and last record
+    pub first_position: u32,
+    pub last_position: u32,
+    pub record_count: usize,
+    /// Header record number minus positional entry number
+    pub shift: i64,
+}
+
+/// A position in the $MFT whose record could not be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorruptRecord {
+    pub position: u32,
+    /// Byte offset of the record in the input
+    pub offset: u64,
+    /// NTFS itself marked the record bad with a BAAD signature
+    pub baad: bool,
+    /// Catalogue code of the failure: MFTE-E015 for BAAD records, else the parse error's
+    pub code: ErrorCode,
+    pub reason: String,
+}
+
+/// A run of consecutive $MFT positions holding no FILE record below the
+/// highest one that does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MftGap {
+    pub first_position: u32,
+    pub last_position: u32,
+    pub record_count: usize,
+    /// True when every byte of the run is zero, as NTFS leaves space it never
+    /// wrote a record to; false when something else overwrote the records
+    pub zeroed: bool,
+}
+
+/// How much of the $MFT has ever held records, from its FILE signatures
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MftLayout {
+    /// Whole FILE records the input holds
+    pub record_count: usize,
+    /// Highest position holding a FILE record; every entry ever used lies at
+    /// or below it, so one more is the most files the volume has held at once
+    pub high_water_mark: Option<u32>,
+    /// Zeroed positions after the high-water mark, never written to
+    pub never_used_records: usize,
+    pub gaps: Vec<MftGap>,
+    /// Size of the $MFT according to the $DATA attribute of its own record 0
+    pub expected_size: Option<u64>,
+    pub actual_size: u64,
+    /// The input is smaller than `expected_size` or ends part-way through a record
+    pub truncated: bool,
+}
+
+/// An $MFT entry whose FILE record and the $MFT's own $BITMAP disagree on
+/// whether it is allocated, as NTFS always updates both together
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MftBitmapMismatch {
+    pub entry_number: u32,
+    /// Sequence number from the record header; 0 when there is no FILE record
+    pub sequence_number: u16,
+    /// The record header's in-use flag
+    pub record_in_use: bool,
+    /// The entry's bit in the $MFT's $BITMAP
+    pub bitmap_allocated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MftStatistics {
+    pub total_records: usize,
+    pub active_files: usize,
+    pub active_directories: usize,
+    pub deleted_files: usize,
+    pub deleted_directories: usize,
+    pub ads_streams: usize,
+    pub orphans: usize,
+    pub extension_records: usize,
+    /// Records carrying each alternate data stream name, most common first
+    pub ads_names: Vec<AdsNameCount>,
+}
+
+/// How many records carry an alternate data stream of a given name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdsNameCount {
+    pub name: String,
+    pub count: usize,
+    /// Not a stream name Windows or common software is known to write
+    pub unusual: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsnJournalEntry {
-    pub offset: u64,
-    pub timestamp: DateTime<Utc>,
+    /// Absolute byte offset of the record within $J
+    pub source_offset: u64,
+    pub record_length: u32,
+    pub timestamp: FileTime,
+    /// "timestamp=0xRAW invalid|future" when the timestamp is out of range
+    pub timestamp_anomalies: String,
     pub entry_number: u32,
     pub sequence_number: u16,
     pub parent_entry_number: u32,
     pub parent_sequence_number: u16,
     pub file_name: String,
     pub full_path: String,
+    /// Where `full_path` came from; empty when the parent directory is unknown
+    pub path_source: Option<PathSource>,
     pub extension: String,
+    pub suspicious_name: bool,
+    pub category: FileCategory,
     pub reason: String,
+    /// USN_REASON_* bits behind `reason`
+    #[serde(skip)]
+    pub reason_flags: u32,
     pub file_attributes: u32,
     pub usn: u64,
 }
 
+impl MapTimes for UsnJournalEntry {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.timestamp = map(self.timestamp);
+    }
+}
+
+/// USN Journal entry as written by --raw: the on-disk integers, without
+/// timestamp conversion, extension lookup or reason names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawUsnJournalEntry {
+    pub source_offset: u64,
+    pub record_length: u32,
+    /// FILETIME: 100-nanosecond intervals since 1601-01-01 UTC
+    pub timestamp: u64,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub parent_entry_number: u32,
+    pub parent_sequence_number: u16,
+    pub file_name: String,
+    pub full_path: String,
+    pub path_source: Option<PathSource>,
+    pub reason: u32,
+    pub file_attributes: u32,
+    pub usn: u64,
+}
+
+impl MapTimes for RawUsnJournalEntry {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.timestamp = map(FileTime(self.timestamp)).raw();
+    }
+}
+
+impl From<&UsnJournalEntry> for RawUsnJournalEntry {
+    fn from(entry: &UsnJournalEntry) -> Self {
+        Self {
+            source_offset: entry.source_offset,
+            record_length: entry.record_length,
+            timestamp: entry.timestamp.raw(),
+            entry_number: entry.entry_number,
+            sequence_number: entry.sequence_number,
+            parent_entry_number: entry.parent_entry_number,
+            parent_sequence_number: entry.parent_sequence_number,
+            file_name: entry.file_name.clone(),
+            full_path: entry.full_path.clone(),
+            path_source: entry.path_source,
+            reason: entry.reason_flags,
+            file_attributes: entry.file_attributes,
+            usn: entry.usn,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BootSector {
     pub bytes_per_sector: u16,
@@ -95,11 +425,74 @@ pub struct BootSector {
 pub struct SecurityDescriptor {
     pub id: u32,
     pub hash: u32,
+    /// Whether the stored hash matches one recomputed over the descriptor
+    pub hash_valid: bool,
     pub offset: u64,
     pub length: u32,
     pub descriptor: Vec<u8>,
 }
 
+/// How a security descriptor differs between a baseline $SDS and a later one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DescriptorChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A security ID whose descriptor was added, removed or rewritten between two $SDS files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityDescriptorDiff {
+    pub security_id: u32,
+    pub change: DescriptorChange,
+    pub old_owner: String,
+    pub new_owner: String,
+    /// Parts that differ, e.g. "owner|dacl"; empty for added and removed descriptors
+    pub changed_parts: String,
+    pub old_hash: Option<u32>,
+    pub new_hash: Option<u32>,
+}
+
+/// A file whose owner or ACLs differ from the baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionDrift {
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub full_path: String,
+    /// None without a baseline $MFT
+    pub old_security_id: Option<u32>,
+    pub new_security_id: u32,
+    pub old_owner: String,
+    pub new_owner: String,
+    /// Parts of the effective descriptor that differ, e.g. "owner|dacl", or "new" when the baseline has no descriptor to compare
+    pub changed_parts: String,
+}
+
+/// Security descriptor with its binary body hex-encoded for text output formats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityDescriptorHex {
+    pub id: u32,
+    pub hash: u32,
+    pub hash_valid: bool,
+    pub offset: u64,
+    pub length: u32,
+    pub descriptor_hex: String,
+}
+
+impl From<&SecurityDescriptor> for SecurityDescriptorHex {
+    fn from(descriptor: &SecurityDescriptor) -> Self {
+        Self {
+            id: descriptor.id,
+            hash: descriptor.hash,
+            hash_valid: descriptor.hash_valid,
+            offset: descriptor.offset,
+            length: descriptor.length,
+            descriptor_hex: hex::encode(&descriptor.descriptor),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexEntry {
     pub entry_number: u32,
@@ -107,15 +500,324 @@ pub struct IndexEntry {
     pub parent_entry_number: u32,
     pub parent_sequence_number: u16,
     pub file_name: String,
+    pub name_namespace: NameNamespace,
     pub full_path: String,
+    /// Where `full_path` came from; empty when the parent directory is unknown
+    pub path_source: Option<PathSource>,
+    pub extension: String,
+    pub suspicious_name: bool,
+    pub category: FileCategory,
     pub file_size: u64,
     pub is_directory: bool,
-    pub created: DateTime<Utc>,
-    pub modified: DateTime<Utc>,
-    pub accessed: DateTime<Utc>,
+    pub created: FileTime,
+    pub modified: FileTime,
+    pub accessed: FileTime,
+    /// Timestamps past year 9999 or the time of the run, as "name=0xRAW invalid|future" separated by '|'
+    pub timestamp_anomalies: String,
     pub attributes: u32,
+    /// The update sequence (fixup) check of the entry's INDX page failed, so the entry may be torn
+    pub fixup_error: bool,
+    /// Catalogue code of the error the entry was read despite, e.g. MFTE-E014 for a failed fixup
+    pub error_code: Option<ErrorCode>,
+}
+
+impl MapTimes for IndexEntry {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        for time in [&mut self.created, &mut self.modified, &mut self.accessed] {
+            *time = map(*time);
+        }
+    }
+}
+
+/// A $FILE_NAME attribute carved from a $LogFile RCRD page. The log keeps
+/// copies of names for files recently created, renamed or deleted, so these
+/// can outlive the FILE records that held them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFileName {
+    /// Absolute byte offset of the first copy within $LogFile
+    pub source_offset: u64,
+    pub parent_entry_number: u32,
+    pub parent_sequence_number: u16,
+    /// Path of the parent directory in the $MFT given with -m
+    pub parent_path: String,
+    pub file_name: String,
+    pub name_namespace: NameNamespace,
+    pub extension: String,
+    pub is_directory: bool,
+    pub file_size: u64,
+    pub created: FileTime,
+    pub modified: FileTime,
+    pub record_changed: FileTime,
+    pub accessed: FileTime,
+    pub attributes: u32,
+    /// Copies of the same name, parent and creation time found in the log
+    pub occurrences: usize,
+    /// Whether the $MFT given with -m still has an in-use file of this name in
+    /// the same directory; None without -m and for DOS 8.3 names
+    pub in_mft: Option<bool>,
+}
+
+impl MapTimes for LogFileName {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        for time in [&mut self.created, &mut self.modified, &mut self.record_changed, &mut self.accessed] {
+            *time = map(*time);
+        }
+    }
+}
+
+/// A contiguous run of clusters from a non-resident attribute's mapping pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataRun {
+    /// First virtual cluster number covered by this run
+    pub vcn: u64,
+    /// First logical cluster on the volume, or None for sparse runs
+    pub lcn: Option<u64>,
+    /// Number of clusters in the run
+    pub length: u64,
+}
+
+/// Clusters of one non-resident $DATA stream, as written by --runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamRunList {
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub in_use: bool,
+    pub full_path: String,
+    /// ADS name, empty for the unnamed stream
+    pub stream: String,
+    /// Clusters mapped to the volume, sparse runs excluded
+    pub allocated_clusters: u64,
+    /// Runs in VCN order; `lcn` is null for sparse runs
+    pub runs: Vec<DataRun>,
+}
+
+/// A $DATA stream whose data runs cover a looked-up cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterOwner {
+    pub cluster: u64,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub in_use: bool,
+    /// ADS name, empty for the unnamed stream
+    pub stream: String,
+    /// Cluster within the stream
+    pub vcn: u64,
+    /// Byte offset within the stream, when the cluster size is known
+    pub file_offset: Option<u64>,
+    pub full_path: String,
+}
+
+/// Kind of evidence behind a [`WipingFinding`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WipingPattern {
+    /// Consecutive deleted FILE records whose names are gone or overwritten
+    DeletedRecordRun,
+    /// Names like ZZZZZZZZ.ZZZ that secure-delete tools rename files to between passes
+    WiperRename,
+    /// Many files deleted within a short time in $J
+    MassDeletion,
+    /// Largest run of free clusters in $Bitmap; context rather than a finding on its own
+    LargestFreeExtent,
+}
+
+impl fmt::Display for WipingPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WipingPattern::DeletedRecordRun => "deleted-record-run",
+            WipingPattern::WiperRename => "wiper-rename",
+            WipingPattern::MassDeletion => "mass-deletion",
+            WipingPattern::LargestFreeExtent => "largest-free-extent",
+        })
+    }
+}
+
+/// A pattern suggesting files were wiped or deleted in bulk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WipingFinding {
+    pub pattern: WipingPattern,
+    /// Records, entries or clusters involved
+    pub count: u64,
+    /// Time span of $J evidence, when the pattern comes from the journal
+    pub start: Option<FileTime>,
+    pub end: Option<FileTime>,
+    pub detail: String,
+}
+
+impl MapTimes for WipingFinding {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.start = self.start.map(&map);
+        self.end = self.end.map(&map);
+    }
+}
+
+/// Why a renamed file's new extension looks like ransomware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RansomwareIndicator {
+    /// An extension used by a known ransomware family, e.g. .locked or .wncry
+    KnownExtension,
+    /// A random-looking extension of mixed letters and digits, e.g. .a8f3k2x9
+    RandomExtension,
+}
+
+/// A $J rename that changed a file's extension to a ransomware-like one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RansomwareRename {
+    /// Index of the alert the rename belongs to, in time order
+    pub alert: usize,
+    pub timestamp: FileTime,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub parent_entry_number: u32,
+    /// Empty when the journal no longer holds the RENAME_OLD_NAME record
+    pub old_name: String,
+    pub new_name: String,
+    pub new_extension: String,
+    pub indicator: RansomwareIndicator,
+    /// Resolved path when known, otherwise the new name
+    pub path: String,
+}
+
+impl MapTimes for RansomwareRename {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.timestamp = map(self.timestamp);
+    }
+}
+
+/// A burst of ransomware-like renames in $J
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RansomwareAlert {
+    pub start: FileTime,
+    pub end: FileTime,
+    pub renamed_files: usize,
+    /// Files deleted between `start` and `end`, e.g. originals removed after encryption
+    pub deleted_files: usize,
+    /// Most frequent new extensions with their counts, e.g. "locked (812)|a8f3k2x9 (3)"
+    pub extensions: String,
+    /// The first few affected paths; every rename is in the ransomware CSV
+    pub sample_paths: Vec<String>,
+}
+
+impl MapTimes for RansomwareAlert {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.start = map(self.start);
+        self.end = map(self.end);
+    }
+}
+
+/// One file written by --dr, as listed in the dump's manifest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DumpedFile {
+    /// Name of the written file in the dump directory
+    pub dump_name: String,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub in_use: bool,
+    pub full_path: String,
+    /// What was dumped: "data" for a file's content, "ads" for an alternate data stream, "ea" for an extended attribute
+    pub content: String,
+    /// Name of the alternate data stream or extended attribute; empty for a file's content
+    pub name: String,
+    pub size: u64,
+    pub md5: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+/// Recursive totals for one directory, from its $MFT subtree
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectorySize {
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub full_path: String,
+    /// In-use files directly inside the directory, and their logical size
+    pub file_count: u64,
+    pub size: u64,
+    /// In-use files and directories anywhere below the directory
+    pub total_file_count: u64,
+    pub total_directory_count: u64,
+    pub total_size: u64,
+    /// Deleted files anywhere below the directory whose records survive
+    pub total_deleted_file_count: u64,
+    pub total_deleted_size: u64,
+}
+
+/// An $MFT entry whose sequence number stands out from its neighbours', a sign
+/// the entry has been freed and reused far more often than the records around it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceChurn {
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    /// Median sequence number of the surrounding entries
+    pub neighbor_median: u16,
+    pub in_use: bool,
+    pub full_path: String,
+}
+
+/// A file that occupied an $MFT entry before its current record, rebuilt from $J
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousOccupant {
+    pub entry_number: u32,
+    /// Sequence number the entry had while this file occupied it
+    pub sequence_number: u16,
+    /// Sequence number and name of the record now in the entry
+    pub current_sequence_number: u16,
+    pub current_name: String,
+    /// Last name the journal recorded for the file; earlier names follow in `previous_names`
+    pub file_name: String,
+    pub previous_names: String,
+    pub parent_entry_number: u32,
+    pub parent_sequence_number: u16,
+    pub first_seen: FileTime,
+    pub last_seen: FileTime,
+    /// The journal saw the file created and deleted
+    pub created: bool,
+    pub deleted: bool,
+    pub usn_records: usize,
+}
+
+impl MapTimes for PreviousOccupant {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.first_seen = map(self.first_seen);
+        self.last_seen = map(self.last_seen);
+    }
+}
+
+/// One cluster range of the volume usage map and the stream that maps it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterMapRow {
+    pub first_cluster: u64,
+    pub last_cluster: u64,
+    pub cluster_count: u64,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub in_use: bool,
+    /// ADS name, empty for the unnamed stream
+    pub stream: String,
+    /// Cluster within the stream that `first_cluster` maps to
+    pub vcn: u64,
+    pub full_path: String,
+}
+
+/// A file's entry number and creation time, for the --entry-map dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryTimeRow {
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    /// $STANDARD_INFORMATION created time
+    pub created: FileTime,
+    pub is_directory: bool,
+    pub full_path: String,
+}
+
+impl MapTimes for EntryTimeRow {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.created = map(self.created);
+    }
 }
 
+/// A row of the condensed --fl file listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileListEntry {
     pub entry_number: u32,
@@ -125,12 +827,135 @@ pub struct FileListEntry {
     pub extension: String,
     pub file_size: u64,
     pub is_directory: bool,
-    pub created: DateTime<Utc>,
-    pub modified: DateTime<Utc>,
+    pub created: FileTime,
+    pub modified: FileTime,
+}
+
+impl MapTimes for FileListEntry {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        self.created = map(self.created);
+        self.modified = map(self.modified);
+    }
+}
+
+/// Stable code of each kind of parse or validation error, written as
+/// `MFTE-E001` in logs, the run summary and per-record error fields so
+/// pipelines can triage failures without matching on message text.
+///
+/// Codes are never renumbered or reused; new kinds get the next free number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCode {
+    /// The data ends before the structure being read does
+    Truncated,
+    /// The input is smaller than one record of its artifact
+    InputTooSmall,
+    /// A record or page lacks its signature (FILE, INDX, RSTR, ...)
+    BadSignature,
+    /// The boot sector is not a usable NTFS boot sector
+    BootSector,
+    /// An attribute header's lengths or offsets fall outside its FILE record
+    AttributeHeader,
+    /// A data run list cannot be decoded
+    DataRun,
+    /// An $I30 index entry is malformed
+    IndexEntry,
+    /// A $J record is malformed, or a region of $J holds no valid record
+    UsnRecord,
+    /// A $Secure:$SDS entry is malformed
+    SecurityDescriptor,
+    /// A $REPARSE_POINT is malformed
+    ReparsePoint,
+    /// An $EA or $EA_INFORMATION attribute is malformed
+    ExtendedAttribute,
+    /// An $EFS stream is malformed
+    EfsMetadata,
+    /// A compressed stream does not decompress
+    Decompression,
+    /// The update sequence (fixup) check of a record or page failed
+    Fixup,
+    /// NTFS marked the FILE record BAAD
+    BaadRecord,
+    /// The $MFT or a stream cannot be located within the volume
+    VolumeLayout,
+    /// Reading the input failed
+    Io,
+    /// The command line asks for something that cannot be done
+    InvalidOption,
+}
+
+impl ErrorCode {
+    /// Every code, in catalogue order
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::Truncated,
+        ErrorCode::InputTooSmall,
+        ErrorCode::BadSignature,
+        ErrorCode::BootSector,
+        ErrorCode::AttributeHeader,
+        ErrorCode::DataRun,
+        ErrorCode::IndexEntry,
+        ErrorCode::UsnRecord,
+        ErrorCode::SecurityDescriptor,
+        ErrorCode::ReparsePoint,
+        ErrorCode::ExtendedAttribute,
+        ErrorCode::EfsMetadata,
+        ErrorCode::Decompression,
+        ErrorCode::Fixup,
+        ErrorCode::BaadRecord,
+        ErrorCode::VolumeLayout,
+        ErrorCode::Io,
+        ErrorCode::InvalidOption,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Truncated => "MFTE-E001",
+            ErrorCode::InputTooSmall => "MFTE-E002",
+            ErrorCode::BadSignature => "MFTE-E003",
+            ErrorCode::BootSector => "MFTE-E004",
+            ErrorCode::AttributeHeader => "MFTE-E005",
+            ErrorCode::DataRun => "MFTE-E006",
+            ErrorCode::IndexEntry => "MFTE-E007",
+            ErrorCode::UsnRecord => "MFTE-E008",
+            ErrorCode::SecurityDescriptor => "MFTE-E009",
+            ErrorCode::ReparsePoint => "MFTE-E010",
+            ErrorCode::ExtendedAttribute => "MFTE-E011",
+            ErrorCode::EfsMetadata => "MFTE-E012",
+            ErrorCode::Decompression => "MFTE-E013",
+            ErrorCode::Fixup => "MFTE-E014",
+            ErrorCode::BaadRecord => "MFTE-E015",
+            ErrorCode::VolumeLayout => "MFTE-E016",
+            ErrorCode::Io => "MFTE-E017",
+            ErrorCode::InvalidOption => "MFTE-E018",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|known| known.as_str() == code)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown error code {}", code)))
+    }
 }
 
 #[derive(Debug)]
 pub struct ParseError {
+    pub code: ErrorCode,
     pub message: String,
     pub offset: Option<u64>,
 }
@@ -138,12 +963,27 @@ pub struct ParseError {
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.offset {
-            Some(offset) => write!(f, "Parse error at offset 0x{:x}: {}", offset, self.message),
-            None => write!(f, "Parse error: {}", self.message),
+            Some(offset) => write!(f, "Parse error {} at offset 0x{:x}: {}", self.code, offset, self.message),
+            None => write!(f, "Parse error {}: {}", self.code, self.message),
         }
     }
 }
 
 impl std::error::Error for ParseError {}
 
+impl From<std::io::Error> for ParseError {
+    fn from(error: std::io::Error) -> Self {
+        // Cursor reads past the end of a buffer are truncated structures, not I/O failures
+        let code = match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => ErrorCode::Truncated,
+            _ => ErrorCode::Io,
+        };
+        ParseError {
+            code,
+            message: error.to_string(),
+            offset: None,
+        }
+    }
+}
+
 pub type ParseResult<T> = Result<T, ParseError>;
\ No newline at end of file
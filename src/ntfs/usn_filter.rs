@@ -0,0 +1,117 @@
+use super::mft::MftParser;
+use super::names::extract_extension;
+use super::path_resolver::PathResolver;
+use super::types::PathSource;
+use super::usn_journal::UsnRecord;
+
+/// Reason, extension and path criteria checked against each $J record as it
+/// is read, so records nobody wants are dropped before they are built and
+/// serialized.
+///
+/// Paths come from the directories of a $MFT read once up front, kept current
+/// from the directory creates and renames in the journal itself, so
+/// directories made after startup resolve too. Every criterion given must
+/// match; a record whose path can't be resolved never matches an include path.
+pub struct UsnFilter {
+    /// USN_REASON_* bits, any of which a record must carry; 0 for any reason
+    reasons: u32,
+    /// Lowercase extensions without the dot
+    extensions: Vec<String>,
+    /// Lowercase '/' separated path prefixes
+    include_paths: Vec<String>,
+    exclude_paths: Vec<String>,
+    paths: PathResolver,
+}
+
+impl UsnFilter {
+    /// A filter keeping every record, with paths resolved from the directories in `mft`
+    pub fn new(mft: &MftParser) -> Self {
+        Self {
+            reasons: 0,
+            extensions: Vec::new(),
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            paths: PathResolver::new(Some(mft)),
+        }
+    }
+
+    /// Keep only records carrying at least one of these USN_REASON_* bits
+    pub fn with_reasons(mut self, reasons: u32) -> Self {
+        self.reasons = reasons;
+        self
+    }
+
+    /// Keep only files with one of these extensions, given with or without the dot
+    pub fn with_extensions(mut self, extensions: &[String]) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Keep only records under one of `include` (when any are given) and under none of `exclude`.
+    ///
+    /// Prefixes match whole path components, case-insensitively; `\` and `/`
+    /// are both accepted and a leading drive letter is ignored.
+    pub fn with_paths(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.include_paths = include.iter().map(|path| normalize_path(path)).collect();
+        self.exclude_paths = exclude.iter().map(|path| normalize_path(path)).collect();
+        self
+    }
+
+    /// Whether to keep `record`; directory changes update the path lookups
+    /// whether or not they are kept
+    pub fn keep(&mut self, record: &UsnRecord) -> bool {
+        self.paths.learn_usn(record);
+
+        if self.reasons != 0 && record.reason & self.reasons == 0 {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            // "gz" also matches the compound "tar.gz"
+            let extension = extract_extension(&record.file_name).extension.to_lowercase();
+            let matches = |wanted: &String| {
+                extension == *wanted || extension.strip_suffix(wanted.as_str()).is_some_and(|rest| rest.ends_with('.'))
+            };
+            if !self.extensions.iter().any(matches) {
+                return false;
+            }
+        }
+        if self.include_paths.is_empty() && self.exclude_paths.is_empty() {
+            return true;
+        }
+
+        match self.full_path(record) {
+            Some((path, _)) => {
+                let path = path.to_lowercase();
+                (self.include_paths.is_empty() || self.include_paths.iter().any(|prefix| under(&path, prefix)))
+                    && !self.exclude_paths.iter().any(|prefix| under(&path, prefix))
+            }
+            None => self.include_paths.is_empty(),
+        }
+    }
+
+    /// Full path of the record's file and where it came from, when its parent directory is known
+    pub fn full_path(&self, record: &UsnRecord) -> Option<(String, PathSource)> {
+        self.paths.resolve(record.parent_entry_number, record.parent_sequence_number, &record.file_name)
+    }
+}
+
+/// `C:\Users\Public\` as `/users/public`
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/").to_lowercase();
+    let path = match path.as_bytes() {
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => &path[2..],
+        _ => &path[..],
+    };
+    format!("/{}", path.trim_matches('/'))
+}
+
+/// Whether `path` is `prefix` or lies beneath it
+fn under(path: &str, prefix: &str) -> bool {
+    prefix == "/"
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
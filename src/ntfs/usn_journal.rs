@@ -1,60 +1,287 @@
-use super::types::{UsnJournalEntry, ParseError, ParseResult};
+use super::category::{self, FileCategory};
+use super::deadline::Deadline;
+use super::filetime::{self, FileTime};
+use super::names::{decode_utf16le, extract_extension, ExtensionInfo};
+use super::path_resolver::PathResolver;
+use super::types::{UsnJournalEntry, ErrorCode, ParseError, ParseResult, PathSource};
+use super::usn_filter::UsnFilter;
+use crate::artifact::{InputInfo, NO_MATCH, WEAK_MATCH};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::{DateTime, Utc};
 use std::io::{Cursor, Read};
 
+/// Smallest USN_RECORD_V2: the fixed header with an empty name
+const USN_V2_MIN_RECORD_LENGTH: u32 = 60;
+
+/// Upper bound for a USN_RECORD_V2 with a 255 character name, with headroom
+const USN_MAX_RECORD_LENGTH: u32 = 1024;
+
+/// Records always start on 8-byte boundaries
+const USN_RECORD_ALIGNMENT: u64 = 8;
+
+/// USN_REASON_* bits and their names, in bit order
+const USN_REASONS: &[(u32, &str)] = &[
+    (0x00000001, "DATA_OVERWRITE"),
+    (0x00000002, "DATA_EXTEND"),
+    (0x00000004, "DATA_TRUNCATION"),
+    (0x00000010, "NAMED_DATA_OVERWRITE"),
+    (0x00000020, "NAMED_DATA_EXTEND"),
+    (0x00000040, "NAMED_DATA_TRUNCATION"),
+    (0x00000100, "FILE_CREATE"),
+    (0x00000200, "FILE_DELETE"),
+    (0x00000400, "EA_CHANGE"),
+    (0x00000800, "SECURITY_CHANGE"),
+    (0x00001000, "RENAME_OLD_NAME"),
+    (0x00002000, "RENAME_NEW_NAME"),
+    (0x00004000, "INDEXABLE_CHANGE"),
+    (0x00008000, "BASIC_INFO_CHANGE"),
+    (0x00010000, "HARD_LINK_CHANGE"),
+    (0x00020000, "COMPRESSION_CHANGE"),
+    (0x00040000, "ENCRYPTION_CHANGE"),
+    (0x00080000, "OBJECT_ID_CHANGE"),
+    (0x00100000, "REPARSE_POINT_CHANGE"),
+    (0x00200000, "STREAM_CHANGE"),
+    (0x80000000, "CLOSE"),
+];
+
+/// On-disk fields of one USN_RECORD_V2, read before an entry is built from it
+pub struct UsnRecord {
+    pub source_offset: u64,
+    pub record_length: u32,
+    pub entry_number: u32,
+    pub sequence_number: u16,
+    pub parent_entry_number: u32,
+    pub parent_sequence_number: u16,
+    pub usn: u64,
+    pub timestamp: u64,
+    pub reason: u32,
+    pub file_attributes: u32,
+    pub file_name: String,
+}
+
 pub struct UsnJournalParser {
     data: Vec<u8>,
+    base_offset: u64,
     entries: Vec<UsnJournalEntry>,
+    error_count: usize,
+    skipped_bytes: u64,
+    filtered: usize,
+    raw: bool,
+    deadline: Deadline,
+    timed_out: bool,
+    paths: Option<PathResolver>,
 }
 
 impl UsnJournalParser {
     pub fn new(data: Vec<u8>) -> Self {
         Self {
             data,
+            base_offset: 0,
             entries: Vec::new(),
+            error_count: 0,
+            skipped_bytes: 0,
+            filtered: 0,
+            raw: false,
+            deadline: Deadline::none(),
+            timed_out: false,
+            paths: None,
         }
     }
 
+    /// Offset of `data` within the full $J stream, for journals read without their sparse prefix
+    pub fn with_base_offset(mut self, base_offset: u64) -> Self {
+        self.base_offset = base_offset;
+        self
+    }
+
+    /// Skip extension, category and reason-name decoding; entries keep only on-disk values
+    pub fn raw_mode(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    /// Stop reading entries once `deadline` passes, keeping those parsed so far
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Fill `full_path` from `paths`, which learns the directories the journal
+    /// creates and renames as it is read
+    pub fn with_path_resolver(mut self, paths: PathResolver) -> Self {
+        self.paths = Some(paths);
+        self
+    }
+
     pub fn parse(&mut self) -> ParseResult<()> {
-        let mut cursor = Cursor::new(&self.data);
-        let mut offset = 0u64;
-
-        while (cursor.position() as usize) < self.data.len() {
-            match self.parse_entry(&mut cursor, offset) {
-                Ok(Some(entry)) => {
-                    offset += entry.offset;
-                    self.entries.push(entry);
+        self.parse_entries(usize::MAX, None, |_| {})
+    }
+
+    /// Parse only the records `filter` keeps, skipping the rest before their
+    /// entries are built
+    pub fn parse_filtered(&mut self, filter: &mut UsnFilter) -> ParseResult<()> {
+        self.parse_entries(usize::MAX, Some(filter), |_| {})
+    }
+
+    /// Parse entries, handing each completed batch to `on_batch` while parsing continues.
+    ///
+    /// Zero padding at the end of journal pages is skipped silently; anything else that
+    /// does not look like a USN_RECORD_V2 is stepped over one alignment unit at a time
+    /// until the next plausible record, so one corrupt page does not truncate the output.
+    pub fn parse_batched(&mut self, batch_size: usize, on_batch: impl FnMut(&[UsnJournalEntry])) -> ParseResult<()> {
+        self.parse_entries(batch_size, None, on_batch)
+    }
+
+    fn parse_entries(
+        &mut self,
+        batch_size: usize,
+        mut filter: Option<&mut UsnFilter>,
+        mut on_batch: impl FnMut(&[UsnJournalEntry]),
+    ) -> ParseResult<()> {
+        let data_len = self.data.len() as u64;
+        let mut position = 0u64;
+        let mut in_corrupt_region = false;
+        let mut batch_start = self.entries.len();
+
+        while position + USN_V2_MIN_RECORD_LENGTH as u64 <= data_len {
+            if self.deadline.expired() {
+                log::warn!(
+                    "Timed out at offset 0x{:x}; keeping {} USN Journal entries parsed so far",
+                    self.base_offset + position,
+                    self.entries.len()
+                );
+                self.timed_out = true;
+                break;
+            }
+
+            if self.read_u32_at(position) == 0 {
+                let next = self.next_non_zero(position);
+                if next > position {
+                    position = next;
+                    continue;
+                }
+            }
+
+            if !self.is_plausible_record(position) {
+                if !in_corrupt_region {
+                    log::warn!("{}: skipping unparseable USN data at offset 0x{:x}", ErrorCode::UsnRecord, self.base_offset + position);
+                    self.error_count += 1;
+                    in_corrupt_region = true;
+                }
+                self.skipped_bytes += USN_RECORD_ALIGNMENT;
+                position += USN_RECORD_ALIGNMENT;
+                continue;
+            }
+            in_corrupt_region = false;
+
+            let mut cursor = Cursor::new(&self.data);
+            cursor.set_position(position);
+            // is_plausible_record bounds record_length to the data and to at least
+            // one header, so each parsed entry moves the position forward
+            match self.read_record(&mut cursor) {
+                Ok(record) => {
+                    position += record.record_length as u64;
+                    match filter.as_deref_mut() {
+                        Some(filter) => {
+                            if filter.keep(&record) {
+                                let full_path = filter.full_path(&record);
+                                self.push_entry(record, full_path);
+                            } else {
+                                self.filtered += 1;
+                            }
+                        }
+                        None => {
+                            let full_path = self.paths.as_mut().and_then(|paths| {
+                                paths.learn_usn(&record);
+                                paths.resolve(record.parent_entry_number, record.parent_sequence_number, &record.file_name)
+                            });
+                            self.push_entry(record, full_path);
+                        }
+                    }
                 }
-                Ok(None) => break, // End of valid entries
                 Err(e) => {
-                    log::warn!("Failed to parse USN Journal entry at offset 0x{:x}: {}", offset, e);
-                    break;
+                    log::warn!("Failed to parse USN record: {}", e);
+                    self.error_count += 1;
+                    self.skipped_bytes += USN_RECORD_ALIGNMENT;
+                    position += USN_RECORD_ALIGNMENT;
+                    continue;
                 }
             }
+
+            if self.entries.len() - batch_start >= batch_size {
+                on_batch(&self.entries[batch_start..]);
+                batch_start = self.entries.len();
+            }
         }
 
-        log::info!("Parsed {} USN Journal entries", self.entries.len());
+        if batch_start < self.entries.len() {
+            on_batch(&self.entries[batch_start..]);
+        }
+
+        if self.skipped_bytes > 0 {
+            log::warn!(
+                "Skipped {} bytes in {} corrupt USN region(s)",
+                self.skipped_bytes, self.error_count
+            );
+        }
+        if self.filtered > 0 {
+            log::info!("Parsed {} USN Journal entries, {} filtered out", self.entries.len(), self.filtered);
+        } else {
+            log::info!("Parsed {} USN Journal entries", self.entries.len());
+        }
         Ok(())
     }
 
-    fn parse_entry(&self, cursor: &mut Cursor<&Vec<u8>>, base_offset: u64) -> ParseResult<Option<UsnJournalEntry>> {
-        let start_pos = cursor.position();
+    fn read_u32_at(&self, position: u64) -> u32 {
+        let p = position as usize;
+        self.data
+            .get(p..p + 4)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
 
-        if start_pos + 60 > self.data.len() as u64 {
-            return Ok(None); // Not enough data for minimum USN record
-        }
+    fn read_u16_at(&self, position: u64) -> u16 {
+        let p = position as usize;
+        self.data
+            .get(p..p + 2)
+            .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    /// First aligned position at or after `position` that is not all zeros
+    fn next_non_zero(&self, position: u64) -> u64 {
+        let start = position as usize;
+        self.data[start..]
+            .chunks(USN_RECORD_ALIGNMENT as usize)
+            .position(|chunk| chunk.iter().any(|&b| b != 0))
+            .map_or(self.data.len() as u64, |index| position + index as u64 * USN_RECORD_ALIGNMENT)
+    }
+
+    /// Check the fixed USN_RECORD_V2 header fields for internal consistency
+    fn is_plausible_record(&self, position: u64) -> bool {
+        let record_length = self.read_u32_at(position);
+        let major_version = self.read_u16_at(position + 4);
+        let file_name_length = self.read_u16_at(position + 56);
+        let file_name_offset = self.read_u16_at(position + 58);
+
+        (USN_V2_MIN_RECORD_LENGTH..=USN_MAX_RECORD_LENGTH).contains(&record_length)
+            && (record_length as u64).is_multiple_of(USN_RECORD_ALIGNMENT)
+            && position + record_length as u64 <= self.data.len() as u64
+            && major_version == 2
+            && file_name_offset as u32 == USN_V2_MIN_RECORD_LENGTH
+            && file_name_length.is_multiple_of(2)
+            && file_name_offset as u32 + file_name_length as u32 <= record_length
+    }
+
+    /// Parse one record whose header has already passed `is_plausible_record`
+    fn read_record(&self, cursor: &mut Cursor<&Vec<u8>>) -> ParseResult<UsnRecord> {
+        let start_pos = cursor.position();
+        let source_offset = self.base_offset + start_pos;
 
         let record_length = cursor.read_u32::<LittleEndian>()
             .map_err(|_| ParseError {
+                code: ErrorCode::UsnRecord,
                 message: "Failed to read USN record length".to_string(),
-                offset: Some(base_offset + start_pos),
+                offset: Some(source_offset),
             })?;
 
-        if record_length == 0 {
-            return Ok(None); // End of records
-        }
-
         let _major_version = cursor.read_u16::<LittleEndian>().unwrap();
         let _minor_version = cursor.read_u16::<LittleEndian>().unwrap();
 
@@ -76,87 +303,138 @@ impl UsnJournalParser {
         let file_name_offset = cursor.read_u16::<LittleEndian>().unwrap();
 
         // Read filename
-        let current_pos = cursor.position();
         cursor.set_position(start_pos + file_name_offset as u64);
 
         let mut name_bytes = vec![0u8; file_name_length as usize];
-        cursor.read_exact(&mut name_bytes).unwrap();
+        cursor.read_exact(&mut name_bytes).map_err(|_| ParseError {
+            code: ErrorCode::UsnRecord,
+            message: "USN record name runs past the end of the journal".to_string(),
+            offset: Some(source_offset),
+        })?;
 
-        let file_name = string_from_utf16le(&name_bytes)
+        let file_name = decode_utf16le(&name_bytes)
             .unwrap_or_else(|_| String::from("INVALID_NAME"));
 
-        // Extract extension
-        let extension = if let Some(dot_pos) = file_name.rfind('.') {
-            file_name[dot_pos + 1..].to_string()
+        Ok(UsnRecord {
+            source_offset,
+            record_length,
+            entry_number,
+            sequence_number,
+            parent_entry_number,
+            parent_sequence_number,
+            usn,
+            timestamp,
+            reason,
+            file_attributes,
+            file_name,
+        })
+    }
+
+    fn push_entry(&mut self, record: UsnRecord, full_path: Option<(String, PathSource)>) {
+        let mut entry = self.build_entry(record);
+        if let Some((path, source)) = full_path {
+            entry.full_path = path;
+            entry.path_source = Some(source);
+        }
+        self.entries.push(entry);
+    }
+
+    fn build_entry(&self, record: UsnRecord) -> UsnJournalEntry {
+        let UsnRecord {
+            source_offset,
+            record_length,
+            entry_number,
+            sequence_number,
+            parent_entry_number,
+            parent_sequence_number,
+            usn,
+            timestamp,
+            reason,
+            file_attributes,
+            file_name,
+        } = record;
+
+        let is_directory = (file_attributes & 0x10) != 0;
+        let (extension_info, file_category, reason_names) = if self.raw {
+            let category = if is_directory { FileCategory::Directory } else { FileCategory::Other };
+            (ExtensionInfo { extension: String::new(), suspicious: false }, category, String::new())
         } else {
-            String::new()
+            let extension_info = extract_extension(&file_name);
+            let category = category::from_extension(&extension_info.extension, is_directory);
+            (extension_info, category, format_usn_reason(reason))
         };
 
-        // Convert Windows FILETIME to DateTime<Utc>
-        let datetime = windows_filetime_to_datetime(timestamp);
+        // Raw FILETIME; the calendar date is only built when the entry is written
+        let datetime = FileTime(timestamp);
 
-        let entry = UsnJournalEntry {
-            offset: record_length as u64,
+        UsnJournalEntry {
+            source_offset,
+            record_length,
             timestamp: datetime,
+            timestamp_anomalies: filetime::anomalies([("timestamp", Some(datetime))]),
             entry_number,
             sequence_number,
             parent_entry_number,
             parent_sequence_number,
             file_name,
             full_path: String::new(), // Will be resolved later if MFT is available
-            extension,
-            reason: format_usn_reason(reason),
+            path_source: None,
+            extension: extension_info.extension,
+            suspicious_name: extension_info.suspicious,
+            category: file_category,
+            reason: reason_names,
+            reason_flags: reason,
             file_attributes,
             usn,
-        };
-
-        // Move to next record
-        cursor.set_position(start_pos + record_length as u64);
-
-        Ok(Some(entry))
+        }
     }
 
     pub fn get_entries(&self) -> &[UsnJournalEntry] {
         &self.entries
     }
-}
 
-fn windows_filetime_to_datetime(filetime: u64) -> DateTime<Utc> {
-    // Windows FILETIME is 100-nanosecond intervals since January 1, 1601
-    // Unix timestamp is seconds since January 1, 1970
-    const FILETIME_UNIX_DIFF: u64 = 11644473600; // seconds between 1601 and 1970
+    /// Number of corrupt regions stepped over while parsing
+    pub fn get_error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// Total bytes skipped inside corrupt regions (zero padding is not counted)
+    pub fn get_skipped_bytes(&self) -> u64 {
+        self.skipped_bytes
+    }
 
-    let seconds = filetime / 10_000_000 - FILETIME_UNIX_DIFF;
-    let nanos = ((filetime % 10_000_000) * 100) as u32;
+    /// Records a filter dropped
+    pub fn get_filtered_count(&self) -> usize {
+        self.filtered
+    }
 
-    DateTime::<Utc>::from_timestamp(seconds as i64, nanos)
-        .unwrap_or_else(|| DateTime::<Utc>::from_timestamp(0, 0).unwrap())
+    /// True when parsing stopped early at the deadline
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+/// $J has no signature, so only a plausible leading record length is checked
+pub fn detect(input: &InputInfo) -> u8 {
+    let header = &input.header;
+    if header.len() < USN_V2_MIN_RECORD_LENGTH as usize {
+        return NO_MATCH;
+    }
+
+    let record_length = u32::from_le_bytes(header[..4].try_into().unwrap());
+    if record_length > USN_V2_MIN_RECORD_LENGTH && record_length < 0x10000 {
+        WEAK_MATCH
+    } else {
+        NO_MATCH
+    }
 }
 
 fn format_usn_reason(reason: u32) -> String {
-    let mut reasons = Vec::new();
-
-    if reason & 0x00000001 != 0 { reasons.push("DATA_OVERWRITE"); }
-    if reason & 0x00000002 != 0 { reasons.push("DATA_EXTEND"); }
-    if reason & 0x00000004 != 0 { reasons.push("DATA_TRUNCATION"); }
-    if reason & 0x00000010 != 0 { reasons.push("NAMED_DATA_OVERWRITE"); }
-    if reason & 0x00000020 != 0 { reasons.push("NAMED_DATA_EXTEND"); }
-    if reason & 0x00000040 != 0 { reasons.push("NAMED_DATA_TRUNCATION"); }
-    if reason & 0x00000100 != 0 { reasons.push("FILE_CREATE"); }
-    if reason & 0x00000200 != 0 { reasons.push("FILE_DELETE"); }
-    if reason & 0x00000400 != 0 { reasons.push("EA_CHANGE"); }
-    if reason & 0x00000800 != 0 { reasons.push("SECURITY_CHANGE"); }
-    if reason & 0x00001000 != 0 { reasons.push("RENAME_OLD_NAME"); }
-    if reason & 0x00002000 != 0 { reasons.push("RENAME_NEW_NAME"); }
-    if reason & 0x00004000 != 0 { reasons.push("INDEXABLE_CHANGE"); }
-    if reason & 0x00008000 != 0 { reasons.push("BASIC_INFO_CHANGE"); }
-    if reason & 0x00010000 != 0 { reasons.push("HARD_LINK_CHANGE"); }
-    if reason & 0x00020000 != 0 { reasons.push("COMPRESSION_CHANGE"); }
-    if reason & 0x00040000 != 0 { reasons.push("ENCRYPTION_CHANGE"); }
-    if reason & 0x00080000 != 0 { reasons.push("OBJECT_ID_CHANGE"); }
-    if reason & 0x00100000 != 0 { reasons.push("REPARSE_POINT_CHANGE"); }
-    if reason & 0x00200000 != 0 { reasons.push("STREAM_CHANGE"); }
-    if reason & 0x80000000 != 0 { reasons.push("CLOSE"); }
+    let reasons: Vec<&str> = USN_REASONS
+        .iter()
+        .filter(|(flag, _)| reason & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
 
     if reasons.is_empty() {
         format!("UNKNOWN(0x{:08x})", reason)
@@ -165,11 +443,59 @@ fn format_usn_reason(reason: u32) -> String {
     }
 }
 
-// Helper trait for UTF-16LE string conversion
-fn string_from_utf16le(bytes: &[u8]) -> Result<String, std::string::FromUtf16Error> {
-    let utf16_chars: Vec<u16> = bytes
-        .chunks_exact(2)
-        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
-    String::from_utf16(&utf16_chars)
-}
\ No newline at end of file
+/// The USN_REASON_* bit for a reason name such as "FILE_CREATE", with or
+/// without the USN_REASON_ prefix and in any case
+pub fn reason_flag(name: &str) -> Option<u32> {
+    let name = name.trim().to_ascii_uppercase();
+    let name = name.strip_prefix("USN_REASON_").unwrap_or(&name);
+    USN_REASONS.iter().find(|(_, known)| *known == name).map(|(flag, _)| *flag)
+}
+
+/// Every reason name, for error messages
+pub fn reason_names() -> impl Iterator<Item = &'static str> {
+    USN_REASONS.iter().map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A USN_RECORD_V2 header claiming `record_length`, padded to `size` bytes,
+    /// named "a" so that a 64-byte record is valid
+    fn usn_record(record_length: u32, size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[0..4].copy_from_slice(&record_length.to_le_bytes());
+        data[4..6].copy_from_slice(&2u16.to_le_bytes());
+        data[56..58].copy_from_slice(&2u16.to_le_bytes());
+        data[58..60].copy_from_slice(&60u16.to_le_bytes());
+        data[60] = b'a';
+        data
+    }
+
+    #[test]
+    fn skips_zero_record_length() {
+        let mut parser = UsnJournalParser::new(usn_record(0, 64));
+        parser.parse().unwrap();
+        assert!(parser.get_entries().is_empty());
+        assert_eq!(parser.get_error_count(), 1);
+    }
+
+    #[test]
+    fn skips_record_length_past_buffer() {
+        let mut parser = UsnJournalParser::new(usn_record(0x200, 64));
+        parser.parse().unwrap();
+        assert!(parser.get_entries().is_empty());
+        assert_eq!(parser.get_error_count(), 1);
+    }
+
+    #[test]
+    fn resumes_after_corrupt_record() {
+        let mut data = usn_record(0x200, 64);
+        data.extend(usn_record(64, 64));
+        let mut parser = UsnJournalParser::new(data);
+        parser.parse().unwrap();
+        assert_eq!(parser.get_entries().len(), 1);
+        assert_eq!(parser.get_entries()[0].file_name, "a");
+        assert_eq!(parser.get_error_count(), 1);
+    }
+}
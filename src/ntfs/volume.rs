@@ -0,0 +1,635 @@
+use super::attribute::AttributeHeader;
+use super::boot::{self, BootParser, BOOT_FILE_SIZE};
+use super::compression;
+use super::fixup;
+use super::runlist::decode_runlist;
+use super::names::decode_utf16le_lossy;
+use super::types::{BootSector, DataRun, ErrorCode, ParseError, ParseResult};
+use crate::artifact::{InputInfo, NO_MATCH, SIGNATURE_MATCH};
+use log::{debug, warn};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Entry number of the $LogFile metadata file
+const LOGFILE_ENTRY: usize = 2;
+
+/// Entry number of the $Bitmap metadata file
+const BITMAP_ENTRY: usize = 6;
+
+/// $BITMAP attribute type; $MFT record 0's has a bit for each $MFT entry in use
+const MFT_BITMAP_ATTRIBUTE: u32 = 0xB0;
+
+/// Entry number of the $Secure metadata file, whose $SDS stream holds the security descriptors
+const SECURE_ENTRY: usize = 9;
+
+/// Entry number of the $Extend directory that holds $UsnJrnl
+const EXTEND_ENTRY: u64 = 11;
+
+/// Largest single read issued against the device
+const READ_CHUNK_SIZE: usize = 4 << 20;
+
+/// Largest plausible compression unit, as a power of two of clusters; NTFS writes 4 (16 clusters)
+const MAX_COMPRESSION_UNIT: u16 = 16;
+
+/// Reads NTFS metadata files directly from a block device or volume image.
+///
+/// All reads are sector aligned so the same code works for Linux/macOS block
+/// devices, raw images and Windows volume handles such as `\\.\C:`.
+pub struct VolumeReader {
+    file: File,
+    boot: BootSector,
+    sector_size: u64,
+    cluster_size: u64,
+    volume_size: u64,
+    /// Data runs of the $MFT itself, once record 0 has been read
+    mft_runs: Option<Vec<DataRun>>,
+}
+
+/// FILE records that hold the $J stream of $UsnJrnl: its base record and any
+/// extension records. Found once from the full $MFT, then re-read on every poll.
+#[derive(Debug, Clone)]
+pub struct UsnJournalLocation {
+    records: Vec<u64>,
+}
+
+/// The part of $J written since a given offset
+#[derive(Debug, Clone)]
+pub struct UsnJournalTail {
+    /// Offset within $J of the first byte of `data`
+    pub start: u64,
+    /// Current size of $J; the next poll continues from here
+    pub end: u64,
+    pub data: Vec<u8>,
+    /// UsnJournalID from $UsnJrnl:$Max, which changes when the journal is recreated
+    pub journal_id: Option<u64>,
+}
+
+/// One non-resident $DATA attribute (or fragment of one) found in a FILE record
+struct DataAttribute {
+    runs: Vec<DataRun>,
+    starting_vcn: u64,
+    real_size: u64,
+}
+
+impl VolumeReader {
+    pub fn open(path: &Path) -> ParseResult<Self> {
+        let mut file = crate::evidence::open_read_only(path)?;
+
+        // Block devices report a zero length in their metadata, so ask the device itself
+        let volume_size = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut sector = vec![0u8; 512];
+        file.read_exact(&mut sector)?;
+        if sector[3..11] != *b"NTFS    " {
+            return Err(ParseError {
+                code: ErrorCode::BootSector,
+                message: "Volume does not start with an NTFS boot sector".to_string(),
+                offset: Some(3),
+            });
+        }
+
+        let boot = BootParser::parse(&sector)?;
+        let sector_size = boot.bytes_per_sector as u64;
+        let cluster_size = sector_size * boot.sectors_per_cluster as u64;
+        if !sector_size.is_power_of_two() || sector_size < 512 || cluster_size == 0 {
+            return Err(ParseError {
+                code: ErrorCode::BootSector,
+                message: format!(
+                    "Invalid volume geometry: {} bytes per sector, {} sectors per cluster",
+                    boot.bytes_per_sector, boot.sectors_per_cluster
+                ),
+                offset: Some(11),
+            });
+        }
+
+        Ok(Self {
+            file,
+            boot,
+            sector_size,
+            cluster_size,
+            volume_size,
+            mft_runs: None,
+        })
+    }
+
+    pub fn boot_sector(&self) -> &BootSector {
+        &self.boot
+    }
+
+    pub fn cluster_size(&self) -> u64 {
+        self.cluster_size
+    }
+
+    pub fn volume_size(&self) -> u64 {
+        self.volume_size
+    }
+
+    /// Size of a FILE record as declared by the boot sector
+    pub fn mft_record_size(&self) -> usize {
+        let clusters = self.boot.clusters_per_mft_record;
+        if clusters < 0 {
+            1usize << (-(clusters as i32)) as u32
+        } else {
+            clusters as usize * self.cluster_size as usize
+        }
+    }
+
+    /// Read `length` bytes at `offset`, widening the request to whole sectors
+    pub fn read_at(&mut self, offset: u64, length: usize) -> ParseResult<Vec<u8>> {
+        let aligned_start = offset - offset % self.sector_size;
+        let end = offset + length as u64;
+        let aligned_end = end.div_ceil(self.sector_size) * self.sector_size;
+
+        if end > self.volume_size {
+            return Err(ParseError {
+                code: ErrorCode::Truncated,
+                message: format!(
+                    "Read of {} bytes at 0x{:x} runs past the end of the volume ({} bytes)",
+                    length, offset, self.volume_size
+                ),
+                offset: Some(offset),
+            });
+        }
+
+        let mut buffer = vec![0u8; (aligned_end - aligned_start) as usize];
+        self.file.seek(SeekFrom::Start(aligned_start))?;
+
+        // Images need not be a whole number of sectors; tolerate a short final read
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = self.file.read(&mut buffer[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+
+        let skip = (offset - aligned_start) as usize;
+        if filled < skip + length {
+            return Err(ParseError {
+                code: ErrorCode::Truncated,
+                message: format!("Unexpected end of volume reading 0x{:x}", offset),
+                offset: Some(offset),
+            });
+        }
+
+        buffer.truncate(skip + length);
+        buffer.drain(..skip);
+        Ok(buffer)
+    }
+
+    /// Read the complete $MFT by following the data runs of its own record 0
+    pub fn read_mft(&mut self) -> ParseResult<Vec<u8>> {
+        let data = self.mft_data_attribute()?;
+        self.read_stream(&data.runs, data.real_size, false).map(|(_, data)| data)
+    }
+
+    /// Read one FILE record of the $MFT by entry number, without fixups applied
+    pub fn read_mft_record(&mut self, entry: u64) -> ParseResult<Vec<u8>> {
+        let runs = match self.mft_runs {
+            Some(ref runs) => runs.clone(),
+            None => self.mft_data_attribute()?.runs,
+        };
+        let record_size = self.mft_record_size() as u64;
+        let start = entry.checked_mul(record_size).ok_or_else(|| ParseError {
+            code: ErrorCode::VolumeLayout,
+            message: format!("MFT entry {} is out of range", entry),
+            offset: None,
+        })?;
+
+        let (_, record) = self.read_stream_from(&runs, start + record_size, start, false)?;
+        if record.len() as u64 != record_size {
+            return Err(ParseError {
+                code: ErrorCode::VolumeLayout,
+                message: format!("MFT entry {} lies beyond the end of the $MFT data runs", entry),
+                offset: Some(start),
+            });
+        }
+        Ok(record)
+    }
+
+    /// The $DATA attribute of $MFT record 0, whose runs map the whole $MFT
+    fn mft_data_attribute(&mut self) -> ParseResult<DataAttribute> {
+        let record_size = self.mft_record_size();
+        let mft_offset = self.boot.mft_start_cluster.checked_mul(self.cluster_size).ok_or_else(|| ParseError {
+            code: ErrorCode::VolumeLayout,
+            message: format!("MFT start cluster {} is out of range", self.boot.mft_start_cluster),
+            offset: Some(48),
+        })?;
+        debug!("Reading $MFT record 0 at volume offset 0x{:x}", mft_offset);
+
+        let mut record = self.read_at(mft_offset, record_size)?;
+        apply_fixups(&mut record)?;
+
+        let data = find_data_attributes(&record, "")
+            .into_iter()
+            .find(|attribute| attribute.starting_vcn == 0)
+            .ok_or_else(|| ParseError {
+                code: ErrorCode::VolumeLayout,
+                message: "$MFT record 0 has no non-resident $DATA attribute".to_string(),
+                offset: Some(mft_offset),
+            })?;
+
+        let mapped_bytes: u64 = data.runs.iter().map(|run| run.length).sum::<u64>() * self.cluster_size;
+        if mapped_bytes < data.real_size {
+            warn!(
+                "$MFT data runs in record 0 cover {} of {} bytes; the rest is described by an attribute list and will be skipped",
+                mapped_bytes, data.real_size
+            );
+        }
+
+        self.mft_runs = Some(data.runs.clone());
+        Ok(data)
+    }
+
+    /// Read the $BITMAP of $MFT record 0, with a bit for each $MFT entry in use
+    pub fn read_mft_bitmap(&mut self) -> ParseResult<Option<Vec<u8>>> {
+        let mut record = self.read_mft_record(0)?;
+        apply_fixups(&mut record)?;
+
+        let Some(bitmap) = find_non_resident_attributes(&record, MFT_BITMAP_ATTRIBUTE, "")
+            .into_iter()
+            .find(|attribute| attribute.starting_vcn == 0)
+        else {
+            return Ok(None);
+        };
+        self.read_stream(&bitmap.runs, bitmap.real_size, false).map(|(_, data)| Some(data))
+    }
+
+    /// Read the cluster allocation bitmap from the $Bitmap record of the extracted $MFT
+    pub fn read_bitmap(&mut self, mft: &[u8]) -> ParseResult<Option<Vec<u8>>> {
+        let record_size = self.mft_record_size();
+        let Some(raw) = mft.chunks_exact(record_size).nth(BITMAP_ENTRY) else {
+            return Ok(None);
+        };
+        if !raw.starts_with(b"FILE") {
+            debug!("MFT entry {} is not a FILE record; no $Bitmap available", BITMAP_ENTRY);
+            return Ok(None);
+        }
+
+        let mut record = raw.to_vec();
+        apply_fixups(&mut record)?;
+
+        let Some(data) = find_data_attributes(&record, "")
+            .into_iter()
+            .find(|attribute| attribute.starting_vcn == 0)
+        else {
+            return Ok(None);
+        };
+
+        self.read_stream(&data.runs, data.real_size, false).map(|(_, data)| Some(data))
+    }
+
+    /// Locate $Extend\$UsnJrnl in the extracted $MFT and read its $J stream.
+    ///
+    /// The sparse range at the start of $J is skipped, so the result holds only the
+    /// allocated tail of the journal where live records are kept, together with the
+    /// offset of that tail within $J.
+    pub fn read_usn_journal(&mut self, mft: &[u8]) -> ParseResult<Option<(u64, Vec<u8>)>> {
+        let Some(entry) = self.usn_journal_entry(mft) else {
+            return Ok(None);
+        };
+        self.read_entry_stream(mft, entry, "$J", true)
+    }
+
+    /// Find the records that hold $UsnJrnl:$J, for reading the journal again later
+    /// without the full $MFT
+    pub fn locate_usn_journal(&self, mft: &[u8]) -> Option<UsnJournalLocation> {
+        let record_size = self.mft_record_size();
+        let entry = self.usn_journal_entry(mft)?;
+        let records = mft
+            .chunks_exact(record_size)
+            .enumerate()
+            .filter(|(index, raw)| *index == entry || base_record_entry(raw) == Some(entry as u64))
+            .map(|(index, _)| index as u64)
+            .collect();
+        Some(UsnJournalLocation { records })
+    }
+
+    /// Read what was appended to $J since offset `from`, re-reading its records so
+    /// growth and wrapping since the last read are seen.
+    ///
+    /// Once the journal has wrapped past `from`, reading resumes at the oldest
+    /// allocated byte; `start` tells how much was lost. An `end` below `from`
+    /// means the journal was deleted and recreated. None when the records no
+    /// longer hold a $J stream.
+    pub fn read_usn_journal_tail(&mut self, location: &UsnJournalLocation, from: u64) -> ParseResult<Option<UsnJournalTail>> {
+        let mut fragments = Vec::new();
+        let mut journal_id = None;
+        for &entry in &location.records {
+            let raw = self.read_mft_record(entry)?;
+            fragments.extend(self.stream_fragments(entry as usize, &raw, "$J"));
+            journal_id = journal_id.or_else(|| usn_journal_id(&raw));
+        }
+        if fragments.is_empty() {
+            return Ok(None);
+        }
+
+        fragments.sort_by_key(|fragment| fragment.starting_vcn);
+        let real_size = fragments[0].real_size;
+        let runs: Vec<DataRun> = fragments.into_iter().flat_map(|fragment| fragment.runs).collect();
+
+        let (start, data) = self.read_stream_from(&runs, real_size, from, true)?;
+        Ok(Some(UsnJournalTail { start, end: real_size, data, journal_id }))
+    }
+
+    fn usn_journal_entry(&self, mft: &[u8]) -> Option<usize> {
+        let entry = mft
+            .chunks_exact(self.mft_record_size())
+            .position(|record| has_file_name(record, "$UsnJrnl", EXTEND_ENTRY))?;
+        debug!("Found $UsnJrnl at MFT entry {}", entry);
+        Some(entry)
+    }
+
+    /// Read the $Secure:$SDS stream of the extracted $MFT
+    pub fn read_sds(&mut self, mft: &[u8]) -> ParseResult<Option<Vec<u8>>> {
+        self.read_entry_stream(mft, SECURE_ENTRY, "$SDS", false)
+            .map(|stream| stream.map(|(_, data)| data))
+    }
+
+    /// Read the $LogFile of the extracted $MFT
+    pub fn read_logfile(&mut self, mft: &[u8]) -> ParseResult<Option<Vec<u8>>> {
+        self.read_entry_stream(mft, LOGFILE_ENTRY, "", false)
+            .map(|stream| stream.map(|(_, data)| data))
+    }
+
+    /// Read the $Boot file, the first 8 KB of the volume
+    pub fn read_boot(&mut self) -> ParseResult<Vec<u8>> {
+        self.read_at(0, BOOT_FILE_SIZE.min(self.volume_size) as usize)
+    }
+
+    /// Read the non-resident $DATA stream `name` of an MFT entry.
+    ///
+    /// The stream may be split across extension records when the file is heavily
+    /// fragmented, so the runs of every record whose base is `entry` are joined.
+    fn read_entry_stream(
+        &mut self,
+        mft: &[u8],
+        entry: usize,
+        name: &str,
+        skip_leading_sparse: bool,
+    ) -> ParseResult<Option<(u64, Vec<u8>)>> {
+        let record_size = self.mft_record_size();
+
+        let mut fragments = Vec::new();
+        for (index, raw) in mft.chunks_exact(record_size).enumerate() {
+            if index == entry || base_record_entry(raw) == Some(entry as u64) {
+                fragments.extend(self.stream_fragments(index, raw, name));
+            }
+        }
+
+        if fragments.is_empty() {
+            return Ok(None);
+        }
+
+        fragments.sort_by_key(|fragment| fragment.starting_vcn);
+        let real_size = fragments[0].real_size;
+        let runs: Vec<DataRun> = fragments.into_iter().flat_map(|fragment| fragment.runs).collect();
+
+        self.read_stream(&runs, real_size, skip_leading_sparse).map(Some)
+    }
+
+    /// The pieces of $DATA stream `name` held by one FILE record
+    fn stream_fragments(&self, entry: usize, raw: &[u8], name: &str) -> Vec<DataAttribute> {
+        let mut record = raw.to_vec();
+        if apply_fixups(&mut record).is_err() {
+            warn!("Skipping MFT entry {} with invalid fixups while locating $DATA:{}", entry, name);
+            return Vec::new();
+        }
+        find_data_attributes(&record, name)
+    }
+
+    /// Read a file's $DATA stream, decompressing it when it is NTFS-compressed.
+    ///
+    /// A compressed stream is stored in units of 2^`compression_unit` clusters.
+    /// A unit whose clusters are all allocated didn't compress and is stored as
+    /// is; one whose last clusters are sparse holds LZNT1 chunks in the others;
+    /// a unit that is sparse throughout is zeros.
+    pub fn read_file_data(&mut self, runs: &[DataRun], real_size: u64, compression_unit: u16) -> ParseResult<Vec<u8>> {
+        if compression_unit == 0 || compression_unit > MAX_COMPRESSION_UNIT {
+            return self.read_stream(runs, real_size, false).map(|(_, data)| data);
+        }
+
+        let unit_clusters = 1u64 << compression_unit;
+        let unit_size = (unit_clusters * self.cluster_size) as usize;
+        let (_, raw) = self.read_stream(runs, real_size.div_ceil(unit_size as u64) * unit_size as u64, false)?;
+
+        let mut data = Vec::with_capacity(raw.len());
+        for (index, unit) in raw.chunks(unit_size).enumerate() {
+            let first_vcn = index as u64 * unit_clusters;
+            let compressed = runs
+                .iter()
+                .any(|run| run.lcn.is_none() && run.vcn < first_vcn + unit_clusters && run.vcn + run.length > first_vcn);
+            data.resize(index * unit_size, 0);
+            if compressed {
+                let mut decompressed = compression::decompress_lznt1(unit).map_err(|error| ParseError {
+                    code: error.code,
+                    message: format!("compression unit {}: {}", index, error.message),
+                    offset: None,
+                })?;
+                decompressed.resize(unit_size, 0);
+                data.extend_from_slice(&decompressed);
+            } else {
+                data.extend_from_slice(unit);
+            }
+        }
+        data.truncate(real_size as usize);
+        Ok(data)
+    }
+
+    /// Concatenate the clusters described by `runs`, truncated to `real_size`.
+    ///
+    /// Returns the stream offset of the first byte read, which is non-zero only when
+    /// leading sparse runs are skipped.
+    fn read_stream(&mut self, runs: &[DataRun], real_size: u64, skip_leading_sparse: bool) -> ParseResult<(u64, Vec<u8>)> {
+        self.read_stream_from(runs, real_size, 0, skip_leading_sparse)
+    }
+
+    /// Like `read_stream`, starting at stream offset `from` instead of the beginning
+    fn read_stream_from(
+        &mut self,
+        runs: &[DataRun],
+        real_size: u64,
+        from: u64,
+        skip_leading_sparse: bool,
+    ) -> ParseResult<(u64, Vec<u8>)> {
+        if real_size.saturating_sub(from) > self.volume_size && !skip_leading_sparse {
+            return Err(ParseError {
+                code: ErrorCode::VolumeLayout,
+                message: format!("Stream size {} exceeds volume size {}", real_size, self.volume_size),
+                offset: None,
+            });
+        }
+
+        let mut data = Vec::new();
+        let mut stream_start = from;
+        for run in runs {
+            let run_start = run.vcn.saturating_mul(self.cluster_size);
+            if run_start >= real_size {
+                break;
+            }
+            let end = run.vcn.saturating_add(run.length).saturating_mul(self.cluster_size).min(real_size);
+            if end <= from {
+                continue;
+            }
+            let start = run_start.max(from);
+            let length = (end - start) as usize;
+
+            match run.lcn {
+                None if skip_leading_sparse && data.is_empty() => stream_start = end,
+                None => data.resize(data.len() + length, 0),
+                Some(lcn) => {
+                    let mut offset = lcn.saturating_mul(self.cluster_size) + (start - run_start);
+                    let mut remaining = length;
+                    while remaining > 0 {
+                        let chunk = remaining.min(READ_CHUNK_SIZE);
+                        data.extend_from_slice(&self.read_at(offset, chunk)?);
+                        offset += chunk as u64;
+                        remaining -= chunk;
+                    }
+                }
+            }
+        }
+
+        Ok((stream_start, data))
+    }
+}
+
+/// A volume image or device starts with a boot sector and is larger than a $Boot extract
+pub fn detect(input: &InputInfo) -> u8 {
+    if boot::has_boot_signature(&input.header) && (input.size > BOOT_FILE_SIZE || !input.is_regular_file) {
+        SIGNATURE_MATCH
+    } else {
+        NO_MATCH
+    }
+}
+
+/// Check the FILE signature, then restore the sector-end bytes saved in the
+/// update sequence array
+fn apply_fixups(record: &mut [u8]) -> ParseResult<()> {
+    if record.len() < 8 || &record[0..4] != b"FILE" {
+        return Err(ParseError {
+            code: ErrorCode::BadSignature,
+            message: "Missing FILE signature".to_string(),
+            offset: Some(0),
+        });
+    }
+    fixup::apply(record).map_err(|code| ParseError {
+        code,
+        message: "Update sequence check failed".to_string(),
+        offset: Some(4),
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// Walk the attributes of an in-use FILE record, yielding (type, name, attribute bytes)
+fn attributes(record: &[u8]) -> Vec<(u32, String, &[u8])> {
+    let mut found = Vec::new();
+    if record.len() < 0x18 || &record[0..4] != b"FILE" {
+        return found;
+    }
+    let flags = read_u16(record, 0x16).unwrap_or(0);
+    if flags & 0x01 == 0 {
+        return found;
+    }
+
+    let mut pos = read_u16(record, 0x14).unwrap_or(0) as usize;
+    while let Some(attr_type) = read_u32(record, pos) {
+        if attr_type == 0xFFFFFFFF {
+            break;
+        }
+        let length = read_u32(record, pos + 4).unwrap_or(0) as usize;
+        if length < 0x18 || pos + length > record.len() {
+            break;
+        }
+        let attribute = &record[pos..pos + length];
+        let Ok(header) = AttributeHeader::parse(attribute) else {
+            break;
+        };
+
+        found.push((attr_type, header.name(attribute), attribute));
+        pos += length;
+    }
+
+    found
+}
+
+fn find_data_attributes(record: &[u8], name: &str) -> Vec<DataAttribute> {
+    find_non_resident_attributes(record, 0x80, name)
+}
+
+/// Non-resident attributes of `attr_type` named `name` in a FILE record
+fn find_non_resident_attributes(record: &[u8], attr_type: u32, name: &str) -> Vec<DataAttribute> {
+    attributes(record)
+        .into_iter()
+        .filter(|(found_type, attr_name, attribute)| *found_type == attr_type && attr_name == name && attribute[8] != 0)
+        .filter_map(|(_, _, attribute)| {
+            let header = AttributeHeader::parse(attribute).ok()?;
+            let non_resident = header.non_resident()?;
+            let runs = decode_runlist(header.runlist(attribute)?, non_resident.starting_vcn)
+                .map_err(|e| warn!("Invalid data runs for attribute 0x{:X}:{}: {}", attr_type, name, e))
+                .ok()?;
+            Some(DataAttribute {
+                runs,
+                starting_vcn: non_resident.starting_vcn,
+                real_size: non_resident.real_size,
+            })
+        })
+        .collect()
+}
+
+/// UsnJournalID from the resident $Max stream of a $UsnJrnl record
+fn usn_journal_id(raw: &[u8]) -> Option<u64> {
+    let mut record = raw.to_vec();
+    apply_fixups(&mut record).ok()?;
+    attributes(&record)
+        .into_iter()
+        .filter(|(attr_type, name, attribute)| *attr_type == 0x80 && name == "$Max" && attribute[8] == 0)
+        .find_map(|(_, _, attribute)| {
+            let header = AttributeHeader::parse(attribute).ok()?;
+            // $Max: maximum size, allocation delta, then the journal ID
+            read_u64(header.resident_content(attribute)?, 0x10)
+        })
+}
+
+/// Check whether a record carries a $FILE_NAME with this name under the given parent
+fn has_file_name(record: &[u8], name: &str, parent_entry: u64) -> bool {
+    attributes(record).into_iter().any(|(attr_type, _, attribute)| {
+        if attr_type != 0x30 || attribute[8] != 0 {
+            return false;
+        }
+        let Some(content_offset) = read_u16(attribute, 0x14) else {
+            return false;
+        };
+        let content = &attribute[(content_offset as usize).min(attribute.len())..];
+        let (Some(parent), Some(&name_length)) = (read_u64(content, 0), content.get(64)) else {
+            return false;
+        };
+        let Some(name_bytes) = content.get(66..66 + name_length as usize * 2) else {
+            return false;
+        };
+        parent & 0x0000_FFFF_FFFF_FFFF == parent_entry && decode_utf16le_lossy(name_bytes) == name
+    })
+}
+
+/// Entry number of the base record when this record is an extension record
+fn base_record_entry(record: &[u8]) -> Option<u64> {
+    if record.len() < 0x28 || &record[0..4] != b"FILE" {
+        return None;
+    }
+    let base = read_u64(record, 0x20)? & 0x0000_FFFF_FFFF_FFFF;
+    (base != 0).then_some(base)
+}
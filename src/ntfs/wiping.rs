@@ -0,0 +1,177 @@
+use super::bitmap::ClusterBitmap;
+use super::filetime::FileTime;
+use super::types::{MftRecord, UsnJournalEntry, WipingFinding, WipingPattern};
+use std::collections::HashSet;
+
+/// Consecutive deleted records with missing or overwritten names before a run is reported
+const MIN_DELETED_RUN: usize = 16;
+
+/// Deletions this close together belong to the same burst
+const MASS_DELETION_GAP_SECONDS: i64 = 10;
+
+/// Files a burst must delete to be reported
+const MIN_MASS_DELETION: usize = 200;
+
+/// Shortest name (without dots) taken for a wiper's overwrite pass
+const MIN_WIPER_NAME_LENGTH: usize = 8;
+
+/// Entry numbers listed in a finding's detail before it is cut short
+const MAX_LISTED_ENTRIES: usize = 10;
+
+const USN_REASON_FILE_DELETE: u32 = 0x0000_0200;
+const USN_REASON_RENAME_NEW_NAME: u32 = 0x0000_2000;
+
+/// Tools such as SDelete rename a file through AAAAAAAA.AAA ... ZZZZZZZZ.ZZZ
+/// before deleting it, so its real name doesn't survive in the $MFT
+pub fn is_wiper_name(name: &str) -> bool {
+    let mut letters = name.chars().filter(|&c| c != '.');
+    let Some(first) = letters.next() else {
+        return false;
+    };
+    let mut length = 1;
+    for c in letters {
+        if c != first {
+            return false;
+        }
+        length += 1;
+    }
+    first.is_ascii_alphanumeric() && length >= MIN_WIPER_NAME_LENGTH
+}
+
+/// Runs of deleted records with wiped names, and records named by a wiper
+pub fn mft_findings(records: &[MftRecord]) -> Vec<WipingFinding> {
+    let mut findings = Vec::new();
+
+    // Records are in $MFT order, so a run is a sequence of adjacent entry numbers
+    let mut run: Vec<u32> = Vec::new();
+    let wiped = |record: &MftRecord| {
+        !record.in_use && record.base_record_reference.is_empty() && (record.file_name.is_empty() || is_wiper_name(&record.file_name))
+    };
+    for record in records.iter().filter(|record| !record.is_ads && !record.is_hard_link) {
+        let adjacent = run.last().is_some_and(|&last| record.entry_number == last + 1);
+        if wiped(record) {
+            if !adjacent {
+                push_deleted_run(&mut findings, &run);
+                run.clear();
+            }
+            run.push(record.entry_number);
+        } else {
+            push_deleted_run(&mut findings, &run);
+            run.clear();
+        }
+    }
+    push_deleted_run(&mut findings, &run);
+
+    let renamed: Vec<u32> = records
+        .iter()
+        .filter(|record| !record.is_ads && is_wiper_name(&record.file_name))
+        .map(|record| record.entry_number)
+        .collect();
+    if !renamed.is_empty() {
+        findings.push(WipingFinding {
+            pattern: WipingPattern::WiperRename,
+            count: renamed.len() as u64,
+            start: None,
+            end: None,
+            detail: format!("$MFT records named like a wiper's overwrite pass: entries {}", list_entries(&renamed)),
+        });
+    }
+
+    findings
+}
+
+fn push_deleted_run(findings: &mut Vec<WipingFinding>, run: &[u32]) {
+    if run.len() < MIN_DELETED_RUN {
+        return;
+    }
+    findings.push(WipingFinding {
+        pattern: WipingPattern::DeletedRecordRun,
+        count: run.len() as u64,
+        start: None,
+        end: None,
+        detail: format!(
+            "Entries {}-{} are consecutive deleted records with empty or overwritten names",
+            run[0],
+            run[run.len() - 1]
+        ),
+    });
+}
+
+/// Bursts of deletions and renames to wiper names in $J
+pub fn usn_findings(entries: &[UsnJournalEntry]) -> Vec<WipingFinding> {
+    let mut findings = Vec::new();
+
+    // A deletion is journalled more than once (FILE_DELETE, then with CLOSE);
+    // count each file reference once, at its first deletion record
+    let mut seen = HashSet::new();
+    let mut deletions: Vec<FileTime> = entries
+        .iter()
+        .filter(|entry| entry.reason_flags & USN_REASON_FILE_DELETE != 0)
+        .filter(|entry| seen.insert((entry.entry_number, entry.sequence_number)))
+        .map(|entry| entry.timestamp)
+        .collect();
+    deletions.sort_unstable();
+
+    let mut burst_start = 0;
+    for index in 1..=deletions.len() {
+        let burst_ends = index == deletions.len()
+            || deletions[index].timestamp() - deletions[index - 1].timestamp() > MASS_DELETION_GAP_SECONDS;
+        if !burst_ends {
+            continue;
+        }
+
+        let burst = &deletions[burst_start..index];
+        if burst.len() >= MIN_MASS_DELETION {
+            let (start, end) = (burst[0], burst[burst.len() - 1]);
+            let seconds = (end.timestamp() - start.timestamp()).max(1);
+            findings.push(WipingFinding {
+                pattern: WipingPattern::MassDeletion,
+                count: burst.len() as u64,
+                start: Some(start),
+                end: Some(end),
+                detail: format!("{} files deleted in {} s ({} per second)", burst.len(), seconds, burst.len() as i64 / seconds),
+            });
+        }
+        burst_start = index;
+    }
+
+    let renames: Vec<&UsnJournalEntry> = entries
+        .iter()
+        .filter(|entry| entry.reason_flags & USN_REASON_RENAME_NEW_NAME != 0 && is_wiper_name(&entry.file_name))
+        .collect();
+    if let (Some(first), Some(last)) = (renames.first(), renames.last()) {
+        let mut files: Vec<u32> = renames.iter().map(|entry| entry.entry_number).collect();
+        files.sort_unstable();
+        files.dedup();
+        findings.push(WipingFinding {
+            pattern: WipingPattern::WiperRename,
+            count: renames.len() as u64,
+            start: Some(first.timestamp),
+            end: Some(last.timestamp),
+            detail: format!("Renames to wiper overwrite-pass names, affecting entries {}", list_entries(&files)),
+        });
+    }
+
+    findings
+}
+
+/// The largest run of free clusters, as context for free-space wiping
+pub fn free_space_finding(bitmap: &ClusterBitmap, volume_clusters: u64) -> Option<WipingFinding> {
+    let (lcn, length) = bitmap.largest_free_extent()?;
+    let percent = length as f64 * 100.0 / volume_clusters.max(1) as f64;
+    Some(WipingFinding {
+        pattern: WipingPattern::LargestFreeExtent,
+        count: length,
+        start: None,
+        end: None,
+        detail: format!("{} free clusters starting at LCN {} ({:.1}% of the volume)", length, lcn, percent),
+    })
+}
+
+fn list_entries(entries: &[u32]) -> String {
+    let listed: Vec<String> = entries.iter().take(MAX_LISTED_ENTRIES).map(u32::to_string).collect();
+    match entries.len().saturating_sub(MAX_LISTED_ENTRIES) {
+        0 => listed.join(", "),
+        more => format!("{} and {} more", listed.join(", "), more),
+    }
+}
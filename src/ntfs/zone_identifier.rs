@@ -0,0 +1,52 @@
+use super::names::decode_utf16le;
+
+/// Name of the alternate data stream browsers and mail clients attach to
+/// downloads (Mark-of-the-Web)
+pub const ZONE_IDENTIFIER_STREAM: &str = "Zone.Identifier";
+
+/// The fields of a Zone.Identifier stream, e.g.
+///
+/// ```text
+/// [ZoneTransfer]
+/// ZoneId=3
+/// ReferrerUrl=https://example.com/
+/// HostUrl=https://example.com/setup.exe
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZoneIdentifier {
+    /// The stream as text, without trailing whitespace or NULs
+    pub contents: String,
+    /// URL zone: 0 local machine, 1 intranet, 2 trusted, 3 internet, 4 restricted
+    pub zone_id: Option<u32>,
+    pub referrer_url: String,
+    pub host_url: String,
+}
+
+/// Parse a Zone.Identifier stream; keys are matched case-insensitively and
+/// unknown keys are only kept in `contents`
+pub fn parse(content: &[u8]) -> ZoneIdentifier {
+    // Some tools write the stream as UTF-16 with a byte order mark
+    let text = match content {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16le(rest).unwrap_or_else(|_| String::from_utf8_lossy(content).into_owned()),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(content).into_owned(),
+    };
+
+    let mut zone = ZoneIdentifier {
+        contents: text.trim_end_matches(|c: char| c == '\0' || c.is_whitespace()).to_string(),
+        ..ZoneIdentifier::default()
+    };
+    for line in zone.contents.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "zoneid" => zone.zone_id = value.parse().ok(),
+            "referrerurl" => zone.referrer_url = value.to_string(),
+            "hosturl" => zone.host_url = value.to_string(),
+            _ => {}
+        }
+    }
+    zone
+}
@@ -0,0 +1,131 @@
+use crate::evidence::{self, EvidenceVerification};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Audit log file name, shared by every run writing to the same directory
+pub const AUDIT_LOG_NAME: &str = "mfte-rs_audit.jsonl";
+
+/// One chain-of-custody record, appended as a single JSON line per invocation
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub arguments: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    pub host: HostInfo,
+    pub inputs: Vec<AuditInput>,
+    pub outputs: Vec<PathBuf>,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    pub status: AuditStatus,
+    pub error: Option<String>,
+    pub evidence: Option<EvidenceVerification>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditInput {
+    pub role: &'static str,
+    pub path: PathBuf,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditStatus {
+    Running,
+    Success,
+    Failed,
+}
+
+impl AuditRecord {
+    /// Start a record for this invocation, hashing each input
+    pub fn begin(inputs: Vec<(&'static str, PathBuf)>, outputs: Vec<PathBuf>) -> Self {
+        let inputs = inputs
+            .into_iter()
+            .map(|(role, path)| {
+                let sha256 = evidence::input_sha256(&path).ok();
+                AuditInput { role, path, sha256 }
+            })
+            .collect();
+
+        Self {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            arguments: std::env::args().collect(),
+            working_directory: std::env::current_dir().ok(),
+            host: HostInfo::current(),
+            inputs,
+            outputs,
+            started: Utc::now(),
+            finished: None,
+            status: AuditStatus::Running,
+            error: None,
+            evidence: None,
+        }
+    }
+
+    pub fn finish(&mut self, error: Option<String>) {
+        self.finished = Some(Utc::now());
+        self.status = if error.is_some() { AuditStatus::Failed } else { AuditStatus::Success };
+        self.error = error;
+    }
+
+    /// Append this record to the audit log in every output directory
+    pub fn append_to_outputs(&self) -> Result<Vec<PathBuf>> {
+        let line = serde_json::to_string(self)?;
+        let mut written = Vec::new();
+
+        for dir in &self.outputs {
+            let path = dir.join(AUDIT_LOG_NAME);
+            append_line(&path, &line)?;
+            written.push(path);
+        }
+
+        Ok(written)
+    }
+}
+
+impl HostInfo {
+    fn current() -> Self {
+        Self {
+            hostname: hostname(),
+            user: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    if let Ok(name) = std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")) {
+        return Some(name);
+    }
+
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Audit logs are only ever appended to, never truncated
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    file.flush()?;
+    Ok(())
+}
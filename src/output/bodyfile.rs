@@ -1,9 +1,77 @@
+use crate::ntfs::filetime::FileTime;
 use crate::ntfs::types::*;
 use anyhow::Result;
+use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
+const FILE_ATTRIBUTE_READONLY: u32 = 0x0001;
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x0002;
+const FILE_ATTRIBUTE_SYSTEM: u32 = 0x0004;
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x0010;
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x0400;
+
+/// Settings shared by every line of a bodyfile
+#[derive(Debug, Clone, Default)]
+pub struct BodyfileOptions {
+    /// Drive letter prefixed to every path
+    pub drive_letter: String,
+    /// Use LF vs CRLF line endings
+    pub use_lf: bool,
+    /// Owner SID RID by security ID, written as the UID column when $SDS is available
+    pub owner_uids: HashMap<u32, u32>,
+    /// Add a "($FILE_NAME)" row with the 0x30 timestamps after each MFT record
+    pub file_name_rows: bool,
+}
+
+impl BodyfileOptions {
+    pub fn new(drive_letter: &str, use_lf: bool) -> Self {
+        Self {
+            drive_letter: drive_letter.to_string(),
+            use_lf,
+            owner_uids: HashMap::new(),
+            file_name_rows: false,
+        }
+    }
+
+    pub fn with_file_name_rows(mut self, file_name_rows: bool) -> Self {
+        self.file_name_rows = file_name_rows;
+        self
+    }
+
+    pub fn with_owner_uids(mut self, owner_uids: HashMap<u32, u32>) -> Self {
+        self.owner_uids = owner_uids;
+        self
+    }
+
+    fn uid(&self, security_id: i32) -> u32 {
+        self.owner_uids.get(&(security_id as u32)).copied().unwrap_or(0)
+    }
+}
+
+/// Bodyfile mode string approximated from Windows file attributes.
+///
+/// The type is `d` for directories, `l` for reparse points and `r` otherwise.
+/// Read-only files lose their write bits, system files show `s` in the owner
+/// execute position and hidden files show `t` in the other execute position,
+/// so `r/r-sr-xr-t` is a read-only hidden system file.
+pub fn mode_string(is_directory: bool, attributes: u32) -> String {
+    let file_type = if is_directory || attributes & FILE_ATTRIBUTE_DIRECTORY != 0 {
+        'd'
+    } else if attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+        'l'
+    } else {
+        'r'
+    };
+
+    let write = if attributes & FILE_ATTRIBUTE_READONLY != 0 { '-' } else { 'w' };
+    let owner_execute = if attributes & FILE_ATTRIBUTE_SYSTEM != 0 { 's' } else { 'x' };
+    let other_execute = if attributes & FILE_ATTRIBUTE_HIDDEN != 0 { 't' } else { 'x' };
+
+    format!("{file_type}/r{write}{owner_execute}r{write}xr{write}{other_execute}")
+}
+
 pub struct BodyfileOutput;
 
 impl BodyfileOutput {
@@ -12,118 +80,153 @@ impl BodyfileOutput {
     pub fn write_mft_records<P: AsRef<Path>>(
         records: &[MftRecord],
         path: P,
-        drive_letter: &str,
-        use_lf: bool,
+        options: &BodyfileOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        let mut writer = BufWriter::new(file);
-        let newline = if use_lf { "\n" } else { "\r\n" };
-
-        for record in records {
-            if !record.in_use {
-                continue;
-            }
-
-            let full_path = if record.parent_path.is_empty() {
-                format!("{}:/{}", drive_letter, record.file_name)
-            } else {
-                format!("{}:/{}/{}", drive_letter, record.parent_path, record.file_name)
-            };
-
-            let mode = if record.is_directory { "d" } else { "r" };
-            let permissions = format!("{}/r-xr-xr-x", mode);
-
-            // Convert timestamps to Unix epoch
-            let atime = record.last_access_0x10
-                .map(|t| t.timestamp())
-                .unwrap_or(0);
-            let mtime = record.last_modified_0x10
-                .map(|t| t.timestamp())
-                .unwrap_or(0);
-            let ctime = record.last_record_change_0x10
-                .map(|t| t.timestamp())
-                .unwrap_or(0);
-            let crtime = record.created_0x10
-                .map(|t| t.timestamp())
-                .unwrap_or(0);
-
-            let line = format!(
-                "0|{}|{}|{}|0|0|{}|{}|{}|{}|{}{}",
-                full_path,
-                record.entry_number,
-                permissions,
-                record.file_size,
-                atime,
-                mtime,
-                ctime,
-                crtime,
-                newline
-            );
-
-            writer.write_all(line.as_bytes())?;
-        }
-
-        writer.flush()?;
-        Ok(())
+        Self::write_lines(records, path, |record| Self::mft_record_line(record, options))
     }
 
     /// Write USN Journal entries in bodyfile format
     pub fn write_usn_journal_entries<P: AsRef<Path>>(
         entries: &[UsnJournalEntry],
         path: P,
-        drive_letter: &str,
-        use_lf: bool,
+        options: &BodyfileOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
+        Self::write_lines(entries, path, |entry| Some(Self::usn_journal_line(entry, options)))
+    }
+
+    /// Write Index entries in bodyfile format
+    pub fn write_index_entries<P: AsRef<Path>>(
+        entries: &[IndexEntry],
+        path: P,
+        options: &BodyfileOptions,
+    ) -> Result<()> {
+        Self::write_lines(entries, path, |entry| Some(Self::index_entry_line(entry, options)))
+    }
+
+    /// Format a single MFT record as a bodyfile line; unallocated records are skipped.
+    ///
+    /// With `file_name_rows`, a second line with the $FILE_NAME timestamps follows,
+    /// its name suffixed with " ($FILE_NAME)" as MFTECmd does.
+    pub fn mft_record_line(record: &MftRecord, options: &BodyfileOptions) -> Option<String> {
+        if !record.in_use {
+            return None;
         }
-        let file = File::create(path)?;
-        let mut writer = BufWriter::new(file);
-        let newline = if use_lf { "\n" } else { "\r\n" };
-
-        for entry in entries {
-            let full_path = if entry.full_path.is_empty() {
-                format!("{}:/{}", drive_letter, entry.file_name)
-            } else {
-                format!("{}:{}", drive_letter, entry.full_path)
-            };
-
-            let is_directory = (entry.file_attributes & 0x10) != 0;
-            let mode = if is_directory { "d" } else { "r" };
-            let permissions = format!("{}/r-xr-xr-x", mode);
-
-            let timestamp = entry.timestamp.timestamp();
-
-            let line = format!(
-                "0|{}|{}|{}|0|0|0|{}|{}|{}|{}{}",
-                full_path,
-                entry.entry_number,
-                permissions,
-                timestamp,
-                timestamp,
-                timestamp,
-                timestamp,
-                newline
-            );
 
-            writer.write_all(line.as_bytes())?;
+        let drive_letter = &options.drive_letter;
+        let full_path = if record.parent_path.is_empty() {
+            format!("{}:/{}", drive_letter, record.file_name)
+        } else {
+            format!("{}:/{}/{}", drive_letter, record.parent_path, record.file_name)
+        };
+
+        let mut line = mft_line(
+            record,
+            &full_path,
+            [
+                record.last_access_0x10,
+                record.last_modified_0x10,
+                record.last_record_change_0x10,
+                record.created_0x10,
+            ],
+            options,
+        );
+
+        if options.file_name_rows && record.created_0x30.is_some() {
+            line.push_str(&mft_line(
+                record,
+                &format!("{} ($FILE_NAME)", full_path),
+                [
+                    record.last_access_0x30,
+                    record.last_modified_0x30,
+                    record.last_record_change_0x30,
+                    record.created_0x30,
+                ],
+                options,
+            ));
         }
 
-        writer.flush()?;
-        Ok(())
+        Some(line)
     }
 
-    /// Write Index entries in bodyfile format
-    pub fn write_index_entries<P: AsRef<Path>>(
-        entries: &[IndexEntry],
+    /// Format a single USN Journal entry as a bodyfile line
+    pub fn usn_journal_line(entry: &UsnJournalEntry, options: &BodyfileOptions) -> String {
+        usn_line(
+            &entry.file_name,
+            &entry.full_path,
+            entry.entry_number,
+            entry.file_attributes,
+            entry.timestamp.timestamp(),
+            options,
+        )
+    }
+
+    /// Format a --raw USN Journal entry as a bodyfile line
+    pub fn raw_usn_journal_line(entry: &RawUsnJournalEntry, options: &BodyfileOptions) -> String {
+        usn_line(
+            &entry.file_name,
+            &entry.full_path,
+            entry.entry_number,
+            entry.file_attributes,
+            FileTime(entry.timestamp).timestamp(),
+            options,
+        )
+    }
+
+    /// Format a single Index entry as a bodyfile line
+    pub fn index_entry_line(entry: &IndexEntry, options: &BodyfileOptions) -> String {
+        let drive_letter = &options.drive_letter;
+        let full_path = if entry.full_path.is_empty() {
+            format!("{}:/{}", drive_letter, entry.file_name)
+        } else {
+            format!("{}:{}", drive_letter, entry.full_path)
+        };
+
+        let permissions = mode_string(entry.is_directory, entry.attributes);
+
+        let atime = entry.accessed.timestamp();
+        let mtime = entry.modified.timestamp();
+        let ctime = entry.modified.timestamp(); // Use modified as record change time
+        let crtime = entry.created.timestamp();
+
+        format!(
+            "0|{}|{}|{}|0|0|{}|{}|{}|{}|{}{}",
+            full_path,
+            entry.entry_number,
+            permissions,
+            entry.file_size,
+            atime,
+            mtime,
+            ctime,
+            crtime,
+            newline(options.use_lf)
+        )
+    }
+
+    /// Format a name carved from $LogFile as a bodyfile line with its $FILE_NAME timestamps
+    pub fn logfile_name_line(name: &LogFileName, options: &BodyfileOptions) -> String {
+        let drive_letter = &options.drive_letter;
+        let full_path = match name.parent_path.as_str() {
+            "" | "/" => format!("{}:/{}", drive_letter, name.file_name),
+            parent => format!("{}:{}/{}", drive_letter, parent, name.file_name),
+        };
+
+        format!(
+            "0|{} ($LogFile)|0|{}|0|0|{}|{}|{}|{}|{}{}",
+            full_path,
+            mode_string(name.is_directory, name.attributes),
+            name.file_size,
+            name.accessed.timestamp(),
+            name.modified.timestamp(),
+            name.record_changed.timestamp(),
+            name.created.timestamp(),
+            newline(options.use_lf)
+        )
+    }
+
+    fn write_lines<T, P: AsRef<Path>>(
+        items: &[T],
         path: P,
-        drive_letter: &str,
-        use_lf: bool,
+        format_line: impl Fn(&T) -> Option<String>,
     ) -> Result<()> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
@@ -131,40 +234,66 @@ impl BodyfileOutput {
         }
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
-        let newline = if use_lf { "\n" } else { "\r\n" };
-
-        for entry in entries {
-            let full_path = if entry.full_path.is_empty() {
-                format!("{}:/{}", drive_letter, entry.file_name)
-            } else {
-                format!("{}:{}", drive_letter, entry.full_path)
-            };
-
-            let mode = if entry.is_directory { "d" } else { "r" };
-            let permissions = format!("{}/r-xr-xr-x", mode);
-
-            let atime = entry.accessed.timestamp();
-            let mtime = entry.modified.timestamp();
-            let ctime = entry.modified.timestamp(); // Use modified as record change time
-            let crtime = entry.created.timestamp();
-
-            let line = format!(
-                "0|{}|{}|{}|0|0|{}|{}|{}|{}|{}{}",
-                full_path,
-                entry.entry_number,
-                permissions,
-                entry.file_size,
-                atime,
-                mtime,
-                ctime,
-                crtime,
-                newline
-            );
 
+        for line in items.iter().filter_map(format_line) {
             writer.write_all(line.as_bytes())?;
         }
 
         writer.flush()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// One MFT bodyfile line; `times` are atime, mtime, ctime and crtime
+fn mft_line(record: &MftRecord, full_path: &str, times: [Option<FileTime>; 4], options: &BodyfileOptions) -> String {
+    // Convert timestamps to Unix epoch
+    let [atime, mtime, ctime, crtime] = times.map(|t| t.map(|t| t.timestamp()).unwrap_or(0));
+
+    format!(
+        "0|{}|{}|{}|{}|0|{}|{}|{}|{}|{}{}",
+        full_path,
+        record.entry_number,
+        mode_string(record.is_directory, record.si_flags),
+        options.uid(record.security_id),
+        record.file_size,
+        atime,
+        mtime,
+        ctime,
+        crtime,
+        newline(options.use_lf)
+    )
+}
+
+fn usn_line(
+    file_name: &str,
+    full_path: &str,
+    entry_number: u32,
+    file_attributes: u32,
+    timestamp: i64,
+    options: &BodyfileOptions,
+) -> String {
+    let drive_letter = &options.drive_letter;
+    let full_path = if full_path.is_empty() {
+        format!("{}:/{}", drive_letter, file_name)
+    } else {
+        format!("{}:{}", drive_letter, full_path)
+    };
+
+    let permissions = mode_string(false, file_attributes);
+
+    format!(
+        "0|{}|{}|{}|0|0|0|{}|{}|{}|{}{}",
+        full_path,
+        entry_number,
+        permissions,
+        timestamp,
+        timestamp,
+        timestamp,
+        timestamp,
+        newline(options.use_lf)
+    )
+}
+
+fn newline(use_lf: bool) -> &'static str {
+    if use_lf { "\n" } else { "\r\n" }
+}
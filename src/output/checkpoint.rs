@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Where monitor left off in a volume's $J, persisted so a restarted monitor
+/// resumes with the next change instead of the journal's end:
+///
+/// ```json
+/// { "volume_serial_number": 1311768467294899695, "journal_id": 133497..., "next_usn": 8544, "updated": "..." }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsnCheckpoint {
+    /// Serial number from the boot sector, so a checkpoint is never applied to another volume
+    pub volume_serial_number: u64,
+    /// UsnJournalID from $UsnJrnl:$Max; a different ID means the journal was recreated
+    pub journal_id: Option<u64>,
+    /// USN (offset within $J) of the first change not yet handled
+    pub next_usn: u64,
+    pub updated: DateTime<Utc>,
+}
+
+impl UsnCheckpoint {
+    pub fn new(volume_serial_number: u64, journal_id: Option<u64>, next_usn: u64) -> Self {
+        Self {
+            volume_serial_number,
+            journal_id,
+            next_usn,
+            updated: Utc::now(),
+        }
+    }
+
+    /// Load a checkpoint; None when there is none yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read checkpoint from {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .with_context(|| format!("Failed to parse checkpoint in {}", path.display()))
+    }
+
+    /// Write the checkpoint, replacing the previous one only once the new contents are complete
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)? + "\n")?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to write checkpoint to {}", path.display()))
+    }
+}
@@ -0,0 +1,294 @@
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Serializes a record followed by extra columns, e.g. those added by
+/// --lookup tables. The columns become fields of the record itself, so CSV
+/// headers, JSON objects and SQLite tables all pick them up.
+pub struct WithColumns<'a, T: ?Sized> {
+    pub record: &'a T,
+    pub columns: &'a [(Arc<str>, String)],
+}
+
+impl<T: Serialize + ?Sized> Serialize for WithColumns<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.record.serialize(AppendColumns { inner: serializer, columns: self.columns })
+    }
+}
+
+struct AppendColumns<'a, S> {
+    inner: S,
+    columns: &'a [(Arc<str>, String)],
+}
+
+struct ColumnFields<'a, S> {
+    inner: S,
+    columns: &'a [(Arc<str>, String)],
+}
+
+impl<S: ser::SerializeStruct> ser::SerializeStruct for ColumnFields<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, key: &'static str, value: &V) -> Result<(), S::Error> {
+        self.inner.serialize_field(key, value)
+    }
+
+    fn end(mut self) -> Result<S::Ok, S::Error> {
+        for (name, value) in self.columns {
+            self.inner.serialize_field(static_name(name), value)?;
+        }
+        self.inner.end()
+    }
+}
+
+/// Serde takes struct field names that live for the whole run, so each distinct
+/// column name is leaked once, however many records and inputs carry it
+fn static_name(name: &str) -> &'static str {
+    static NAMES: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+    let names = NAMES.get_or_init(Default::default);
+    if let Some(&name) = names.read().unwrap().get(name) {
+        return name;
+    }
+    let mut names = names.write().unwrap();
+    match names.get(name) {
+        Some(&name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.into());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+// Everything except top-level structs passes straight through to the wrapped serializer
+impl<'a, S: Serializer> Serializer for AppendColumns<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = ColumnFields<'a, S::SerializeStruct>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(ColumnFields {
+            inner: self.inner.serialize_struct(name, len + self.columns.len())?,
+            columns: self.columns,
+        })
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> { self.inner.serialize_bool(v) }
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> { self.inner.serialize_i8(v) }
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> { self.inner.serialize_i16(v) }
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> { self.inner.serialize_i32(v) }
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> { self.inner.serialize_i64(v) }
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> { self.inner.serialize_i128(v) }
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> { self.inner.serialize_u8(v) }
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> { self.inner.serialize_u16(v) }
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> { self.inner.serialize_u32(v) }
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> { self.inner.serialize_u64(v) }
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> { self.inner.serialize_u128(v) }
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> { self.inner.serialize_f32(v) }
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> { self.inner.serialize_f64(v) }
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> { self.inner.serialize_char(v) }
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> { self.inner.serialize_str(v) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> { self.inner.serialize_bytes(v) }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> { self.inner.serialize_none() }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> { self.inner.serialize_unit() }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(self, name: &'static str, value: &V) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &V,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        self.inner.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        self.inner.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        self.inner.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        self.inner.serialize_tuple_variant(name, index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        self.inner.serialize_map(len)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        self.inner.serialize_struct_variant(name, index, variant, len)
+    }
+}
+
+/// The value of a record's top-level field `name` as text: strings as they
+/// are, other values as JSON. None when the record has no such field or it
+/// is null. Only that field's value is serialized.
+pub(crate) fn field_text<T: Serialize + ?Sized>(record: &T, name: &str) -> Option<String> {
+    let mut text = None;
+    let _ = record.serialize(FieldPicker { name, text: &mut text });
+    text
+}
+
+struct FieldPicker<'a> {
+    name: &'a str,
+    text: &'a mut Option<String>,
+}
+
+impl ser::SerializeStruct for FieldPicker<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, key: &'static str, value: &V) -> Result<(), Self::Error> {
+        if key == self.name {
+            *self.text = match serde_json::to_value(value)? {
+                Value::String(text) => Some(text),
+                Value::Null => None,
+                other => Some(other.to_string()),
+            };
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Only a top-level struct has fields to pick; anything else has none
+impl<'a> Serializer for FieldPicker<'a> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<(), serde_json::Error>;
+    type SerializeTuple = Impossible<(), serde_json::Error>;
+    type SerializeTupleStruct = Impossible<(), serde_json::Error>;
+    type SerializeTupleVariant = Impossible<(), serde_json::Error>;
+    type SerializeMap = Impossible<(), serde_json::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), serde_json::Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_i8(self, _: i8) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_i16(self, _: i16) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_i32(self, _: i32) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_i64(self, _: i64) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_u8(self, _: u8) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_u16(self, _: u16) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_u32(self, _: u32) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_u64(self, _: u64) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_f32(self, _: f32) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_f64(self, _: f64) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_char(self, _: char) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_str(self, _: &str) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_none(self) -> Result<(), Self::Error> { Ok(()) }
+    fn serialize_unit(self) -> Result<(), Self::Error> { Ok(()) }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(self, _name: &'static str, value: &V) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &V,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(ser::Error::custom("a sequence has no fields"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(ser::Error::custom("a tuple has no fields"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(ser::Error::custom("a tuple struct has no fields"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(ser::Error::custom("a tuple variant has no fields"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(ser::Error::custom("map keys are not fields"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(ser::Error::custom("a struct variant's fields are not top-level"))
+    }
+}
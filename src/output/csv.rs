@@ -1,8 +1,61 @@
+use crate::evidence;
 use crate::ntfs::types::*;
 use anyhow::Result;
-use csv::Writer;
+use chrono::{DateTime, Utc};
+use csv::{QuoteStyle, Writer, WriterBuilder};
+use serde::Serialize;
 use std::fs::{create_dir_all, File};
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the CSV column layout, bumped whenever columns are added, removed or reordered
+pub const CSV_SCHEMA_VERSION: u32 = 22;
+
+/// Commented provenance lines written above the CSV header
+#[derive(Debug, Clone)]
+pub struct CsvPreamble {
+    pub source: PathBuf,
+    pub sha256: Option<String>,
+    pub generated: DateTime<Utc>,
+}
+
+impl CsvPreamble {
+    pub fn new(source: &Path) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            sha256: evidence::input_sha256(source).ok(),
+            generated: Utc::now(),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "# tool: {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+        writeln!(writer, "# source: {}", self.source.display())?;
+        writeln!(writer, "# sha256: {}", self.sha256.as_deref().unwrap_or("unavailable"))?;
+        writeln!(writer, "# timezone: UTC")?;
+        writeln!(writer, "# schema_version: {}", CSV_SCHEMA_VERSION)?;
+        writeln!(writer, "# generated: {}", self.generated.to_rfc3339())?;
+        Ok(())
+    }
+}
+
+/// Field separator, quoting and provenance preamble shared by every CSV a run writes
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote_style: QuoteStyle,
+    pub preamble: Option<CsvPreamble>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote_style: QuoteStyle::Necessary,
+            preamble: None,
+        }
+    }
+}
 
 pub struct CsvOutput;
 
@@ -10,116 +63,143 @@ impl CsvOutput {
     pub fn write_mft_records<P: AsRef<Path>>(
         records: &[MftRecord],
         path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;;
-        let mut writer = Writer::from_writer(file);
-
-        for record in records {
-            writer.serialize(record)?;
-        }
-
-        writer.flush()?;
-        Ok(())
+        Self::write_rows(records, path, options)
     }
 
     pub fn write_usn_journal_entries<P: AsRef<Path>>(
         entries: &[UsnJournalEntry],
         path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        let mut writer = Writer::from_writer(file);
-
-        for entry in entries {
-            writer.serialize(entry)?;
-        }
-
-        writer.flush()?;
-        Ok(())
+        Self::write_rows(entries, path, options)
     }
 
     pub fn write_boot_sector<P: AsRef<Path>>(
         boot: &BootSector,
         path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        let mut writer = Writer::from_writer(file);
-
-        writer.serialize(boot)?;
-        writer.flush()?;
-        Ok(())
+        Self::write_rows(std::slice::from_ref(boot), path, options)
     }
 
     pub fn write_security_descriptors<P: AsRef<Path>>(
         descriptors: &[SecurityDescriptor],
         path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        let mut writer = Writer::from_writer(file);
-
-        for descriptor in descriptors {
-            // Convert binary data to hex string for CSV
-            let descriptor_csv = SecurityDescriptorCsv {
-                id: descriptor.id,
-                hash: descriptor.hash,
-                offset: descriptor.offset,
-                length: descriptor.length,
-                descriptor_hex: hex::encode(&descriptor.descriptor),
-            };
-            writer.serialize(&descriptor_csv)?;
-        }
+        // Convert binary data to hex string for CSV
+        let descriptors_hex: Vec<SecurityDescriptorHex> = descriptors
+            .iter()
+            .map(SecurityDescriptorHex::from)
+            .collect();
 
-        writer.flush()?;
-        Ok(())
+        Self::write_rows(&descriptors_hex, path, options)
     }
 
     pub fn write_index_entries<P: AsRef<Path>>(
         entries: &[IndexEntry],
         path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        let mut writer = Writer::from_writer(file);
-
-        for entry in entries {
-            writer.serialize(entry)?;
-        }
-
-        writer.flush()?;
-        Ok(())
+        Self::write_rows(entries, path, options)
     }
 
     pub fn write_file_listing<P: AsRef<Path>>(
         entries: &[FileListEntry],
         path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(entries, path, options)
+    }
+
+    pub fn write_cluster_map<P: AsRef<Path>>(
+        rows: &[ClusterMapRow],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_entry_map<P: AsRef<Path>>(
+        rows: &[EntryTimeRow],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_directory_sizes<P: AsRef<Path>>(
+        rows: &[DirectorySize],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_sequence_churn<P: AsRef<Path>>(
+        rows: &[SequenceChurn],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_previous_occupants<P: AsRef<Path>>(
+        rows: &[PreviousOccupant],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_descriptor_diffs<P: AsRef<Path>>(
+        rows: &[SecurityDescriptorDiff],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_permission_drift<P: AsRef<Path>>(
+        rows: &[PermissionDrift],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_ransomware_renames<P: AsRef<Path>>(
+        rows: &[RansomwareRename],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    pub fn write_dump_manifest<P: AsRef<Path>>(
+        rows: &[DumpedFile],
+        path: P,
+        options: &CsvOptions,
+    ) -> Result<()> {
+        Self::write_rows(rows, path, options)
+    }
+
+    fn write_rows<T: Serialize, P: AsRef<Path>>(
+        rows: &[T],
+        path: P,
+        options: &CsvOptions,
     ) -> Result<()> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
             create_dir_all(parent)?;
         }
         let file = File::create(path)?;
-        let mut writer = Writer::from_writer(file);
+        let mut writer = csv_writer(file, options)?;
 
-        for entry in entries {
-            writer.serialize(entry)?;
+        for row in rows {
+            writer.serialize(row)?;
         }
 
         writer.flush()?;
@@ -127,11 +207,13 @@ impl CsvOutput {
     }
 }
 
-#[derive(serde::Serialize)]
-struct SecurityDescriptorCsv {
-    id: u32,
-    hash: u32,
-    offset: u64,
-    length: u32,
-    descriptor_hex: String,
-}
\ No newline at end of file
+/// Create a CSV writer, writing the provenance preamble first when requested
+pub fn csv_writer<W: Write>(mut output: W, options: &CsvOptions) -> io::Result<Writer<W>> {
+    if let Some(ref preamble) = options.preamble {
+        preamble.write_to(&mut output)?;
+    }
+    Ok(WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .quote_style(options.quote_style)
+        .from_writer(output))
+}
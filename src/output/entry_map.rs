@@ -0,0 +1,82 @@
+use crate::ntfs::types::{EntryTimeRow, MftRecord};
+
+/// Heatmap width and height limit in pixels; time runs left to right and
+/// entry numbers bottom to top
+pub const ENTRY_MAP_SIZE: u64 = 512;
+
+/// One row for each file and directory in use with a $STANDARD_INFORMATION
+/// created time, in entry order
+pub fn rows(records: &[MftRecord]) -> Vec<EntryTimeRow> {
+    records
+        .iter()
+        .filter(|record| record.in_use && !record.is_ads && !record.is_hard_link)
+        .filter_map(|record| {
+            let created = record.created_0x10.filter(|created| created.timestamp() != 0)?;
+            Some(EntryTimeRow {
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                created,
+                is_directory: record.is_directory,
+                full_path: record.full_path.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Pixels per axis: (time span per column in 100 ns ticks, entries per row, height)
+pub fn entry_map_scale(rows: &[EntryTimeRow]) -> (u64, u64, u64) {
+    let (first, last) = time_range(rows);
+    let ticks_per_column = (last - first + 1).div_ceil(ENTRY_MAP_SIZE).max(1);
+    let entries = rows.iter().map(|row| row.entry_number as u64 + 1).max().unwrap_or(1);
+    let entries_per_row = entries.div_ceil(ENTRY_MAP_SIZE).max(1);
+    (ticks_per_column, entries_per_row, entries.div_ceil(entries_per_row))
+}
+
+fn time_range(rows: &[EntryTimeRow]) -> (u64, u64) {
+    let first = rows.iter().map(|row| row.created.raw()).min().unwrap_or(0);
+    let last = rows.iter().map(|row| row.created.raw()).max().unwrap_or(0);
+    (first, last)
+}
+
+/// Write the rows as a PNG heatmap of entry number against created time.
+///
+/// Each pixel counts the files created in its time span within its range of
+/// entries, from black through red and yellow to white on a log scale. Files
+/// dropped in bulk show up as vertical streaks; the $MFT growing shows up as
+/// the lowest free entries climbing over time.
+#[cfg(feature = "png")]
+pub fn write_heatmap<P: AsRef<std::path::Path>>(path: P, rows: &[EntryTimeRow]) -> anyhow::Result<()> {
+    use std::fs::{create_dir_all, File};
+    use std::io::BufWriter;
+
+    let (first, _) = time_range(rows);
+    let (ticks_per_column, entries_per_row, height) = entry_map_scale(rows);
+    let mut counts = vec![0u64; (ENTRY_MAP_SIZE * height) as usize];
+    for row in rows {
+        let column = (row.created.raw() - first) / ticks_per_column;
+        let line = height - 1 - row.entry_number as u64 / entries_per_row;
+        counts[(line * ENTRY_MAP_SIZE + column) as usize] += 1;
+    }
+
+    let peak = (counts.iter().copied().max().unwrap_or(0) as f64).ln_1p().max(f64::MIN_POSITIVE);
+    let mut pixels = Vec::with_capacity(counts.len() * 3);
+    for &count in &counts {
+        let level = (count as f64).ln_1p() / peak;
+        // Black to red, red to yellow, yellow to white in thirds
+        let channel = |start: f64| ((level * 3.0 - start).clamp(0.0, 1.0) * 255.0) as u8;
+        let color = if count == 0 { [0, 0, 0] } else { [channel(0.0).max(64), channel(1.0), channel(2.0)] };
+        pixels.extend_from_slice(&color);
+    }
+
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), ENTRY_MAP_SIZE as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the forwarder to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a write may block before the forwarder counts as unreachable
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// First wait between delivery attempts; it doubles after each failure
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Sends JSON Lines over TCP to a log forwarder (Logstash, Fluent Bit, Vector,
+/// a SIEM's raw TCP input, ...). The connection is opened on first use and
+/// reopened after a failure.
+///
+/// Lines are queued and delivered in batches once enough are waiting or the
+/// flush interval has passed. A batch that can't be delivered is retried with
+/// exponential backoff; after the last retry the undelivered lines go to the
+/// spool file, if one is set, and are sent ahead of newer lines once the
+/// forwarder is reachable again.
+pub struct TcpForwarder {
+    address: String,
+    stream: Option<BufWriter<TcpStream>>,
+    pending: VecDeque<Vec<u8>>,
+    batch_size: usize,
+    flush_interval: Duration,
+    last_flush: Instant,
+    max_rate: Option<u32>,
+    retries: u32,
+    spool: Option<PathBuf>,
+    spool_max_bytes: u64,
+}
+
+impl TcpForwarder {
+    /// `address` is `host:port`; it is resolved when connecting, so a forwarder
+    /// that comes up later is still reached
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            stream: None,
+            pending: VecDeque::new(),
+            batch_size: usize::MAX,
+            flush_interval: Duration::ZERO,
+            last_flush: Instant::now(),
+            max_rate: None,
+            retries: 0,
+            spool: None,
+            spool_max_bytes: u64::MAX,
+        }
+    }
+
+    /// Write and flush at most `batch_size` lines at a time, and hold lines
+    /// until that many are queued or `flush_interval` has passed since the last delivery
+    pub fn with_batching(mut self, batch_size: usize, flush_interval: Duration) -> Self {
+        self.batch_size = batch_size.max(1);
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Send no more than `lines_per_second`, pausing between batches
+    pub fn with_max_rate(mut self, lines_per_second: Option<u32>) -> Self {
+        self.max_rate = lines_per_second.filter(|&rate| rate > 0);
+        self
+    }
+
+    /// Retry a failed batch this many times, backing off between attempts
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Keep undeliverable lines in `path`, up to `max_bytes`, instead of dropping them
+    pub fn with_spool(mut self, path: Option<PathBuf>, max_bytes: u64) -> Self {
+        self.spool = path;
+        self.spool_max_bytes = max_bytes;
+        self
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Lines queued in memory, waiting for a full batch or the flush interval
+    pub fn held(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queue lines, each including its line ending, and deliver the queue when it is due
+    pub fn send(&mut self, lines: Vec<Vec<u8>>) -> Result<()> {
+        self.pending.extend(lines);
+        let due = self.pending.len() >= self.batch_size || self.last_flush.elapsed() >= self.flush_interval;
+        if due { self.flush() } else { Ok(()) }
+    }
+
+    /// Deliver spooled and queued lines now.
+    ///
+    /// When the forwarder stays unreachable through every retry, the lines not
+    /// yet delivered are spooled, or dropped without a spool, and the error is returned.
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        let spooled = self.load_spool()?;
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.deliver();
+        match result {
+            Ok(()) if spooled => {
+                if let Some(ref path) = self.spool {
+                    fs::remove_file(path).with_context(|| format!("Failed to remove spool file {}", path.display()))?;
+                    log::info!("Delivered the lines spooled in {}", path.display());
+                }
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let undelivered = self.pending.len();
+                match self.spool.clone() {
+                    Some(path) => {
+                        self.write_spool(&path)?;
+                        Err(e.context(format!("{} lines kept in {} for the next attempt", undelivered, path.display())))
+                    }
+                    None => {
+                        self.pending.clear();
+                        Err(e.context(format!("{} lines dropped", undelivered)))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send the queue batch by batch, retrying each batch with backoff
+    fn deliver(&mut self) -> Result<()> {
+        while !self.pending.is_empty() {
+            let count = self.pending.len().min(self.batch_size);
+            let started = Instant::now();
+
+            let mut backoff = INITIAL_BACKOFF;
+            let mut attempt = 0;
+            loop {
+                match self.send_batch(count) {
+                    Ok(()) => break,
+                    Err(e) if attempt < self.retries => {
+                        attempt += 1;
+                        log::warn!(
+                            "Forwarding to {} failed ({:#}); retry {} of {} in {}s",
+                            self.address,
+                            e,
+                            attempt,
+                            self.retries,
+                            backoff.as_secs()
+                        );
+                        std::thread::sleep(backoff);
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            self.pending.drain(..count);
+
+            if let Some(rate) = self.max_rate {
+                let budget = Duration::from_secs_f64(count as f64 / rate as f64);
+                std::thread::sleep(budget.saturating_sub(started.elapsed()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write and flush the first `count` queued lines. On failure the
+    /// connection is dropped so the next attempt reconnects; the batch may have
+    /// been partly delivered.
+    fn send_batch(&mut self, count: usize) -> Result<()> {
+        let result = self.try_send_batch(count);
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    fn try_send_batch(&mut self, count: usize) -> Result<()> {
+        if self.stream.is_none() {
+            self.stream = Some(BufWriter::new(self.connect()?));
+        }
+        let stream = self.stream.as_mut().unwrap();
+        for line in self.pending.iter().take(count) {
+            stream.write_all(line)?;
+        }
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Put the spool's lines ahead of the queue; true when there were any
+    fn load_spool(&mut self) -> Result<bool> {
+        let Some(ref path) = self.spool else {
+            return Ok(false);
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open spool file {}", path.display())),
+        };
+
+        let mut spooled = Vec::new();
+        for line in BufReader::new(file).split(b'\n') {
+            let mut line = line.with_context(|| format!("Failed to read spool file {}", path.display()))?;
+            line.push(b'\n');
+            spooled.push(line);
+        }
+        let count = spooled.len();
+        for line in spooled.into_iter().rev() {
+            self.pending.push_front(line);
+        }
+        Ok(count > 0)
+    }
+
+    /// Replace the spool with the undelivered lines, oldest first, dropping the
+    /// newest beyond the size limit
+    fn write_spool(&mut self, path: &Path) -> Result<()> {
+        let temporary = path.with_extension("tmp");
+        let mut output = BufWriter::new(
+            File::create(&temporary).with_context(|| format!("Failed to create spool file {}", temporary.display()))?,
+        );
+
+        let mut written = 0u64;
+        let mut dropped = 0;
+        for line in self.pending.drain(..) {
+            if written + line.len() as u64 > self.spool_max_bytes {
+                dropped += 1;
+                continue;
+            }
+            output.write_all(&line)?;
+            written += line.len() as u64;
+        }
+        output.flush()?;
+        drop(output);
+        fs::rename(&temporary, path).with_context(|| format!("Failed to replace spool file {}", path.display()))?;
+
+        if dropped > 0 {
+            log::warn!("Spool {} is full; dropped the {} newest lines", path.display(), dropped);
+        }
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<TcpStream> {
+        let addresses = self
+            .address
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve forwarder address {}", self.address))?;
+
+        let mut last_error = None;
+        for address in addresses {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    log::info!("Connected to forwarder {}", self.address);
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) => Err(e).with_context(|| format!("Failed to connect to forwarder {}", self.address)),
+            None => anyhow::bail!("Forwarder address {} did not resolve to any address", self.address),
+        }
+    }
+}
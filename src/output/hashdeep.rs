@@ -0,0 +1,30 @@
+use crate::ntfs::types::DumpedFile;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Name of the hash manifest written next to dumped and recovered files
+pub const HASHDEEP_FILE_NAME: &str = "hashdeep.txt";
+
+/// Write the hashes of `files`, which were written to `dir`, in hashdeep's
+/// format, with names relative to `dir`. `hashdeep -r -x -k hashdeep.txt .`
+/// run in `dir` then lists every file whose content is not in the manifest,
+/// which should only be the manifests themselves.
+pub fn write_hashdeep(files: &[DumpedFile], dir: &Path) -> Result<()> {
+    let path = dir.join(HASHDEEP_FILE_NAME);
+    let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "%%%% HASHDEEP-1.0")?;
+    writeln!(writer, "%%%% size,md5,sha1,sha256,filename")?;
+    let base = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    writeln!(writer, "## Invoked from: {}", base.display())?;
+    writeln!(writer, "## $ {}", std::env::args().collect::<Vec<_>>().join(" "))?;
+    writeln!(writer, "##")?;
+    for file in files {
+        writeln!(writer, "{},{},{},{},{}", file.size, file.md5, file.sha1, file.sha256, file.dump_name)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -1,124 +1,211 @@
+use crate::evidence::EvidenceVerification;
 use crate::ntfs::types::*;
+use super::normalize::Normalized;
 use anyhow::Result;
-use serde_json;
+use serde::ser::{self, SerializeSeq, Serializer};
+use serde::Serialize;
+use serde_json::ser::Formatter;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
 use std::path::Path;
 
+const WRITE_BUFFER_SIZE: usize = 1 << 20;
+
+/// Layout options for JSON output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Indent output for human reading instead of writing compact JSON
+    pub pretty: bool,
+    /// Write each record as an array of values, preceded by a header array of field names
+    pub values: bool,
+    /// Leave out volatile fields and fix timestamp precision, for diffing runs
+    pub normalized: bool,
+}
+
 pub struct JsonOutput;
 
 impl JsonOutput {
     pub fn write_mft_records<P: AsRef<Path>>(
         records: &[MftRecord],
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, records)?;
-        Ok(())
+        Self::write_array(records, path, options)
     }
 
     pub fn write_usn_journal_entries<P: AsRef<Path>>(
         entries: &[UsnJournalEntry],
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, entries)?;
-        Ok(())
+        Self::write_array(entries, path, options)
     }
 
     pub fn write_boot_sector<P: AsRef<Path>>(
         boot: &BootSector,
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, boot)?;
-        Ok(())
+        Self::write_value(boot, path, options)
     }
 
     pub fn write_security_descriptors<P: AsRef<Path>>(
         descriptors: &[SecurityDescriptor],
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-
         // Convert binary data to hex for JSON serialization
-        let descriptors_json: Vec<_> = descriptors
+        let descriptors_json: Vec<SecurityDescriptorHex> = descriptors
             .iter()
-            .map(|desc| SecurityDescriptorJson {
-                id: desc.id,
-                hash: desc.hash,
-                offset: desc.offset,
-                length: desc.length,
-                descriptor_hex: hex::encode(&desc.descriptor),
-            })
+            .map(SecurityDescriptorHex::from)
             .collect();
 
-        serde_json::to_writer_pretty(file, &descriptors_json)?;
-        Ok(())
+        Self::write_array(&descriptors_json, path, options)
     }
 
     pub fn write_index_entries<P: AsRef<Path>>(
         entries: &[IndexEntry],
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, entries)?;
-        Ok(())
+        Self::write_array(entries, path, options)
     }
 
     pub fn write_file_listing<P: AsRef<Path>>(
         entries: &[FileListEntry],
         path: P,
+        options: JsonOptions,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
-        }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, entries)?;
-        Ok(())
+        Self::write_array(entries, path, options)
+    }
+
+    pub fn write_stream_run_lists<P: AsRef<Path>>(
+        streams: &[StreamRunList],
+        path: P,
+        options: JsonOptions,
+    ) -> Result<()> {
+        Self::write_array(streams, path, options)
+    }
+
+    pub fn write_corrupt_records<P: AsRef<Path>>(
+        records: &[CorruptRecord],
+        path: P,
+        options: JsonOptions,
+    ) -> Result<()> {
+        Self::write_array(records, path, options)
     }
 
     pub fn write_analysis_summary<P: AsRef<Path>>(
         summary: &AnalysisSummary,
         path: P,
     ) -> Result<()> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            create_dir_all(parent)?;
+        // The summary is small and meant for people, so it is always indented
+        Self::write_value(summary, path, JsonOptions { pretty: true, ..JsonOptions::default() })
+    }
+
+    pub fn write_batch_summary<P: AsRef<Path>>(
+        summary: &BatchSummary,
+        path: P,
+    ) -> Result<()> {
+        Self::write_value(summary, path, JsonOptions { pretty: true, ..JsonOptions::default() })
+    }
+
+    fn write_array<T: Serialize, P: AsRef<Path>>(
+        records: &[T],
+        path: P,
+        options: JsonOptions,
+    ) -> Result<()> {
+        let mut writer = create_output(path.as_ref())?;
+        stream_array(&mut writer, options, |write_record| {
+            records.iter().try_for_each(write_record)
+        })?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_value<T: Serialize, P: AsRef<Path>>(
+        value: &T,
+        path: P,
+        options: JsonOptions,
+    ) -> Result<()> {
+        let mut writer = create_output(path.as_ref())?;
+        if options.pretty {
+            serde_json::to_writer_pretty(&mut writer, value)?;
+        } else {
+            serde_json::to_writer(&mut writer, value)?;
         }
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, summary)?;
+        writer.flush()?;
         Ok(())
     }
 }
 
-#[derive(serde::Serialize)]
-struct SecurityDescriptorJson {
-    id: u32,
-    hash: u32,
-    offset: u64,
-    length: u32,
-    descriptor_hex: String,
+fn create_output(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let file = File::create(path)?;
+    Ok(BufWriter::with_capacity(WRITE_BUFFER_SIZE, file))
+}
+
+/// Stream records into a single JSON array.
+///
+/// `feed` is handed a callback that serializes one record; it is called once per
+/// record so writers can pull from slices or channels without collecting first.
+pub fn stream_array<T, W, F>(writer: W, options: JsonOptions, feed: F) -> Result<()>
+where
+    T: Serialize,
+    W: Write,
+    F: FnOnce(&mut dyn FnMut(&T) -> Result<()>) -> Result<()>,
+{
+    if options.pretty {
+        stream_with(serde_json::Serializer::pretty(writer), options, feed)
+    } else {
+        stream_with(serde_json::Serializer::new(writer), options, feed)
+    }
+}
+
+fn stream_with<T, W, Fm, F>(mut serializer: serde_json::Serializer<W, Fm>, options: JsonOptions, feed: F) -> Result<()>
+where
+    T: Serialize,
+    W: Write,
+    Fm: Formatter,
+    F: FnOnce(&mut dyn FnMut(&T) -> Result<()>) -> Result<()>,
+{
+    let mut seq = serializer.serialize_seq(None)?;
+    let mut header_written = false;
+
+    feed(&mut |record: &T| {
+        match (options.values, options.normalized) {
+            (true, normalized) => {
+                if !header_written {
+                    let names = if normalized { field_names(&Normalized(record)) } else { field_names(record) };
+                    seq.serialize_element(&names)?;
+                    header_written = true;
+                }
+                if normalized {
+                    seq.serialize_element(&ValuesOnly { value: &Normalized(record), keys: None })?;
+                } else {
+                    seq.serialize_element(&ValuesOnly { value: record, keys: None })?;
+                }
+            }
+            (false, true) => seq.serialize_element(&Normalized(record))?,
+            (false, false) => seq.serialize_element(record)?,
+        }
+        Ok(())
+    })?;
+
+    seq.end()?;
+    Ok(())
+}
+
+/// Collect the top-level field names of a struct in declaration order
+pub(crate) fn field_names<T: Serialize>(record: &T) -> Vec<&'static str> {
+    let keys = RefCell::new(Vec::new());
+    let mut sink = serde_json::Serializer::new(std::io::sink());
+    let _ = ValuesOnly { value: record, keys: Some(&keys) }.serialize(&mut sink);
+    keys.into_inner()
 }
 
 #[derive(serde::Serialize)]
@@ -129,4 +216,213 @@ pub struct AnalysisSummary {
     pub processing_time_ms: u128,
     pub errors_encountered: usize,
     pub warnings: Vec<String>,
-}
\ No newline at end of file
+    /// Parsing stopped at --timeout, so the results are partial
+    pub timed_out: bool,
+    pub mft_statistics: Option<MftStatistics>,
+    /// $MFT regions whose header record numbers don't match their position
+    pub record_number_shifts: Vec<RecordNumberShift>,
+    /// BAAD records and FILE records that could not be parsed
+    pub corrupt_records: usize,
+    /// Errors met while parsing, counted by catalogue code, e.g. {"MFTE-E014": 3}
+    pub error_codes: BTreeMap<ErrorCode, usize>,
+    /// Records whose anomaly_score reaches the anomaly model's threshold
+    pub anomalous_records: usize,
+    /// Gaps, high-water mark and truncation of the $MFT
+    pub mft_layout: Option<MftLayout>,
+    /// $MFT entries whose in-use flag disagrees with the $MFT's $BITMAP
+    pub mft_bitmap_mismatches: Vec<MftBitmapMismatch>,
+    /// Patterns suggesting wiping or mass deletion, plus the largest free extent
+    pub wiping_findings: Vec<WipingFinding>,
+    /// Bursts of $J renames to ransomware-like extensions
+    pub ransomware_alerts: Vec<RansomwareAlert>,
+    pub evidence: Option<EvidenceVerification>,
+}
+
+impl AnalysisSummary {
+    pub fn new(file_type: FileType, file_size: u64, records_processed: usize) -> Self {
+        Self {
+            file_type: file_type.to_string(),
+            file_size,
+            records_processed,
+            processing_time_ms: 0,
+            errors_encountered: 0,
+            warnings: Vec::new(),
+            timed_out: false,
+            mft_statistics: None,
+            record_number_shifts: Vec::new(),
+            corrupt_records: 0,
+            error_codes: BTreeMap::new(),
+            anomalous_records: 0,
+            mft_layout: None,
+            mft_bitmap_mismatches: Vec::new(),
+            wiping_findings: Vec::new(),
+            ransomware_alerts: Vec::new(),
+            evidence: None,
+        }
+    }
+
+    /// Count `count` more errors of kind `code`
+    pub fn count_errors(&mut self, code: ErrorCode, count: usize) {
+        if count > 0 {
+            *self.error_codes.entry(code).or_default() += count;
+        }
+    }
+}
+
+/// Outcome of one input of a directory run
+#[derive(serde::Serialize)]
+pub struct BatchInputSummary {
+    pub input: String,
+    pub file_type: String,
+    /// "ok", "altered" (input changed while processing) or "failed"
+    pub status: &'static str,
+    pub error: Option<String>,
+    /// Catalogue code of the error a failed input stopped at, when it has one
+    pub error_code: Option<ErrorCode>,
+    pub summary: Option<AnalysisSummary>,
+}
+
+/// Consolidated summary of a directory run
+#[derive(serde::Serialize)]
+pub struct BatchSummary {
+    pub root: String,
+    pub threads: usize,
+    pub processing_time_ms: u128,
+    pub inputs: Vec<BatchInputSummary>,
+}
+
+/// Serializes a struct as a tuple of its field values, optionally recording field names
+struct ValuesOnly<'a, T> {
+    value: &'a T,
+    keys: Option<&'a RefCell<Vec<&'static str>>>,
+}
+
+impl<T: Serialize> Serialize for ValuesOnly<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(StructAsTuple { inner: serializer, keys: self.keys })
+    }
+}
+
+struct StructAsTuple<'a, S> {
+    inner: S,
+    keys: Option<&'a RefCell<Vec<&'static str>>>,
+}
+
+struct TupleFields<'a, S> {
+    inner: S,
+    keys: Option<&'a RefCell<Vec<&'static str>>>,
+}
+
+impl<S: ser::SerializeTuple> ser::SerializeStruct for TupleFields<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, key: &'static str, value: &V) -> Result<(), S::Error> {
+        if let Some(keys) = self.keys {
+            keys.borrow_mut().push(key);
+        }
+        self.inner.serialize_element(value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+// Everything except top-level structs passes straight through to the wrapped serializer
+impl<'a, S: Serializer> Serializer for StructAsTuple<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = TupleFields<'a, S::SerializeTuple>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(TupleFields { inner: self.inner.serialize_tuple(len)?, keys: self.keys })
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> { self.inner.serialize_bool(v) }
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> { self.inner.serialize_i8(v) }
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> { self.inner.serialize_i16(v) }
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> { self.inner.serialize_i32(v) }
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> { self.inner.serialize_i64(v) }
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> { self.inner.serialize_i128(v) }
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> { self.inner.serialize_u8(v) }
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> { self.inner.serialize_u16(v) }
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> { self.inner.serialize_u32(v) }
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> { self.inner.serialize_u64(v) }
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> { self.inner.serialize_u128(v) }
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> { self.inner.serialize_f32(v) }
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> { self.inner.serialize_f64(v) }
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> { self.inner.serialize_char(v) }
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> { self.inner.serialize_str(v) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> { self.inner.serialize_bytes(v) }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> { self.inner.serialize_none() }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> { self.inner.serialize_unit() }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(self, name: &'static str, value: &V) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &V,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        self.inner.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        self.inner.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        self.inner.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        self.inner.serialize_tuple_variant(name, index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        self.inner.serialize_map(len)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        self.inner.serialize_struct_variant(name, index, variant, len)
+    }
+}
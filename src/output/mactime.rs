@@ -0,0 +1,216 @@
+use super::bodyfile::BodyfileOptions;
+use super::sink::{Batches, OutputSink};
+use crate::record::ArtifactRecord;
+use anyhow::Result;
+use chrono::DateTime;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// One line of a bodyfile: MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyEntry {
+    pub name: String,
+    pub inode: String,
+    pub mode: String,
+    pub uid: String,
+    pub gid: String,
+    pub size: u64,
+    pub atime: i64,
+    pub mtime: i64,
+    pub ctime: i64,
+    pub crtime: i64,
+}
+
+impl BodyEntry {
+    /// Parse a bodyfile line; None for blank or malformed lines.
+    ///
+    /// The name is everything between the MD5 and the last nine fields, so
+    /// names containing '|' survive.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (_md5, rest) = line.split_once('|')?;
+        let mut fields: Vec<&str> = rest.rsplitn(10, '|').collect();
+        if fields.len() != 10 {
+            return None;
+        }
+        fields.reverse();
+
+        Some(Self {
+            name: fields[0].to_string(),
+            inode: fields[1].to_string(),
+            mode: fields[2].to_string(),
+            uid: fields[3].to_string(),
+            gid: fields[4].to_string(),
+            size: fields[5].parse().ok()?,
+            atime: fields[6].parse().ok()?,
+            mtime: fields[7].parse().ok()?,
+            ctime: fields[8].parse().ok()?,
+            crtime: fields[9].parse().ok()?,
+        })
+    }
+}
+
+/// A moment in the timeline: one entry and which of its times fall on it
+#[derive(Debug, Clone, Copy)]
+struct TimelineEvent {
+    time: i64,
+    entry: usize,
+    /// m, a, c and b flags
+    macb: [bool; 4],
+}
+
+/// Chronological timeline of bodyfile entries, rendered like TSK's mactime.
+///
+/// Each distinct non-zero time of an entry becomes one event whose macb column
+/// shows which timestamps share that second. Events are grouped under a header
+/// per day, and the time is only printed on the first event of each second.
+#[derive(Debug, Default)]
+pub struct Timeline {
+    entries: Vec<BodyEntry>,
+    events: Vec<TimelineEvent>,
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep events in `start..=end`, as Unix seconds
+    pub fn with_range(mut self, start: Option<i64>, end: Option<i64>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    pub fn add(&mut self, entry: BodyEntry) {
+        let index = self.entries.len();
+        let times = [entry.mtime, entry.atime, entry.ctime, entry.crtime];
+
+        for (position, &time) in times.iter().enumerate() {
+            // Later slots with the same time are folded into the first event
+            if time == 0 || times[..position].contains(&time) || !self.in_range(time) {
+                continue;
+            }
+            self.events.push(TimelineEvent {
+                time,
+                entry: index,
+                macb: times.map(|other| other == time),
+            });
+        }
+
+        self.entries.push(entry);
+    }
+
+    /// Add every line of a bodyfile, returning how many were skipped as malformed
+    pub fn add_lines<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) -> usize {
+        let mut skipped = 0;
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match BodyEntry::parse(line) {
+                Some(entry) => self.add(entry),
+                None => skipped += 1,
+            }
+        }
+        skipped
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    fn in_range(&self, time: i64) -> bool {
+        self.start.is_none_or(|start| time >= start) && self.end.is_none_or(|end| time <= end)
+    }
+
+    /// Sort the events and write the rendered timeline
+    pub fn write<W: Write>(&mut self, output: &mut W) -> Result<()> {
+        let entries = &self.entries;
+        self.events.sort_by(|a, b| {
+            a.time.cmp(&b.time).then_with(|| entries[a.entry].name.cmp(&entries[b.entry].name))
+        });
+
+        let mut current_day = None;
+        let mut previous_time = None;
+
+        for event in &self.events {
+            let datetime = DateTime::from_timestamp(event.time, 0).unwrap_or_default();
+            let day = datetime.date_naive();
+            if current_day != Some(day) {
+                if current_day.is_some() {
+                    writeln!(output)?;
+                }
+                writeln!(output, "{}", datetime.format("%a %b %d %Y"))?;
+                current_day = Some(day);
+            }
+
+            let time = if previous_time == Some(event.time) {
+                String::new()
+            } else {
+                datetime.format("%H:%M:%S").to_string()
+            };
+            previous_time = Some(event.time);
+
+            let entry = &self.entries[event.entry];
+            let macb: String = event
+                .macb
+                .iter()
+                .zip(['m', 'a', 'c', 'b'])
+                .map(|(&set, flag)| if set { flag } else { '.' })
+                .collect();
+
+            writeln!(
+                output,
+                "  {:<8} {:>12} {} {} {:<8} {:<8} {:<8} {}",
+                time, entry.size, macb, entry.mode, entry.uid, entry.gid, entry.inode, entry.name
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders records straight to a mactime timeline, without an intermediate bodyfile
+pub struct MactimeSink {
+    path: PathBuf,
+    options: BodyfileOptions,
+}
+
+impl MactimeSink {
+    pub fn new(path: PathBuf, options: BodyfileOptions) -> Self {
+        Self { path, options }
+    }
+}
+
+impl OutputSink<ArtifactRecord> for MactimeSink {
+    fn format(&self) -> &'static str {
+        "Mactime"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, ArtifactRecord>) -> Result<()> {
+        let mut timeline = Timeline::new();
+        for batch in batches {
+            for record in batch.iter() {
+                if let Some(lines) = record.body_line(&self.options) {
+                    timeline.add_lines(lines.lines());
+                }
+            }
+        }
+
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut output = BufWriter::new(File::create(&self.path)?);
+        timeline.write(&mut output)?;
+        output.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::{bail, Context, Result};
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Column whose value identifies a row across exports, see `ArtifactRecord::record_id`
+const RECORD_ID_COLUMN: &str = "record_id";
+
+/// Separators tried when reading a CSV, as written with --delimiter
+const CSV_DELIMITERS: [u8; 4] = [b',', b'\t', b'|', b';'];
+
+/// CSV and JSON Lines outputs from many hosts combined into one dataset.
+///
+/// Rows keep every column of every input: the header is the union of the
+/// inputs' columns in the order they were first seen, after the host column,
+/// and a row lacking a column leaves it empty. That lines up exports written
+/// by different schema versions or with different --context and --lookup columns.
+pub struct MergedDataset {
+    columns: Vec<String>,
+    index: HashMap<String, usize>,
+    rows: Vec<Vec<Value>>,
+    /// Dedupe keys of the rows kept so far; None when duplicates are kept
+    seen: Option<HashSet<String>>,
+    duplicates: usize,
+}
+
+impl MergedDataset {
+    /// `host_column` names the column added to every row for the host it came from
+    pub fn new(host_column: &str) -> Self {
+        let mut dataset = Self {
+            columns: Vec::new(),
+            index: HashMap::new(),
+            rows: Vec::new(),
+            seen: None,
+            duplicates: 0,
+        };
+        dataset.column(host_column);
+        dataset
+    }
+
+    /// Drop rows already merged: the same host and record_id, or the same
+    /// host and values for rows without a record_id
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.seen = dedupe.then(HashSet::new);
+        self
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Rows dropped by dedupe
+    pub fn duplicate_count(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Merge a CSV or JSON Lines file, picked by its extension; returns the number of rows read
+    pub fn add_file(&mut self, path: &Path, host: &str) -> Result<usize> {
+        match extension(path).as_str() {
+            "csv" => self.add_csv(path, host),
+            "jsonl" => self.add_jsonl(path, host),
+            _ => bail!("{} is not a .csv or .jsonl output", path.display()),
+        }
+    }
+
+    /// Merge a CSV; `#` preamble lines written by --csv-meta are skipped and
+    /// the separator is taken from the header line
+    pub fn add_csv(&mut self, path: &Path, host: &str) -> Result<usize> {
+        let mut input = BufReader::new(File::open(path).with_context(|| format!("Failed to open {}", path.display()))?);
+        let mut header = String::new();
+        loop {
+            header.clear();
+            if input.read_line(&mut header)? == 0 {
+                return Ok(0);
+            }
+            if !header.starts_with('#') && !header.trim().is_empty() {
+                break;
+            }
+        }
+        let delimiter = CSV_DELIMITERS
+            .into_iter()
+            .max_by_key(|&delimiter| header.bytes().filter(|&byte| byte == delimiter).count())
+            .unwrap_or(b',');
+
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(header.as_bytes().chain(input));
+        let columns: Vec<usize> = reader.headers()?.iter().map(|name| self.column(name)).collect();
+
+        let mut count = 0;
+        for (line, row) in reader.records().enumerate() {
+            let row = row.with_context(|| format!("Failed to read row {} of {}", line + 1, path.display()))?;
+            let values = columns.iter().zip(row.iter()).map(|(&column, value)| (column, Value::String(value.to_string())));
+            self.push(host, values);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Merge a JSON Lines file, one object per line
+    pub fn add_jsonl(&mut self, path: &Path, host: &str) -> Result<usize> {
+        let input = BufReader::new(File::open(path).with_context(|| format!("Failed to open {}", path.display()))?);
+        let mut count = 0;
+        for (line, text) in input.lines().enumerate() {
+            let text = text.with_context(|| format!("Failed to read {}", path.display()))?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let OrderedObject(fields) = serde_json::from_str(&text)
+                .with_context(|| format!("Line {} of {} is not a JSON object", line + 1, path.display()))?;
+            let values: Vec<(usize, Value)> = fields.into_iter().map(|(name, value)| (self.column(&name), value)).collect();
+            self.push(host, values);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Order rows by a column, oldest first for timestamps. Numbers compare by
+    /// value, other values as text, which puts the UTC timestamps every output
+    /// uses in chronological order; rows without a value go last
+    pub fn sort_by(&mut self, column: &str) -> Result<()> {
+        let Some(&column) = self.index.get(column) else {
+            bail!("No input has a column named {}", column);
+        };
+        self.rows.sort_by(|a, b| compare_values(a.get(column), b.get(column)));
+        Ok(())
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = ::csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record(&self.columns)?;
+        for row in &self.rows {
+            writer.write_record((0..self.columns.len()).map(|column| row.get(column).map(text).unwrap_or_default()))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_jsonl(&self, path: &Path) -> Result<()> {
+        let mut output = BufWriter::new(File::create(path).with_context(|| format!("Failed to create {}", path.display()))?);
+        for row in &self.rows {
+            // Fields in header order; columns the row lacks are left out rather than written as null
+            let mut fields = self.columns.iter().zip(row).filter(|(_, value)| !value.is_null());
+            output.write_all(b"{")?;
+            if let Some((name, value)) = fields.next() {
+                write!(output, "{}:{}", Value::from(name.as_str()), value)?;
+            }
+            for (name, value) in fields {
+                write!(output, ",{}:{}", Value::from(name.as_str()), value)?;
+            }
+            output.write_all(b"}\n")?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Index of a column, adding it to the header the first time it is seen
+    fn column(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.index.get(name) {
+            return index;
+        }
+        self.columns.push(name.to_string());
+        self.index.insert(name.to_string(), self.columns.len() - 1);
+        self.columns.len() - 1
+    }
+
+    /// Add a row; a host value the export already carries (e.g. from
+    /// --context host=...) is kept
+    fn push(&mut self, host: &str, values: impl IntoIterator<Item = (usize, Value)>) {
+        let mut row = vec![Value::Null; self.columns.len()];
+        for (column, value) in values {
+            row[column] = value;
+        }
+        if text(&row[0]).is_empty() {
+            row[0] = Value::String(host.to_string());
+        }
+
+        if let Some(ref mut seen) = self.seen {
+            let key = match self.index.get(RECORD_ID_COLUMN).and_then(|&column| row.get(column)) {
+                Some(Value::String(id)) if !id.is_empty() => format!("{}\n{}", text(&row[0]), id),
+                _ => Value::Array(row.clone()).to_string(),
+            };
+            if !seen.insert(key) {
+                self.duplicates += 1;
+                return;
+            }
+        }
+        self.rows.push(row);
+    }
+}
+
+/// The .csv and .jsonl files to merge for an input: the file itself, or the
+/// outputs in a directory and its subdirectories, in name order. With
+/// `name_filter`, only files whose name contains it (case-insensitive).
+pub fn input_files(path: &Path, name_filter: Option<&str>) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let filter = name_filter.map(str::to_lowercase);
+    let mut files = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let entries = std::fs::read_dir(&directory).with_context(|| format!("Failed to read directory {}", directory.display()))?;
+        for entry in entries {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() {
+                pending.push(entry_path);
+                continue;
+            }
+            let name = entry_path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+            let wanted = matches!(extension(&entry_path).as_str(), "csv" | "jsonl")
+                && filter.as_ref().is_none_or(|filter| name.contains(filter.as_str()));
+            if wanted {
+                files.push(entry_path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// A JSON object's fields in the order they were written, which serde_json's
+/// `Map` does not keep
+struct OrderedObject(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for OrderedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldVisitor;
+
+        impl<'de> Visitor<'de> for FieldVisitor {
+            type Value = OrderedObject;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedObject, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(OrderedObject(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldVisitor)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// A value as written to CSV: strings unquoted, missing values empty
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let a = a.map(text).filter(|text| !text.is_empty());
+    let b = b.map(text).filter(|text| !text.is_empty());
+    match (a, b) {
+        (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a.cmp(&b),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
@@ -1,4 +1,22 @@
 pub mod csv;
 pub mod json;
 pub mod bodyfile;
-pub mod table;
\ No newline at end of file
+pub mod hashdeep;
+pub mod mactime;
+pub mod normalize;
+pub mod columns;
+pub mod table;
+pub mod usage_map;
+pub mod entry_map;
+pub mod pipeline;
+pub mod audit;
+pub mod sink;
+pub mod template;
+pub mod rotate;
+pub mod forward;
+pub mod checkpoint;
+pub mod merge;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "parquet")]
+pub mod parquet;
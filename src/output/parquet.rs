@@ -0,0 +1,374 @@
+use super::sink::{Batches, OutputSink};
+use anyhow::{Context, Result};
+use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::types::Type;
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fs::{create_dir_all, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Records as rows of a Parquet file, one optional column per field and one
+/// row group per batch
+pub struct ParquetSink {
+    path: PathBuf,
+}
+
+impl ParquetSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+/// Name and type of every column, in field order
+type Columns = Vec<(&'static str, ColumnKind)>;
+
+/// Parquet type of a column, from the Rust type of its field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Boolean,
+    Int64,
+    /// u64 fields, stored as INT64 marked unsigned so no value is out of range
+    UInt64,
+    Double,
+    /// Strings, and anything else as JSON text, like nested values in SQLite
+    Text,
+}
+
+impl ColumnKind {
+    fn schema_type(self, name: &str) -> Result<Type> {
+        let builder = match self {
+            ColumnKind::Boolean => Type::primitive_type_builder(name, PhysicalType::BOOLEAN),
+            ColumnKind::Int64 => Type::primitive_type_builder(name, PhysicalType::INT64),
+            ColumnKind::UInt64 => Type::primitive_type_builder(name, PhysicalType::INT64)
+                .with_logical_type(Some(LogicalType::Integer { bit_width: 64, is_signed: false })),
+            ColumnKind::Double => Type::primitive_type_builder(name, PhysicalType::DOUBLE),
+            ColumnKind::Text => Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY).with_logical_type(Some(LogicalType::String)),
+        };
+        Ok(builder.with_repetition(Repetition::OPTIONAL).build()?)
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for ParquetSink {
+    fn format(&self) -> &'static str {
+        "Parquet"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let mut writer: Option<(SerializedFileWriter<File>, Columns)> = None;
+        for batch in batches {
+            if batch.is_empty() {
+                continue;
+            }
+            let rows = batch
+                .iter()
+                .map(|record| match serde_json::to_value(record)? {
+                    Value::Object(fields) => Ok(fields),
+                    _ => anyhow::bail!("Parquet output requires records that serialize as structs"),
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let (file_writer, columns) = match writer {
+                Some(ref mut writer) => writer,
+                None => {
+                    let columns = record_columns(&batch)?;
+                    let file_writer = create_writer(&self.path, &columns)?;
+                    writer.insert((file_writer, columns))
+                }
+            };
+
+            let mut row_group = file_writer.next_row_group()?;
+            for &(name, kind) in columns.iter() {
+                let mut column = row_group.next_column()?.context("Parquet schema has fewer columns than the records")?;
+                write_column(&mut column, name, kind, &rows)?;
+                column.close()?;
+            }
+            row_group.close()?;
+        }
+
+        match writer {
+            Some((file_writer, _)) => {
+                file_writer.close()?;
+            }
+            // No records: still leave a valid file, with no columns and no rows
+            None => {
+                create_writer(&self.path, &[])?.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_writer(path: &Path, columns: &[(&'static str, ColumnKind)]) -> Result<SerializedFileWriter<File>> {
+    let fields = columns
+        .iter()
+        .map(|&(name, kind)| kind.schema_type(name).map(Arc::new))
+        .collect::<Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("schema").with_fields(fields).build()?;
+    // Like the other sinks, a rerun replaces the previous output instead of appending to it
+    let file = File::create(path).with_context(|| format!("Failed to create Parquet file: {}", path.display()))?;
+    Ok(SerializedFileWriter::new(file, Arc::new(schema), Arc::new(WriterProperties::builder().build()))?)
+}
+
+/// Every field of the record type with the Parquet type of its Rust type, as
+/// serde reports it. An Option field takes its type from the first record of
+/// `batch` that sets it; one unset in all of them is written as text, which
+/// holds any value it gets later.
+fn record_columns<T: Serialize>(batch: &[T]) -> Result<Columns> {
+    let mut columns: Vec<(&'static str, Option<ColumnKind>)> = Vec::new();
+    for record in batch {
+        let mut fields = Vec::new();
+        record.serialize(KindProbe { fields: Some(&mut fields) })?;
+        if columns.is_empty() {
+            columns = fields;
+        } else {
+            for ((_, kind), (_, field_kind)) in columns.iter_mut().zip(fields) {
+                *kind = kind.or(field_kind);
+            }
+        }
+        if columns.iter().all(|(_, kind)| kind.is_some()) {
+            break;
+        }
+    }
+    if columns.is_empty() {
+        anyhow::bail!("Parquet output requires records that serialize as structs");
+    }
+    Ok(columns.into_iter().map(|(name, kind)| (name, kind.unwrap_or(ColumnKind::Text))).collect())
+}
+
+/// Write the values of one column of a batch; nulls and missing fields get
+/// definition level 0
+fn write_column(column: &mut SerializedColumnWriter<'_>, name: &str, kind: ColumnKind, rows: &[Map<String, Value>]) -> Result<()> {
+    let values = rows.iter().map(|row| row.get(name).filter(|value| !value.is_null()));
+    let levels: Vec<i16> = values.clone().map(|value| value.is_some() as i16).collect();
+    let mismatch = |value: &Value| anyhow::anyhow!("Column {} holds {} in a later batch, which does not fit its Parquet type", name, value);
+
+    match kind {
+        ColumnKind::Boolean => {
+            let data = values.flatten().map(|value| value.as_bool().ok_or_else(|| mismatch(value))).collect::<Result<Vec<_>>>()?;
+            column.typed::<BoolType>().write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnKind::Int64 => {
+            let data = values.flatten().map(|value| value.as_i64().ok_or_else(|| mismatch(value))).collect::<Result<Vec<_>>>()?;
+            column.typed::<Int64Type>().write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnKind::UInt64 => {
+            // Parquet keeps unsigned 64-bit values in INT64's bits
+            let data = values
+                .flatten()
+                .map(|value| value.as_u64().map(|value| value as i64).ok_or_else(|| mismatch(value)))
+                .collect::<Result<Vec<_>>>()?;
+            column.typed::<Int64Type>().write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnKind::Double => {
+            let data = values.flatten().map(|value| value.as_f64().ok_or_else(|| mismatch(value))).collect::<Result<Vec<_>>>()?;
+            column.typed::<DoubleType>().write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnKind::Text => {
+            let data: Vec<ByteArray> = values
+                .flatten()
+                .map(|value| match value {
+                    Value::String(s) => ByteArray::from(s.as_str()),
+                    other => ByteArray::from(other.to_string().as_str()),
+                })
+                .collect();
+            column.typed::<ByteArrayType>().write_batch(&data, Some(&levels), None)?;
+        }
+    }
+    Ok(())
+}
+
+/// The Parquet type of a serialized value, None for an unset Option. At the
+/// top level, a struct's fields and their types go to `fields` instead.
+struct KindProbe<'a> {
+    fields: Option<&'a mut Vec<(&'static str, Option<ColumnKind>)>>,
+}
+
+struct FieldKinds<'a> {
+    fields: &'a mut Vec<(&'static str, Option<ColumnKind>)>,
+}
+
+/// Nested values stop the probe; they are written as JSON text
+fn nested() -> serde_json::Error {
+    ser::Error::custom("nested value")
+}
+
+impl ser::SerializeStruct for FieldKinds<'_> {
+    type Ok = Option<ColumnKind>;
+    type Error = serde_json::Error;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, key: &'static str, value: &V) -> Result<(), Self::Error> {
+        let kind = value.serialize(KindProbe { fields: None }).unwrap_or(Some(ColumnKind::Text));
+        self.fields.push((key, kind));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+}
+
+impl<'a> Serializer for KindProbe<'a> {
+    type Ok = Option<ColumnKind>;
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = FieldKinds<'a>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        match self.fields {
+            Some(fields) => Ok(FieldKinds { fields }),
+            None => Err(nested()),
+        }
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Boolean)) }
+    fn serialize_i8(self, _: i8) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_i16(self, _: i16) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_i32(self, _: i32) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_i64(self, _: i64) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_u8(self, _: u8) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_u16(self, _: u16) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_u32(self, _: u32) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Int64)) }
+    fn serialize_u64(self, _: u64) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::UInt64)) }
+    fn serialize_f32(self, _: f32) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Double)) }
+    fn serialize_f64(self, _: f64) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Double)) }
+    fn serialize_char(self, _: char) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Text)) }
+    fn serialize_str(self, _: &str) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Text)) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, Self::Error> { Ok(Some(ColumnKind::Text)) }
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> { Ok(None) }
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> { Ok(None) }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(ColumnKind::Text))
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(self, _name: &'static str, value: &V) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &V,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(nested())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(nested())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    #[derive(serde::Serialize)]
+    struct Row {
+        usn: u64,
+        size: Option<u32>,
+        deleted: Option<bool>,
+        name: String,
+        times: Vec<u64>,
+    }
+
+    fn row(usn: u64, size: Option<u32>, deleted: Option<bool>) -> Row {
+        Row { usn, size, deleted, name: format!("file{}", usn), times: vec![usn] }
+    }
+
+    #[test]
+    fn schema_follows_field_types_not_first_batch_values() {
+        let path = std::env::temp_dir().join(format!("mfte-rs-parquet-{}.parquet", std::process::id()));
+        // The first record leaves size unset and deleted is never set in the
+        // first batch; usn only goes past i64::MAX in the second
+        let batches = vec![
+            Arc::new(vec![row(1, None, None), row(2, Some(7), None)]),
+            Arc::new(vec![row(u64::MAX, Some(u32::MAX), Some(true))]),
+        ];
+        Box::new(ParquetSink::new(path.clone())).consume(&mut batches.into_iter()).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr_ptr();
+        let types: Vec<(String, PhysicalType)> = schema.columns().iter().map(|column| (column.name().to_string(), column.physical_type())).collect();
+        assert_eq!(
+            types,
+            [
+                ("usn".to_string(), PhysicalType::INT64),
+                ("size".to_string(), PhysicalType::INT64),
+                ("deleted".to_string(), PhysicalType::BYTE_ARRAY),
+                ("name".to_string(), PhysicalType::BYTE_ARRAY),
+                ("times".to_string(), PhysicalType::BYTE_ARRAY),
+            ]
+        );
+
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect();
+        let field = |row: usize, column: usize| rows[row].get_column_iter().nth(column).unwrap().1.clone();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(field(2, 0), Field::ULong(u64::MAX));
+        assert_eq!(field(0, 1), Field::Null);
+        assert_eq!(field(2, 1), Field::Long(u64::from(u32::MAX) as i64));
+        assert_eq!(field(2, 2), Field::Str("true".to_string()));
+        assert_eq!(field(1, 4), Field::Str("[2]".to_string()));
+        let _ = std::fs::remove_file(&path);
+    }
+}
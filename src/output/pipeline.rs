@@ -0,0 +1,90 @@
+use super::sink::OutputSink;
+use anyhow::{anyhow, Context, Result};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Number of batches each writer may fall behind before the producer blocks
+const CHANNEL_CAPACITY: usize = 8;
+
+/// Records per batch sent to writer threads
+pub const BATCH_SIZE: usize = 4096;
+
+pub type Batch<T> = Arc<Vec<T>>;
+
+/// Fans batches of parsed records out to one thread per output writer.
+///
+/// Every writer has its own bounded channel, so a slow sink applies backpressure
+/// to the producer instead of buffering the whole artifact in memory.
+pub struct WriterPipeline<T> {
+    senders: Vec<SyncSender<Batch<T>>>,
+    handles: Vec<(String, JoinHandle<Result<()>>)>,
+}
+
+impl<T: Send + Sync + 'static> Default for WriterPipeline<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + Sync + 'static> WriterPipeline<T> {
+    pub fn new() -> Self {
+        Self {
+            senders: Vec::new(),
+            handles: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// Spawn a writer thread that consumes batches until the pipeline is finished
+    pub fn spawn<F>(&mut self, name: &str, writer: F) -> Result<()>
+    where
+        F: FnOnce(Receiver<Batch<T>>) -> Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+        let handle = thread::Builder::new()
+            .name(format!("writer-{}", name))
+            .spawn(move || writer(receiver))
+            .with_context(|| format!("Failed to start {} writer thread", name))?;
+
+        self.senders.push(sender);
+        self.handles.push((name.to_string(), handle));
+        Ok(())
+    }
+
+    /// Spawn a writer thread that feeds every batch to an output sink
+    pub fn spawn_sink(&mut self, sink: Box<dyn OutputSink<T>>) -> Result<()> {
+        let name = sink.format().to_lowercase();
+        self.spawn(&name, move |receiver| sink.consume(&mut receiver.into_iter()))
+    }
+
+    /// Send a batch to every writer, blocking while any of them is full
+    pub fn send(&self, batch: Vec<T>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let batch = Arc::new(batch);
+        for sender in &self.senders {
+            // A disconnected writer has failed; its error is reported by finish()
+            let _ = sender.send(Arc::clone(&batch));
+        }
+    }
+
+    /// Close the channels and wait for every writer to flush
+    pub fn finish(self) -> Result<()> {
+        drop(self.senders);
+
+        for (name, handle) in self.handles {
+            handle
+                .join()
+                .map_err(|_| anyhow!("{} writer thread panicked", name))?
+                .with_context(|| format!("{} writer failed", name))?;
+        }
+
+        Ok(())
+    }
+}
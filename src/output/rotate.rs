@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{self, create_dir_all, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON Lines file that is rotated once it reaches a size limit, for long-running
+/// collection. The active file is `<name>.jsonl`; rotated ones are
+/// `<name>.1.jsonl` (newest) up to `<name>.<keep>.jsonl`, and older ones are deleted.
+pub struct RotatingJsonl {
+    dir: PathBuf,
+    name: String,
+    max_bytes: u64,
+    keep: usize,
+    output: BufWriter<File>,
+    written: u64,
+}
+
+impl RotatingJsonl {
+    /// Open `<dir>/<name>.jsonl` for appending; lines already in it count towards the limit
+    pub fn open(dir: &Path, name: &str, max_bytes: u64, keep: usize) -> Result<Self> {
+        create_dir_all(dir).with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+        let path = dir.join(format!("{}.jsonl", name));
+        let (output, written) = open_append(&path)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            name: name.to_string(),
+            max_bytes,
+            keep,
+            output,
+            written,
+        })
+    }
+
+    /// File currently written to
+    pub fn path(&self) -> PathBuf {
+        self.rotated_path(0)
+    }
+
+    /// Append one record as a line, rotating first when the line would cross the limit
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.write_line(&line)
+    }
+
+    /// Append one already serialized line, including its line ending
+    pub fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.output.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.output.flush()?;
+
+        let oldest = self.rotated_path(self.keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest).with_context(|| format!("Failed to remove {}", oldest.display()))?;
+        }
+        for generation in (0..self.keep).rev() {
+            let from = self.rotated_path(generation);
+            if from.exists() {
+                let to = self.rotated_path(generation + 1);
+                fs::rename(&from, &to).with_context(|| format!("Failed to rotate {} to {}", from.display(), to.display()))?;
+            }
+        }
+        // With nothing to keep, the active file itself is dropped
+        let active = self.path();
+        if self.keep == 0 && active.exists() {
+            fs::remove_file(&active)?;
+        }
+
+        let (output, written) = open_append(&active)?;
+        self.output = output;
+        self.written = written;
+        log::info!("Rotated {}", active.display());
+        Ok(())
+    }
+
+    fn rotated_path(&self, generation: usize) -> PathBuf {
+        match generation {
+            0 => self.dir.join(format!("{}.jsonl", self.name)),
+            n => self.dir.join(format!("{}.{}.jsonl", self.name, n)),
+        }
+    }
+}
+
+fn open_append(path: &Path) -> Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let written = file.metadata()?.len();
+    Ok((BufWriter::new(file), written))
+}
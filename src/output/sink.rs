@@ -0,0 +1,262 @@
+use super::csv::{csv_writer, CsvOptions, CsvPreamble};
+use super::json::{stream_array, JsonOptions};
+use super::normalize::Normalized;
+use super::pipeline::Batch;
+use super::template::Template;
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const WRITE_BUFFER_SIZE: usize = 1 << 20;
+
+/// Stream of record batches handed to a sink
+pub type Batches<'a, T> = &'a mut dyn Iterator<Item = Batch<T>>;
+
+/// A destination for parsed records.
+///
+/// Every artifact feeds the same sinks, so a new output format only has to be
+/// implemented once. Sinks run on their own writer thread and consume batches
+/// until the producer finishes.
+pub trait OutputSink<T>: Send {
+    /// Format name used in log messages (e.g. "CSV")
+    fn format(&self) -> &'static str;
+
+    /// File the sink writes to
+    fn path(&self) -> &Path;
+
+    /// Write every batch, then flush and close the output
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()>;
+}
+
+fn create_output(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let file = File::create(path)?;
+    Ok(BufWriter::with_capacity(WRITE_BUFFER_SIZE, file))
+}
+
+/// Records as CSV rows, optionally preceded by the provenance preamble
+pub struct CsvSink {
+    path: PathBuf,
+    options: CsvOptions,
+    normalized: bool,
+}
+
+impl CsvSink {
+    pub fn new(path: PathBuf, options: CsvOptions) -> Self {
+        Self { path, options, normalized: false }
+    }
+
+    /// Leave out volatile fields and fix timestamp precision, for diffing runs
+    pub fn normalized(mut self, normalized: bool) -> Self {
+        self.normalized = normalized;
+        self
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for CsvSink {
+    fn format(&self) -> &'static str {
+        "CSV"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        let mut writer = csv_writer(create_output(&self.path)?, &self.options)?;
+        for batch in batches {
+            for record in batch.iter() {
+                if self.normalized {
+                    writer.serialize(Normalized(record))?;
+                } else {
+                    writer.serialize(record)?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Records as lines laid out by a --template, under a header naming the fields
+pub struct TemplateSink {
+    path: PathBuf,
+    template: Template,
+    preamble: Option<CsvPreamble>,
+}
+
+impl TemplateSink {
+    pub fn new(path: PathBuf, template: Template, preamble: Option<CsvPreamble>) -> Self {
+        Self { path, template, preamble }
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for TemplateSink {
+    fn format(&self) -> &'static str {
+        "Template"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        let mut output = create_output(&self.path)?;
+        if let Some(ref preamble) = self.preamble {
+            preamble.write_to(&mut output)?;
+        }
+        writeln!(output, "{}", self.template.header())?;
+        for batch in batches {
+            for record in batch.iter() {
+                writeln!(output, "{}", self.template.render(record)?)?;
+            }
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// Records as a single JSON array, or a lone object for single-record artifacts
+pub struct JsonSink {
+    path: PathBuf,
+    options: JsonOptions,
+    single_object: bool,
+}
+
+impl JsonSink {
+    pub fn new(path: PathBuf, options: JsonOptions) -> Self {
+        Self { path, options, single_object: false }
+    }
+
+    /// Write the first record as a bare object instead of wrapping it in an array
+    pub fn single_object(mut self) -> Self {
+        self.single_object = true;
+        self
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for JsonSink {
+    fn format(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        let mut output = create_output(&self.path)?;
+
+        if self.single_object {
+            for batch in batches {
+                let Some(record) = batch.first() else {
+                    continue;
+                };
+                match (self.options.pretty, self.options.normalized) {
+                    (true, true) => serde_json::to_writer_pretty(&mut output, &Normalized(record))?,
+                    (true, false) => serde_json::to_writer_pretty(&mut output, record)?,
+                    (false, true) => serde_json::to_writer(&mut output, &Normalized(record))?,
+                    (false, false) => serde_json::to_writer(&mut output, record)?,
+                }
+                break;
+            }
+        } else {
+            stream_array(&mut output, self.options, |write_record| {
+                for batch in batches {
+                    batch.iter().try_for_each(&mut *write_record)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// One compact JSON object per line, suited to log shippers and line-oriented tools
+pub struct JsonlSink {
+    path: PathBuf,
+    normalized: bool,
+}
+
+impl JsonlSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, normalized: false }
+    }
+
+    /// Leave out volatile fields and fix timestamp precision, for diffing runs
+    pub fn normalized(mut self, normalized: bool) -> Self {
+        self.normalized = normalized;
+        self
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for JsonlSink {
+    fn format(&self) -> &'static str {
+        "JSONL"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        let mut output = create_output(&self.path)?;
+        for batch in batches {
+            for record in batch.iter() {
+                if self.normalized {
+                    serde_json::to_writer(&mut output, &Normalized(record))?;
+                } else {
+                    serde_json::to_writer(&mut output, record)?;
+                }
+                output.write_all(b"\n")?;
+            }
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// Formats one record as a line of text; records formatted to None are skipped
+pub type LineFormatter<T> = Box<dyn Fn(&T) -> Option<String> + Send>;
+
+/// One formatted line per record, used for bodyfile output
+pub struct LineSink<T> {
+    format: &'static str,
+    path: PathBuf,
+    format_line: LineFormatter<T>,
+}
+
+impl<T> LineSink<T> {
+    pub fn new(format: &'static str, path: PathBuf, format_line: LineFormatter<T>) -> Self {
+        Self { format, path, format_line }
+    }
+}
+
+impl<T> OutputSink<T> for LineSink<T> {
+    fn format(&self) -> &'static str {
+        self.format
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        let mut output = create_output(&self.path)?;
+        for batch in batches {
+            for record in batch.iter() {
+                if let Some(line) = (self.format_line)(record) {
+                    output.write_all(line.as_bytes())?;
+                }
+            }
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use super::json::field_names;
+use super::sink::{Batches, OutputSink};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{create_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+
+/// Records as rows of a single table in a SQLite database, one column per field
+pub struct SqliteSink {
+    path: PathBuf,
+    table: String,
+}
+
+impl SqliteSink {
+    pub fn new(path: PathBuf, table: &str) -> Self {
+        Self { path, table: table.to_string() }
+    }
+}
+
+impl<T: Serialize> OutputSink<T> for SqliteSink {
+    fn format(&self) -> &'static str {
+        "SQLite"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn consume(self: Box<Self>, batches: Batches<'_, T>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+        // Like the other sinks, a rerun replaces the previous output instead of appending to it
+        if self.path.exists() {
+            remove_file(&self.path)?;
+        }
+
+        let mut connection = Connection::open(&self.path)
+            .with_context(|| format!("Failed to create SQLite database: {}", self.path.display()))?;
+        let transaction = connection.transaction()?;
+        let mut columns: Option<Vec<&'static str>> = None;
+
+        for batch in batches {
+            for record in batch.iter() {
+                let columns = match columns {
+                    Some(ref columns) => columns,
+                    None => {
+                        let names = field_names(record);
+                        transaction.execute(&create_table_sql(&self.table, &names), [])?;
+                        columns.insert(names)
+                    }
+                };
+
+                let Value::Object(mut fields) = serde_json::to_value(record)? else {
+                    anyhow::bail!("SQLite output requires records that serialize as structs");
+                };
+                let row = columns.iter().map(|name| sql_value(fields.remove(*name).unwrap_or(Value::Null)));
+                transaction
+                    .prepare_cached(&insert_sql(&self.table, columns.len()))?
+                    .execute(params_from_iter(row))?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn create_table_sql(table: &str, columns: &[&str]) -> String {
+    let columns: Vec<String> = columns.iter().map(|name| quote_identifier(name)).collect();
+    format!("CREATE TABLE {} ({})", quote_identifier(table), columns.join(", "))
+}
+
+fn insert_sql(table: &str, column_count: usize) -> String {
+    let placeholders: Vec<String> = (1..=column_count).map(|i| format!("?{}", i)).collect();
+    format!("INSERT INTO {} VALUES ({})", quote_identifier(table), placeholders.join(", "))
+}
+
+/// Map a JSON value onto SQLite's storage classes; nested values are kept as JSON text
+fn sql_value(value: Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(b as i64),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => SqlValue::Integer(i),
+            (None, Some(f)) if n.is_f64() => SqlValue::Real(f),
+            // u64 values above i64::MAX are stored as text so no digits are lost
+            _ => SqlValue::Text(n.to_string()),
+        },
+        Value::String(s) => SqlValue::Text(s),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
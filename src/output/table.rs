@@ -1,6 +1,16 @@
 use crate::ntfs::types::*;
+use super::json::BatchSummary;
 use std::io::{self, Write};
 
+/// Affected paths shown under each ransomware alert
+const ALERT_PATHS_SHOWN: usize = 5;
+
+/// Alternate data stream names listed under the MFT statistics; the JSON summary has all of them
+const ADS_NAMES_SHOWN: usize = 20;
+
+/// Corrupt $MFT records listed on the console; --errors writes them all
+const CORRUPT_RECORDS_SHOWN: usize = 20;
+
 pub struct TableOutput;
 
 impl TableOutput {
@@ -38,10 +48,9 @@ impl TableOutput {
                      modified);
         }
 
-        if let Some(limit) = limit {
-            if records.len() > limit {
-                println!("\n... and {} more records", records.len() - limit);
-            }
+        if let Some(limit) = limit
+            && records.len() > limit {
+            println!("\n... and {} more records", records.len() - limit);
         }
     }
 
@@ -64,7 +73,10 @@ impl TableOutput {
                 entry.file_name.clone()
             };
 
-            let reason = if entry.reason.len() > 28 {
+            let reason = if entry.reason.is_empty() {
+                // --raw skips reason names
+                format!("0x{:08x}", entry.reason_flags)
+            } else if entry.reason.len() > 28 {
                 format!("{}...", &entry.reason[..25])
             } else {
                 entry.reason.clone()
@@ -78,10 +90,9 @@ impl TableOutput {
                      reason);
         }
 
-        if let Some(limit) = limit {
-            if entries.len() > limit {
-                println!("\n... and {} more entries", entries.len() - limit);
-            }
+        if let Some(limit) = limit
+            && entries.len() > limit {
+            println!("\n... and {} more entries", entries.len() - limit);
         }
     }
 
@@ -109,9 +120,9 @@ impl TableOutput {
             None => descriptors,
         };
 
-        println!("{:<8} {:<12} {:<16} {:<8} {:<20}",
-                 "ID", "Hash", "Offset", "Length", "Descriptor (hex)");
-        println!("{}", "-".repeat(70));
+        println!("{:<8} {:<12} {:<6} {:<16} {:<8} {:<20}",
+                 "ID", "Hash", "Valid", "Offset", "Length", "Descriptor (hex)");
+        println!("{}", "-".repeat(77));
 
         for desc in descriptors_to_show {
             let descriptor_preview = if desc.descriptor.len() > 16 {
@@ -120,18 +131,18 @@ impl TableOutput {
                 hex::encode(&desc.descriptor)
             };
 
-            println!("{:<8} {:<12} 0x{:<14X} {:<8} {}",
+            println!("{:<8} {:<12} {:<6} 0x{:<14X} {:<8} {}",
                      desc.id,
                      desc.hash,
+                     if desc.hash_valid { "yes" } else { "NO" },
                      desc.offset,
                      desc.length,
                      descriptor_preview);
         }
 
-        if let Some(limit) = limit {
-            if descriptors.len() > limit {
-                println!("\n... and {} more descriptors", descriptors.len() - limit);
-            }
+        if let Some(limit) = limit
+            && descriptors.len() > limit {
+            println!("\n... and {} more descriptors", descriptors.len() - limit);
         }
     }
 
@@ -164,11 +175,257 @@ impl TableOutput {
                      modified);
         }
 
-        if let Some(limit) = limit {
-            if entries.len() > limit {
-                println!("\n... and {} more entries", entries.len() - limit);
+        if let Some(limit) = limit
+            && entries.len() > limit {
+            println!("\n... and {} more entries", entries.len() - limit);
+        }
+    }
+
+    pub fn print_logfile_names(names: &[LogFileName], limit: Option<usize>) {
+        let names_to_show = match limit {
+            Some(n) => &names[..n.min(names.len())],
+            None => names,
+        };
+
+        println!("{:<10} {:<40} {:<10} {:<20} {:<20} {:<7}",
+                 "Parent", "File Name", "Size", "Created", "Modified", "In MFT");
+        println!("{}", "-".repeat(112));
+
+        for name in names_to_show {
+            let file_name = if name.file_name.len() > 38 {
+                format!("{}...", &name.file_name[..35])
+            } else {
+                name.file_name.clone()
+            };
+            let in_mft = match name.in_mft {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "",
+            };
+
+            println!("{:<10} {:<40} {:<10} {:<20} {:<20} {:<7}",
+                     format!("{}-{}", name.parent_entry_number, name.parent_sequence_number),
+                     file_name,
+                     name.file_size,
+                     name.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+                     name.modified.format("%Y-%m-%d %H:%M:%S").to_string(),
+                     in_mft);
+        }
+
+        if let Some(limit) = limit
+            && names.len() > limit {
+            println!("... and {} more names", names.len() - limit);
+        }
+    }
+
+    pub fn print_cluster_owners(owners: &[ClusterOwner]) {
+        println!("{:<14} {:<8} {:<6} {:<7} {:<12} {:<14} {:<20} Path",
+                 "Cluster", "Entry", "Seq", "In Use", "VCN", "File Offset", "Stream");
+        println!("{}", "-".repeat(125));
+
+        for owner in owners {
+            let file_offset = owner.file_offset.map(|offset| offset.to_string()).unwrap_or_default();
+            println!("{:<14} {:<8} {:<6} {:<7} {:<12} {:<14} {:<20} {}",
+                     owner.cluster,
+                     owner.entry_number,
+                     owner.sequence_number,
+                     if owner.in_use { "yes" } else { "no" },
+                     owner.vcn,
+                     file_offset,
+                     if owner.stream.is_empty() { "$DATA" } else { &owner.stream },
+                     owner.full_path);
+        }
+    }
+
+    pub fn print_descriptor_diffs(diffs: &[SecurityDescriptorDiff]) {
+        println!("\nSecurity Descriptor Changes:");
+        println!("{}", "-".repeat(120));
+        println!("{:<12} {:<8} {:<16} {:<40} New Owner", "Security ID", "Change", "Parts", "Old Owner");
+        for diff in diffs {
+            let change = match diff.change {
+                DescriptorChange::Added => "added",
+                DescriptorChange::Removed => "removed",
+                DescriptorChange::Changed => "changed",
+            };
+            println!("{:<12} {:<8} {:<16} {:<40} {}",
+                     diff.security_id,
+                     change,
+                     diff.changed_parts,
+                     diff.old_owner,
+                     diff.new_owner);
+        }
+    }
+
+    pub fn print_permission_drift(drift: &[PermissionDrift]) {
+        println!("\nFiles With Changed Permissions:");
+        println!("{}", "-".repeat(120));
+        println!("{:<8} {:<6} {:<16} {:<16} {:<12} Path", "Entry", "Seq", "Security IDs", "Parts", "Owner");
+        for file in drift {
+            let ids = match file.old_security_id {
+                Some(old) => format!("{} -> {}", old, file.new_security_id),
+                None => file.new_security_id.to_string(),
+            };
+            let owner = if file.old_owner == file.new_owner { "same" } else { "changed" };
+            println!("{:<8} {:<6} {:<16} {:<16} {:<12} {}",
+                     file.entry_number,
+                     file.sequence_number,
+                     ids,
+                     file.changed_parts,
+                     owner,
+                     file.full_path);
+        }
+    }
+
+    pub fn print_mft_statistics(stats: &MftStatistics) {
+        println!("\nMFT Statistics:");
+        println!("{}", "-".repeat(30));
+        println!("Total Records:       {}", stats.total_records);
+        println!("Active Files:        {}", stats.active_files);
+        println!("Active Directories:  {}", stats.active_directories);
+        println!("Deleted Files:       {}", stats.deleted_files);
+        println!("Deleted Directories: {}", stats.deleted_directories);
+        println!("ADS Streams:         {}", stats.ads_streams);
+        println!("Orphans:             {}", stats.orphans);
+        println!("Extension Records:   {}", stats.extension_records);
+
+        if !stats.ads_names.is_empty() {
+            println!("\nADS Names:");
+            println!("{}", "-".repeat(60));
+            println!("{:>8}  Name", "Records");
+            for ads in stats.ads_names.iter().take(ADS_NAMES_SHOWN) {
+                let flag = if ads.unusual { "  (unusual)" } else { "" };
+                println!("{:>8}  {}{}", ads.count, ads.name, flag);
+            }
+            let more = stats.ads_names.len().saturating_sub(ADS_NAMES_SHOWN);
+            if more > 0 {
+                println!("... and {} more", more);
+            }
+        }
+    }
+
+    pub fn print_record_number_shifts(shifts: &[RecordNumberShift]) {
+        println!("\nShifted Record Numbers:");
+        println!("{}", "-".repeat(60));
+        println!("{:<22} {:<10} Shift", "Positions", "Records");
+        for region in shifts {
+            println!("{:<22} {:<10} {:+}",
+                     format!("{}-{}", region.first_position, region.last_position),
+                     region.record_count,
+                     region.shift);
+        }
+    }
+
+    pub fn print_corrupt_records(records: &[CorruptRecord]) {
+        println!("\nCorrupt Records:");
+        println!("{}", "-".repeat(100));
+        println!("{:<10} {:<14} {:<10} Reason", "Position", "Offset", "Code");
+        for record in records.iter().take(CORRUPT_RECORDS_SHOWN) {
+            println!("{:<10} {:<14} {:<10} {}", record.position, format!("0x{:x}", record.offset), record.code, record.reason);
+        }
+        let more = records.len().saturating_sub(CORRUPT_RECORDS_SHOWN);
+        if more > 0 {
+            println!("... and {} more", more);
+        }
+    }
+
+    pub fn print_mft_layout(layout: &MftLayout) {
+        println!("\nMFT Layout:");
+        println!("{}", "-".repeat(60));
+        println!("Records:             {}", layout.record_count);
+        match layout.high_water_mark {
+            Some(mark) => println!("High-Water Mark:     {} ({} entries ever used)", mark, mark as u64 + 1),
+            None => println!("High-Water Mark:     none"),
+        }
+        println!("Never Used:          {}", layout.never_used_records);
+        if let Some(expected_size) = layout.expected_size {
+            println!("Size:                {} of {} bytes{}",
+                     layout.actual_size,
+                     expected_size,
+                     if layout.truncated { " (truncated)" } else { "" });
+        } else if layout.truncated {
+            println!("Size:                {} bytes (truncated)", layout.actual_size);
+        }
+
+        if !layout.gaps.is_empty() {
+            println!("\n{:<22} {:<10} Content", "Gap Positions", "Records");
+            for gap in &layout.gaps {
+                println!("{:<22} {:<10} {}",
+                         format!("{}-{}", gap.first_position, gap.last_position),
+                         gap.record_count,
+                         if gap.zeroed { "zeroed" } else { "not FILE records" });
+            }
+        }
+    }
+
+    pub fn print_mft_bitmap_mismatches(mismatches: &[MftBitmapMismatch]) {
+        println!("\n$MFT Bitmap Mismatches:");
+        println!("{}", "-".repeat(60));
+        println!("{:<12} {:<10} {:<14} Bitmap", "Entry", "Sequence", "Record");
+        for mismatch in mismatches {
+            println!("{:<12} {:<10} {:<14} {}",
+                     mismatch.entry_number,
+                     mismatch.sequence_number,
+                     if mismatch.record_in_use { "in use" } else { "not in use" },
+                     if mismatch.bitmap_allocated { "allocated" } else { "free" });
+        }
+    }
+
+    pub fn print_wiping_findings(findings: &[WipingFinding]) {
+        println!("\nWiping and Mass Deletion:");
+        println!("{}", "-".repeat(100));
+        println!("{:<20} {:>8} {:<30} Detail", "Pattern", "Count", "Time span");
+        for finding in findings {
+            let span = match (finding.start, finding.end) {
+                (Some(start), Some(end)) => format!("{} - {}", start.format("%Y-%m-%d %H:%M:%S"), end.format("%H:%M:%S")),
+                _ => String::new(),
+            };
+            println!("{:<20} {:>8} {:<30} {}", finding.pattern.to_string(), finding.count, span, finding.detail);
+        }
+    }
+
+    pub fn print_ransomware_alerts(alerts: &[RansomwareAlert]) {
+        println!("\nPossible Ransomware Activity:");
+        println!("{}", "-".repeat(100));
+        println!("{:<40} {:>8} {:>8} Extensions", "Time span", "Renamed", "Deleted");
+        for alert in alerts {
+            let span = format!("{} - {}", alert.start.format("%Y-%m-%d %H:%M:%S"), alert.end.format("%Y-%m-%d %H:%M:%S"));
+            println!("{:<40} {:>8} {:>8} {}", span, alert.renamed_files, alert.deleted_files, alert.extensions);
+            for path in alert.sample_paths.iter().take(ALERT_PATHS_SHOWN) {
+                println!("    {}", path);
+            }
+            if alert.renamed_files > ALERT_PATHS_SHOWN {
+                println!("    ... and {} more", alert.renamed_files - ALERT_PATHS_SHOWN);
+            }
+        }
+    }
+
+    pub fn print_batch_summary(batch: &BatchSummary) {
+        println!("\nBatch Summary:");
+        println!("{}", "-".repeat(100));
+        println!("{:<8} {:<8} {:>10} {:>8} {:>10} Input", "Status", "Type", "Records", "Errors", "Time (ms)");
+        for input in &batch.inputs {
+            let (records, errors, time) = match input.summary {
+                Some(ref summary) => (
+                    summary.records_processed.to_string(),
+                    summary.errors_encountered.to_string(),
+                    summary.processing_time_ms.to_string(),
+                ),
+                None => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+            println!("{:<8} {:<8} {:>10} {:>8} {:>10} {}", input.status, input.file_type, records, errors, time, input.input);
+            if let Some(ref error) = input.error {
+                println!("         {}", error);
             }
         }
+
+        let failed = batch.inputs.iter().filter(|input| input.status != "ok").count();
+        println!(
+            "{} input(s), {} failed, {} thread(s), {} ms",
+            batch.inputs.len(),
+            failed,
+            batch.threads,
+            batch.processing_time_ms
+        );
     }
 
     pub fn print_summary(file_type: &str, record_count: usize, processing_time: u128) {
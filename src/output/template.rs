@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::DateTime;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Short names for the $MFT timestamp columns, so templates can say
+/// `{si_created}` instead of `{created_0x10}`
+const FIELD_ALIASES: &[(&str, &str)] = &[
+    ("si_created", "created_0x10"),
+    ("si_modified", "last_modified_0x10"),
+    ("si_changed", "last_record_change_0x10"),
+    ("si_accessed", "last_access_0x10"),
+    ("fn_created", "created_0x30"),
+    ("fn_modified", "last_modified_0x30"),
+    ("fn_changed", "last_record_change_0x30"),
+    ("fn_accessed", "last_access_0x30"),
+];
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Field {
+        /// Name as written in the template, used for the header
+        name: String,
+        /// Record field the value comes from
+        field: String,
+        /// strftime pattern for timestamp fields
+        format: Option<String>,
+    },
+}
+
+/// A --template line layout: literal text with `{field}` or `{field:strftime}`
+/// placeholders, e.g. `{entry_number},{full_path},{si_created:%Y%m%d}`.
+///
+/// Field names are the record's CSV column names; `{{` and `}}` write literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => bail!("Unmatched '}}' in template; write '}}}}' for a literal brace"),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => bail!("Unclosed '{{' in template"),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if !parts.iter().any(|part| matches!(part, Part::Field { .. })) {
+            bail!("Template has no {{field}} placeholders");
+        }
+        Ok(Self { parts })
+    }
+
+    /// The template with each placeholder replaced by its field name
+    pub fn header(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.as_str(),
+                Part::Field { name, .. } => name.as_str(),
+            })
+            .collect()
+    }
+
+    /// One output line for `record`, without the line ending
+    pub fn render<T: Serialize>(&self, record: &T) -> Result<String> {
+        let value = serde_json::to_value(record)?;
+        let Value::Object(fields) = value else {
+            bail!("Templates apply only to records with named fields");
+        };
+
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Field { name, field, format } => {
+                    let value = fields
+                        .get(field)
+                        .ok_or_else(|| anyhow!("Unknown template field '{}' for this artifact", name))?;
+                    line.push_str(&format_value(name, value, format.as_deref())?);
+                }
+            }
+        }
+        Ok(line)
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name.trim(), Some(format.to_string())),
+        None => (placeholder.trim(), None),
+    };
+    if name.is_empty() {
+        bail!("Empty field name in template placeholder '{{{}}}'", placeholder);
+    }
+
+    // chrono panics when asked to render an invalid pattern, so reject it here
+    if let Some(ref format) = format
+        && StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+    {
+        bail!("Invalid timestamp format '{}' for template field '{}'", format, name);
+    }
+
+    let field = FIELD_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, field)| field);
+    Ok(Part::Field { name: name.to_string(), field: field.to_string(), format })
+}
+
+fn format_value(name: &str, value: &Value, format: Option<&str>) -> Result<String> {
+    let Some(format) = format else {
+        return Ok(match value {
+            Value::Null => String::new(),
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        });
+    };
+
+    match value {
+        Value::Null => Ok(String::new()),
+        Value::String(text) => {
+            let timestamp = DateTime::parse_from_rfc3339(text)
+                .map_err(|_| anyhow!("Template field '{}' is not a timestamp; only timestamps take a format", name))?;
+            Ok(timestamp.format(format).to_string())
+        }
+        _ => bail!("Template field '{}' is not a timestamp; only timestamps take a format", name),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::ntfs::cluster_map::ClusterMap;
+use crate::ntfs::mft::MftParser;
+use crate::ntfs::types::ClusterMapRow;
+use anyhow::Result;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Heatmap width in pixels; the height follows from the cluster count
+pub const HEATMAP_WIDTH: u64 = 512;
+
+/// Upper bound on heatmap pixels, so large volumes are scaled down
+const HEATMAP_MAX_PIXELS: u64 = HEATMAP_WIDTH * HEATMAP_WIDTH;
+
+const COLOR_UNMAPPED: [u8; 3] = [32, 32, 32];
+
+/// One row per extent of the cluster map, in cluster order
+pub fn rows(map: &ClusterMap, parser: &MftParser) -> Vec<ClusterMapRow> {
+    map.extents()
+        .iter()
+        .filter_map(|extent| {
+            let record = parser.get_record(extent.entry_number)?;
+            Some(ClusterMapRow {
+                first_cluster: extent.lcn,
+                last_cluster: extent.lcn + extent.length - 1,
+                cluster_count: extent.length,
+                entry_number: record.entry_number,
+                sequence_number: record.sequence_number,
+                in_use: record.in_use,
+                stream: extent.stream.clone(),
+                vcn: extent.vcn,
+                full_path: record.full_path.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Clusters per heatmap pixel and the image height for `total_clusters`
+pub fn heatmap_scale(total_clusters: u64) -> (u64, u64) {
+    let clusters_per_pixel = total_clusters.div_ceil(HEATMAP_MAX_PIXELS).max(1);
+    let height = total_clusters.div_ceil(clusters_per_pixel).div_ceil(HEATMAP_WIDTH).max(1);
+    (clusters_per_pixel, height)
+}
+
+/// Write the cluster map as a binary PPM image, one pixel per group of clusters
+/// in row-major order starting at cluster 0.
+///
+/// Green pixels are used by live files, red ones only by deleted files and
+/// yellow ones by both; brightness grows with the share of mapped clusters.
+/// Dark grey pixels are not mapped by any record.
+pub fn write_heatmap<P: AsRef<Path>>(path: P, map: &ClusterMap, parser: &MftParser, total_clusters: u64) -> Result<()> {
+    let (clusters_per_pixel, height) = heatmap_scale(total_clusters);
+    let pixel_count = (HEATMAP_WIDTH * height) as usize;
+
+    // Mapped clusters per pixel, by live and deleted owners
+    let mut live = vec![0u64; pixel_count];
+    let mut deleted = vec![0u64; pixel_count];
+
+    for extent in map.extents() {
+        let Some(record) = parser.get_record(extent.entry_number) else {
+            continue;
+        };
+        let counts = if record.in_use { &mut live } else { &mut deleted };
+
+        let end = extent.lcn.saturating_add(extent.length);
+        let mut cluster = extent.lcn;
+        while cluster < end {
+            let pixel = cluster / clusters_per_pixel;
+            let Some(count) = counts.get_mut(pixel as usize) else {
+                break;
+            };
+            let pixel_end = ((pixel + 1) * clusters_per_pixel).min(end);
+            *count += pixel_end - cluster;
+            cluster = pixel_end;
+        }
+    }
+
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let mut output = BufWriter::new(File::create(path)?);
+    write!(output, "P6\n{} {}\n255\n", HEATMAP_WIDTH, height)?;
+
+    for (&live, &deleted) in live.iter().zip(&deleted) {
+        let brightness = |count: u64| (64 + 191 * count.min(clusters_per_pixel) / clusters_per_pixel) as u8;
+        let color = match (live, deleted) {
+            (0, 0) => COLOR_UNMAPPED,
+            (live, 0) => [0, brightness(live), 0],
+            (0, deleted) => [brightness(deleted), 0, 0],
+            (live, deleted) => {
+                let level = brightness(live.max(deleted));
+                [level, level, 0]
+            }
+        };
+        output.write_all(&color)?;
+    }
+
+    output.flush()?;
+    Ok(())
+}
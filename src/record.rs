@@ -0,0 +1,307 @@
+use crate::ntfs::filetime::{FileTime, MapTimes};
+use crate::ntfs::types::*;
+use crate::output::bodyfile::{BodyfileOptions, BodyfileOutput};
+use crate::output::columns::{self, WithColumns};
+use crate::output::pipeline::WriterPipeline;
+use crate::output::sink::OutputSink;
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// One parsed record from any supported artifact.
+///
+/// Every artifact flows through the same [`RecordPipeline`] as this type, so
+/// filters, enrichment and output sinks are written once and apply to all of them.
+/// It serializes exactly like the record it wraps.
+// Batches hold one artifact at a time, so boxing the large MFT variant would only add allocations
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum ArtifactRecord {
+    Mft(MftRecord),
+    Usn(UsnJournalEntry),
+    UsnRaw(RawUsnJournalEntry),
+    I30(IndexEntry),
+    Sds(SecurityDescriptorHex),
+    Boot(BootSector),
+    LogFile(LogFileName),
+    /// A record with columns added by enrichment, written after its own fields
+    Enriched(Box<ArtifactRecord>, Vec<(Arc<str>, String)>),
+}
+
+impl ArtifactRecord {
+    /// Short lowercase artifact name, matching the parser registry names
+    pub fn artifact(&self) -> &'static str {
+        match self {
+            Self::Mft(_) => "mft",
+            Self::Usn(_) | Self::UsnRaw(_) => "usn",
+            Self::I30(_) => "i30",
+            Self::Sds(_) => "sds",
+            Self::Boot(_) => "boot",
+            Self::LogFile(_) => "logfile",
+            Self::Enriched(record, _) => record.artifact(),
+        }
+    }
+
+    /// Deterministic ID for the record, the same in every export of the same
+    /// evidence: a name-based UUID (version 8, SHA-256) of the artifact, the
+    /// volume serial number and the fields that identify the record within it.
+    ///
+    /// $MFT rows are identified by entry, sequence and stream; $J changes by
+    /// entry, sequence and USN (parsed and --raw output agree); $I30 entries by
+    /// parent, entry and name; $SDS descriptors by security ID and offset; and
+    /// $LogFile names by their offset.
+    pub fn record_id(&self, volume_serial_number: u64) -> String {
+        let key = match self {
+            Self::Mft(record) => {
+                // Stream rows share their file's entry and sequence
+                let stream = if record.is_ads { record.file_name.rsplit_once(':').map_or("", |(_, name)| name) } else { "" };
+                if record.is_hard_link {
+                    // Hard link rows share it too, and are told apart by the name's directory
+                    format!("{}|{}|{}|{}|{}", record.entry_number, record.sequence_number, stream, record.parent_entry_number, record.file_name)
+                } else {
+                    format!("{}|{}|{}", record.entry_number, record.sequence_number, stream)
+                }
+            }
+            Self::Usn(entry) => format!("{}|{}|{}", entry.entry_number, entry.sequence_number, entry.usn),
+            Self::UsnRaw(entry) => format!("{}|{}|{}", entry.entry_number, entry.sequence_number, entry.usn),
+            Self::I30(entry) => format!(
+                "{}|{}|{}|{}|{}|{:?}",
+                entry.parent_entry_number,
+                entry.parent_sequence_number,
+                entry.entry_number,
+                entry.sequence_number,
+                entry.file_name,
+                entry.name_namespace
+            ),
+            Self::Sds(descriptor) => format!("{}|{}", descriptor.id, descriptor.offset),
+            Self::Boot(boot) => boot.volume_serial_number.to_string(),
+            Self::LogFile(name) => name.source_offset.to_string(),
+            Self::Enriched(record, _) => return record.record_id(volume_serial_number),
+        };
+
+        let digest = Sha256::new()
+            .chain_update(self.artifact())
+            .chain_update(volume_serial_number.to_le_bytes())
+            .chain_update(key)
+            .finalize();
+        let mut bytes: [u8; 16] = digest[..16].try_into().unwrap();
+        bytes[6] = (bytes[6] & 0x0F) | 0x80; // version 8
+        bytes[8] = (bytes[8] & 0x3F) | 0x80; // RFC 9562 variant
+        let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+
+    /// The record with `columns` appended, after any columns it already has
+    pub fn with_columns(self, columns: impl IntoIterator<Item = (Arc<str>, String)>) -> Self {
+        match self {
+            Self::Enriched(record, mut existing) => {
+                existing.extend(columns);
+                Self::Enriched(record, existing)
+            }
+            record => Self::Enriched(Box::new(record), columns.into_iter().collect()),
+        }
+    }
+
+    /// The text of a field or enrichment column, strings as they are and other
+    /// values as JSON; None when the record has no such field or it is null
+    pub fn field(&self, name: &str) -> Option<String> {
+        columns::field_text(self, name)
+    }
+
+    /// Bodyfile line for timeline artifacts; None for records without file timestamps
+    pub fn body_line(&self, options: &BodyfileOptions) -> Option<String> {
+        match self {
+            Self::Mft(record) => BodyfileOutput::mft_record_line(record, options),
+            Self::Usn(entry) => Some(BodyfileOutput::usn_journal_line(entry, options)),
+            Self::UsnRaw(entry) => Some(BodyfileOutput::raw_usn_journal_line(entry, options)),
+            Self::I30(entry) => Some(BodyfileOutput::index_entry_line(entry, options)),
+            Self::LogFile(name) => Some(BodyfileOutput::logfile_name_line(name, options)),
+            Self::Sds(_) | Self::Boot(_) => None,
+            Self::Enriched(record, _) => record.body_line(options),
+        }
+    }
+}
+
+impl MapTimes for ArtifactRecord {
+    fn map_times(&mut self, map: impl Fn(FileTime) -> FileTime) {
+        match self {
+            Self::Mft(record) => record.map_times(map),
+            Self::Usn(entry) => entry.map_times(map),
+            Self::UsnRaw(entry) => entry.map_times(map),
+            Self::I30(entry) => entry.map_times(map),
+            Self::LogFile(name) => name.map_times(map),
+            Self::Sds(_) | Self::Boot(_) => {}
+            Self::Enriched(record, _) => record.map_times(map),
+        }
+    }
+}
+
+impl Serialize for ArtifactRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Enriched(record, columns) => WithColumns { record: &OwnFields(record), columns }.serialize(serializer),
+            record => OwnFields(record).serialize(serializer),
+        }
+    }
+}
+
+/// A record's own fields, without any enrichment columns
+struct OwnFields<'a>(&'a ArtifactRecord);
+
+impl Serialize for OwnFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            ArtifactRecord::Mft(record) => record.serialize(serializer),
+            ArtifactRecord::Usn(entry) => entry.serialize(serializer),
+            ArtifactRecord::UsnRaw(entry) => entry.serialize(serializer),
+            ArtifactRecord::I30(entry) => entry.serialize(serializer),
+            ArtifactRecord::Sds(descriptor) => descriptor.serialize(serializer),
+            ArtifactRecord::Boot(boot) => boot.serialize(serializer),
+            ArtifactRecord::LogFile(name) => name.serialize(serializer),
+            // with_columns never nests enriched records
+            ArtifactRecord::Enriched(record, _) => OwnFields(record).serialize(serializer),
+        }
+    }
+}
+
+impl From<MftRecord> for ArtifactRecord {
+    fn from(record: MftRecord) -> Self {
+        Self::Mft(record)
+    }
+}
+
+impl From<UsnJournalEntry> for ArtifactRecord {
+    fn from(entry: UsnJournalEntry) -> Self {
+        Self::Usn(entry)
+    }
+}
+
+impl From<RawUsnJournalEntry> for ArtifactRecord {
+    fn from(entry: RawUsnJournalEntry) -> Self {
+        Self::UsnRaw(entry)
+    }
+}
+
+impl From<IndexEntry> for ArtifactRecord {
+    fn from(entry: IndexEntry) -> Self {
+        Self::I30(entry)
+    }
+}
+
+impl From<SecurityDescriptorHex> for ArtifactRecord {
+    fn from(descriptor: SecurityDescriptorHex) -> Self {
+        Self::Sds(descriptor)
+    }
+}
+
+impl From<LogFileName> for ArtifactRecord {
+    fn from(name: LogFileName) -> Self {
+        Self::LogFile(name)
+    }
+}
+
+impl From<BootSector> for ArtifactRecord {
+    fn from(boot: BootSector) -> Self {
+        Self::Boot(boot)
+    }
+}
+
+/// Keeps a record when it returns true
+pub type RecordFilter = Box<dyn Fn(&ArtifactRecord) -> bool + Send + Sync>;
+
+/// A filter on $MFT records that lets records of every other artifact through
+pub fn mft_filter(keep: impl Fn(&MftRecord) -> bool + Send + Sync + 'static) -> RecordFilter {
+    // Filters run before enrichment, so records are never wrapped yet
+    Box::new(move |record: &ArtifactRecord| match record {
+        ArtifactRecord::Mft(record) => keep(record),
+        _ => true,
+    })
+}
+
+/// Adds to or rewrites fields of a record before it is written
+pub type RecordEnricher = Box<dyn Fn(ArtifactRecord) -> ArtifactRecord + Send + Sync>;
+
+/// Filter, enrich and write records of any artifact.
+///
+/// Batches pass through every filter, then every enricher, and are then fanned
+/// out to the output sinks on their writer threads.
+pub struct RecordPipeline {
+    filters: Vec<RecordFilter>,
+    enrichers: Vec<RecordEnricher>,
+    writers: WriterPipeline<ArtifactRecord>,
+    outputs: Vec<(&'static str, PathBuf)>,
+    records_written: usize,
+}
+
+impl Default for RecordPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordPipeline {
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            enrichers: Vec::new(),
+            writers: WriterPipeline::new(),
+            outputs: Vec::new(),
+            records_written: 0,
+        }
+    }
+
+    pub fn add_filter(&mut self, filter: RecordFilter) {
+        self.filters.push(filter);
+    }
+
+    /// The records every filter keeps, for outputs written outside the
+    /// pipeline such as the console preview; borrowed when there are no filters
+    pub fn retain<'a, R: Clone + Into<ArtifactRecord>>(&self, records: &'a [R]) -> Cow<'a, [R]> {
+        if self.filters.is_empty() {
+            return Cow::Borrowed(records);
+        }
+        Cow::Owned(records.iter().filter(|record| self.keeps(&(*record).clone().into())).cloned().collect())
+    }
+
+    fn keeps(&self, record: &ArtifactRecord) -> bool {
+        self.filters.iter().all(|keep| keep(record))
+    }
+
+    pub fn add_enricher(&mut self, enricher: RecordEnricher) {
+        self.enrichers.push(enricher);
+    }
+
+    /// Start a writer thread for a sink
+    pub fn add_sink(&mut self, sink: Box<dyn OutputSink<ArtifactRecord>>) -> Result<()> {
+        self.outputs.push((sink.format(), sink.path().to_path_buf()));
+        self.writers.spawn_sink(sink)
+    }
+
+    /// True when no sink would receive records, so callers can skip converting them
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+
+    /// Filter and enrich a batch, then hand it to every sink
+    pub fn send<R: Into<ArtifactRecord>>(&mut self, batch: impl IntoIterator<Item = R>) {
+        let batch: Vec<ArtifactRecord> = batch
+            .into_iter()
+            .map(Into::into)
+            .filter(|record| self.keeps(record))
+            .map(|record| self.enrichers.iter().fold(record, |record, enrich| enrich(record)))
+            .collect();
+
+        self.records_written += batch.len();
+        self.writers.send(batch);
+    }
+
+    /// Wait for every sink to flush, returning the format and path of each output
+    pub fn finish(self) -> Result<Vec<(&'static str, PathBuf)>> {
+        self.writers.finish()?;
+        log::debug!("Wrote {} records to {} outputs", self.records_written, self.outputs.len());
+        Ok(self.outputs)
+    }
+}